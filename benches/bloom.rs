@@ -0,0 +1,137 @@
+use std::hint::black_box;
+
+use bloomlib::{AlignedStore, AllocStore, BloomFilter};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Filter sizes (expected items): in L1/L2, in the last-level cache, and in DRAM.
+const SIZES: [usize; 3] = [10_000, 1_000_000, 10_000_000];
+
+/// The false positive rate of every benchmarked filter.
+const FP_RATE: f64 = 0.01;
+
+/// The number of items per measured batch.
+const BATCH: usize = 1024;
+
+/// A simple Xorshift pseudo-random number generator, so the data is
+/// deterministic across runs.
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut state = self.state;
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        self.state = state;
+        state
+    }
+
+    fn take(&mut self, count: usize) -> Vec<u64> {
+        (0..count).map(|_| self.next_u64()).collect()
+    }
+}
+
+/// Builds a filter for `n` items holding `n` random keys, and returns it with
+/// a batch of inserted keys and a batch of keys that were never inserted.
+fn filled<B: AllocStore>(n: usize) -> (BloomFilter<u64, B>, Vec<u64>, Vec<u64>) {
+    let mut rng = Random::new(12345);
+    let keys = rng.take(n);
+    let mut filter = BloomFilter::from_params(n, FP_RATE);
+    filter.insert_keys(&keys);
+    let hits = keys
+        .iter()
+        .step_by((n / BATCH).max(1))
+        .take(BATCH)
+        .copied()
+        .collect();
+    (filter, hits, rng.take(BATCH))
+}
+
+fn bench_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    for n in SIZES {
+        let (mut filter, _, keys) = filled::<Vec<u64>>(n);
+        group.bench_function(BenchmarkId::new("single", n), |b| {
+            b.iter(|| {
+                for key in &keys {
+                    filter.insert(black_box(key));
+                }
+            })
+        });
+        group.bench_function(BenchmarkId::new("keys", n), |b| {
+            b.iter(|| filter.insert_keys(black_box(&keys)))
+        });
+    }
+    group.finish();
+}
+
+/// Benchmarks single lookups of present (`hit`) and absent (`miss`) keys.
+fn bench_contains<B: AllocStore>(c: &mut Criterion, store: &str) {
+    let mut group = c.benchmark_group(format!("contains/{}", store));
+    group.throughput(Throughput::Elements(BATCH as u64));
+    for n in SIZES {
+        let (filter, hits, misses) = filled::<B>(n);
+        for (name, keys) in [("hit", &hits), ("miss", &misses)] {
+            group.bench_function(BenchmarkId::new(name, n), |b| {
+                b.iter(|| {
+                    keys.iter()
+                        .filter(|key| filter.contains(black_box(key)))
+                        .count()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_contains_stores(c: &mut Criterion) {
+    bench_contains::<Vec<u64>>(c, "u64");
+    bench_contains::<Vec<u8>>(c, "u8");
+    bench_contains::<AlignedStore>(c, "aligned");
+}
+
+/// Benchmarks the batch lookups: `contains_batch` hashes each key with the
+/// standard hasher, `contains_keys` hashes eight integer keys at a time.
+fn bench_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    for n in SIZES {
+        let (filter, _, misses) = filled::<Vec<u64>>(n);
+        group.bench_function(BenchmarkId::new("contains_batch", n), |b| {
+            b.iter(|| filter.contains_batch(black_box(&misses)))
+        });
+        group.bench_function(BenchmarkId::new("contains_keys", n), |b| {
+            b.iter(|| filter.contains_keys(black_box(&misses)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_merge(c: &mut Criterion) {
+    let mut group = c.benchmark_group("merge");
+    for n in SIZES {
+        let (mut filter, _, _) = filled::<Vec<u64>>(n);
+        let (other, _, _) = filled::<Vec<u64>>(n);
+        group.throughput(Throughput::Bytes(filter.memory_usage_bytes() as u64));
+        group.bench_function(BenchmarkId::from_parameter(n), |b| {
+            b.iter(|| filter.merge(black_box(&other)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_insert,
+    bench_contains_stores,
+    bench_batch,
+    bench_merge
+);
+criterion_main!(benches);
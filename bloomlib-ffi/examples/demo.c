@@ -0,0 +1,41 @@
+/*
+ * Builds a filter, serializes it and loads it back.
+ *
+ *   cargo build --release -p bloomlib-ffi
+ *   cc -Ibloomlib-ffi/include bloomlib-ffi/examples/demo.c \
+ *      target/release/libbloomlib_ffi.a -lpthread -ldl -lm -o demo
+ */
+#include <stdio.h>
+#include <string.h>
+
+#include "bloomlib.h"
+
+int main(void) {
+    bloom_filter *filter = NULL;
+    bloom_status status = bloom_new(1000, 0.01, &filter);
+    if (status != BLOOM_STATUS_OK) {
+        fprintf(stderr, "bloom_new: %s\n", bloom_status_message(status));
+        return 1;
+    }
+
+    const char *key = "apple";
+    bloom_insert(filter, (const uint8_t *)key, strlen(key));
+
+    size_t len = bloom_serialized_len(filter);
+    uint8_t *bytes = malloc(len);
+    bloom_serialize(filter, bytes, len, &len);
+    bloom_free(filter);
+
+    bloom_filter *loaded = NULL;
+    status = bloom_deserialize(bytes, len, &loaded);
+    free(bytes);
+    if (status != BLOOM_STATUS_OK) {
+        fprintf(stderr, "bloom_deserialize: %s\n", bloom_status_message(status));
+        return 1;
+    }
+
+    printf("apple: %d\n", bloom_contains(loaded, (const uint8_t *)"apple", 5));
+    printf("pear: %d\n", bloom_contains(loaded, (const uint8_t *)"pear", 4));
+    bloom_free(loaded);
+    return 0;
+}
@@ -0,0 +1,149 @@
+/* Generated by cbindgen from bloomlib-ffi. Do not edit. */
+
+#ifndef BLOOMLIB_H
+#define BLOOMLIB_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The result of a fallible call.
+typedef enum bloom_status {
+  // The call succeeded.
+  BLOOM_STATUS_OK = 0,
+  // A required pointer was null.
+  BLOOM_STATUS_NULL_POINTER,
+  // The expected item count, false positive rate or hash count was invalid.
+  BLOOM_STATUS_INVALID_ARGUMENT,
+  // The filter is too large to be allocated on this platform.
+  BLOOM_STATUS_CAPACITY_OVERFLOW,
+  // The filters differ in bit count or hash count.
+  BLOOM_STATUS_INCOMPATIBLE_FILTERS,
+  // The output buffer is too small; the required length was written.
+  BLOOM_STATUS_BUFFER_TOO_SMALL,
+  // The bytes are not a serialized filter.
+  BLOOM_STATUS_INVALID_FORMAT,
+} bloom_status;
+
+// An opaque Bloom filter of byte-string keys.
+typedef struct bloom_filter bloom_filter;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a filter for `expected_items` items at a target false positive
+// rate, storing it in `*out`.
+//
+// # Safety
+//
+// `out` must be valid for writes.
+enum bloom_status bloom_new(size_t expected_items,
+                            double false_positive_rate,
+                            struct bloom_filter **out);
+
+// Creates a filter for `expected_items` items with `hash_count` hash
+// functions, storing it in `*out`.
+//
+// # Safety
+//
+// `out` must be valid for writes.
+enum bloom_status bloom_new_with_hashes(size_t expected_items,
+                                        uint32_t hash_count,
+                                        struct bloom_filter **out);
+
+// Frees a filter. Does nothing if `filter` is null.
+//
+// # Safety
+//
+// `filter` must be null or a live filter, which must not be used again.
+void bloom_free(struct bloom_filter *filter);
+
+// Inserts the `len` bytes at `key`.
+//
+// # Safety
+//
+// `filter` must be a live filter and `key` must be valid for reads of
+// `len` bytes (or null if `len` is 0).
+enum bloom_status bloom_insert(struct bloom_filter *filter, const uint8_t *key, size_t len);
+
+// Returns `true` if the `len` bytes at `key` might have been inserted, and
+// `false` if they definitely were not or a pointer is null.
+//
+// # Safety
+//
+// As for `bloom_insert`.
+bool bloom_contains(const struct bloom_filter *filter, const uint8_t *key, size_t len);
+
+// Removes all items from the filter.
+//
+// # Safety
+//
+// `filter` must be null or a live filter.
+void bloom_clear(struct bloom_filter *filter);
+
+// Adds all items of `other` to `filter`. Both must have the same bit count
+// and hash count.
+//
+// # Safety
+//
+// Both pointers must be live filters; they may be the same filter.
+enum bloom_status bloom_merge(struct bloom_filter *filter, const struct bloom_filter *other);
+
+// Returns the number of bits (m) in the filter, or 0 if `filter` is null.
+//
+// # Safety
+//
+// `filter` must be null or a live filter.
+uint64_t bloom_bit_count(const struct bloom_filter *filter);
+
+// Returns the number of hash functions (k), or 0 if `filter` is null.
+//
+// # Safety
+//
+// `filter` must be null or a live filter.
+uint32_t bloom_hash_count(const struct bloom_filter *filter);
+
+// Returns the length in bytes of the filter's serialized form, or 0 if
+// `filter` is null.
+//
+// # Safety
+//
+// `filter` must be null or a live filter.
+size_t bloom_serialized_len(const struct bloom_filter *filter);
+
+// Serializes the filter into the `capacity` bytes at `out`, storing the
+// serialized length in `*written`.
+//
+// If `capacity` is too small, nothing is written to `out` and
+// `BLOOM_STATUS_BUFFER_TOO_SMALL` is returned; `*written` then holds the
+// required length.
+//
+// # Safety
+//
+// `filter` must be a live filter, `out` must be valid for writes of
+// `capacity` bytes and `written` must be valid for writes.
+enum bloom_status bloom_serialize(const struct bloom_filter *filter,
+                                  uint8_t *out,
+                                  size_t capacity,
+                                  size_t *written);
+
+// Deserializes a filter from the `len` bytes at `data`, storing it in
+// `*out`.
+//
+// # Safety
+//
+// `data` must be valid for reads of `len` bytes and `out` must be valid
+// for writes.
+enum bloom_status bloom_deserialize(const uint8_t *data, size_t len, struct bloom_filter **out);
+
+// Returns a static, NUL-terminated description of a status.
+const char *bloom_status_message(enum bloom_status status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BLOOMLIB_H */
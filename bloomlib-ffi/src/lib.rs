@@ -0,0 +1,384 @@
+//! C bindings for `bloomlib`.
+//!
+//! Build the crate as a `cdylib` or `staticlib` and include
+//! `include/bloomlib.h`. Keys are byte strings, hashed like a Rust `[u8]`,
+//! so a filter serialized from C loads as a `BloomFilter<[u8]>` in Rust and
+//! vice versa.
+//!
+//! Every function taking a filter pointer expects one returned by
+//! `bloom_new`, `bloom_new_with_hashes` or `bloom_deserialize` and not yet
+//! passed to `bloom_free`. A filter may be read from several threads at once,
+//! but must not be modified while it is read.
+
+use std::ffi::c_char;
+use std::ptr;
+
+use bloomlib::{BloomError, BloomFilter};
+
+/// An opaque Bloom filter of byte-string keys.
+pub struct Filter(BloomFilter<[u8]>);
+
+/// The result of a fallible call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The call succeeded.
+    Ok = 0,
+    /// A required pointer was null.
+    NullPointer,
+    /// The expected item count, false positive rate or hash count was invalid.
+    InvalidArgument,
+    /// The filter is too large to be allocated on this platform.
+    CapacityOverflow,
+    /// The filters differ in bit count or hash count.
+    IncompatibleFilters,
+    /// The output buffer is too small; the required length was written.
+    BufferTooSmall,
+    /// The bytes are not a serialized filter.
+    InvalidFormat,
+}
+
+impl From<BloomError> for Status {
+    fn from(err: BloomError) -> Self {
+        match err {
+            BloomError::ZeroExpectedItems
+            | BloomError::InvalidFalsePositiveRate(_)
+            | BloomError::ZeroHashCount
+            | BloomError::InsufficientMemory(_)
+            | BloomError::Cancelled => Status::InvalidArgument,
+            BloomError::CapacityOverflow { .. } => Status::CapacityOverflow,
+            BloomError::IncompatibleFilters { .. } | BloomError::KeyMismatch => {
+                Status::IncompatibleFilters
+            }
+            BloomError::InvalidFormat(_) | BloomError::AuthenticationFailed => {
+                Status::InvalidFormat
+            }
+        }
+    }
+}
+
+/// Views `len` bytes at `data` as a slice, allowing a null `data` when `len`
+/// is 0.
+///
+/// # Safety
+///
+/// Unless null, `data` must be valid for reads of `len` bytes.
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    if data.is_null() {
+        return (len == 0).then_some(&[]);
+    }
+    Some(std::slice::from_raw_parts(data, len))
+}
+
+/// Stores a new filter in `out`, or reports why it could not be created.
+///
+/// # Safety
+///
+/// `out` must be null or valid for writes.
+unsafe fn create(out: *mut *mut Filter, result: Result<BloomFilter<[u8]>, BloomError>) -> Status {
+    if out.is_null() {
+        return Status::NullPointer;
+    }
+    match result {
+        Ok(filter) => {
+            *out = Box::into_raw(Box::new(Filter(filter)));
+            Status::Ok
+        }
+        Err(err) => err.into(),
+    }
+}
+
+/// Creates a filter for `expected_items` items at a target false positive
+/// rate, storing it in `*out`.
+///
+/// # Safety
+///
+/// `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bloom_new(
+    expected_items: usize,
+    false_positive_rate: f64,
+    out: *mut *mut Filter,
+) -> Status {
+    create(
+        out,
+        BloomFilter::try_new(expected_items, false_positive_rate),
+    )
+}
+
+/// Creates a filter for `expected_items` items with `hash_count` hash
+/// functions, storing it in `*out`.
+///
+/// # Safety
+///
+/// `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bloom_new_with_hashes(
+    expected_items: usize,
+    hash_count: u32,
+    out: *mut *mut Filter,
+) -> Status {
+    create(out, BloomFilter::try_new(expected_items, hash_count))
+}
+
+/// Frees a filter. Does nothing if `filter` is null.
+///
+/// # Safety
+///
+/// `filter` must be null or a live filter, which must not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn bloom_free(filter: *mut Filter) {
+    if !filter.is_null() {
+        drop(Box::from_raw(filter));
+    }
+}
+
+/// Inserts the `len` bytes at `key`.
+///
+/// # Safety
+///
+/// `filter` must be a live filter and `key` must be valid for reads of
+/// `len` bytes (or null if `len` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn bloom_insert(filter: *mut Filter, key: *const u8, len: usize) -> Status {
+    match (filter.as_mut(), bytes(key, len)) {
+        (Some(filter), Some(key)) => {
+            filter.0.insert(key);
+            Status::Ok
+        }
+        _ => Status::NullPointer,
+    }
+}
+
+/// Returns `true` if the `len` bytes at `key` might have been inserted, and
+/// `false` if they definitely were not or a pointer is null.
+///
+/// # Safety
+///
+/// As for `bloom_insert`.
+#[no_mangle]
+pub unsafe extern "C" fn bloom_contains(filter: *const Filter, key: *const u8, len: usize) -> bool {
+    match (filter.as_ref(), bytes(key, len)) {
+        (Some(filter), Some(key)) => filter.0.contains(key),
+        _ => false,
+    }
+}
+
+/// Removes all items from the filter.
+///
+/// # Safety
+///
+/// `filter` must be null or a live filter.
+#[no_mangle]
+pub unsafe extern "C" fn bloom_clear(filter: *mut Filter) {
+    if let Some(filter) = filter.as_mut() {
+        filter.0.clear();
+    }
+}
+
+/// Adds all items of `other` to `filter`. Both must have the same bit count
+/// and hash count.
+///
+/// # Safety
+///
+/// Both pointers must be live filters; they may be the same filter.
+#[no_mangle]
+pub unsafe extern "C" fn bloom_merge(filter: *mut Filter, other: *const Filter) -> Status {
+    if filter.is_null() || other.is_null() {
+        return Status::NullPointer;
+    }
+    if ptr::eq(filter, other) {
+        return Status::Ok;
+    }
+    match (*filter).0.merge(&(*other).0) {
+        Ok(()) => Status::Ok,
+        Err(err) => err.into(),
+    }
+}
+
+/// Returns the number of bits (m) in the filter, or 0 if `filter` is null.
+///
+/// # Safety
+///
+/// `filter` must be null or a live filter.
+#[no_mangle]
+pub unsafe extern "C" fn bloom_bit_count(filter: *const Filter) -> u64 {
+    filter.as_ref().map_or(0, |filter| filter.0.bit_count())
+}
+
+/// Returns the number of hash functions (k), or 0 if `filter` is null.
+///
+/// # Safety
+///
+/// `filter` must be null or a live filter.
+#[no_mangle]
+pub unsafe extern "C" fn bloom_hash_count(filter: *const Filter) -> u32 {
+    filter.as_ref().map_or(0, |filter| filter.0.hash_count())
+}
+
+/// Returns the length in bytes of the filter's serialized form, or 0 if
+/// `filter` is null.
+///
+/// # Safety
+///
+/// `filter` must be null or a live filter.
+#[no_mangle]
+pub unsafe extern "C" fn bloom_serialized_len(filter: *const Filter) -> usize {
+    filter
+        .as_ref()
+        .map_or(0, |filter| filter.0.serialized_len())
+}
+
+/// Serializes the filter into the `capacity` bytes at `out`, storing the
+/// serialized length in `*written`.
+///
+/// If `capacity` is too small, nothing is written to `out` and
+/// `BLOOM_STATUS_BUFFER_TOO_SMALL` is returned; `*written` then holds the
+/// required length.
+///
+/// # Safety
+///
+/// `filter` must be a live filter, `out` must be valid for writes of
+/// `capacity` bytes and `written` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bloom_serialize(
+    filter: *const Filter,
+    out: *mut u8,
+    capacity: usize,
+    written: *mut usize,
+) -> Status {
+    let Some(filter) = filter.as_ref() else {
+        return Status::NullPointer;
+    };
+    if written.is_null() {
+        return Status::NullPointer;
+    }
+    let len = filter.0.serialized_len();
+    *written = len;
+    if capacity < len {
+        return Status::BufferTooSmall;
+    }
+    if out.is_null() {
+        return Status::NullPointer;
+    }
+    let buffer = std::slice::from_raw_parts_mut(out, len);
+    filter
+        .0
+        .write_to(buffer)
+        .expect("the buffer holds the serialized length");
+    Status::Ok
+}
+
+/// Deserializes a filter from the `len` bytes at `data`, storing it in
+/// `*out`.
+///
+/// # Safety
+///
+/// `data` must be valid for reads of `len` bytes and `out` must be valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bloom_deserialize(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut Filter,
+) -> Status {
+    match bytes(data, len) {
+        Some(data) => create(out, BloomFilter::from_bytes(data)),
+        None => Status::NullPointer,
+    }
+}
+
+/// Returns a static, NUL-terminated description of a status.
+#[no_mangle]
+pub extern "C" fn bloom_status_message(status: Status) -> *const c_char {
+    let message = match status {
+        Status::Ok => c"ok",
+        Status::NullPointer => c"a required pointer was null",
+        Status::InvalidArgument => {
+            c"invalid expected item count, false positive rate or hash count"
+        }
+        Status::CapacityOverflow => c"the filter is too large for this platform",
+        Status::IncompatibleFilters => c"the filters differ in bit count or hash count",
+        Status::BufferTooSmall => c"the output buffer is too small",
+        Status::InvalidFormat => c"the bytes are not a serialized filter",
+    };
+    message.as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_filter(expected_items: usize, rate: f64) -> *mut Filter {
+        let mut filter = ptr::null_mut();
+        assert_eq!(
+            unsafe { bloom_new(expected_items, rate, &mut filter) },
+            Status::Ok
+        );
+        filter
+    }
+
+    #[test]
+    fn test_insert_serialize_round_trip() {
+        let filter = new_filter(1000, 0.01);
+        unsafe {
+            assert_eq!(bloom_insert(filter, b"apple".as_ptr(), 5), Status::Ok);
+            assert!(bloom_contains(filter, b"apple".as_ptr(), 5));
+            assert!(!bloom_contains(filter, b"banana".as_ptr(), 6));
+
+            let mut written = 0;
+            assert_eq!(
+                bloom_serialize(filter, ptr::null_mut(), 0, &mut written),
+                Status::BufferTooSmall
+            );
+            assert_eq!(written, bloom_serialized_len(filter));
+            let mut buffer = vec![0u8; written];
+            assert_eq!(
+                bloom_serialize(filter, buffer.as_mut_ptr(), buffer.len(), &mut written),
+                Status::Ok
+            );
+
+            // The bytes load as a Rust filter of `[u8]`, and back.
+            let rust: BloomFilter<[u8]> = BloomFilter::from_bytes(&buffer).unwrap();
+            assert!(rust.contains(b"apple"));
+            let mut loaded = ptr::null_mut();
+            assert_eq!(
+                bloom_deserialize(buffer.as_ptr(), buffer.len(), &mut loaded),
+                Status::Ok
+            );
+            assert!(bloom_contains(loaded, b"apple".as_ptr(), 5));
+            assert_eq!(bloom_hash_count(loaded), bloom_hash_count(filter));
+
+            bloom_free(loaded);
+            bloom_free(filter);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut filter = ptr::null_mut();
+        unsafe {
+            assert_eq!(bloom_new(0, 0.01, &mut filter), Status::InvalidArgument);
+            assert_eq!(bloom_new(10, 1.5, &mut filter), Status::InvalidArgument);
+            assert_eq!(
+                bloom_new_with_hashes(10, 0, &mut filter),
+                Status::InvalidArgument
+            );
+            assert!(filter.is_null());
+            assert_eq!(bloom_new(10, 0.01, ptr::null_mut()), Status::NullPointer);
+            assert_eq!(
+                bloom_deserialize(b"nope".as_ptr(), 4, &mut filter),
+                Status::InvalidFormat
+            );
+
+            let (small, large) = (new_filter(10, 0.01), new_filter(1000, 0.01));
+            assert_eq!(bloom_merge(small, large), Status::IncompatibleFilters);
+            assert_eq!(bloom_merge(small, small), Status::Ok);
+            assert_eq!(bloom_insert(small, ptr::null(), 1), Status::NullPointer);
+            assert_eq!(bloom_insert(small, ptr::null(), 0), Status::Ok);
+            bloom_free(small);
+            bloom_free(large);
+            bloom_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! Checks that `include/bloomlib.h` matches the bindings.
+//!
+//! Run with `UPDATE_HEADER=1` to regenerate the header after changing them.
+
+use std::path::Path;
+
+#[test]
+fn test_header_is_up_to_date() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::Builder::new()
+        .with_src(crate_dir.join("src/lib.rs"))
+        .with_config(config)
+        .generate()
+        .expect("cbindgen failed to parse the bindings")
+        .write(&mut generated);
+
+    let header_path = crate_dir.join("include/bloomlib.h");
+    if std::env::var_os("UPDATE_HEADER").is_some() {
+        std::fs::write(&header_path, &generated).unwrap();
+    }
+    let header = std::fs::read(&header_path).unwrap_or_default();
+    assert!(
+        header == generated,
+        "include/bloomlib.h is out of date; rerun this test with UPDATE_HEADER=1"
+    );
+}
@@ -0,0 +1,189 @@
+//! Procedural macros for `bloomlib`.
+//!
+//! Use these through the `macros` feature of `bloomlib` rather than depending
+//! on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Ident, LitFloat, LitInt, LitStr, Token};
+
+/// Builds a `bloomlib::StaticBloom` from a word list at compile time.
+///
+/// The file is read relative to the crate's `CARGO_MANIFEST_DIR`, one key per
+/// line. Leading and trailing whitespace is trimmed and blank lines are
+/// skipped. The filter is sized for the number of keys and either a target
+/// false positive rate (`fp = <f64>`) or a hash count (`hashes = <u32>`),
+/// using the same formulas as `BloomFilter::new`.
+///
+/// ```ignore
+/// use bloomlib::{bloom, StaticBloom};
+///
+/// static RESERVED: StaticBloom = bloom!("keywords.txt", fp = 0.001);
+/// ```
+#[proc_macro]
+pub fn bloom(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as BloomArgs);
+    match expand(args) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Sizing parameter for the generated filter.
+enum Params {
+    FalsePositiveRate(f64),
+    HashCount(u32),
+}
+
+/// Parsed arguments: `"path", fp = 0.01` or `"path", hashes = 7`.
+struct BloomArgs {
+    path: LitStr,
+    params: Params,
+    params_span: Span,
+}
+
+impl Parse for BloomArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path: LitStr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let name: Ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+
+        let params = match name.to_string().as_str() {
+            "fp" => {
+                let rate: LitFloat = input.parse()?;
+                let rate = rate.base10_parse::<f64>()?;
+                if !(rate > 0.0 && rate < 1.0) {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "False positive rate must be between 0.0 and 1.0, exclusive.",
+                    ));
+                }
+                Params::FalsePositiveRate(rate)
+            }
+            "hashes" => {
+                let hashes: LitInt = input.parse()?;
+                let hashes = hashes.base10_parse::<u32>()?;
+                if hashes == 0 {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "Hash count must be greater than 0.",
+                    ));
+                }
+                Params::HashCount(hashes)
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "expected `fp = <rate>` or `hashes = <count>`",
+                ))
+            }
+        };
+        input.parse::<Option<Token![,]>>()?;
+
+        Ok(BloomArgs {
+            path,
+            params,
+            params_span: name.span(),
+        })
+    }
+}
+
+fn expand(args: BloomArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| syn::Error::new(args.path.span(), "CARGO_MANIFEST_DIR is not set"))?;
+    let path = std::path::Path::new(&manifest_dir).join(args.path.value());
+    let contents = std::fs::read_to_string(&path).map_err(|err| {
+        syn::Error::new(
+            args.path.span(),
+            format!("failed to read {}: {}", path.display(), err),
+        )
+    })?;
+
+    let keys: Vec<&str> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    if keys.is_empty() {
+        return Err(syn::Error::new(
+            args.params_span,
+            "Expected items must be greater than 0.",
+        ));
+    }
+
+    let (m, k) = optimal_params(keys.len(), &args.params);
+    let num_u64s = m.div_ceil(64) as usize;
+    let mut words = vec![0u64; num_u64s];
+    let bit_count = (num_u64s * 64) as u64;
+    for key in &keys {
+        let (h1, h2) = const_hashes(key.as_bytes());
+        for i in 0..k {
+            let bit_index = bit_index(h1, h2, i, bit_count);
+            words[(bit_index / 64) as usize] |= 1u64 << (bit_index % 64);
+        }
+    }
+
+    // Referencing the file through `include_bytes!` makes cargo rebuild when it changes.
+    let tracked = path.to_string_lossy().into_owned();
+    Ok(quote! {
+        {
+            const _: &[u8] = include_bytes!(#tracked);
+            ::bloomlib::StaticBloom::from_raw_parts(&[#(#words),*], #k)
+        }
+    })
+}
+
+/// Computes the bit count (m) and hash count (k), as `BloomFilter::new` does.
+fn optimal_params(expected_items: usize, params: &Params) -> (u64, u32) {
+    let ln2 = std::f64::consts::LN_2;
+    match *params {
+        Params::FalsePositiveRate(p) => {
+            let m = ((-(expected_items as f64) * p.ln()) / (ln2 * ln2)).ceil() as u64;
+            let k = ((m as f64 / expected_items as f64) * ln2).ceil() as u32;
+            (m, k)
+        }
+        Params::HashCount(k) => {
+            let m = ((k as f64 * expected_items as f64) / ln2).ceil() as u64;
+            (m, k)
+        }
+    }
+}
+
+/// Copy of `bloomlib`'s const hash (FNV-1a with a SplitMix64 finalizer).
+///
+/// A proc-macro crate cannot depend on the crate it generates code for, so
+/// this must be kept in sync with `const_hashes` in `bloomlib`.
+fn const_hashes(bytes: &[u8]) -> (u64, u64) {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    let h1 = splitmix64(hash);
+    let h2 = splitmix64(h1 ^ 0x9e37_79b9_7f4a_7c15);
+    (h1, h2)
+}
+
+/// Copy of `bloomlib`'s `hashing::bit_index`, which `const_bit` also uses:
+/// double hashing, reduced with a mask for a power-of-two bit count and
+/// with Lemire's fastrange otherwise. The tests check the result against
+/// `BloomFilter`'s own probes.
+fn bit_index(h1: u64, h2: u64, i: u32, bit_count: u64) -> u64 {
+    let hash = h1.wrapping_add(h2.wrapping_mul(i as u64));
+    if bit_count.is_power_of_two() {
+        hash & (bit_count - 1)
+    } else {
+        ((hash as u128 * bit_count as u128) >> 64) as u64
+    }
+}
+
+/// The SplitMix64 finalizer.
+fn splitmix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,87 @@
+use bloomlib::{BloomFilter, ConstBloomFilter, StaticBloom};
+use bloomlib_macros::bloom;
+
+const KEYWORDS: [&str; 10] = [
+    "fn", "let", "match", "impl", "trait", "struct", "enum", "where", "loop", "while",
+];
+
+static RESERVED: StaticBloom = bloom!("tests/keywords.txt", fp = 0.01);
+
+#[test]
+fn test_bloom_contains_all_keys() {
+    for keyword in KEYWORDS {
+        assert!(RESERVED.contains(keyword));
+    }
+    assert!(!RESERVED.contains("banana"));
+}
+
+#[test]
+fn test_bloom_matches_const_filter() {
+    // n = 10, p = 0.01 -> m = 96 bits (2 words), k = 7
+    assert_eq!(RESERVED.hash_count(), 7);
+    let expected = ConstBloomFilter::<2>::from_keys(7, &KEYWORDS);
+    assert_eq!(RESERVED.as_words(), expected.as_words());
+}
+
+#[test]
+fn test_bloom_with_hash_count() {
+    let filter = bloom!("tests/keywords.txt", hashes = 3);
+    assert_eq!(filter.hash_count(), 3);
+    for keyword in KEYWORDS {
+        assert!(filter.contains(keyword));
+    }
+}
+
+/// The hashes `ConstBloomFilter` and `bloom!` derive from a key: FNV-1a,
+/// then a SplitMix64 finalizer for `h1` and a second round for `h2`.
+fn const_hashes(bytes: &[u8]) -> (u64, u64) {
+    let splitmix64 = |mut z: u64| {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    let h1 = splitmix64(hash);
+    (h1, splitmix64(h1 ^ 0x9e37_79b9_7f4a_7c15))
+}
+
+/// Loads `words` as a `BloomFilter` through the serialized format, which
+/// ends with a CRC-32 (IEEE 802.3) of the header and words.
+fn load(words: &[u64], hash_count: u32) -> BloomFilter<str> {
+    let mut bytes = b"BLMF".to_vec();
+    bytes.extend(2u32.to_le_bytes());
+    bytes.extend((words.len() as u64 * 64).to_le_bytes());
+    bytes.extend(hash_count.to_le_bytes());
+    bytes.extend(words.iter().flat_map(|word| word.to_le_bytes()));
+    let crc = !bytes.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg())
+        })
+    });
+    bytes.extend(crc.to_le_bytes());
+    BloomFilter::from_bytes(&bytes).unwrap()
+}
+
+#[test]
+fn test_bloom_matches_runtime_filter() {
+    // 128 bits, a power of two, which `BloomFilter` reduces with a mask.
+    assert!(RESERVED.bit_count().is_power_of_two());
+    let built = load(RESERVED.as_words(), RESERVED.hash_count());
+
+    // Replaying the keys' hashes sets the bits `BloomFilter` probes for them.
+    let mut log = b"BLMR".to_vec();
+    log.extend(1u32.to_le_bytes());
+    log.extend(RESERVED.bit_count().to_le_bytes());
+    log.extend(RESERVED.hash_count().to_le_bytes());
+    for keyword in KEYWORDS {
+        let (h1, h2) = const_hashes(keyword.as_bytes());
+        log.push(1);
+        log.extend(h1.to_le_bytes());
+        log.extend(h2.to_le_bytes());
+    }
+    let mut replayed = load(&vec![0; RESERVED.as_words().len()], RESERVED.hash_count());
+    replayed.replay(&log[..]).unwrap();
+    assert_eq!(replayed.to_bytes(), built.to_bytes());
+}
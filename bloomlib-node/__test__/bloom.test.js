@@ -0,0 +1,39 @@
+const assert = require("node:assert");
+const fs = require("node:fs");
+const os = require("node:os");
+const path = require("node:path");
+const test = require("node:test");
+
+const { BloomFilter } = require("../bloomlib.node");
+
+test("inserts and looks up strings and buffers", () => {
+  const filter = new BloomFilter(1000, 0.01);
+  filter.insert("apple");
+  filter.insert(Buffer.from("raw"));
+
+  assert.ok(filter.contains("apple"));
+  assert.ok(filter.contains(Buffer.from("raw")));
+  // Strings and buffers hash differently, like `str` and `[u8]` in Rust.
+  assert.ok(!filter.contains(Buffer.from("apple")));
+  assert.deepStrictEqual(filter.containsMany(["apple", "pear"]), [true, false]);
+});
+
+test("round-trips through buffers and files", () => {
+  const filter = BloomFilter.withHashes(1000, 7);
+  filter.insert("apple");
+  assert.strictEqual(filter.hashCount, 7);
+
+  const copy = BloomFilter.fromBuffer(filter.toBuffer());
+  assert.ok(copy.contains("apple"));
+  assert.strictEqual(copy.bitCount, filter.bitCount);
+
+  const file = path.join(fs.mkdtempSync(path.join(os.tmpdir(), "bloomlib-")), "fruit.bloom");
+  filter.save(file);
+  assert.ok(BloomFilter.load(file).contains("apple"));
+});
+
+test("reports invalid input", () => {
+  assert.throws(() => new BloomFilter(0, 0.01), /Expected items/);
+  assert.throws(() => BloomFilter.fromBuffer(Buffer.from("nope")), /Invalid serialized filter/);
+  assert.throws(() => new BloomFilter(10, 0.01).merge(new BloomFilter(10000, 0.01)), /Cannot combine/);
+});
@@ -0,0 +1,148 @@
+//! Node.js bindings for `bloomlib`.
+//!
+//! Build the addon with `napi build --release` (from `@napi-rs/cli`) in this
+//! directory, or copy the `cdylib` cargo builds to `bloomlib.node`.
+//!
+//! Keys hash exactly as in Rust: strings as `str` and `Buffer`s as `[u8]`,
+//! so filters written by Rust batch jobs with `BloomFilter::write_to` can be
+//! loaded and queried directly.
+
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Write};
+
+use napi::bindgen_prelude::{Buffer, Either};
+use napi::{Error, Result};
+use napi_derive::napi;
+
+/// A key passed from JavaScript.
+enum Key {
+    Str(String),
+    Bytes(Buffer),
+}
+
+impl Key {
+    fn new(key: Either<String, Buffer>) -> Key {
+        match key {
+            Either::A(key) => Key::Str(key),
+            Either::B(key) => Key::Bytes(key),
+        }
+    }
+}
+
+// Delegates to the wrapped value, so a key hashes like the Rust type it
+// stands for.
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Key::Str(key) => key.as_str().hash(state),
+            Key::Bytes(key) => key[..].hash(state),
+        }
+    }
+}
+
+fn to_napi_error(err: impl std::fmt::Display) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+/// A Bloom filter of string and `Buffer` keys.
+#[napi]
+pub struct BloomFilter {
+    inner: bloomlib::BloomFilter<Key>,
+}
+
+#[napi]
+impl BloomFilter {
+    /// Creates a filter for `expectedItems` items at a target false positive
+    /// rate.
+    #[napi(constructor)]
+    pub fn new(expected_items: u32, fp_rate: f64) -> Result<Self> {
+        let inner = bloomlib::BloomFilter::try_new(expected_items as usize, fp_rate)
+            .map_err(to_napi_error)?;
+        Ok(BloomFilter { inner })
+    }
+
+    /// Creates a filter for `expectedItems` items with `hashCount` hash
+    /// functions.
+    #[napi(factory)]
+    pub fn with_hashes(expected_items: u32, hash_count: u32) -> Result<Self> {
+        let inner = bloomlib::BloomFilter::try_new(expected_items as usize, hash_count)
+            .map_err(to_napi_error)?;
+        Ok(BloomFilter { inner })
+    }
+
+    /// Loads a filter serialized by `toBuffer` or by Rust's `to_bytes`.
+    #[napi(factory)]
+    pub fn from_buffer(bytes: Buffer) -> Result<Self> {
+        let inner = bloomlib::BloomFilter::from_bytes(&bytes).map_err(to_napi_error)?;
+        Ok(BloomFilter { inner })
+    }
+
+    /// Reads a filter from a file written by `save` or by Rust's `write_to`.
+    #[napi(factory)]
+    pub fn load(path: String) -> Result<Self> {
+        let file = File::open(path).map_err(to_napi_error)?;
+        let inner =
+            bloomlib::BloomFilter::read_from(BufReader::new(file)).map_err(to_napi_error)?;
+        Ok(BloomFilter { inner })
+    }
+
+    /// Inserts a string or `Buffer`.
+    #[napi]
+    pub fn insert(&mut self, key: Either<String, Buffer>) {
+        self.inner.insert(&Key::new(key));
+    }
+
+    /// Returns `true` if the key might have been inserted, and `false` if it
+    /// definitely was not.
+    #[napi]
+    pub fn contains(&self, key: Either<String, Buffer>) -> bool {
+        self.inner.contains(&Key::new(key))
+    }
+
+    /// Checks an array of keys in one call, returning one result per key.
+    #[napi]
+    pub fn contains_many(&self, keys: Vec<Either<String, Buffer>>) -> Vec<bool> {
+        let keys: Vec<Key> = keys.into_iter().map(Key::new).collect();
+        self.inner.contains_batch(&keys)
+    }
+
+    /// Adds all keys of another filter with the same bit and hash count.
+    #[napi]
+    pub fn merge(&mut self, other: &BloomFilter) -> Result<()> {
+        self.inner.merge(&other.inner).map_err(to_napi_error)
+    }
+
+    /// Removes all keys.
+    #[napi]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// Serializes the filter into a `Buffer`.
+    #[napi]
+    pub fn to_buffer(&self) -> Buffer {
+        self.inner.to_bytes().into()
+    }
+
+    /// Writes the filter to a file.
+    #[napi]
+    pub fn save(&self, path: String) -> Result<()> {
+        let file = File::create(path).map_err(to_napi_error)?;
+        let mut writer = BufWriter::new(file);
+        self.inner.write_to(&mut writer).map_err(to_napi_error)?;
+        writer.flush().map_err(to_napi_error)
+    }
+
+    /// The number of bits (m).
+    #[napi(getter)]
+    pub fn bit_count(&self) -> f64 {
+        self.inner.bit_count() as f64
+    }
+
+    /// The number of hash functions (k).
+    #[napi(getter)]
+    pub fn hash_count(&self) -> u32 {
+        self.inner.hash_count()
+    }
+}
@@ -0,0 +1,206 @@
+//! Python bindings for `bloomlib`.
+//!
+//! Build and install the `bloomlib` Python module with `maturin develop` or
+//! `maturin build` from this directory.
+//!
+//! Keys are hashed exactly as the matching Rust types: `str` as `str`,
+//! `bytes` as `[u8]` and `int` as `u64` (negative ints as `i64`). A filter
+//! saved from Python therefore loads in Rust as, say, a `BloomFilter<str>`
+//! when it was filled with strings.
+
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Write};
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyInt, PyString};
+
+use bloomlib::{BloomError, BloomFilter};
+
+/// A key converted from a Python object.
+enum Key {
+    Str(String),
+    Bytes(Vec<u8>),
+    Int(u64),
+}
+
+// Delegates to the wrapped value, so a key hashes like the Rust type it
+// stands for.
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Key::Str(key) => key.as_str().hash(state),
+            Key::Bytes(key) => key.as_slice().hash(state),
+            Key::Int(key) => key.hash(state),
+        }
+    }
+}
+
+impl Key {
+    fn extract(key: &Bound<'_, PyAny>) -> PyResult<Key> {
+        if let Ok(key) = key.cast::<PyString>() {
+            Ok(Key::Str(key.to_cow()?.into_owned()))
+        } else if let Ok(key) = key.cast::<PyBytes>() {
+            Ok(Key::Bytes(key.as_bytes().to_vec()))
+        } else if key.is_instance_of::<PyInt>() {
+            // `i64` and `u64` hash the same bits, so negative ints take the
+            // two's complement.
+            match key.extract::<u64>() {
+                Ok(key) => Ok(Key::Int(key)),
+                Err(_) => Ok(Key::Int(key.extract::<i64>()? as u64)),
+            }
+        } else {
+            Err(PyTypeError::new_err(format!(
+                "keys must be str, bytes or int, not {}",
+                key.get_type().name()?
+            )))
+        }
+    }
+
+    fn extract_all(keys: &Bound<'_, PyAny>) -> PyResult<Vec<Key>> {
+        keys.try_iter()?.map(|key| Key::extract(&key?)).collect()
+    }
+}
+
+fn value_error(err: BloomError) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+/// A Bloom filter of `str`, `bytes` or `int` keys.
+///
+/// Create it for an expected number of items and either a target false
+/// positive rate or, with `hashes`, a fixed number of hash functions.
+#[pyclass(name = "BloomFilter", module = "bloomlib")]
+struct PyBloomFilter {
+    inner: BloomFilter<Key>,
+}
+
+#[pymethods]
+impl PyBloomFilter {
+    #[new]
+    #[pyo3(signature = (expected_items, fp_rate = 0.01, *, hashes = None))]
+    fn new(expected_items: usize, fp_rate: f64, hashes: Option<u32>) -> PyResult<Self> {
+        let inner = match hashes {
+            Some(hashes) => BloomFilter::try_new(expected_items, hashes),
+            None => BloomFilter::try_new(expected_items, fp_rate),
+        };
+        Ok(PyBloomFilter {
+            inner: inner.map_err(value_error)?,
+        })
+    }
+
+    /// Inserts a key.
+    fn insert(&mut self, key: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.inner.insert(&Key::extract(key)?);
+        Ok(())
+    }
+
+    /// Returns `True` if the key might have been inserted, and `False` if it
+    /// definitely was not.
+    fn contains(&self, key: &Bound<'_, PyAny>) -> PyResult<bool> {
+        Ok(self.inner.contains(&Key::extract(key)?))
+    }
+
+    fn __contains__(&self, key: &Bound<'_, PyAny>) -> PyResult<bool> {
+        self.contains(key)
+    }
+
+    /// Inserts every key of an iterable, releasing the GIL while hashing.
+    fn insert_many(&mut self, py: Python<'_>, keys: &Bound<'_, PyAny>) -> PyResult<()> {
+        let keys = Key::extract_all(keys)?;
+        let inner = &mut self.inner;
+        py.detach(|| {
+            for key in &keys {
+                inner.insert(key);
+            }
+        });
+        Ok(())
+    }
+
+    /// Checks every key of an iterable, releasing the GIL while hashing, and
+    /// returns one result per key.
+    fn contains_many(&self, py: Python<'_>, keys: &Bound<'_, PyAny>) -> PyResult<Vec<bool>> {
+        let keys = Key::extract_all(keys)?;
+        let inner = &self.inner;
+        Ok(py.detach(|| inner.contains_batch(&keys)))
+    }
+
+    /// Adds all keys of another filter with the same bit and hash count.
+    fn merge(slf: &Bound<'_, Self>, other: &Bound<'_, Self>) -> PyResult<()> {
+        if slf.is(other) {
+            return Ok(());
+        }
+        slf.borrow_mut()
+            .inner
+            .merge(&other.borrow().inner)
+            .map_err(value_error)
+    }
+
+    /// Removes all keys.
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// Returns the filter in the binary format `BloomFilter::from_bytes`
+    /// reads in Rust.
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.inner.to_bytes())
+    }
+
+    /// Loads a filter written by `to_bytes` (or by Rust's `to_bytes`).
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        let inner = BloomFilter::from_bytes(data).map_err(value_error)?;
+        Ok(PyBloomFilter { inner })
+    }
+
+    /// Writes the filter to a file, releasing the GIL.
+    fn save(&self, py: Python<'_>, path: std::path::PathBuf) -> PyResult<()> {
+        let inner = &self.inner;
+        py.detach(|| {
+            let mut writer = BufWriter::new(File::create(path)?);
+            inner.write_to(&mut writer)?;
+            writer.flush()
+        })?;
+        Ok(())
+    }
+
+    /// Reads a filter from a file written by `save`, releasing the GIL.
+    #[staticmethod]
+    fn load(py: Python<'_>, path: std::path::PathBuf) -> PyResult<Self> {
+        let inner = py.detach(|| BloomFilter::read_from(BufReader::new(File::open(path)?)))?;
+        Ok(PyBloomFilter { inner })
+    }
+
+    /// The number of bits (m).
+    #[getter]
+    fn bit_count(&self) -> u64 {
+        self.inner.bit_count()
+    }
+
+    /// The number of hash functions (k).
+    #[getter]
+    fn hash_count(&self) -> u32 {
+        self.inner.hash_count()
+    }
+
+    /// Returns the fraction of bits that are set.
+    fn fill_ratio(&self) -> f64 {
+        self.inner.fill_ratio()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "BloomFilter(bit_count={}, hash_count={})",
+            self.inner.bit_count(),
+            self.inner.hash_count()
+        )
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "bloomlib")]
+fn bloomlib_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyBloomFilter>()
+}
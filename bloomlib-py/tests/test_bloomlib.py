@@ -0,0 +1,70 @@
+import os
+import tempfile
+import unittest
+
+from bloomlib import BloomFilter
+
+
+class BloomFilterTest(unittest.TestCase):
+    def test_insert_and_contains(self):
+        bf = BloomFilter(1000, 0.01)
+        bf.insert("apple")
+        bf.insert(b"banana")
+        bf.insert(42)
+        bf.insert(-7)
+
+        self.assertIn("apple", bf)
+        self.assertTrue(bf.contains(b"banana"))
+        self.assertIn(42, bf)
+        self.assertIn(-7, bf)
+        # Strings and bytes hash differently, like `str` and `[u8]` in Rust.
+        self.assertNotIn(b"apple", bf)
+        self.assertNotIn("cherry", bf)
+
+        with self.assertRaises(TypeError):
+            bf.insert(1.5)
+
+    def test_bulk_operations(self):
+        bf = BloomFilter(10_000, hashes=7)
+        self.assertEqual(bf.hash_count, 7)
+        bf.insert_many(f"key-{i}" for i in range(10_000))
+        self.assertTrue(all(bf.contains_many(f"key-{i}" for i in range(10_000))))
+        misses = sum(bf.contains_many(f"other-{i}" for i in range(10_000)))
+        self.assertLess(misses, 200)
+
+    def test_merge(self):
+        left, right = BloomFilter(100), BloomFilter(100)
+        left.insert("apple")
+        right.insert("banana")
+        left.merge(right)
+        left.merge(left)
+        self.assertIn("banana", left)
+
+        with self.assertRaises(ValueError):
+            left.merge(BloomFilter(100_000))
+
+    def test_save_and_load(self):
+        bf = BloomFilter(1000)
+        bf.insert_many(["apple", "banana"])
+
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "fruit.bloom")
+            bf.save(path)
+            loaded = BloomFilter.load(path)
+        self.assertIn("banana", loaded)
+        self.assertEqual(loaded.bit_count, bf.bit_count)
+
+        copy = BloomFilter.from_bytes(bf.to_bytes())
+        self.assertEqual(copy.to_bytes(), bf.to_bytes())
+        with self.assertRaises(ValueError):
+            BloomFilter.from_bytes(b"not a filter")
+
+    def test_invalid_parameters(self):
+        with self.assertRaises(ValueError):
+            BloomFilter(0)
+        with self.assertRaises(ValueError):
+            BloomFilter(100, 1.5)
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,143 @@
+//! WebAssembly bindings for `bloomlib`.
+//!
+//! Build the JavaScript package with `wasm-pack build` from this directory.
+//!
+//! Filters are byte-for-byte compatible with native ones: `toBytes` and
+//! `fromBytes` use the format of `BloomFilter::to_bytes`, string keys hash
+//! like a Rust `str` and byte keys like a Rust `[u8]` on a 64-bit target. A
+//! filter built natively can be shipped to the browser and queried there.
+
+use std::hash::{Hash, Hasher};
+
+use wasm_bindgen::prelude::*;
+
+/// A key passed from JavaScript.
+enum Key {
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Key::Str(key) => key.as_str().hash(state),
+            // A `[u8]` hashes its length as a `usize` first, which is only 4
+            // bytes on wasm32. Writing it as a `u64` reproduces the hash of
+            // 64-bit targets.
+            Key::Bytes(key) => {
+                state.write_u64(key.len() as u64);
+                state.write(key);
+            }
+        }
+    }
+}
+
+/// A Bloom filter of string and byte-array keys.
+#[wasm_bindgen]
+pub struct BloomFilter {
+    inner: bloomlib::BloomFilter<Key>,
+}
+
+fn js_error(err: bloomlib::BloomError) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+#[wasm_bindgen]
+impl BloomFilter {
+    /// Creates a filter for `expectedItems` items at a target false positive
+    /// rate.
+    #[wasm_bindgen(constructor)]
+    pub fn new(expected_items: usize, fp_rate: f64) -> Result<BloomFilter, JsError> {
+        let inner = bloomlib::BloomFilter::try_new(expected_items, fp_rate).map_err(js_error)?;
+        Ok(BloomFilter { inner })
+    }
+
+    /// Creates a filter for `expectedItems` items with `hashCount` hash
+    /// functions.
+    #[wasm_bindgen(js_name = withHashes)]
+    pub fn with_hashes(expected_items: usize, hash_count: u32) -> Result<BloomFilter, JsError> {
+        let inner = bloomlib::BloomFilter::try_new(expected_items, hash_count).map_err(js_error)?;
+        Ok(BloomFilter { inner })
+    }
+
+    /// Loads a filter serialized by `toBytes` or by the native library.
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<BloomFilter, JsError> {
+        let inner = bloomlib::BloomFilter::from_bytes(bytes).map_err(js_error)?;
+        Ok(BloomFilter { inner })
+    }
+
+    /// Inserts a string.
+    pub fn insert(&mut self, key: String) {
+        self.inner.insert(&Key::Str(key));
+    }
+
+    /// Returns `true` if the string might have been inserted, and `false` if
+    /// it definitely was not.
+    pub fn contains(&self, key: String) -> bool {
+        self.inner.contains(&Key::Str(key))
+    }
+
+    /// Inserts the bytes of a `Uint8Array`.
+    #[wasm_bindgen(js_name = insertBytes)]
+    pub fn insert_bytes(&mut self, key: Vec<u8>) {
+        self.inner.insert(&Key::Bytes(key));
+    }
+
+    /// Returns `true` if the bytes might have been inserted, and `false` if
+    /// they definitely were not.
+    #[wasm_bindgen(js_name = containsBytes)]
+    pub fn contains_bytes(&self, key: Vec<u8>) -> bool {
+        self.inner.contains(&Key::Bytes(key))
+    }
+
+    /// Adds all keys of another filter with the same bit and hash count.
+    pub fn merge(&mut self, other: &BloomFilter) -> Result<(), JsError> {
+        self.inner.merge(&other.inner).map_err(js_error)
+    }
+
+    /// Removes all keys.
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// Serializes the filter into a `Uint8Array`.
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+
+    /// The number of bits (m), as a number rather than a `BigInt`.
+    #[wasm_bindgen(getter, js_name = bitCount)]
+    pub fn bit_count(&self) -> f64 {
+        self.inner.bit_count() as f64
+    }
+
+    /// The number of hash functions (k).
+    #[wasm_bindgen(getter, js_name = hashCount)]
+    pub fn hash_count(&self) -> u32 {
+        self.inner.hash_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compatible_with_native_filters() {
+        let mut native: bloomlib::BloomFilter<str> = bloomlib::BloomFilter::new(1000, 0.01);
+        native.insert("apple");
+
+        let mut filter = BloomFilter::from_bytes(&native.to_bytes()).unwrap();
+        assert!(filter.contains("apple".into()));
+        assert!(!filter.contains("pear".into()));
+
+        filter.insert_bytes(b"raw".to_vec());
+        assert!(filter.contains_bytes(b"raw".to_vec()));
+        let bytes: bloomlib::BloomFilter<[u8]> =
+            bloomlib::BloomFilter::from_bytes(&filter.to_bytes()).unwrap();
+        assert!(bytes.contains(b"raw"));
+        assert!(!bytes.contains(b"apple"));
+    }
+}
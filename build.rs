@@ -0,0 +1,48 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // The gRPC stubs are generated from Rust definitions that mirror
+    // `proto/bloom.proto`, so building needs no `protoc`.
+    #[cfg(feature = "grpc")]
+    grpc::generate();
+}
+
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    /// The methods of `proto/bloom.proto`: Rust name, route name, request
+    /// and response message.
+    const METHODS: [(&str, &str, &str, &str); 5] = [
+        ("check", "Check", "CheckRequest", "CheckResponse"),
+        ("insert", "Insert", "InsertRequest", "InsertResponse"),
+        (
+            "check_multi",
+            "CheckMulti",
+            "CheckMultiRequest",
+            "CheckMultiResponse",
+        ),
+        ("merge", "Merge", "MergeRequest", "MergeResponse"),
+        ("stats", "Stats", "StatsRequest", "StatsResponse"),
+    ];
+
+    pub fn generate() {
+        let service = METHODS
+            .iter()
+            .fold(
+                Service::builder().name("Bloom").package("bloomlib.v1"),
+                |service, (name, route, input, output)| {
+                    service.method(
+                        Method::builder()
+                            .name(name)
+                            .route_name(route)
+                            .input_type(format!("super::{}", input))
+                            .output_type(format!("super::{}", output))
+                            .codec_path("tonic_prost::ProstCodec")
+                            .build(),
+                    )
+                },
+            )
+            .build();
+        Builder::new().compile(&[service]);
+    }
+}
@@ -1,4 +1,4 @@
-use bloomlib::BloomFilter;
+use bloomlib::{stats, BloomFilter};
 
 fn main() {
     // Configuration
@@ -34,25 +34,23 @@ fn main() {
     println!("Found {}/10 known values (Should be 10)", present_count);
 
     // Check false positives
-    // We check numbers outside the inserted range.
-    let check_range_start = expected_elements;
-    let check_range_end = expected_elements + 10_000;
+    // `stats::simulate` builds a filter with the same configuration, fills it with random keys and
+    // probes it with keys that were never inserted.
+    let sample_size = 100_000;
     println!(
-        "Checking range {} to {} for false positives...",
-        check_range_start, check_range_end
+        "Probing {} never-inserted values for false positives...",
+        sample_size
     );
+    let estimate = stats::simulate(expected_elements, fp_rate, sample_size, 12345)
+        .expect("valid configuration");
+    let (low, high) = estimate.interval(0.95);
 
-    let mut fp_count = 0;
-    for i in check_range_start..check_range_end {
-        if filter.contains(&i) {
-            fp_count += 1;
-        }
-    }
-
-    let actual_fp_rate = fp_count as f64 / 10_000.0;
-    println!("False Positives found: {}", fp_count);
+    println!("False Positives found: {}", estimate.false_positives);
     println!(
-        "Actual FP Rate: {:.4} (Target: {})",
-        actual_fp_rate, actual_fp_rate
+        "Actual FP Rate: {:.4} (95% CI {:.4} to {:.4}, Target: {})",
+        estimate.rate(),
+        low,
+        high,
+        fp_rate
     );
 }
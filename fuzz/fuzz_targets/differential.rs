@@ -0,0 +1,111 @@
+//! Applies random operation sequences to a Bloom filter and a reference
+//! `HashSet`, checking that the filter never loses an item and never changes
+//! shape.
+
+#![no_main]
+
+use std::collections::HashSet;
+
+use bloomlib::{AlignedStore, AllocStore, BloomFilter};
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Insert(u32),
+    InsertKeys(Vec<u32>),
+    Contains(u32),
+    ContainsBatch(Vec<u32>),
+    Clear,
+    /// Merges in a filter with the same parameters holding these keys.
+    Merge(Vec<u32>),
+    /// Merges in a filter with different parameters, which must be rejected.
+    MergeMismatched,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    expected_items: u16,
+    /// The false positive rate is `2^-(fp_exponent % 20 + 1)`.
+    fp_exponent: u8,
+    ops: Vec<Op>,
+}
+
+/// Runs the operations against a filter stored in `B`.
+fn run<B: AllocStore + Clone>(input: &Input) {
+    let expected_items = input.expected_items.max(1) as usize;
+    let fp_rate = 0.5f64.powi(input.fp_exponent as i32 % 20 + 1);
+
+    let mut filter: BloomFilter<u32, B> = BloomFilter::from_params(expected_items, fp_rate);
+    let mut reference = HashSet::new();
+    let geometry = (
+        filter.bit_count(),
+        filter.hash_count(),
+        filter.memory_usage_bytes(),
+    );
+
+    for op in &input.ops {
+        match op {
+            Op::Insert(key) => {
+                filter.insert(key);
+                reference.insert(*key);
+            }
+            Op::InsertKeys(keys) => {
+                filter.insert_keys(keys);
+                reference.extend(keys);
+            }
+            Op::Contains(key) => {
+                assert!(
+                    filter.contains(key) || !reference.contains(key),
+                    "false negative: {}",
+                    key
+                );
+            }
+            Op::ContainsBatch(keys) => {
+                let single: Vec<bool> = keys.iter().map(|key| filter.contains(key)).collect();
+                assert_eq!(filter.contains_batch(keys), single);
+                assert_eq!(filter.contains_keys(keys), single);
+            }
+            Op::Clear => {
+                filter.clear();
+                reference.clear();
+            }
+            Op::Merge(keys) => {
+                let mut other: BloomFilter<u32, B> =
+                    BloomFilter::from_params(expected_items, fp_rate);
+                other.insert_keys(keys);
+                filter.merge(&other).unwrap();
+                reference.extend(keys);
+            }
+            Op::MergeMismatched => {
+                let other: BloomFilter<u32, B> =
+                    BloomFilter::from_params(expected_items + 1000, fp_rate);
+                let before = filter.clone();
+                assert!(filter.merge(&other).is_err());
+                let probe: Vec<u32> = reference.iter().copied().chain(0..64).collect();
+                assert_eq!(filter.contains_keys(&probe), before.contains_keys(&probe));
+            }
+        }
+
+        let current = (
+            filter.bit_count(),
+            filter.hash_count(),
+            filter.memory_usage_bytes(),
+        );
+        assert_eq!(current, geometry, "geometry changed");
+    }
+
+    for key in &reference {
+        assert!(filter.contains(key), "false negative: {}", key);
+    }
+    let frozen = filter.freeze();
+    for key in &reference {
+        assert!(frozen.contains(key), "false negative after freeze: {}", key);
+    }
+}
+
+fuzz_target!(|input: Input| {
+    run::<Vec<u64>>(&input);
+    run::<Vec<u8>>(&input);
+    run::<AlignedStore>(&input);
+});
@@ -0,0 +1,115 @@
+use std::alloc::Allocator;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::counters::OpCounters;
+use crate::storage::zeroed_vec_in;
+use crate::{BloomError, BloomFilter, BloomFilterBuilder, FilterParams, Word};
+
+impl<T: ?Sized + Hash, W: Word, A: Allocator> BloomFilter<T, Vec<W, A>> {
+    /// Creates a new Bloom Filter whose bit array is allocated in `alloc`.
+    ///
+    /// Requires the `allocator_api` feature (and a nightly compiler). Use it
+    /// to place the bits in an arena, a NUMA-pinned region, or an allocator
+    /// that accounts for memory against a budget. Allocators that implement
+    /// `Default` also work with [`BloomFilter::from_params`].
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn from_params_in(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+        alloc: A,
+    ) -> Self {
+        Self::try_from_params_in(expected_items, params, alloc)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a new Bloom Filter in `alloc`, returning an error instead of
+    /// panicking. See [`BloomFilter::try_new`].
+    pub fn try_from_params_in(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+        alloc: A,
+    ) -> Result<Self, BloomError> {
+        BloomFilterBuilder::<T, Vec<W, A>>::new(expected_items, params).try_build_in(alloc)
+    }
+
+    /// Returns the allocator holding the bit array.
+    pub fn allocator(&self) -> &A {
+        self.bit_vec.allocator()
+    }
+}
+
+impl<T: ?Sized + Hash, W: Word, A: Allocator> BloomFilterBuilder<T, Vec<W, A>> {
+    /// Builds the filter with its bit array allocated in `alloc`.
+    ///
+    /// Requires the `allocator_api` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn build_in(self, alloc: A) -> BloomFilter<T, Vec<W, A>> {
+        self.try_build_in(alloc)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Builds the filter in `alloc`, returning an error instead of panicking.
+    pub fn try_build_in(self, alloc: A) -> Result<BloomFilter<T, Vec<W, A>>, BloomError> {
+        let geometry = self.geometry()?;
+
+        Ok(BloomFilter {
+            bit_vec: zeroed_vec_in(geometry.word_count, alloc),
+            bit_count: geometry.bit_count,
+            hash_fn_count: geometry.hash_count,
+            counters: OpCounters::default(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{AllocError, Global, Layout};
+    use std::ptr::NonNull;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// An allocator that charges every live byte to a budget counter.
+    #[derive(Clone, Copy)]
+    struct Tracked<'a>(&'a AtomicUsize);
+
+    unsafe impl Allocator for Tracked<'_> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.fetch_add(layout.size(), Ordering::Relaxed);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.0.fetch_sub(layout.size(), Ordering::Relaxed);
+            unsafe { Global.deallocate(ptr, layout) }
+        }
+    }
+
+    #[test]
+    fn test_filter_memory_is_charged_to_allocator() {
+        let budget = AtomicUsize::new(0);
+        let mut bf = BloomFilter::<str, Vec<u64, _>>::from_params_in(1000, 0.01, Tracked(&budget));
+        assert_eq!(budget.load(Ordering::Relaxed), bf.memory_usage_bytes());
+
+        bf.insert("seen");
+        assert!(bf.contains("seen"));
+        assert!(!bf.contains("unseen"));
+
+        drop(bf);
+        assert_eq!(budget.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_default_allocator_store() {
+        let bf = BloomFilter::<u64, Vec<u32, Global>>::from_params(100, 0.01);
+        let built = BloomFilterBuilder::<u64, Vec<u32>>::new(100, 0.01).build_in(Global);
+        assert_eq!(bf.bit_vec, built.bit_vec);
+    }
+}
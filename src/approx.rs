@@ -0,0 +1,118 @@
+use std::hash::Hash;
+
+use crate::{BitStore, BloomFilter};
+
+/// The operations common to the crate's approximate membership structures,
+/// so that application code can be generic over the one it uses.
+///
+/// Implemented by [`BloomFilter`], [`AtomicBloomFilter`](crate::AtomicBloomFilter),
+/// [`CountingBloomFilter`](crate::CountingBloomFilter),
+/// [`ExpiringBloomFilter`](crate::ExpiringBloomFilter),
+/// [`KeyedBloomFilter`](crate::KeyedBloomFilter),
+/// [`ScalableBloomFilter`](crate::ScalableBloomFilter) and
+/// [`ShardedBloomFilter`](crate::ShardedBloomFilter). The read-only
+/// [`FrozenBloomFilter`](crate::FrozenBloomFilter) has no `insert`, so it
+/// does not implement it.
+///
+/// Types whose inserts take `&self` implement `insert` through `&mut self`
+/// here too; call their own methods to insert from many threads.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{ApproximateSet, BloomFilter, ScalableBloomFilter};
+///
+/// fn load(set: &mut impl ApproximateSet<str>, words: &[&str]) -> f64 {
+///     for word in words {
+///         set.insert(word);
+///     }
+///     set.fp_estimate()
+/// }
+///
+/// let words = ["apple", "banana", "cherry"];
+/// let mut fixed: BloomFilter<str> = BloomFilter::new(100, 0.01);
+/// let mut growing: ScalableBloomFilter<str> = ScalableBloomFilter::new(100, 0.01);
+/// assert!(load(&mut fixed, &words) < 0.01);
+/// assert!(load(&mut growing, &words) < 0.01);
+/// assert!(ApproximateSet::contains(&fixed, "apple"));
+/// assert!((fixed.estimated_len() - 3.0).abs() < 0.5);
+/// ```
+pub trait ApproximateSet<T: ?Sized> {
+    /// Inserts an item.
+    fn insert(&mut self, item: &T);
+
+    /// Checks if an item might be in the set: `false` means it is definitely
+    /// not.
+    fn contains(&self, item: &T) -> bool;
+
+    /// Estimates the number of distinct items inserted.
+    fn estimated_len(&self) -> f64;
+
+    /// Estimates the probability that [`contains`](Self::contains) reports
+    /// an item that was never inserted, given the items inserted so far.
+    fn fp_estimate(&self) -> f64;
+}
+
+/// Estimates the number of distinct items in a filter of `bit_count` bits
+/// and `hash_count` hashes with a fraction `fill_ratio` of them set, as
+/// `-(m / k) * ln(1 - fill_ratio)`.
+pub(crate) fn estimated_items(fill_ratio: f64, bit_count: u64, hash_count: u32) -> f64 {
+    -(bit_count as f64 / hash_count as f64) * (1.0 - fill_ratio).ln()
+}
+
+impl<T: ?Sized + Hash, B: BitStore> ApproximateSet<T> for BloomFilter<T, B> {
+    fn insert(&mut self, item: &T) {
+        BloomFilter::insert(self, item);
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        BloomFilter::contains(self, item)
+    }
+
+    fn estimated_len(&self) -> f64 {
+        self.estimated_items()
+    }
+
+    fn fp_estimate(&self) -> f64 {
+        self.fill_ratio().powi(self.hash_fn_count as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        AtomicBloomFilter, CountingBloomFilter, ExpiringBloomFilter, FilterKey, KeyedBloomFilter,
+        ScalableBloomFilter, ShardedBloomFilter,
+    };
+
+    /// Inserts 1000 items and checks the set's estimates against a filter
+    /// sized for them.
+    fn check(mut set: impl ApproximateSet<u64>, name: &str) {
+        assert_eq!(set.estimated_len(), 0.0, "{}", name);
+        assert_eq!(set.fp_estimate(), 0.0, "{}", name);
+        for i in 0..1000 {
+            set.insert(&i);
+        }
+        assert!((0..1000).all(|i| set.contains(&i)), "{}", name);
+        let len = set.estimated_len();
+        assert!((len - 1000.0).abs() < 50.0, "{}: {} items", name, len);
+        let fp = set.fp_estimate();
+        assert!(fp > 0.002 && fp < 0.02, "{}: fp {}", name, fp);
+    }
+
+    #[test]
+    fn test_implementations_agree() {
+        check(BloomFilter::<u64>::new(1000, 0.01), "bloom");
+        check(AtomicBloomFilter::new(1000, 0.01), "atomic");
+        check(CountingBloomFilter::new(1000, 0.01), "counting");
+        let ttl = Duration::from_secs(3600);
+        check(ExpiringBloomFilter::new(1000, 0.01, ttl), "expiring");
+        let key = FilterKey::from_bytes([7; 16]);
+        check(KeyedBloomFilter::new(key, 1000, 0.01), "keyed");
+        check(ScalableBloomFilter::new(300, 0.01), "scalable");
+        check(ShardedBloomFilter::new(1000, 0.01, 4), "sharded");
+    }
+}
@@ -0,0 +1,323 @@
+use std::alloc::{self, Layout};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::approx::{self, ApproximateSet};
+use crate::counters::OpCounters;
+use crate::hashing;
+use crate::params::Geometry;
+use crate::{BloomError, BloomFilter, FilterParams};
+
+/// A Bloom Filter that can be shared between threads without locking.
+///
+/// The bit array is a slice of `AtomicU64`, so [`insert`](Self::insert) and
+/// [`contains`](Self::contains) take `&self` and the filter can be used
+/// behind an `Arc` from many threads at once. Bits are only ever set, so
+/// relaxed `fetch_or`/`load` operations are sufficient: an insert that
+/// happens-before a lookup (e.g. via a channel or `join`) is always seen.
+///
+/// Sizing, hashing and probing are identical to [`BloomFilter`], and the two
+/// types convert into each other without rehashing.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use bloomlib::AtomicBloomFilter;
+///
+/// let filter = Arc::new(AtomicBloomFilter::new(1000, 0.01));
+///
+/// let handles: Vec<_> = (0..4u64)
+///     .map(|t| {
+///         let filter = Arc::clone(&filter);
+///         thread::spawn(move || {
+///             for i in 0..100 {
+///                 filter.insert(&(t * 100 + i));
+///             }
+///         })
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+///
+/// assert!(filter.contains(&42));
+/// ```
+#[derive(Debug)]
+pub struct AtomicBloomFilter<T: ?Sized> {
+    /// The bit array stored as atomic u64 words.
+    bit_vec: Box<[AtomicU64]>,
+    /// The total number of bits in the filter (m).
+    bit_count: u64,
+    /// The number of hash functions to use (k).
+    hash_fn_count: u32,
+    /// Phantom data to hold the type information without affecting `Send`/`Sync`.
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: ?Sized + Hash> AtomicBloomFilter<T> {
+    /// Creates a new concurrent Bloom Filter optimized for the given expected
+    /// item count and configuration. See [`BloomFilter::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn new(expected_items: usize, params: impl Into<FilterParams>) -> Self {
+        Self::try_new(expected_items, params).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a new concurrent Bloom Filter, returning an error instead of
+    /// panicking. See [`BloomFilter::try_new`].
+    pub fn try_new(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<Self, BloomError> {
+        let geometry = Geometry::optimal::<u64>(expected_items, params.into())?;
+
+        Ok(AtomicBloomFilter {
+            bit_vec: zeroed_words(geometry.word_count),
+            bit_count: geometry.bit_count,
+            hash_fn_count: geometry.hash_count,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Inserts an item into the Bloom Filter.
+    pub fn insert(&self, item: &T) {
+        let (h1, h2) = hashing::hash_pair(item);
+        self.insert_hashed(h1, h2);
+    }
+
+    /// Checks if an item might be in the Bloom Filter.
+    ///
+    /// Returns `true` if the item might be present (with a probability of false positive).
+    /// Returns `false` if the item is definitely not present.
+    pub fn contains(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair(item);
+        self.contains_hashed(h1, h2)
+    }
+
+    /// Sets the k bits for an already hashed item.
+    pub(crate) fn insert_hashed(&self, h1: u64, h2: u64) {
+        insert_hashed(&self.bit_vec, self.bit_count, self.hash_fn_count, h1, h2);
+    }
+
+    /// Checks the k bits of an already hashed item.
+    pub(crate) fn contains_hashed(&self, h1: u64, h2: u64) -> bool {
+        for i in 0..self.hash_fn_count {
+            let (vec_index, mask) = self.get_bit(h1, h2, i);
+            if (self.bit_vec[vec_index].load(Ordering::Relaxed) & mask) == 0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Clears all bits in the filter.
+    ///
+    /// Inserts running concurrently with `clear` may or may not be retained.
+    pub fn clear(&self) {
+        for slot in self.bit_vec.iter() {
+            slot.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the fraction of bits set. See
+    /// [`BloomFilter::fill_ratio`].
+    pub fn fill_ratio(&self) -> f64 {
+        let set_bits: u64 = self
+            .bit_vec
+            .iter()
+            .map(|slot| slot.load(Ordering::Relaxed).count_ones() as u64)
+            .sum();
+        set_bits as f64 / self.bit_count as f64
+    }
+
+    /// Estimates the number of distinct items inserted so far. See
+    /// [`BloomFilter::estimated_items`].
+    pub fn estimated_items(&self) -> f64 {
+        approx::estimated_items(self.fill_ratio(), self.bit_count, self.hash_fn_count)
+    }
+
+    /// Computes the vector index and bit mask for the i-th hash position.
+    #[inline]
+    fn get_bit(&self, h1: u64, h2: u64, i: u32) -> (usize, u64) {
+        let bit_index = hashing::bit_index(h1, h2, i, self.bit_count);
+        ((bit_index / 64) as usize, 1u64 << (bit_index % 64))
+    }
+
+    /// Returns the approximate memory usage of the bit vector in bytes.
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.bit_vec.len() * 8
+    }
+
+    /// Returns the number of hash functions (k) being used.
+    pub fn hash_count(&self) -> u32 {
+        self.hash_fn_count
+    }
+}
+
+impl<T: ?Sized> AtomicBloomFilter<T> {
+    /// Sets the bits of several already hashed items with one
+    /// read-modify-write per distinct word they touch. `scratch` holds the
+    /// positions while they are merged and is left empty.
+    pub(crate) fn insert_hashed_batch(
+        &self,
+        hashes: &[(u64, u64)],
+        scratch: &mut Vec<(usize, u64)>,
+    ) {
+        scratch.clear();
+        for &(h1, h2) in hashes {
+            scratch.extend((0..self.hash_fn_count).map(|i| {
+                let bit_index = hashing::bit_index(h1, h2, i, self.bit_count);
+                ((bit_index / 64) as usize, 1u64 << (bit_index % 64))
+            }));
+        }
+        scratch.sort_unstable_by_key(|&(vec_index, _)| vec_index);
+
+        let mut positions = scratch.drain(..).peekable();
+        while let Some((vec_index, mut mask)) = positions.next() {
+            while let Some((_, next)) = positions.next_if(|&(next, _)| next == vec_index) {
+                mask |= next;
+            }
+            let word = &self.bit_vec[vec_index];
+            if word.load(Ordering::Relaxed) & mask != mask {
+                word.fetch_or(mask, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Sets the k bits for an already hashed item in the atomic words of a
+/// filter of `bit_count` bits and `hash_count` hashes.
+pub(crate) fn insert_hashed(
+    words: &[AtomicU64],
+    bit_count: u64,
+    hash_count: u32,
+    h1: u64,
+    h2: u64,
+) {
+    for i in 0..hash_count {
+        let bit_index = hashing::bit_index(h1, h2, i, bit_count);
+        let (word, mask) = (&words[(bit_index / 64) as usize], 1u64 << (bit_index % 64));
+        // Skip the read-modify-write if the bit is already set; this keeps
+        // the cache line shared on hot, saturated words.
+        if word.load(Ordering::Relaxed) & mask == 0 {
+            word.fetch_or(mask, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Allocates `count` zeroed atomic words.
+///
+/// The memory comes from `alloc_zeroed`, which for large filters maps fresh
+/// zero pages instead of writing them. Pages are then first touched by
+/// whichever thread sets bits in them, which keeps them local to that thread's
+/// NUMA node and avoids paying for zeroing up front.
+fn zeroed_words(count: usize) -> Box<[AtomicU64]> {
+    if count == 0 {
+        return Box::new([]);
+    }
+    let layout = Layout::array::<AtomicU64>(count).expect("word count validated by Geometry");
+    // SAFETY: the layout has a non-zero size, an all-zero bit pattern is a
+    // valid `AtomicU64`, and the allocation is handed to a `Box` with the
+    // same layout, which frees it with the global allocator.
+    unsafe {
+        let ptr = alloc::alloc_zeroed(layout) as *mut AtomicU64;
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, count))
+    }
+}
+
+impl<T: ?Sized + Hash> ApproximateSet<T> for AtomicBloomFilter<T> {
+    fn insert(&mut self, item: &T) {
+        AtomicBloomFilter::insert(self, item);
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        AtomicBloomFilter::contains(self, item)
+    }
+
+    fn estimated_len(&self) -> f64 {
+        self.estimated_items()
+    }
+
+    fn fp_estimate(&self) -> f64 {
+        self.fill_ratio().powi(self.hash_fn_count as i32)
+    }
+}
+
+impl<T: ?Sized> From<BloomFilter<T>> for AtomicBloomFilter<T> {
+    fn from(filter: BloomFilter<T>) -> Self {
+        AtomicBloomFilter {
+            bit_vec: filter.bit_vec.into_iter().map(AtomicU64::new).collect(),
+            bit_count: filter.bit_count,
+            hash_fn_count: filter.hash_fn_count,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> From<AtomicBloomFilter<T>> for BloomFilter<T> {
+    fn from(filter: AtomicBloomFilter<T>) -> Self {
+        BloomFilter {
+            bit_vec: filter
+                .bit_vec
+                .into_vec()
+                .into_iter()
+                .map(AtomicU64::into_inner)
+                .collect(),
+            bit_count: filter.bit_count,
+            hash_fn_count: filter.hash_fn_count,
+            counters: OpCounters::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_insert_and_contains() {
+        let filter = AtomicBloomFilter::new(4000, 0.01);
+        thread::scope(|scope| {
+            for t in 0..4u64 {
+                let filter = &filter;
+                scope.spawn(move || {
+                    for i in 0..1000 {
+                        filter.insert(&(t * 1000 + i));
+                    }
+                });
+            }
+        });
+
+        for i in 0..4000u64 {
+            assert!(filter.contains(&i));
+        }
+        filter.clear();
+        assert!(!filter.contains(&0));
+    }
+
+    #[test]
+    fn test_conversion_preserves_bits() {
+        let mut bf = BloomFilter::new(100, 0.01);
+        bf.insert("seen");
+
+        let atomic = AtomicBloomFilter::from(bf.clone());
+        assert_eq!(atomic.hash_count(), bf.hash_count());
+        assert!(atomic.contains("seen"));
+        atomic.insert("also seen");
+
+        let back = BloomFilter::from(atomic);
+        assert!(back.contains("seen"));
+        assert!(back.contains("also seen"));
+        assert_eq!(back.bit_vec.len(), bf.bit_vec.len());
+    }
+}
@@ -0,0 +1,119 @@
+use std::hint::black_box;
+use std::time::Instant;
+
+use bloomlib::params;
+use bloomlib::{ApproximateSet, BloomFilter, FilterKey, KeyedBloomFilter};
+
+/// Time inserts and lookups on this machine, for capacity planning.
+///
+/// Inserts `--items` integer keys into a filter sized for them, then looks
+/// up every inserted key (hits) and as many keys that were not inserted
+/// (misses). Misses also give the observed false positive rate.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// The number of keys to insert.
+    #[arg(short = 'n', long, default_value_t = 1_000_000)]
+    items: usize,
+    /// The target false positive rate.
+    #[arg(long, default_value_t = 0.01)]
+    fp: f64,
+    /// The hash function.
+    #[arg(long, value_enum, default_value_t = Hasher::Default)]
+    hasher: Hasher,
+    /// Print the results as a JSON object.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum Hasher {
+    /// SipHash-1-3, as `BloomFilter` uses.
+    Default,
+    /// SipHash-2-4 under a secret key, as `KeyedBloomFilter` uses.
+    Keyed,
+}
+
+/// The measurements: nanoseconds per operation, and the fraction of misses
+/// reported as present.
+struct Timings {
+    insert_ns: f64,
+    hit_ns: f64,
+    miss_ns: f64,
+    false_positive_rate: f64,
+}
+
+pub fn run(args: Args) -> crate::Result {
+    // Both filters are sized like `BloomFilter::new`.
+    let sizing = params::size(args.items, args.fp)?;
+    let timings = match args.hasher {
+        Hasher::Default => measure(BloomFilter::<u64>::new(args.items, args.fp), args.items),
+        Hasher::Keyed => {
+            let key = FilterKey::from_bytes([0x5a; 16]);
+            measure(KeyedBloomFilter::new(key, args.items, args.fp), args.items)
+        }
+    };
+
+    let name = match args.hasher {
+        Hasher::Default => "default",
+        Hasher::Keyed => "keyed",
+    };
+    if args.json {
+        println!(
+            "{{\"items\": {}, \"fp\": {}, \"hasher\": \"{}\", \"bits\": {}, \"hashes\": {}, \
+             \"memory_bytes\": {}, \"insert_ns\": {:.2}, \"hit_ns\": {:.2}, \"miss_ns\": {:.2}, \
+             \"observed_fp\": {:.6}}}",
+            args.items,
+            args.fp,
+            name,
+            sizing.bit_count,
+            sizing.hash_count,
+            sizing.memory_bytes(),
+            timings.insert_ns,
+            timings.hit_ns,
+            timings.miss_ns,
+            timings.false_positive_rate
+        );
+    } else {
+        println!("hasher:              {}", name);
+        println!("bits:                {}", sizing.bit_count);
+        println!("hashes:              {}", sizing.hash_count);
+        println!("memory:              {} bytes", sizing.memory_bytes());
+        println!("insert:              {:.2} ns/op", timings.insert_ns);
+        println!("hit:                 {:.2} ns/op", timings.hit_ns);
+        println!("miss:                {:.2} ns/op", timings.miss_ns);
+        println!("observed fp rate:    {:.6}", timings.false_positive_rate);
+    }
+    Ok(())
+}
+
+/// Runs the three passes over an empty `filter`.
+fn measure(mut filter: impl ApproximateSet<u64>, items: usize) -> Timings {
+    let items = items as u64;
+    let per_op = |start: Instant| start.elapsed().as_nanos() as f64 / items as f64;
+
+    let start = Instant::now();
+    for key in 0..items {
+        filter.insert(black_box(&key));
+    }
+    let insert_ns = per_op(start);
+
+    let start = Instant::now();
+    let hits = (0..items)
+        .filter(|key| filter.contains(black_box(key)))
+        .count();
+    let hit_ns = per_op(start);
+    assert_eq!(hits as u64, items, "a Bloom filter has no false negatives");
+
+    let start = Instant::now();
+    let false_positives = (items..2 * items)
+        .filter(|key| filter.contains(black_box(key)))
+        .count();
+    let miss_ns = per_op(start);
+
+    Timings {
+        insert_ns,
+        hit_ns,
+        miss_ns,
+        false_positive_rate: false_positives as f64 / items as f64,
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use bloomlib::{BloomFilter, ExternalBuilder, FilterParams};
+
+/// Build a filter file from newline-delimited keys.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// The file to read keys from, one per line; stdin if absent or `-`.
+    input: Option<PathBuf>,
+    /// The filter file to write.
+    #[arg(short, long)]
+    output: PathBuf,
+    /// The number of distinct keys to size the filter for [default: the
+    /// number of input lines].
+    #[arg(short = 'n', long)]
+    items: Option<usize>,
+    /// The target false positive rate.
+    #[arg(long, default_value_t = 0.01)]
+    fp: f64,
+    /// A fixed number of hash functions, instead of a target rate.
+    #[arg(long, conflicts_with = "fp")]
+    hashes: Option<u32>,
+    /// Build in parts of at most this many bytes, spilling the keys' bits
+    /// to disk next to the output, for filters larger than memory.
+    #[arg(long, requires = "items")]
+    max_memory: Option<u64>,
+}
+
+pub fn run(args: Args) -> crate::Result {
+    let params = match args.hashes {
+        Some(hashes) => FilterParams::HashCount(hashes),
+        None => FilterParams::FalsePositiveRate(args.fp),
+    };
+    if let Some(max_memory) = args.max_memory {
+        return build_external(&args, params, max_memory);
+    }
+    let mut lines = crate::open_input(args.input.as_deref())?.lines();
+
+    let (filter, count) = match args.items {
+        Some(items) => {
+            let mut filter: BloomFilter<str> = BloomFilter::try_new(items, params)?;
+            let mut count = 0;
+            for line in lines {
+                filter.insert(&line?);
+                count += 1;
+            }
+            (filter, count)
+        }
+        // Without a size, the keys are buffered to count them first.
+        None => {
+            let keys = lines.by_ref().collect::<Result<Vec<String>, _>>()?;
+            let mut filter: BloomFilter<str> = BloomFilter::try_new(keys.len().max(1), params)?;
+            for key in &keys {
+                filter.insert(key);
+            }
+            (filter, keys.len())
+        }
+    };
+
+    crate::save_filter(&filter, &args.output)?;
+    eprintln!(
+        "{}: {} keys, {} bits, {} hashes",
+        args.output.display(),
+        count,
+        filter.bit_count(),
+        filter.hash_count()
+    );
+    Ok(())
+}
+
+/// Builds the filter with an `ExternalBuilder`, streaming it to the output.
+fn build_external(args: &Args, params: FilterParams, max_memory: u64) -> crate::Result {
+    let items = args.items.expect("clap requires --items");
+    let spill_dir = match args.output.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut builder: ExternalBuilder<str> =
+        ExternalBuilder::new(items, params, max_memory, spill_dir)?;
+    let count = builder.insert_lines(crate::open_input(args.input.as_deref())?)?;
+    let part_count = builder.part_count();
+
+    let file =
+        File::create(&args.output).map_err(|err| format!("{}: {}", args.output.display(), err))?;
+    let mut writer = BufWriter::new(file);
+    builder.finish_to(&mut writer)?;
+    writer.flush()?;
+    eprintln!(
+        "{}: {} keys, built in {} parts",
+        args.output.display(),
+        count,
+        part_count
+    );
+    Ok(())
+}
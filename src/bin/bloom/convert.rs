@@ -0,0 +1,83 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use bloomlib::{BloomFilter, FilterPart};
+
+/// Split a filter file into part files, or recombine part files into a
+/// filter file.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// A filter file to split, or the part files to recombine, in any order.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// The filter file to write, or with --parts the prefix of the part
+    /// files, which are named `<output>.<index>`.
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Split the filter into this many parts.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    parts: Option<u32>,
+}
+
+pub fn run(args: Args) -> crate::Result {
+    match args.parts {
+        Some(part_count) => split(&args.inputs, &args.output, part_count),
+        None => recombine(&args.inputs, &args.output),
+    }
+}
+
+fn split(inputs: &[PathBuf], output: &Path, part_count: u32) -> crate::Result {
+    let [input] = inputs else {
+        return Err("--parts splits a single filter file".into());
+    };
+    let filter = crate::load_filter(input)?;
+    for part in filter.split_into(part_count) {
+        let path = part_path(output, part.index());
+        let file = File::create(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&part.to_bytes())?;
+        writer.flush()?;
+    }
+    eprintln!(
+        "{}: split into {} parts, {}.0 to {}.{}",
+        input.display(),
+        part_count,
+        output.display(),
+        output.display(),
+        part_count - 1
+    );
+    Ok(())
+}
+
+fn recombine(inputs: &[PathBuf], output: &Path) -> crate::Result {
+    let parts = inputs
+        .iter()
+        .map(|path| load_part(path))
+        .collect::<crate::Result<Vec<_>>>()?;
+    let filter: BloomFilter<str> = BloomFilter::recombine(parts)?;
+    crate::save_filter(&filter, output)?;
+    eprintln!(
+        "{}: recombined {} parts, fill ratio {:.4}",
+        output.display(),
+        inputs.len(),
+        filter.fill_ratio()
+    );
+    Ok(())
+}
+
+/// Returns the path of part `index` written with `prefix`.
+fn part_path(prefix: &Path, index: u32) -> PathBuf {
+    let mut path = prefix.as_os_str().to_owned();
+    path.push(format!(".{}", index));
+    path.into()
+}
+
+/// Reads a part file.
+fn load_part(path: &Path) -> crate::Result<FilterPart<str>> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    FilterPart::from_bytes(&bytes).map_err(|err| format!("{}: {}", path.display(), err).into())
+}
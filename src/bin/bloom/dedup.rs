@@ -0,0 +1,55 @@
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::PathBuf;
+
+use bloomlib::BloomFilter;
+
+/// Print each input line the first time it is seen, in input order.
+///
+/// Unlike `sort -u`, memory is fixed by the filter size however long the
+/// stream. In exchange, a new line is dropped as a false duplicate with the
+/// filter's false positive rate.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// The file to read lines from; stdin if absent or `-`.
+    input: Option<PathBuf>,
+    /// The number of distinct lines to size the filter for.
+    #[arg(short = 'n', long, default_value_t = 10_000_000)]
+    items: usize,
+    /// The false positive rate: the fraction of new lines wrongly dropped.
+    #[arg(long, default_value_t = 0.0001)]
+    fp: f64,
+    /// A filter file to resume from, if it exists, and to save to at the
+    /// end, so lines seen in earlier runs stay deduplicated.
+    #[arg(long)]
+    filter: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> crate::Result {
+    let mut filter: BloomFilter<str> = match &args.filter {
+        Some(path) if path.exists() => crate::load_filter(path)?,
+        _ => BloomFilter::try_new(args.items, args.fp)?,
+    };
+    let input = crate::open_input(args.input.as_deref())?;
+    let mut out = BufWriter::new(io::stdout().lock());
+
+    for line in input.lines() {
+        let line = line?;
+        if filter.check_and_insert(&line) {
+            writeln!(out, "{}", line)?;
+        }
+    }
+    out.flush()?;
+
+    if let Some(path) = &args.filter {
+        crate::save_filter(&filter, path)?;
+    }
+    let rate = filter.expected_fp_after(0);
+    if rate > 2.0 * args.fp {
+        eprintln!(
+            "bloom: warning: the filter is over capacity, so about {:.2}% of new lines \
+             are now dropped; raise --items",
+            rate * 100.0
+        );
+    }
+    Ok(())
+}
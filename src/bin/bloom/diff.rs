@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+
+/// Compare two filter files, e.g. replicas that should hold the same keys.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// The first filter file.
+    first: PathBuf,
+    /// The second filter file.
+    second: PathBuf,
+}
+
+pub fn run(args: Args) -> crate::Result {
+    let first = crate::load_filter(&args.first)?;
+    let second = crate::load_filter(&args.second)?;
+
+    println!(
+        "bits:                {} / {}",
+        first.bit_count(),
+        second.bit_count()
+    );
+    println!(
+        "hashes:              {} / {}",
+        first.hash_count(),
+        second.hash_count()
+    );
+    let Ok(diff) = first.diff(&second) else {
+        println!("compatible:          no");
+        return Ok(());
+    };
+    println!("compatible:          yes");
+    println!(
+        "differing words:     {} of {}",
+        diff.differing_words, diff.word_count
+    );
+    println!(
+        "bits only in each:   {} / {}",
+        diff.bits_only_in_self, diff.bits_only_in_other
+    );
+    println!(
+        "estimated items:     {:.0} / {:.0}",
+        diff.estimated_items_self, diff.estimated_items_other
+    );
+    println!("diverging items:     {:.0}", diff.diverging_items());
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use bloomlib::params::{self, Sizing};
+use bloomlib::FilterParams;
+
+/// Print the size of a filter for an item count and a false positive rate,
+/// or the rate achievable in a given amount of memory.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// The number of distinct keys.
+    #[arg(short = 'n', long)]
+    items: usize,
+    /// The target false positive rate.
+    #[arg(long, default_value_t = 0.01)]
+    fp: f64,
+    /// A fixed number of hash functions, instead of a target rate.
+    #[arg(long, conflicts_with = "fp")]
+    hashes: Option<u32>,
+    /// A memory budget, in bytes or with a K, M or G (binary) suffix; reports
+    /// the rate achievable in it instead.
+    #[arg(long, value_parser = parse_bytes, conflicts_with_all = ["fp", "hashes"])]
+    memory: Option<u64>,
+}
+
+/// Parses a byte count such as `4096`, `512K`, `16MiB` or `2G`.
+fn parse_bytes(arg: &str) -> Result<u64, String> {
+    let digits = arg.trim_end_matches(|ch: char| ch.is_ascii_alphabetic());
+    let shift = match &arg[digits.len()..] {
+        "" | "B" => 0,
+        "K" | "KB" | "KiB" => 10,
+        "M" | "MB" | "MiB" => 20,
+        "G" | "GB" | "GiB" => 30,
+        "T" | "TB" | "TiB" => 40,
+        unit => return Err(format!("unknown unit {:?}", unit)),
+    };
+    let value: u64 = digits.parse().map_err(|err| format!("{}", err))?;
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("{} is too large", arg))
+}
+
+pub fn run(args: Args) -> crate::Result {
+    let sizing = match (args.memory, args.hashes) {
+        (Some(memory), _) => params::size_for_memory(args.items, memory)?,
+        (None, Some(hashes)) => params::size(args.items, FilterParams::HashCount(hashes))?,
+        (None, None) => params::size(args.items, FilterParams::FalsePositiveRate(args.fp))?,
+    };
+    print(&sizing);
+    Ok(())
+}
+
+fn print(sizing: &Sizing) {
+    println!("bits:                {}", sizing.bit_count);
+    println!("hashes:              {}", sizing.hash_count);
+    println!(
+        "memory:              {} bytes ({})",
+        sizing.memory_bytes(),
+        human_bytes(sizing.memory_bytes())
+    );
+    println!("false positive rate: {:.6}", sizing.false_positive_rate);
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.14 MiB`.
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.2} {}", value, UNITS[unit])
+    }
+}
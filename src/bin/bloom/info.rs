@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+/// Print the geometry and fill of a filter file.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// The filter file.
+    filter: PathBuf,
+}
+
+pub fn run(args: Args) -> crate::Result {
+    let filter = crate::load_filter(&args.filter)?;
+    let fill = filter.fill_ratio();
+
+    println!("bits:                {}", filter.bit_count());
+    println!("hashes:              {}", filter.hash_count());
+    println!("size:                {} bytes", filter.serialized_len());
+    println!("fill ratio:          {:.4}", fill);
+    println!("estimated items:     {:.0}", filter.estimated_items());
+    println!(
+        "false positive rate: {:.6}",
+        fill.powi(filter.hash_count() as i32)
+    );
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! The `bloom` command-line tool: build, query, inspect and compare filter
+//! files.
+//!
+//! Keys are the lines of the input, without their line endings, stored in a
+//! `BloomFilter<str>`. Filter files use the format of
+//! [`BloomFilter::write_to`](bloomlib::BloomFilter::write_to), so they load
+//! in Rust with `BloomFilter::<str>::read_from`.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use bloomlib::BloomFilter;
+use clap::{Parser, Subcommand};
+
+mod bench;
+mod build;
+mod convert;
+mod dedup;
+mod diff;
+mod estimate;
+mod info;
+mod merge;
+mod query;
+#[cfg(feature = "serve")]
+mod serve;
+
+/// Build, query, inspect, compare, merge and split Bloom filter files, size
+/// and benchmark them, and deduplicate streams.
+#[derive(Debug, Parser)]
+#[command(name = "bloom", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    Bench(bench::Args),
+    Build(build::Args),
+    Convert(convert::Args),
+    Dedup(dedup::Args),
+    Diff(diff::Args),
+    Estimate(estimate::Args),
+    Query(query::Args),
+    Info(info::Args),
+    Merge(merge::Args),
+    #[cfg(feature = "serve")]
+    Serve(serve::Args),
+}
+
+/// The result of a subcommand.
+type Result<T = ()> = std::result::Result<T, Box<dyn Error>>;
+
+/// Opens `path` for reading lines, or stdin if it is absent or `-`.
+fn open_input(path: Option<&Path>) -> Result<Box<dyn BufRead>> {
+    match path {
+        Some(path) if path != Path::new("-") => {
+            let file = File::open(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+            Ok(Box::new(BufReader::new(file)))
+        }
+        _ => Ok(Box::new(io::stdin().lock())),
+    }
+}
+
+/// Reads a filter file.
+fn load_filter(path: &Path) -> Result<BloomFilter<str>> {
+    let file = File::open(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    BloomFilter::read_from(BufReader::new(file))
+        .map_err(|err| format!("{}: {}", path.display(), err).into())
+}
+
+/// Writes a filter file, replacing any existing one.
+fn save_filter(filter: &BloomFilter<str>, path: &Path) -> Result {
+    let file = File::create(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let mut writer = BufWriter::new(file);
+    filter.write_to(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Bench(args) => bench::run(args),
+        Command::Build(args) => build::run(args),
+        Command::Convert(args) => convert::run(args),
+        Command::Dedup(args) => dedup::run(args),
+        Command::Diff(args) => diff::run(args),
+        Command::Estimate(args) => estimate::run(args),
+        Command::Query(args) => query::run(args),
+        Command::Info(args) => info::run(args),
+        Command::Merge(args) => merge::run(args),
+        #[cfg(feature = "serve")]
+        Command::Serve(args) => serve::run(args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // A closed pipe (e.g. into `head`) is not an error for a filter tool.
+        Err(err)
+            if err
+                .downcast_ref::<io::Error>()
+                .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("bloom: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+/// Union filter files built with the same bit and hash count.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// The filter files to merge.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// The filter file to write; may be one of the inputs.
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+pub fn run(args: Args) -> crate::Result {
+    let (first, rest) = args.inputs.split_first().expect("clap requires an input");
+    let mut merged = crate::load_filter(first)?;
+    // Only one other filter is held in memory at a time.
+    for path in rest {
+        merged
+            .merge(&crate::load_filter(path)?)
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+
+    crate::save_filter(&merged, &args.output)?;
+    eprintln!(
+        "{}: merged {} filters, fill ratio {:.4}",
+        args.output.display(),
+        args.inputs.len(),
+        merged.fill_ratio()
+    );
+    Ok(())
+}
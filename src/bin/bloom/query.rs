@@ -0,0 +1,48 @@
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::PathBuf;
+
+/// The number of keys looked up per batch.
+const BATCH: usize = 4096;
+
+/// Check keys against a filter file, printing each key and `true` or `false`.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// The filter file.
+    filter: PathBuf,
+    /// The file to read keys from, one per line; stdin if absent or `-`.
+    input: Option<PathBuf>,
+    /// Print only the keys that may be in the filter.
+    #[arg(long, conflicts_with = "absent")]
+    present: bool,
+    /// Print only the keys that are definitely not in the filter.
+    #[arg(long)]
+    absent: bool,
+}
+
+pub fn run(args: Args) -> crate::Result {
+    let filter = crate::load_filter(&args.filter)?;
+    let mut lines = crate::open_input(args.input.as_deref())?.lines();
+    let mut out = BufWriter::new(io::stdout().lock());
+
+    let mut keys = Vec::with_capacity(BATCH);
+    loop {
+        keys.clear();
+        for line in lines.by_ref().take(BATCH) {
+            keys.push(line?);
+        }
+        if keys.is_empty() {
+            break;
+        }
+        for (key, found) in keys.iter().zip(filter.contains_batch(&keys)) {
+            if args.present || args.absent {
+                if found == args.present {
+                    writeln!(out, "{}", key)?;
+                }
+            } else {
+                writeln!(out, "{}\t{}", key, found)?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,331 @@
+//! A minimal HTTP server over a set of named filters.
+//!
+//! | Request                              | Body                   | Response                  |
+//! |--------------------------------------|------------------------|---------------------------|
+//! | `GET /filters`                       |                        | JSON array of names       |
+//! | `PUT /filters/{name}?items=N&fp=P`   |                        | creates the filter (201)  |
+//! | `PUT /filters/{name}`                | a serialized filter    | stores the filter (201)   |
+//! | `GET /filters/{name}`                |                        | the serialized filter     |
+//! | `DELETE /filters/{name}`             |                        | removes the filter        |
+//! | `POST /filters/{name}/insert`        | keys, one per line     | `{"inserted": n}`         |
+//! | `POST /filters/{name}/check`         | keys, one per line     | JSON array of booleans    |
+//! | `POST /filters/{name}/merge`         | a serialized filter    | merges it in              |
+//! | `POST /filters/{name}/merge?from=F`  |                        | merges named filter `F`   |
+//! | `GET /filters/{name}/stats`          |                        | geometry and fill as JSON |
+//!
+//! `hashes=K` may replace `fp=P` on creation. Errors are returned as
+//! `{"error": "..."}` with a 4xx status, or 507 when a new filter would
+//! exceed `--max-memory`.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Read};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+use bloomlib::{BloomFilter, FilterParams, FilterRegistry, RegistryError, SharedFilter};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Serve named filters over HTTP.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// The address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: SocketAddr,
+    /// A filter file to serve, as `name=path`; may be repeated.
+    #[arg(long = "filter", value_parser = parse_named_path)]
+    filters: Vec<(String, PathBuf)>,
+    /// The number of worker threads.
+    #[arg(long, default_value_t = 4)]
+    threads: usize,
+    /// The largest request body accepted, in bytes.
+    #[arg(long, default_value_t = 64 << 20)]
+    max_body: u64,
+    /// The most memory all filters together may use, in bytes.
+    #[arg(long)]
+    max_memory: Option<u64>,
+}
+
+fn parse_named_path(arg: &str) -> Result<(String, PathBuf), String> {
+    let (name, path) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=path, got {:?}", arg))?;
+    if !valid_name(name) {
+        return Err(format!("invalid filter name {:?}", name));
+    }
+    Ok((name.to_owned(), path.into()))
+}
+
+/// Filter names are restricted to URL-safe characters.
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"-_.".contains(&byte))
+}
+
+type Registry = FilterRegistry<str>;
+
+/// Looks up a filter, answering 404 if there is none.
+fn get(registry: &Registry, name: &str) -> Result<SharedFilter<str>, HttpError> {
+    registry
+        .get(name)
+        .ok_or_else(|| RegistryError::NoSuchFilter(name.to_owned()).into())
+}
+
+/// An error status and message.
+#[derive(Debug)]
+struct HttpError(u16, String);
+
+impl From<bloomlib::BloomError> for HttpError {
+    fn from(err: bloomlib::BloomError) -> Self {
+        HttpError(400, err.to_string())
+    }
+}
+
+impl From<RegistryError> for HttpError {
+    fn from(err: RegistryError) -> Self {
+        let status = match err {
+            RegistryError::FilterExists(_) => 409,
+            RegistryError::NoSuchFilter(_) => 404,
+            RegistryError::Filter(_) => 400,
+            RegistryError::QuotaExceeded { .. } => 507,
+        };
+        HttpError(status, err.to_string())
+    }
+}
+
+impl From<std::io::Error> for HttpError {
+    fn from(err: std::io::Error) -> Self {
+        HttpError(400, err.to_string())
+    }
+}
+
+type HttpResponse = Response<std::io::Cursor<Vec<u8>>>;
+
+fn json(status: u16, body: String) -> HttpResponse {
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+}
+
+/// Quotes a string as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            ch if ch.is_control() => quoted.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Splits a URL into its path segments and query parameters.
+fn parse_url(url: &str) -> (Vec<&str>, HashMap<&str, &str>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let params = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .collect();
+    (segments, params)
+}
+
+fn param<T: std::str::FromStr>(
+    params: &HashMap<&str, &str>,
+    key: &str,
+) -> Result<Option<T>, HttpError> {
+    params
+        .get(key)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| HttpError(400, format!("invalid {} {:?}", key, value)))
+        })
+        .transpose()
+}
+
+pub fn run(args: Args) -> crate::Result {
+    let registry = Arc::new(match args.max_memory {
+        Some(bytes) => FilterRegistry::with_memory_budget(bytes),
+        None => FilterRegistry::new(),
+    });
+    for (name, path) in &args.filters {
+        registry.add(name, crate::load_filter(path)?)?;
+    }
+
+    let server =
+        Arc::new(Server::http(args.addr).map_err(|err| format!("{}: {}", args.addr, err))?);
+    eprintln!("bloom: listening on {}", server.server_addr());
+    let workers: Vec<_> = (0..args.threads.max(1))
+        .map(|_| {
+            let (server, registry) = (Arc::clone(&server), Arc::clone(&registry));
+            thread::spawn(move || {
+                while let Ok(request) = server.recv() {
+                    serve_one(&registry, request, args.max_body);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().expect("worker panicked");
+    }
+    Ok(())
+}
+
+fn serve_one(registry: &Registry, mut request: Request, max_body: u64) {
+    let mut body = Vec::new();
+    let read = request
+        .as_reader()
+        .take(max_body + 1)
+        .read_to_end(&mut body);
+    let response = match read {
+        Err(err) => json_error(HttpError::from(err)),
+        Ok(_) if body.len() as u64 > max_body => {
+            json_error(HttpError(413, "the body is too large".into()))
+        }
+        Ok(_) => {
+            let (segments, params) = parse_url(request.url());
+            handle(registry, request.method(), &segments, &params, &body).unwrap_or_else(json_error)
+        }
+    };
+    // The client may have hung up; there is nobody left to tell.
+    let _ = request.respond(response);
+}
+
+fn json_error(err: HttpError) -> HttpResponse {
+    json(err.0, format!("{{\"error\": {}}}", json_string(&err.1)))
+}
+
+fn handle(
+    registry: &Registry,
+    method: &Method,
+    segments: &[&str],
+    params: &HashMap<&str, &str>,
+    body: &[u8],
+) -> Result<HttpResponse, HttpError> {
+    let not_found = || HttpError(404, "not found".into());
+    match segments {
+        ["filters"] if *method == Method::Get => {
+            let names: Vec<String> = registry
+                .names()
+                .iter()
+                .map(|name| json_string(name))
+                .collect();
+            Ok(json(200, format!("[{}]", names.join(", "))))
+        }
+        ["filters", name, rest @ ..] => {
+            if !valid_name(name) {
+                return Err(HttpError(400, format!("invalid filter name {:?}", name)));
+            }
+            match (method, rest) {
+                (Method::Put, []) => create(registry, name, params, body),
+                (Method::Get, []) => {
+                    let filter = get(registry, name)?;
+                    let bytes = filter.read().unwrap().to_bytes();
+                    Ok(Response::from_data(bytes).with_header(
+                        Header::from_bytes("Content-Type", "application/octet-stream").unwrap(),
+                    ))
+                }
+                (Method::Delete, []) => {
+                    registry.remove(name).ok_or_else(not_found)?;
+                    Ok(json(200, "{}".into()))
+                }
+                (Method::Post, ["insert"]) => {
+                    let filter = get(registry, name)?;
+                    let mut filter = filter.write().unwrap();
+                    let mut inserted = 0;
+                    for key in body.lines() {
+                        filter.insert(&key?);
+                        inserted += 1;
+                    }
+                    Ok(json(200, format!("{{\"inserted\": {}}}", inserted)))
+                }
+                (Method::Post, ["check"]) => {
+                    let keys = body.lines().collect::<Result<Vec<String>, _>>()?;
+                    let found = get(registry, name)?.read().unwrap().contains_batch(&keys);
+                    let found: Vec<&str> = found
+                        .iter()
+                        .map(|&found| if found { "true" } else { "false" })
+                        .collect();
+                    Ok(json(200, format!("[{}]", found.join(", "))))
+                }
+                (Method::Post, ["merge"]) => merge(registry, name, params, body),
+                (Method::Get, ["stats"]) => {
+                    let filter = get(registry, name)?;
+                    let filter = filter.read().unwrap();
+                    let fill = filter.fill_ratio();
+                    Ok(json(
+                        200,
+                        format!(
+                            "{{\"bits\": {}, \"hashes\": {}, \"fill_ratio\": {}, \
+                             \"estimated_items\": {}, \"false_positive_rate\": {}}}",
+                            filter.bit_count(),
+                            filter.hash_count(),
+                            fill,
+                            filter.estimated_items().min(u64::MAX as f64).round(),
+                            fill.powi(filter.hash_count() as i32)
+                        ),
+                    ))
+                }
+                _ => Err(not_found()),
+            }
+        }
+        _ => Err(not_found()),
+    }
+}
+
+/// Creates a filter from size parameters, or stores a serialized one.
+fn create(
+    registry: &Registry,
+    name: &str,
+    params: &HashMap<&str, &str>,
+    body: &[u8],
+) -> Result<HttpResponse, HttpError> {
+    let filter = if body.is_empty() {
+        let items =
+            param(params, "items")?.ok_or_else(|| HttpError(400, "items is required".into()))?;
+        let filter_params = match param::<u32>(params, "hashes")? {
+            Some(hashes) => FilterParams::HashCount(hashes),
+            None => FilterParams::FalsePositiveRate(param(params, "fp")?.unwrap_or(0.01)),
+        };
+        BloomFilter::try_new(items, filter_params)?
+    } else {
+        BloomFilter::from_bytes(body)?
+    };
+    registry.add(name, filter)?;
+    Ok(json(201, "{}".into()))
+}
+
+/// Merges a serialized filter, or another named filter, into `name`.
+fn merge(
+    registry: &Registry,
+    name: &str,
+    params: &HashMap<&str, &str>,
+    body: &[u8],
+) -> Result<HttpResponse, HttpError> {
+    let filter = get(registry, name)?;
+    let result = match params.get("from") {
+        Some(from) if *from == name => Ok(()),
+        Some(from) => {
+            let other = get(registry, from)?;
+            let other = other.read().unwrap();
+            filter.write().unwrap().merge(&other)
+        }
+        None => {
+            let other: BloomFilter<str> = BloomFilter::from_bytes(body)?;
+            filter.write().unwrap().merge(&other)
+        }
+    };
+    result.map_err(|err| HttpError(409, err.to_string()))?;
+    Ok(json(200, "{}".into()))
+}
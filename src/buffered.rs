@@ -0,0 +1,139 @@
+use std::hash::Hash;
+
+use crate::{hashing, AtomicBloomFilter};
+
+/// A per-thread write buffer in front of a shared [`AtomicBloomFilter`].
+///
+/// Each insert into an `AtomicBloomFilter` is k atomic read-modify-writes on
+/// words other threads are writing too, and under heavy multi-threaded
+/// ingest the cache lines bounce between cores. An `InsertBuffer` instead
+/// hashes items into a local buffer and, once `capacity` items are pending,
+/// flushes them together: their positions are sorted by word and merged,
+/// so the flush performs one `fetch_or` per distinct word touched, and none
+/// for words whose bits are already set. Each thread creates its own buffer
+/// with [`AtomicBloomFilter::buffered`].
+///
+/// Buffered items are not visible to [`AtomicBloomFilter::contains`] until
+/// they are flushed, when the buffer fills, on [`flush`](Self::flush) or
+/// when it is dropped. [`contains`](Self::contains) on the buffer also
+/// checks its own pending items.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use bloomlib::AtomicBloomFilter;
+///
+/// let filter = AtomicBloomFilter::new(100_000, 0.01);
+///
+/// thread::scope(|scope| {
+///     for t in 0..4u64 {
+///         let filter = &filter;
+///         scope.spawn(move || {
+///             let mut buffer = filter.buffered(1024);
+///             for i in 0..25_000 {
+///                 buffer.insert(&(t * 25_000 + i));
+///             }
+///             // Dropping the buffer flushes the rest.
+///         });
+///     }
+/// });
+///
+/// assert!(filter.contains(&42));
+/// ```
+#[derive(Debug)]
+pub struct InsertBuffer<'a, T: ?Sized> {
+    filter: &'a AtomicBloomFilter<T>,
+    /// Hash pairs of items not yet flushed.
+    pending: Vec<(u64, u64)>,
+    capacity: usize,
+    /// Bit positions of a flush, kept to reuse the allocation.
+    scratch: Vec<(usize, u64)>,
+}
+
+impl<T: ?Sized + Hash> AtomicBloomFilter<T> {
+    /// Returns a buffer that inserts into this filter in batches of
+    /// `capacity` items. See [`InsertBuffer`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn buffered(&self, capacity: usize) -> InsertBuffer<'_, T> {
+        assert!(capacity > 0, "Capacity must be greater than 0.");
+        InsertBuffer {
+            filter: self,
+            pending: Vec::with_capacity(capacity),
+            capacity,
+            scratch: Vec::new(),
+        }
+    }
+}
+
+impl<T: ?Sized + Hash> InsertBuffer<'_, T> {
+    /// Buffers an item, flushing the buffer if it is full.
+    pub fn insert(&mut self, item: &T) {
+        self.pending.push(hashing::hash_pair(item));
+        if self.pending.len() >= self.capacity {
+            self.flush();
+        }
+    }
+
+    /// Checks if an item might be in the filter or among the pending items.
+    pub fn contains(&self, item: &T) -> bool {
+        let hashes = hashing::hash_pair(item);
+        self.pending.contains(&hashes) || self.filter.contains_hashed(hashes.0, hashes.1)
+    }
+
+    /// Sets the bits of every pending item in the filter.
+    pub fn flush(&mut self) {
+        if !self.pending.is_empty() {
+            self.filter
+                .insert_hashed_batch(&self.pending, &mut self.scratch);
+            self.pending.clear();
+        }
+    }
+
+    /// Returns the number of items waiting to be flushed.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns the filter the buffer flushes into.
+    pub fn filter(&self) -> &AtomicBloomFilter<T> {
+        self.filter
+    }
+}
+
+impl<T: ?Sized> Drop for InsertBuffer<'_, T> {
+    fn drop(&mut self) {
+        if !self.pending.is_empty() {
+            self.filter
+                .insert_hashed_batch(&self.pending, &mut self.scratch);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BloomFilter;
+
+    #[test]
+    fn test_buffered_matches_direct() {
+        let direct = AtomicBloomFilter::new(10_000, 0.01);
+        let buffered = AtomicBloomFilter::new(10_000, 0.01);
+        {
+            let mut buffer = buffered.buffered(100);
+            for i in 0..10_050u64 {
+                direct.insert(&i);
+                buffer.insert(&i);
+                assert!(buffer.contains(&i));
+            }
+            assert_eq!(buffer.pending(), 50);
+        }
+        assert_eq!(
+            BloomFilter::from(buffered).bit_vec,
+            BloomFilter::from(direct).bit_vec
+        );
+    }
+}
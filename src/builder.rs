@@ -0,0 +1,141 @@
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::counters::OpCounters;
+use crate::params::Geometry;
+use crate::{AllocStore, BitStore, BloomError, BloomFilter, FilterParams};
+
+/// A builder for [`BloomFilter`] options beyond the item count and rate.
+///
+/// Create one with [`BloomFilter::builder`], or with
+/// [`BloomFilterBuilder::new`] for a non-default store.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::BloomFilter;
+///
+/// let mut bf: BloomFilter<str> = BloomFilter::builder(1000, 0.01)
+///     .power_of_two(true)
+///     .build();
+/// bf.insert("seen");
+/// assert!(bf.contains("seen"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct BloomFilterBuilder<T: ?Sized, B = Vec<u64>> {
+    /// The expected number of items to insert (n).
+    expected_items: usize,
+    /// Either the false positive rate or the hash count.
+    params: FilterParams,
+    /// Whether to round the bit count up to a power of two.
+    power_of_two: bool,
+    /// Phantom data to hold the item and storage types.
+    _marker: PhantomData<fn(&T) -> B>,
+}
+
+impl<T: ?Sized + Hash> BloomFilter<T> {
+    /// Returns a builder for a filter sized like [`BloomFilter::new`].
+    pub fn builder(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> BloomFilterBuilder<T> {
+        BloomFilterBuilder::new(expected_items, params)
+    }
+}
+
+impl<T: ?Sized, B: BitStore> BloomFilterBuilder<T, B> {
+    /// Creates a builder for a filter backed by storage `B`.
+    pub fn new(expected_items: usize, params: impl Into<FilterParams>) -> Self {
+        BloomFilterBuilder {
+            expected_items,
+            params: params.into(),
+            power_of_two: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Rounds the bit count (m) up to a power of two.
+    ///
+    /// Every probe then reduces its hash with a mask instead of a 128-bit
+    /// multiply, at the cost of up to twice the memory. The hash count is kept,
+    /// so the extra bits only lower the false positive rate. Off by default.
+    pub fn power_of_two(mut self, enabled: bool) -> Self {
+        self.power_of_two = enabled;
+        self
+    }
+
+    /// Computes the geometry of the configured filter.
+    pub(crate) fn geometry(&self) -> Result<Geometry, BloomError> {
+        let geometry = Geometry::optimal::<B::Word>(self.expected_items, self.params)?;
+        if self.power_of_two {
+            geometry.to_power_of_two::<B::Word>()
+        } else {
+            Ok(geometry)
+        }
+    }
+}
+
+impl<T: ?Sized + Hash, B: AllocStore> BloomFilterBuilder<T, B> {
+    /// Builds the filter.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn build(self) -> BloomFilter<T, B> {
+        self.try_build().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Builds the filter, returning an error instead of panicking.
+    pub fn try_build(self) -> Result<BloomFilter<T, B>, BloomError> {
+        let geometry = self.geometry()?;
+
+        Ok(BloomFilter {
+            bit_vec: B::zeroed(geometry.word_count),
+            bit_count: geometry.bit_count,
+            hash_fn_count: geometry.hash_count,
+            counters: OpCounters::default(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_of_two_sizing() {
+        let bf: BloomFilter<u64> = BloomFilter::builder(100, 0.01).power_of_two(true).build();
+        assert_eq!(bf.bit_count, 1024);
+        assert_eq!(bf.bit_vec.len(), 16);
+
+        let narrow = BloomFilterBuilder::<u64, Vec<u8>>::new(1, 1u32)
+            .power_of_two(true)
+            .build();
+        assert_eq!(narrow.bit_count, 8);
+    }
+
+    #[test]
+    fn test_power_of_two_matches_modulo() {
+        let mut masked: BloomFilter<u64> =
+            BloomFilter::builder(1000, 0.01).power_of_two(true).build();
+        let mut modulo = masked.clone();
+        for i in 0..1000u64 {
+            masked.insert(&i);
+            for k in 0..modulo.hash_fn_count {
+                let (h1, h2) = modulo.get_hashes(&i);
+                let index = h1.wrapping_add(h2.wrapping_mul(k as u64)) % modulo.bit_count;
+                modulo.bit_vec[(index / 64) as usize] |= 1 << (index % 64);
+            }
+        }
+        assert_eq!(masked.bit_vec, modulo.bit_vec);
+    }
+
+    #[test]
+    fn test_default_matches_new() {
+        let built: BloomFilter<str> = BloomFilter::builder(1000, 0.01).build();
+        let new: BloomFilter<str> = BloomFilter::new(1000, 0.01);
+        assert_eq!(built.bit_count, new.bit_count);
+        assert_eq!(built.hash_fn_count, new.hash_fn_count);
+    }
+}
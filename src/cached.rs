@@ -0,0 +1,140 @@
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{hashing, BitStore, BloomFilter};
+
+/// A [`BloomFilter`] with a small cache of recently found keys in front of
+/// its bit array, for lookups dominated by a few hot keys.
+///
+/// A lookup that finds its key tests all k bits, each often a cache miss on
+/// a large filter. This wrapper remembers a fingerprint of the 128-bit hash
+/// of every key it found, in a direct-mapped table of `slots` entries, and
+/// answers later lookups of the same key from the table without touching
+/// the bit array. Keys that are not found are not cached: their lookups
+/// usually stop at the first unset bit anyway.
+///
+/// Only positive answers are cached, and bits are never unset except by
+/// [`clear`](Self::clear), which empties the table, so the cache cannot
+/// cause false negatives. A fingerprint collision can only add a false
+/// positive, with probability about 2^-64 per lookup.
+///
+/// Lookups take `&self` and update the table with relaxed atomics, so the
+/// filter can be shared between reader threads. Size the table a little
+/// above the number of hot keys; each slot takes 8 bytes.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{BloomFilter, CachedBloomFilter};
+///
+/// let mut filter: BloomFilter<str> = BloomFilter::new(1_000_000, 0.01);
+/// filter.insert("popular");
+/// let cached = CachedBloomFilter::new(filter, 4096);
+///
+/// for _ in 0..100 {
+///     assert!(cached.contains("popular"));
+/// }
+/// assert_eq!(cached.cache_hits(), 99);
+/// ```
+#[derive(Debug)]
+pub struct CachedBloomFilter<T: ?Sized, B = Vec<u64>> {
+    filter: BloomFilter<T, B>,
+    /// Fingerprints of found keys; 0 is an empty slot.
+    slots: Box<[AtomicU64]>,
+    hits: AtomicU64,
+}
+
+impl<T: ?Sized + Hash, B: BitStore> CachedBloomFilter<T, B> {
+    /// Puts a cache of `slots` entries in front of `filter`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slots` is 0.
+    pub fn new(filter: BloomFilter<T, B>, slots: usize) -> Self {
+        assert!(slots > 0, "Slots must be greater than 0.");
+        CachedBloomFilter {
+            filter,
+            slots: (0..slots).map(|_| AtomicU64::new(0)).collect(),
+            hits: AtomicU64::new(0),
+        }
+    }
+
+    /// Inserts an item into the filter.
+    pub fn insert(&mut self, item: &T) {
+        self.filter.insert(item);
+    }
+
+    /// Checks if an item might be in the filter, answering from the cache if
+    /// it was found recently.
+    pub fn contains(&self, item: &T) -> bool {
+        let (h1, h2) = self.filter.get_hashes(item);
+        // The low bit keeps every fingerprint distinct from an empty slot.
+        let fingerprint = (h1 ^ h2.rotate_left(32)) | 1;
+        let slot = &self.slots[hashing::fastrange(h2, self.slots.len() as u64) as usize];
+        if slot.load(Ordering::Relaxed) == fingerprint {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            self.filter.counters.record_query(true);
+            return true;
+        }
+        let found = self.filter.contains_hashes(h1, h2);
+        self.filter.counters.record_query(found);
+        if found {
+            slot.store(fingerprint, Ordering::Relaxed);
+        }
+        found
+    }
+
+    /// Returns the number of lookups answered from the cache.
+    pub fn cache_hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Clears the filter and the cache.
+    pub fn clear(&mut self) {
+        self.filter.clear();
+        for slot in self.slots.iter_mut() {
+            *slot.get_mut() = 0;
+        }
+    }
+
+    /// Returns the approximate memory usage of the filter and the cache in
+    /// bytes.
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.filter.memory_usage_bytes() + self.slots.len() * 8
+    }
+
+    /// Returns the filter.
+    pub fn filter(&self) -> &BloomFilter<T, B> {
+        &self.filter
+    }
+
+    /// Unwraps the filter, dropping the cache.
+    pub fn into_inner(self) -> BloomFilter<T, B> {
+        self.filter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_matches_filter() {
+        let mut filter: BloomFilter<u64> = BloomFilter::new(10_000, 0.01);
+        (0..10_000).for_each(|i| filter.insert(&i));
+        let mut cached = CachedBloomFilter::new(filter.clone(), 64);
+
+        for round in 0..3 {
+            for i in (0..20_000).step_by(7) {
+                assert_eq!(cached.contains(&(i % 100)), filter.contains(&(i % 100)));
+                assert_eq!(cached.contains(&i), filter.contains(&i), "round {}", round);
+            }
+        }
+        assert!(cached.cache_hits() > 0);
+
+        cached.clear();
+        assert!(!cached.contains(&1));
+        cached.insert(&1);
+        assert!(cached.contains(&1));
+    }
+}
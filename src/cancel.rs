@@ -0,0 +1,282 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{BitStore, BloomError, BloomFilter};
+
+/// The number of items or words a cancellable operation processes between
+/// checks of its [`CancelToken`].
+pub(crate) const CHECK_INTERVAL: usize = 4096;
+
+/// How far a bulk operation has got, as passed to the callback of
+/// [`CancelToken::with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The items or bytes processed so far.
+    pub done: u64,
+    /// The items or bytes the operation processes in all, if known.
+    pub total: Option<u64>,
+    /// What `done` and `total` count.
+    pub unit: ProgressUnit,
+}
+
+impl Progress {
+    /// The progress of an operation through `done` of `total` bytes.
+    pub(crate) fn bytes(done: usize, total: usize) -> Self {
+        Progress {
+            done: done as u64,
+            total: Some(total as u64),
+            unit: ProgressUnit::Bytes,
+        }
+    }
+}
+
+/// What a [`Progress`] counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressUnit {
+    /// Items inserted, by
+    /// [`extend_cancellable`](BloomFilter::extend_cancellable).
+    Items,
+    /// Bytes of the bit array merged, or of the serialized filter written
+    /// or read.
+    Bytes,
+}
+
+/// Stops a long-running bulk operation, such as
+/// [`extend_cancellable`](BloomFilter::extend_cancellable),
+/// [`merge_cancellable`](BloomFilter::merge_cancellable),
+/// [`write_to_cancellable`](BloomFilter::write_to_cancellable) or
+/// [`read_from_cancellable`](BloomFilter::read_from_cancellable), once
+/// [`cancel`](Self::cancel) is called or a deadline passes, and reports its
+/// progress.
+///
+/// Clones share the cancellation, so one clone can be handed to the thread
+/// running the operation and another kept to cancel it. Operations check
+/// the token every few thousand items or words, so they stop within
+/// microseconds of it, and return [`BloomError::Cancelled`]. Each
+/// operation documents the state it leaves behind.
+///
+/// At every check, and once more when it completes, the operation passes
+/// its [`Progress`] to the callback set with
+/// [`with_progress`](Self::with_progress), e.g. to draw a progress bar. A
+/// callback can also cancel the token, e.g. when the operation runs past
+/// its budget.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use bloomlib::{BloomError, BloomFilter, CancelToken};
+///
+/// let mut filter: BloomFilter<u64> = BloomFilter::new(1_000_000, 0.01);
+/// let cancel = CancelToken::new()
+///     .with_timeout(Duration::from_secs(30))
+///     .with_progress(|progress| {
+///         if let Some(total) = progress.total {
+///             eprint!("\r{}%", progress.done * 100 / total);
+///         }
+///     });
+/// filter.extend_cancellable(0..1_000_000, &cancel).unwrap();
+///
+/// cancel.cancel();
+/// let result = filter.extend_cancellable(0..1_000_000, &cancel);
+/// assert_eq!(result, Err(BloomError::Cancelled));
+/// ```
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+    progress: Option<Arc<dyn Fn(Progress) + Send + Sync>>,
+}
+
+impl CancelToken {
+    /// Creates a token that only cancels when [`cancel`](Self::cancel) is
+    /// called.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also cancels once `deadline` has passed.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Also cancels once `timeout` has passed from now.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Passes the progress of operations using this token to `callback`.
+    pub fn with_progress(mut self, callback: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Cancels the operations using this token or any of its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the token was cancelled or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Reports `progress`, then returns [`BloomError::Cancelled`] if the
+    /// token is cancelled.
+    pub(crate) fn checkpoint(&self, progress: Progress) -> Result<(), BloomError> {
+        self.report(progress);
+        if self.is_cancelled() {
+            Err(BloomError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Passes `progress` to the callback, if there is one.
+    pub(crate) fn report(&self, progress: Progress) {
+        if let Some(callback) = &self.progress {
+            callback(progress);
+        }
+    }
+}
+
+impl fmt::Debug for CancelToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelToken")
+            .field("cancelled", &self.cancelled)
+            .field("deadline", &self.deadline)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: ?Sized + Hash, B: BitStore> BloomFilter<T, B> {
+    /// Inserts every item of `items`, stopping early if `cancel` is
+    /// cancelled.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::Cancelled`] if the token is cancelled before
+    /// every item is inserted. The items before the point of cancellation
+    /// have then been inserted and the rest have not, so the filter is
+    /// consistent, without false negatives for anything inserted. Running
+    /// the operation again with all the items completes it, as inserting an
+    /// item twice changes nothing.
+    pub fn extend_cancellable<I>(
+        &mut self,
+        items: I,
+        cancel: &CancelToken,
+    ) -> Result<(), BloomError>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<T>,
+    {
+        let mut items = items.into_iter().peekable();
+        let total = match items.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(upper as u64),
+            _ => None,
+        };
+        let mut progress = Progress {
+            done: 0,
+            total,
+            unit: ProgressUnit::Items,
+        };
+        while items.peek().is_some() {
+            cancel.checkpoint(progress)?;
+            for item in items.by_ref().take(CHECK_INTERVAL) {
+                self.insert(item.borrow());
+                progress.done += 1;
+            }
+        }
+        cancel.report(progress);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancelled_operations_leave_a_usable_filter() {
+        let mut source: BloomFilter<u64> = BloomFilter::new(100_000, 0.01);
+        source.insert(&1);
+        let cancelled = CancelToken::new();
+        cancelled.cancel();
+        assert!(cancelled.clone().is_cancelled());
+
+        assert_eq!(
+            source.extend_cancellable(2..100_000, &cancelled),
+            Err(BloomError::Cancelled)
+        );
+        assert_eq!(source.extend_cancellable(2..2, &cancelled), Ok(()));
+        let mut target: BloomFilter<u64> = BloomFilter::new(100_000, 0.01);
+        assert_eq!(
+            target.merge_cancellable(&source, &cancelled),
+            Err(BloomError::Cancelled)
+        );
+        let err = source
+            .write_to_cancellable(Vec::new(), &cancelled)
+            .unwrap_err();
+        assert_eq!(
+            err.get_ref()
+                .and_then(|err| err.downcast_ref::<BloomError>()),
+            Some(&BloomError::Cancelled)
+        );
+
+        let expired = CancelToken::new().with_deadline(Instant::now());
+        assert!(expired.is_cancelled());
+
+        let live = CancelToken::new().with_timeout(Duration::from_secs(3600));
+        source.extend_cancellable(2..100_000, &live).unwrap();
+        target.merge_cancellable(&source, &live).unwrap();
+        assert!((1..100_000).all(|i| target.contains(&i)));
+        let mut bytes = Vec::new();
+        target.write_to_cancellable(&mut bytes, &live).unwrap();
+        assert_eq!(bytes, target.to_bytes());
+    }
+
+    #[test]
+    fn test_progress_reaches_total() {
+        use std::sync::Mutex;
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let cancel = CancelToken::new().with_progress({
+            let reports = Arc::clone(&reports);
+            move |progress| reports.lock().unwrap().push(progress)
+        });
+        let mut filter: BloomFilter<u64> = BloomFilter::new(100_000, 0.01);
+        filter.extend_cancellable(0..10_000, &cancel).unwrap();
+        let mut bytes = Vec::new();
+        filter.write_to_cancellable(&mut bytes, &cancel).unwrap();
+        let loaded: BloomFilter<u64> =
+            BloomFilter::read_from_cancellable(&bytes[..], &Default::default(), &cancel).unwrap();
+        assert!((0..10_000).all(|i| loaded.contains(&i)));
+
+        let reports = reports.lock().unwrap();
+        let finals: Vec<_> = reports
+            .iter()
+            .filter(|progress| Some(progress.done) == progress.total)
+            .map(|progress| (progress.done, progress.unit))
+            .collect();
+        let len = bytes.len() as u64;
+        assert_eq!(
+            finals,
+            [
+                (10_000, ProgressUnit::Items),
+                (len, ProgressUnit::Bytes),
+                (len, ProgressUnit::Bytes)
+            ]
+        );
+        assert!(reports
+            .iter()
+            .all(|progress| progress.done <= progress.total.unwrap()));
+    }
+}
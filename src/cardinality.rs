@@ -0,0 +1,215 @@
+use std::hash::Hash;
+
+use crate::{AllocStore, BitStore, BloomError, BloomFilter, FilterParams};
+
+/// A [`BloomFilter`] paired with a HyperLogLog sketch of the distinct items
+/// inserted, both updated from one hash of each item.
+///
+/// The filter answers [`contains`](Self::contains) and the sketch
+/// [`distinct_estimate`](Self::distinct_estimate). The filter's second hash
+/// selects one of the sketch's `2^precision` one-byte registers and the
+/// position of its first set bit updates it, so an insert costs no more
+/// hashing than a plain [`BloomFilter::insert`]. The estimate's standard
+/// error is about `1.04 / sqrt(2^precision)`, e.g. 0.8% at precision 14 for
+/// 16 KiB, however many items arrive.
+///
+/// Unlike [`BloomFilter::estimated_items`], the estimate does not depend on
+/// the filter's fill, so it stays accurate long after the filter is full.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::CardinalityBloomFilter;
+///
+/// let mut seen: CardinalityBloomFilter<u64> = CardinalityBloomFilter::new(10_000, 0.01, 12);
+/// for i in 0..10_000 {
+///     seen.insert(&(i % 2_500));
+/// }
+/// assert!(seen.contains(&42));
+/// let distinct = seen.distinct_estimate();
+/// assert!((distinct - 2_500.0).abs() < 250.0, "{}", distinct);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CardinalityBloomFilter<T: ?Sized, B = Vec<u64>> {
+    filter: BloomFilter<T, B>,
+    /// The HyperLogLog registers: the highest rank seen per bucket.
+    registers: Box<[u8]>,
+    precision: u8,
+}
+
+impl<T: ?Sized + Hash> CardinalityBloomFilter<T> {
+    /// Creates a filter sized like [`BloomFilter::new`] with a sketch of
+    /// `2^precision` registers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not between 4 and 16, or under the same
+    /// conditions as [`BloomFilter::new`].
+    pub fn new(expected_items: usize, params: impl Into<FilterParams>, precision: u8) -> Self {
+        Self::try_new(expected_items, params, precision).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a filter with a sketch, returning an error instead of
+    /// panicking on invalid sizing parameters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not between 4 and 16.
+    pub fn try_new(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+        precision: u8,
+    ) -> Result<Self, BloomError> {
+        Ok(Self::from_filter(
+            BloomFilter::try_new(expected_items, params)?,
+            precision,
+        ))
+    }
+}
+
+impl<T: ?Sized + Hash, B: AllocStore> CardinalityBloomFilter<T, B> {
+    /// Creates a filter backed by storage `B`, with a sketch of
+    /// `2^precision` registers. See [`BloomFilter::from_params`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not between 4 and 16, or under the same
+    /// conditions as [`BloomFilter::new`].
+    pub fn from_params(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+        precision: u8,
+    ) -> Self {
+        Self::from_filter(BloomFilter::from_params(expected_items, params), precision)
+    }
+}
+
+impl<T: ?Sized + Hash, B: BitStore> CardinalityBloomFilter<T, B> {
+    /// Pairs an empty `filter` with a sketch of `2^precision` registers.
+    /// Items already in the filter are not counted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not between 4 and 16.
+    pub fn from_filter(filter: BloomFilter<T, B>, precision: u8) -> Self {
+        assert!(
+            (4..=16).contains(&precision),
+            "Precision must be between 4 and 16."
+        );
+        CardinalityBloomFilter {
+            filter,
+            registers: vec![0; 1 << precision].into_boxed_slice(),
+            precision,
+        }
+    }
+
+    /// Inserts an item into the filter and counts it in the sketch.
+    pub fn insert(&mut self, item: &T) {
+        let (h1, h2) = self.filter.get_hashes(item);
+        self.filter.insert_hashes(h1, h2);
+        self.filter.counters.record_inserts(1);
+        self.count(h2);
+    }
+
+    /// Inserts an item, returning `true` if it was new to the filter, or
+    /// `false` if it was (probably) already present. The sketch counts it
+    /// either way, so a false positive does not bias the estimate.
+    pub fn check_and_insert(&mut self, item: &T) -> bool {
+        let (h1, h2) = self.filter.get_hashes(item);
+        self.count(h2);
+        let found = self.filter.contains_hashes(h1, h2);
+        self.filter.counters.record_query(found);
+        if !found {
+            self.filter.insert_hashes(h1, h2);
+            self.filter.counters.record_inserts(1);
+        }
+        !found
+    }
+
+    /// Checks if an item might be in the filter.
+    pub fn contains(&self, item: &T) -> bool {
+        self.filter.contains(item)
+    }
+
+    /// Estimates the number of distinct items inserted.
+    pub fn distinct_estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-(rank as i32)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+        let empty = self.registers.iter().filter(|&&rank| rank == 0).count();
+        // Linear counting is more accurate while many registers are empty.
+        if estimate <= 2.5 * m && empty > 0 {
+            m * (m / empty as f64).ln()
+        } else {
+            estimate
+        }
+    }
+
+    /// Returns the precision of the sketch.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Returns the filter.
+    pub fn filter(&self) -> &BloomFilter<T, B> {
+        &self.filter
+    }
+
+    /// Clears the filter and the sketch.
+    pub fn clear(&mut self) {
+        self.filter.clear();
+        self.registers.fill(0);
+    }
+
+    /// Returns the approximate memory usage of the filter and the sketch in
+    /// bytes.
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.filter.memory_usage_bytes() + self.registers.len()
+    }
+
+    /// Updates the register `hash` selects with the rank of the rest of it.
+    fn count(&mut self, hash: u64) {
+        let precision = self.precision as u32;
+        let index = (hash >> (64 - precision)) as usize;
+        // The marker bit caps the rank at 64 - precision + 1.
+        let rank = ((hash << precision) | (1 << (precision - 1))).leading_zeros() as u8 + 1;
+        let register = &mut self.registers[index];
+        *register = (*register).max(rank);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distinct_estimate() {
+        let mut filter: CardinalityBloomFilter<u64> =
+            CardinalityBloomFilter::new(100_000, 0.01, 14);
+        assert_eq!(filter.distinct_estimate(), 0.0);
+        for round in 0..3 {
+            for i in 0..100_000 {
+                let new = filter.check_and_insert(&i);
+                assert!(round == 0 || !new);
+            }
+        }
+        let distinct = filter.distinct_estimate();
+        assert!((distinct - 100_000.0).abs() < 3_000.0, "{}", distinct);
+        assert!((0..100_000).all(|i| filter.contains(&i)));
+
+        // Far past the filter's capacity, the sketch still counts.
+        (100_000..1_000_000).for_each(|i| filter.insert(&i));
+        let distinct = filter.distinct_estimate();
+        assert!((distinct - 1_000_000.0).abs() < 30_000.0, "{}", distinct);
+    }
+}
@@ -0,0 +1,223 @@
+use std::cell::Cell;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::approx::{self, ApproximateSet};
+use crate::counters::OpCounters;
+use crate::hashing;
+use crate::params::Geometry;
+use crate::{BloomError, BloomFilter, FilterParams};
+
+/// A Bloom Filter that inserts through a shared reference on one thread.
+///
+/// The bit array is a slice of `Cell<u64>`, so [`insert`](Self::insert)
+/// takes `&self` like [`AtomicBloomFilter`](crate::AtomicBloomFilter) does,
+/// but with plain loads and stores instead of atomic operations. The filter
+/// is `Send` but not `Sync`: it suits code that only holds shared
+/// references, such as callbacks of an event loop or a UI framework, or an
+/// `Rc`, without wrapping a [`BloomFilter`] in a `RefCell`.
+///
+/// Sizing, hashing and probing are identical to [`BloomFilter`], and the two
+/// types convert into each other without rehashing.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use bloomlib::CellBloomFilter;
+///
+/// let seen = Rc::new(CellBloomFilter::new(1000, 0.01));
+/// let on_event = {
+///     let seen = Rc::clone(&seen);
+///     move |id: u64| seen.check_and_insert(&id)
+/// };
+///
+/// assert!(on_event(7));
+/// assert!(!on_event(7));
+/// assert!(seen.contains(&7));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CellBloomFilter<T: ?Sized> {
+    /// The bit array stored as u64 words.
+    bit_vec: Box<[Cell<u64>]>,
+    /// The total number of bits in the filter (m).
+    bit_count: u64,
+    /// The number of hash functions to use (k).
+    hash_fn_count: u32,
+    /// Phantom data to hold the type information without affecting `Send`.
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: ?Sized + Hash> CellBloomFilter<T> {
+    /// Creates a new Bloom Filter optimized for the given expected item count
+    /// and configuration. See [`BloomFilter::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn new(expected_items: usize, params: impl Into<FilterParams>) -> Self {
+        Self::try_new(expected_items, params).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a new Bloom Filter, returning an error instead of panicking.
+    /// See [`BloomFilter::try_new`].
+    pub fn try_new(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<Self, BloomError> {
+        let geometry = Geometry::optimal::<u64>(expected_items, params.into())?;
+
+        Ok(CellBloomFilter {
+            bit_vec: (0..geometry.word_count).map(|_| Cell::new(0)).collect(),
+            bit_count: geometry.bit_count,
+            hash_fn_count: geometry.hash_count,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Inserts an item into the Bloom Filter.
+    pub fn insert(&self, item: &T) {
+        let (h1, h2) = hashing::hash_pair(item);
+        for i in 0..self.hash_fn_count {
+            let (word, mask) = self.get_bit(h1, h2, i);
+            word.set(word.get() | mask);
+        }
+    }
+
+    /// Inserts an item, returning `true` if it was new, or `false` if it was
+    /// (probably) already present. See [`BloomFilter::check_and_insert`].
+    pub fn check_and_insert(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair(item);
+        let mut present = true;
+        for i in 0..self.hash_fn_count {
+            let (word, mask) = self.get_bit(h1, h2, i);
+            present &= word.get() & mask != 0;
+            word.set(word.get() | mask);
+        }
+        !present
+    }
+
+    /// Checks if an item might be in the Bloom Filter.
+    ///
+    /// Returns `true` if the item might be present (with a probability of false positive).
+    /// Returns `false` if the item is definitely not present.
+    pub fn contains(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair(item);
+        (0..self.hash_fn_count).all(|i| {
+            let (word, mask) = self.get_bit(h1, h2, i);
+            word.get() & mask != 0
+        })
+    }
+
+    /// Clears all bits in the filter.
+    pub fn clear(&self) {
+        for word in self.bit_vec.iter() {
+            word.set(0);
+        }
+    }
+
+    /// Returns the fraction of bits set. See
+    /// [`BloomFilter::fill_ratio`].
+    pub fn fill_ratio(&self) -> f64 {
+        let set_bits: u64 = self
+            .bit_vec
+            .iter()
+            .map(|word| word.get().count_ones() as u64)
+            .sum();
+        set_bits as f64 / self.bit_count as f64
+    }
+
+    /// Estimates the number of distinct items inserted so far. See
+    /// [`BloomFilter::estimated_items`].
+    pub fn estimated_items(&self) -> f64 {
+        approx::estimated_items(self.fill_ratio(), self.bit_count, self.hash_fn_count)
+    }
+
+    /// Returns the word and bit mask of the i-th hash position.
+    #[inline]
+    fn get_bit(&self, h1: u64, h2: u64, i: u32) -> (&Cell<u64>, u64) {
+        let bit_index = hashing::bit_index(h1, h2, i, self.bit_count);
+        (
+            &self.bit_vec[(bit_index / 64) as usize],
+            1u64 << (bit_index % 64),
+        )
+    }
+
+    /// Returns the approximate memory usage of the bit vector in bytes.
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.bit_vec.len() * 8
+    }
+
+    /// Returns the number of hash functions (k) being used.
+    pub fn hash_count(&self) -> u32 {
+        self.hash_fn_count
+    }
+}
+
+impl<T: ?Sized + Hash> ApproximateSet<T> for CellBloomFilter<T> {
+    fn insert(&mut self, item: &T) {
+        CellBloomFilter::insert(self, item);
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        CellBloomFilter::contains(self, item)
+    }
+
+    fn estimated_len(&self) -> f64 {
+        self.estimated_items()
+    }
+
+    fn fp_estimate(&self) -> f64 {
+        self.fill_ratio().powi(self.hash_fn_count as i32)
+    }
+}
+
+impl<T: ?Sized> From<BloomFilter<T>> for CellBloomFilter<T> {
+    fn from(filter: BloomFilter<T>) -> Self {
+        CellBloomFilter {
+            bit_vec: filter.bit_vec.into_iter().map(Cell::new).collect(),
+            bit_count: filter.bit_count,
+            hash_fn_count: filter.hash_fn_count,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> From<CellBloomFilter<T>> for BloomFilter<T> {
+    fn from(filter: CellBloomFilter<T>) -> Self {
+        BloomFilter {
+            bit_vec: filter
+                .bit_vec
+                .into_vec()
+                .into_iter()
+                .map(Cell::into_inner)
+                .collect(),
+            bit_count: filter.bit_count,
+            hash_fn_count: filter.hash_fn_count,
+            counters: OpCounters::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_bloom_filter() {
+        let cell = CellBloomFilter::new(1000, 0.01);
+        let mut plain: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        for i in 0..1000u64 {
+            assert_eq!(cell.check_and_insert(&i), plain.check_and_insert(&i));
+        }
+        assert!(!cell.check_and_insert(&7));
+
+        let converted: BloomFilter<u64> = cell.into();
+        assert_eq!(converted.bit_vec, plain.bit_vec);
+        let cell = CellBloomFilter::from(converted);
+        assert!((0..1000).all(|i| cell.contains(&i)));
+        cell.clear();
+        assert_eq!(cell.fill_ratio(), 0.0);
+    }
+}
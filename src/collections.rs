@@ -0,0 +1,98 @@
+use std::collections::{BTreeSet, HashSet};
+use std::hash::Hash;
+
+use crate::params::DEFAULT_FALSE_POSITIVE_RATE;
+use crate::{BloomError, BloomFilter, FilterParams};
+
+impl<T: ?Sized + Hash> BloomFilter<T> {
+    /// Creates a filter sized for exactly the given items and inserts them,
+    /// e.g. to snapshot a set of distinct items.
+    ///
+    /// An empty collection gives a filter sized for one item. See
+    /// [`BloomFilter::new`] for `params`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let users: HashSet<&str> = HashSet::from(["alice", "bob"]);
+    /// let filter: BloomFilter<str> = BloomFilter::from_set(users.iter().copied(), 0.001);
+    /// assert!(filter.contains("alice"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn from_set<'a, I>(items: I, params: impl Into<FilterParams>) -> Self
+    where
+        I: IntoIterator<Item = &'a T>,
+        I::IntoIter: ExactSizeIterator,
+        T: 'a,
+    {
+        Self::try_from_set(items, params).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a filter from a set of items like [`BloomFilter::from_set`],
+    /// returning an error instead of panicking.
+    pub fn try_from_set<'a, I>(
+        items: I,
+        params: impl Into<FilterParams>,
+    ) -> Result<Self, BloomError>
+    where
+        I: IntoIterator<Item = &'a T>,
+        I::IntoIter: ExactSizeIterator,
+        T: 'a,
+    {
+        let items = items.into_iter();
+        let mut filter = Self::try_new(items.len().max(1), params)?;
+        for item in items {
+            filter.insert(item);
+        }
+        Ok(filter)
+    }
+}
+
+/// Snapshots a set into a filter sized for its length, with a false positive
+/// rate of [`DEFAULT_FALSE_POSITIVE_RATE`]. Use [`BloomFilter::from_set`] to
+/// choose the rate.
+impl<T: Hash, S> From<&HashSet<T, S>> for BloomFilter<T> {
+    fn from(set: &HashSet<T, S>) -> Self {
+        Self::from_set(set, DEFAULT_FALSE_POSITIVE_RATE)
+    }
+}
+
+/// Snapshots a set into a filter sized for its length, with a false positive
+/// rate of [`DEFAULT_FALSE_POSITIVE_RATE`]. Use [`BloomFilter::from_set`] to
+/// choose the rate.
+impl<T: Hash> From<&BTreeSet<T>> for BloomFilter<T> {
+    fn from(set: &BTreeSet<T>) -> Self {
+        Self::from_set(set, DEFAULT_FALSE_POSITIVE_RATE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_sets() {
+        let hashed: HashSet<u64> = (0..1000).collect();
+        let ordered: BTreeSet<u64> = (0..1000).collect();
+        let from_hashed = BloomFilter::from(&hashed);
+        let from_ordered = BloomFilter::from(&ordered);
+        assert_eq!(from_hashed.to_bytes(), from_ordered.to_bytes());
+        assert!((0..1000).all(|i| from_hashed.contains(&i)));
+        assert_eq!(from_hashed.to_bytes(), {
+            let mut filter = BloomFilter::new(1000, DEFAULT_FALSE_POSITIVE_RATE);
+            (0..1000u64).for_each(|i| filter.insert(&i));
+            filter.to_bytes()
+        });
+
+        let empty = BloomFilter::from(&HashSet::<u64>::new());
+        assert!(!empty.contains(&0));
+        let tight = BloomFilter::from_set(&hashed, 1e-6);
+        assert!(tight.hash_count() > from_hashed.hash_count());
+    }
+}
@@ -0,0 +1,545 @@
+//! Canonical test vectors for the hash functions and probe sequence.
+//!
+//! Filters persisted with [`BloomFilter::to_bytes`] or exchanged with other
+//! implementations are only readable if every build sets the same bits for
+//! the same key. Those bits depend on the hash function, which the standard
+//! library does not promise to keep, on the double-hashing probe sequence
+//! and on the reduction of indices onto the bit count. [`VECTORS`] records,
+//! for a set of keys, hash backends and geometries, the bits an insert into
+//! an empty filter sets; [`verify`] checks the running build against them.
+//!
+//! Other implementations can use the same table to prove they are
+//! compatible: each entry lists the indices in ascending order, without
+//! duplicates.
+//!
+//! # Examples
+//!
+//! ```
+//! use bloomlib::compat;
+//!
+//! compat::verify().expect("this build hashes like every other");
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::counters::OpCounters;
+use crate::params::Geometry;
+use crate::{hashing, AllocStore, BloomFilter, FilterKey};
+
+/// The key under which the [`HashBackend::Keyed`] vectors are computed:
+/// the bytes 0 to 15.
+pub const KEYED_VECTOR_KEY: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+/// A way the crate hashes keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashBackend {
+    /// SipHash-1-3 with zero keys through `DefaultHasher`, as used by
+    /// [`BloomFilter::insert`].
+    Default,
+    /// SipHash-2-4 under [`KEYED_VECTOR_KEY`], as used by
+    /// [`KeyedBloomFilter`](crate::KeyedBloomFilter).
+    Keyed,
+    /// The vectorized SipHash-1-3 of [`BloomFilter::insert_keys`], for
+    /// integer keys.
+    IntegerBatch,
+}
+
+/// A key of a test vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestKey {
+    /// A `str` key, hashed as `str` is (its bytes, then `0xff`).
+    Str(&'static str),
+    /// A `u64` key, hashed as its 8 native-endian bytes. The vectors give
+    /// the bits of little-endian targets; big-endian ones hash integers
+    /// differently and fail them.
+    U64(u64),
+}
+
+/// The bits one key sets in an empty filter of one geometry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestVector {
+    /// The hash backend.
+    pub backend: HashBackend,
+    /// The key inserted.
+    pub key: TestKey,
+    /// The number of bits (m).
+    pub bit_count: u64,
+    /// The number of hash functions (k).
+    pub hash_count: u32,
+    /// The indices of the bits set, in ascending order.
+    pub expected: &'static [u64],
+}
+
+impl TestVector {
+    /// Inserts the key into an empty filter of the vector's geometry with
+    /// this build, returning the indices of the bits set in ascending order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector pairs [`HashBackend::IntegerBatch`] with a
+    /// [`TestKey::Str`].
+    pub fn bit_indices(&self) -> Vec<u64> {
+        match (self.backend, self.key) {
+            (HashBackend::Default, TestKey::Str(key)) => {
+                self.set_bits(|filter: &mut BloomFilter<str>| filter.insert(key))
+            }
+            (HashBackend::Default, TestKey::U64(key)) => {
+                self.set_bits(|filter: &mut BloomFilter<u64>| filter.insert(&key))
+            }
+            (HashBackend::Keyed, TestKey::Str(key)) => {
+                self.set_bits(|filter: &mut BloomFilter<str>| filter.insert_keyed(key))
+            }
+            (HashBackend::Keyed, TestKey::U64(key)) => {
+                self.set_bits(|filter: &mut BloomFilter<u64>| filter.insert_keyed(&key))
+            }
+            (HashBackend::IntegerBatch, TestKey::U64(key)) => {
+                self.set_bits(|filter: &mut BloomFilter<u64>| filter.insert_keys(&[key]))
+            }
+            (HashBackend::IntegerBatch, TestKey::Str(_)) => {
+                panic!("the integer batch backend only hashes integer keys")
+            }
+        }
+    }
+
+    fn set_bits<T: ?Sized + Hash>(&self, insert: impl FnOnce(&mut BloomFilter<T>)) -> Vec<u64> {
+        let geometry = Geometry::exact::<u64>(self.bit_count, self.hash_count)
+            .expect("test vector geometries are valid");
+        let mut filter = BloomFilter {
+            bit_vec: <Vec<u64> as AllocStore>::zeroed(geometry.word_count),
+            bit_count: geometry.bit_count,
+            hash_fn_count: geometry.hash_count,
+            counters: OpCounters::default(),
+            _marker: PhantomData,
+        };
+        insert(&mut filter);
+        filter
+            .packed_words()
+            .enumerate()
+            .flat_map(|(index, word)| {
+                (0..64)
+                    .filter(move |bit| word >> bit & 1 == 1)
+                    .map(move |bit| index as u64 * 64 + bit)
+            })
+            .collect()
+    }
+}
+
+impl<T: ?Sized + Hash> BloomFilter<T> {
+    fn insert_keyed(&mut self, item: &T) {
+        let (h1, h2) = hashing::hash_pair_with(&FilterKey::from_bytes(KEYED_VECTOR_KEY), item);
+        self.insert_hashes(h1, h2);
+    }
+}
+
+/// A test vector this build does not reproduce, returned by [`verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The vector.
+    pub vector: TestVector,
+    /// The indices this build set.
+    pub found: Vec<u64>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} hashing of {:?} into {} bits with {} hashes set bits {:?}, expected {:?}.",
+            self.vector.backend,
+            self.vector.key,
+            self.vector.bit_count,
+            self.vector.hash_count,
+            self.found,
+            self.vector.expected
+        )
+    }
+}
+
+impl Error for Mismatch {}
+
+/// Checks every entry of [`VECTORS`] against this build.
+///
+/// # Errors
+///
+/// Returns the first vector whose bits this build does not reproduce.
+/// Filters written by other builds must not be loaded into this one then:
+/// lookups would miss items that were inserted.
+pub fn verify() -> Result<(), Mismatch> {
+    for vector in VECTORS {
+        let found = vector.bit_indices();
+        if found != vector.expected {
+            return Err(Mismatch {
+                vector: *vector,
+                found,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The canonical test vectors: each backend with keys of both types (only
+/// integers for [`HashBackend::IntegerBatch`]), into a power-of-two and an
+/// odd bit count.
+pub static VECTORS: &[TestVector] = &[
+    vector(
+        HashBackend::Default,
+        TestKey::Str(""),
+        1024,
+        7,
+        &[155, 383, 495, 611, 723, 839, 951],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::Str(""),
+        9585,
+        13,
+        &[
+            332, 701, 1069, 1438, 1806, 4572, 4941, 5309, 5678, 6046, 6414, 9181, 9549,
+        ],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::Str("a"),
+        1024,
+        7,
+        &[4, 38, 72, 499, 533, 567, 601],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::Str("a"),
+        9585,
+        13,
+        &[
+            233, 1230, 1744, 2741, 3256, 4253, 4768, 5283, 6280, 6794, 7791, 8306, 9303,
+        ],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::Str("bloomlib"),
+        1024,
+        7,
+        &[43, 277, 365, 511, 599, 745, 833],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::Str("bloomlib"),
+        9585,
+        13,
+        &[
+            2367, 2587, 2808, 3029, 3250, 3470, 3691, 7270, 7490, 7711, 7932, 8152, 8373,
+        ],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::Str("The quick brown fox jumps over the lazy dog"),
+        1024,
+        7,
+        &[146, 341, 536, 585, 731, 780, 975],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::Str("The quick brown fox jumps over the lazy dog"),
+        9585,
+        13,
+        &[
+            790, 1336, 2296, 2842, 3803, 4349, 5309, 5855, 6401, 7362, 7908, 8868, 9414,
+        ],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::U64(0x0),
+        1024,
+        7,
+        &[88, 126, 164, 581, 619, 657, 695],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::U64(0x0),
+        9585,
+        13,
+        &[
+            306, 776, 1245, 2411, 2880, 4046, 4516, 4985, 6151, 6620, 7090, 8256, 8725,
+        ],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::U64(0x1),
+        1024,
+        7,
+        &[473, 509, 545, 581, 617, 653, 689],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::U64(0x1),
+        9585,
+        13,
+        &[
+            730, 938, 1146, 2730, 2938, 4522, 4730, 4938, 6522, 6731, 8315, 8523, 8731,
+        ],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::U64(0x123456789abcdef),
+        1024,
+        7,
+        &[25, 219, 413, 440, 634, 828, 1022],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::U64(0x123456789abcdef),
+        9585,
+        13,
+        &[
+            375, 1079, 1784, 2488, 3192, 3896, 5031, 5735, 6439, 7144, 7848, 8552, 9256,
+        ],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::U64(0xffffffffffffffff),
+        1024,
+        7,
+        &[9, 133, 309, 433, 609, 733, 909],
+    ),
+    vector(
+        HashBackend::Default,
+        TestKey::U64(0xffffffffffffffff),
+        9585,
+        13,
+        &[
+            645, 1204, 1764, 2709, 3268, 4213, 4772, 5717, 6277, 7221, 7781, 8725, 9285,
+        ],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::Str(""),
+        1024,
+        7,
+        &[2, 50, 98, 146, 194, 242, 290],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::Str(""),
+        9585,
+        13,
+        &[
+            164, 1092, 2021, 2950, 3878, 4807, 5736, 6034, 6665, 6962, 7593, 7891, 8820,
+        ],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::Str("a"),
+        1024,
+        7,
+        &[160, 269, 343, 452, 635, 818, 1001],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::Str("a"),
+        9585,
+        13,
+        &[
+            434, 1146, 2188, 2900, 3612, 4324, 5036, 5748, 6460, 7172, 7884, 8596, 9308,
+        ],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::Str("bloomlib"),
+        1024,
+        7,
+        &[22, 238, 332, 548, 642, 736, 952],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::Str("bloomlib"),
+        9585,
+        13,
+        &[
+            222, 936, 1128, 1843, 2749, 3655, 4561, 5276, 5467, 6182, 7088, 7994, 8900,
+        ],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::Str("The quick brown fox jumps over the lazy dog"),
+        1024,
+        7,
+        &[13, 80, 147, 214, 836, 903, 970],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::Str("The quick brown fox jumps over the lazy dog"),
+        9585,
+        13,
+        &[
+            539, 1345, 2139, 2945, 3738, 4531, 4545, 5338, 6131, 6938, 7731, 8537, 9331,
+        ],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::U64(0x0),
+        1024,
+        7,
+        &[87, 143, 199, 255, 311, 367, 423],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::U64(0x0),
+        9585,
+        13,
+        &[
+            219, 867, 1516, 2165, 3390, 4038, 4687, 5335, 5984, 6633, 7858, 8506, 9155,
+        ],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::U64(0x1),
+        1024,
+        7,
+        &[180, 209, 502, 531, 560, 853, 882],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::U64(0x1),
+        9585,
+        13,
+        &[
+            1631, 1639, 1647, 1655, 4033, 4041, 4049, 6427, 6435, 6443, 8822, 8830, 8838,
+        ],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::U64(0x123456789abcdef),
+        1024,
+        7,
+        &[148, 324, 396, 572, 748, 924, 996],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::U64(0x123456789abcdef),
+        9585,
+        13,
+        &[
+            284, 649, 1013, 2129, 2493, 2857, 4337, 4702, 6181, 6546, 8025, 8390, 8754,
+        ],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::U64(0xffffffffffffffff),
+        1024,
+        7,
+        &[552, 554, 556, 558, 560, 562, 564],
+    ),
+    vector(
+        HashBackend::Keyed,
+        TestKey::U64(0xffffffffffffffff),
+        9585,
+        13,
+        &[
+            77, 832, 1587, 2112, 2867, 3622, 4377, 5132, 5887, 6642, 7397, 8152, 8907,
+        ],
+    ),
+    vector(
+        HashBackend::IntegerBatch,
+        TestKey::U64(0x0),
+        1024,
+        7,
+        &[88, 126, 164, 581, 619, 657, 695],
+    ),
+    vector(
+        HashBackend::IntegerBatch,
+        TestKey::U64(0x0),
+        9585,
+        13,
+        &[
+            306, 776, 1245, 2411, 2880, 4046, 4516, 4985, 6151, 6620, 7090, 8256, 8725,
+        ],
+    ),
+    vector(
+        HashBackend::IntegerBatch,
+        TestKey::U64(0x1),
+        1024,
+        7,
+        &[473, 509, 545, 581, 617, 653, 689],
+    ),
+    vector(
+        HashBackend::IntegerBatch,
+        TestKey::U64(0x1),
+        9585,
+        13,
+        &[
+            730, 938, 1146, 2730, 2938, 4522, 4730, 4938, 6522, 6731, 8315, 8523, 8731,
+        ],
+    ),
+    vector(
+        HashBackend::IntegerBatch,
+        TestKey::U64(0x123456789abcdef),
+        1024,
+        7,
+        &[25, 219, 413, 440, 634, 828, 1022],
+    ),
+    vector(
+        HashBackend::IntegerBatch,
+        TestKey::U64(0x123456789abcdef),
+        9585,
+        13,
+        &[
+            375, 1079, 1784, 2488, 3192, 3896, 5031, 5735, 6439, 7144, 7848, 8552, 9256,
+        ],
+    ),
+    vector(
+        HashBackend::IntegerBatch,
+        TestKey::U64(0xffffffffffffffff),
+        1024,
+        7,
+        &[9, 133, 309, 433, 609, 733, 909],
+    ),
+    vector(
+        HashBackend::IntegerBatch,
+        TestKey::U64(0xffffffffffffffff),
+        9585,
+        13,
+        &[
+            645, 1204, 1764, 2709, 3268, 4213, 4772, 5717, 6277, 7221, 7781, 8725, 9285,
+        ],
+    ),
+];
+
+const fn vector(
+    backend: HashBackend,
+    key: TestKey,
+    bit_count: u64,
+    hash_count: u32,
+    expected: &'static [u64],
+) -> TestVector {
+    TestVector {
+        backend,
+        key,
+        bit_count,
+        hash_count,
+        expected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vectors_hold() {
+        assert_eq!(VECTORS.len(), 40);
+        verify().unwrap();
+
+        let mut vector = VECTORS[0];
+        vector.expected = &[0, 1];
+        let mismatch = Mismatch {
+            found: vector.bit_indices(),
+            vector,
+        };
+        assert_eq!(mismatch.found, VECTORS[0].expected);
+        assert!(mismatch
+            .to_string()
+            .starts_with("Default hashing of Str(\"\")"));
+    }
+}
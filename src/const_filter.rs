@@ -0,0 +1,343 @@
+use crate::hashing;
+
+/// A fixed-size Bloom Filter whose construction and lookup are `const fn`.
+///
+/// Because the standard library's hashers cannot run in const context, this
+/// filter hashes raw bytes with its own const-friendly function (FNV-1a
+/// followed by a SplitMix64 finalizer). From those two hashes it probes the
+/// same positions as [`BloomFilter`](crate::BloomFilter), including the
+/// masking of power-of-two bit counts, so for equal hashes both set the
+/// same bits. A filter over a static
+/// keyword list can therefore be computed entirely at compile time and placed
+/// in read-only memory.
+///
+/// # Type Parameters
+/// * `WORDS`: The number of `u64` words backing the filter (m = 64 * WORDS).
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{const_word_count, ConstBloomFilter};
+///
+/// const WORDS: usize = const_word_count(4, 7);
+/// static KEYWORDS: ConstBloomFilter<WORDS> =
+///     ConstBloomFilter::from_keys(7, &["fn", "let", "match", "impl"]);
+///
+/// // Lookups can run at compile time...
+/// const HAS_FN: bool = KEYWORDS.contains("fn");
+/// assert!(HAS_FN);
+///
+/// // ...or at runtime.
+/// assert!(!KEYWORDS.contains("banana"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ConstBloomFilter<const WORDS: usize> {
+    /// The bit array, sized at compile time.
+    bit_vec: [u64; WORDS],
+    /// The number of hash functions to use (k).
+    hash_fn_count: u32,
+}
+
+impl<const WORDS: usize> ConstBloomFilter<WORDS> {
+    /// Creates an empty filter using `hash_count` hash functions.
+    ///
+    /// # Panics
+    ///
+    /// Panics (or fails const evaluation) if `WORDS` or `hash_count` is 0.
+    pub const fn new(hash_count: u32) -> Self {
+        assert!(WORDS > 0, "Word count must be greater than 0.");
+        assert!(hash_count > 0, "Hash count must be greater than 0.");
+
+        ConstBloomFilter {
+            bit_vec: [0; WORDS],
+            hash_fn_count: hash_count,
+        }
+    }
+
+    /// Creates a filter using `hash_count` hash functions and inserts all `keys`.
+    pub const fn from_keys(hash_count: u32, keys: &[&str]) -> Self {
+        let mut filter = Self::new(hash_count);
+        let mut i = 0;
+        while i < keys.len() {
+            filter.insert(keys[i]);
+            i += 1;
+        }
+        filter
+    }
+
+    /// Inserts a string key into the filter.
+    pub const fn insert(&mut self, key: &str) {
+        self.insert_bytes(key.as_bytes());
+    }
+
+    /// Inserts a byte-string key into the filter.
+    pub const fn insert_bytes(&mut self, key: &[u8]) {
+        const_insert(&mut self.bit_vec, self.hash_fn_count, key);
+    }
+
+    /// Checks if a string key might be in the filter.
+    ///
+    /// Returns `false` if the key is definitely not present.
+    pub const fn contains(&self, key: &str) -> bool {
+        self.contains_bytes(key.as_bytes())
+    }
+
+    /// Checks if a byte-string key might be in the filter.
+    ///
+    /// Returns `false` if the key is definitely not present.
+    pub const fn contains_bytes(&self, key: &[u8]) -> bool {
+        const_contains(&self.bit_vec, self.hash_fn_count, key)
+    }
+
+    /// Returns the number of hash functions (k) being used.
+    pub const fn hash_count(&self) -> u32 {
+        self.hash_fn_count
+    }
+
+    /// Returns the total number of bits in the filter (m).
+    pub const fn bit_count(&self) -> u64 {
+        (WORDS * 64) as u64
+    }
+
+    /// Returns the underlying words of the bit array.
+    pub const fn as_words(&self) -> &[u64; WORDS] {
+        &self.bit_vec
+    }
+
+    /// Returns a [`StaticBloom`] view over this filter's bits.
+    pub const fn as_static(&'static self) -> StaticBloom {
+        StaticBloom::from_raw_parts(&self.bit_vec, self.hash_fn_count)
+    }
+}
+
+/// A read-only Bloom Filter over a `'static` word slice.
+///
+/// This is the type emitted by the `bloom!` macro (enabled with the `macros`
+/// feature), which hashes a word list at build time and embeds the resulting
+/// bits in the binary. It shares its hashing and probing with
+/// [`ConstBloomFilter`], so both answer identically for the same keys and
+/// geometry.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{ConstBloomFilter, StaticBloom};
+///
+/// static FILTER: ConstBloomFilter<2> = ConstBloomFilter::from_keys(7, &["fn", "let"]);
+/// static VIEW: StaticBloom = FILTER.as_static();
+///
+/// assert!(VIEW.contains("let"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct StaticBloom {
+    /// The bit array, typically embedded in read-only memory.
+    bit_vec: &'static [u64],
+    /// The number of hash functions to use (k).
+    hash_fn_count: u32,
+}
+
+impl StaticBloom {
+    /// Creates a filter from precomputed words and a hash count.
+    ///
+    /// The words must have been produced by the same hashing scheme as
+    /// [`ConstBloomFilter`] (as the `bloom!` macro does).
+    ///
+    /// # Panics
+    ///
+    /// Panics (or fails const evaluation) if `words` is empty or `hash_count` is 0.
+    pub const fn from_raw_parts(words: &'static [u64], hash_count: u32) -> Self {
+        assert!(!words.is_empty(), "Word count must be greater than 0.");
+        assert!(hash_count > 0, "Hash count must be greater than 0.");
+
+        StaticBloom {
+            bit_vec: words,
+            hash_fn_count: hash_count,
+        }
+    }
+
+    /// Checks if a string key might be in the filter.
+    ///
+    /// Returns `false` if the key is definitely not present.
+    pub const fn contains(&self, key: &str) -> bool {
+        self.contains_bytes(key.as_bytes())
+    }
+
+    /// Checks if a byte-string key might be in the filter.
+    ///
+    /// Returns `false` if the key is definitely not present.
+    pub const fn contains_bytes(&self, key: &[u8]) -> bool {
+        const_contains(self.bit_vec, self.hash_fn_count, key)
+    }
+
+    /// Returns the number of hash functions (k) being used.
+    pub const fn hash_count(&self) -> u32 {
+        self.hash_fn_count
+    }
+
+    /// Returns the total number of bits in the filter (m).
+    pub const fn bit_count(&self) -> u64 {
+        (self.bit_vec.len() * 64) as u64
+    }
+
+    /// Returns the underlying words of the bit array.
+    pub const fn as_words(&self) -> &'static [u64] {
+        self.bit_vec
+    }
+}
+
+/// Sets the k bits for `key` in `words`.
+const fn const_insert(words: &mut [u64], hash_count: u32, key: &[u8]) {
+    let (h1, h2) = const_hashes(key);
+    let bit_count = (words.len() * 64) as u64;
+    let mut i = 0;
+    while i < hash_count {
+        let (vec_index, mask) = const_bit(h1, h2, i, bit_count);
+        words[vec_index] |= mask;
+        i += 1;
+    }
+}
+
+/// Checks whether all k bits for `key` are set in `words`.
+const fn const_contains(words: &[u64], hash_count: u32, key: &[u8]) -> bool {
+    let (h1, h2) = const_hashes(key);
+    let bit_count = (words.len() * 64) as u64;
+    let mut i = 0;
+    while i < hash_count {
+        let (vec_index, mask) = const_bit(h1, h2, i, bit_count);
+        if (words[vec_index] & mask) == 0 {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Computes the vector index and bit mask for the i-th hash position, with
+/// the same reduction as a runtime filter: a mask for a power-of-two bit
+/// count and fastrange otherwise.
+#[inline]
+const fn const_bit(h1: u64, h2: u64, i: u32, bit_count: u64) -> (usize, u64) {
+    let bit_index = hashing::bit_index(h1, h2, i, bit_count);
+    ((bit_index / 64) as usize, 1u64 << (bit_index % 64))
+}
+
+/// Returns the number of `u64` words a [`ConstBloomFilter`] needs to hold
+/// `expected_items` items with `hash_count` hash functions.
+///
+/// Uses the same 50% fill rate assumption as [`FilterParams::HashCount`](crate::FilterParams::HashCount),
+/// i.e. m = (k * n) / ln(2), rounded up to whole words.
+pub const fn const_word_count(expected_items: usize, hash_count: u32) -> usize {
+    assert!(expected_items > 0, "Expected items must be greater than 0.");
+    assert!(hash_count > 0, "Hash count must be greater than 0.");
+
+    let exact = (hash_count as f64 * expected_items as f64) / std::f64::consts::LN_2;
+    let mut m = exact as u64;
+    if (m as f64) < exact {
+        m += 1;
+    }
+    m.div_ceil(64) as usize
+}
+
+/// Computes two 64-bit hashes for a byte string in const context.
+///
+/// `h1` is FNV-1a passed through the SplitMix64 finalizer; `h2` is derived
+/// from `h1` by a second finalizer round. The `bloomlib-macros` crate carries
+/// a copy of this function and must be kept in sync with it.
+const fn const_hashes(bytes: &[u8]) -> (u64, u64) {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        i += 1;
+    }
+
+    let h1 = splitmix64(hash);
+    let h2 = splitmix64(h1 ^ 0x9e37_79b9_7f4a_7c15);
+    (h1, h2)
+}
+
+/// The SplitMix64 finalizer.
+pub(crate) const fn splitmix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counters::OpCounters;
+    use crate::BloomFilter;
+    use std::marker::PhantomData;
+
+    const KEYWORDS: [&str; 5] = ["fn", "let", "match", "impl", "trait"];
+    const WORDS: usize = const_word_count(KEYWORDS.len(), 7);
+    static FILTER: ConstBloomFilter<WORDS> = ConstBloomFilter::from_keys(7, &KEYWORDS);
+
+    #[test]
+    fn test_const_construction_and_lookup() {
+        const {
+            assert!(FILTER.contains("match"));
+            assert!(!FILTER.contains("banana"));
+        }
+
+        for keyword in KEYWORDS {
+            assert!(FILTER.contains(keyword));
+        }
+    }
+
+    #[test]
+    fn test_runtime_insert_matches_const() {
+        let mut filter = ConstBloomFilter::<WORDS>::new(7);
+        for keyword in KEYWORDS {
+            filter.insert_bytes(keyword.as_bytes());
+        }
+        assert_eq!(filter.as_words(), FILTER.as_words());
+    }
+
+    #[test]
+    fn test_static_view_matches_owner() {
+        static VIEW: StaticBloom = FILTER.as_static();
+        assert_eq!(VIEW.as_words(), FILTER.as_words());
+        assert_eq!(VIEW.bit_count(), FILTER.bit_count());
+        for keyword in KEYWORDS {
+            assert!(VIEW.contains(keyword));
+        }
+        assert!(!VIEW.contains("banana"));
+    }
+
+    #[test]
+    fn test_bits_match_runtime_filter() {
+        fn check<const WORDS: usize>() {
+            let filter = ConstBloomFilter::<WORDS>::from_keys(7, &KEYWORDS);
+            let mut runtime = BloomFilter::<[u8]> {
+                bit_vec: vec![0u64; WORDS],
+                bit_count: 64 * WORDS as u64,
+                hash_fn_count: 7,
+                counters: OpCounters::default(),
+                _marker: PhantomData,
+            };
+            for keyword in KEYWORDS {
+                let (h1, h2) = const_hashes(keyword.as_bytes());
+                runtime.insert_hashes(h1, h2);
+            }
+            assert_eq!(
+                runtime.bit_vec[..],
+                filter.as_words()[..],
+                "{} words",
+                WORDS
+            );
+        }
+        // 1024 bits, reduced with a mask, and 960, reduced with fastrange.
+        check::<16>();
+        check::<15>();
+    }
+
+    #[test]
+    fn test_const_word_count() {
+        // m = 7 * 100 / ln(2) = 1010 bits -> 16 words
+        assert_eq!(const_word_count(100, 7), 16);
+        assert_eq!(const_word_count(1, 1), 1);
+    }
+}
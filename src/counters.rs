@@ -0,0 +1,213 @@
+#[cfg(feature = "counters")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(feature = "counters")]
+use crate::BloomFilter;
+
+/// A snapshot of a filter's operation counters.
+///
+/// Returned by [`BloomFilter::counters`](crate::BloomFilter::counters),
+/// which requires the `counters` feature.
+#[cfg(feature = "counters")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// The number of items inserted, duplicates included.
+    pub inserts: u64,
+    /// The number of items looked up, including the check of
+    /// `check_and_insert`.
+    pub queries: u64,
+    /// The number of lookups that reported the item present.
+    pub positives: u64,
+}
+
+#[cfg(feature = "counters")]
+impl Counters {
+    /// Returns the fraction of lookups that reported the item present, or 0
+    /// if there were none.
+    ///
+    /// When the looked-up items are known to be absent, this is the observed
+    /// false positive rate, to compare with the rate the filter was sized
+    /// for.
+    pub fn positive_rate(&self) -> f64 {
+        if self.queries == 0 {
+            0.0
+        } else {
+            self.positives as f64 / self.queries as f64
+        }
+    }
+}
+
+#[cfg(feature = "counters")]
+impl<T: ?Sized, B> BloomFilter<T, B> {
+    /// Returns a snapshot of the filter's operation counters.
+    ///
+    /// Requires the `counters` feature. Every insert and lookup through the
+    /// filter's methods is counted, batches and parallel inserts included;
+    /// the counts are kept with relaxed atomics, so a snapshot taken while
+    /// other threads query the filter may be slightly behind.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut bf: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+    /// for i in 0..1000 {
+    ///     bf.insert(&i);
+    /// }
+    /// bf.contains_batch(&(1000..11_000).collect::<Vec<u64>>());
+    ///
+    /// let counters = bf.counters();
+    /// assert_eq!((counters.inserts, counters.queries), (1000, 10_000));
+    /// assert!(counters.positive_rate() < 0.02);
+    /// ```
+    pub fn counters(&self) -> Counters {
+        self.counters.snapshot()
+    }
+
+    /// Sets all operation counters back to 0.
+    ///
+    /// Requires the `counters` feature.
+    pub fn reset_counters(&mut self) {
+        self.counters.reset();
+    }
+}
+
+/// The live counters of a filter.
+///
+/// Relaxed atomics, so that lookups through `&self` can count. Without the
+/// `counters` feature this is empty and every method is a no-op, which
+/// leaves the filter's size and hot paths unchanged.
+#[derive(Debug, Default)]
+pub(crate) struct OpCounters {
+    #[cfg(feature = "counters")]
+    inserts: AtomicU64,
+    #[cfg(feature = "counters")]
+    queries: AtomicU64,
+    #[cfg(feature = "counters")]
+    positives: AtomicU64,
+}
+
+#[cfg(feature = "counters")]
+impl OpCounters {
+    #[inline]
+    pub(crate) fn record_inserts(&self, count: usize) {
+        self.inserts.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_query(&self, found: bool) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        self.positives.fetch_add(found as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_queries(&self, results: &[bool]) {
+        let positives = results.iter().filter(|&&found| found).count();
+        self.queries
+            .fetch_add(results.len() as u64, Ordering::Relaxed);
+        self.positives
+            .fetch_add(positives as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> Counters {
+        Counters {
+            inserts: self.inserts.load(Ordering::Relaxed),
+            queries: self.queries.load(Ordering::Relaxed),
+            positives: self.positives.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        *self = OpCounters::default();
+    }
+}
+
+#[cfg(not(feature = "counters"))]
+impl OpCounters {
+    #[inline(always)]
+    pub(crate) fn record_inserts(&self, _count: usize) {}
+
+    #[inline(always)]
+    pub(crate) fn record_query(&self, _found: bool) {}
+
+    #[inline(always)]
+    pub(crate) fn record_queries(&self, _results: &[bool]) {}
+}
+
+impl OpCounters {
+    /// Creates zeroed counters, also in const context.
+    pub(crate) const fn new() -> Self {
+        OpCounters {
+            #[cfg(feature = "counters")]
+            inserts: AtomicU64::new(0),
+            #[cfg(feature = "counters")]
+            queries: AtomicU64::new(0),
+            #[cfg(feature = "counters")]
+            positives: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Clone for OpCounters {
+    #[cfg(feature = "counters")]
+    fn clone(&self) -> Self {
+        let counters = self.snapshot();
+        OpCounters {
+            inserts: AtomicU64::new(counters.inserts),
+            queries: AtomicU64::new(counters.queries),
+            positives: AtomicU64::new(counters.positives),
+        }
+    }
+
+    #[cfg(not(feature = "counters"))]
+    fn clone(&self) -> Self {
+        OpCounters {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "counters"))]
+    #[test]
+    fn test_disabled_counters_take_no_space() {
+        assert_eq!(std::mem::size_of::<OpCounters>(), 0);
+    }
+
+    #[cfg(feature = "counters")]
+    #[test]
+    fn test_counters() {
+        let mut bf: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        bf.insert(&1);
+        bf.insert_keys(&[2, 3]);
+        assert!(!bf.check_and_insert(&1));
+        assert!(bf.check_and_insert(&4));
+        assert!(bf.contains(&2));
+        assert_eq!(bf.contains_keys(&[3, 4]), [true, true]);
+        assert_eq!(bf.contains_batch(&[1u64, 2]), [true, true]);
+
+        let expected = Counters {
+            inserts: 4,
+            queries: 7,
+            positives: 6,
+        };
+        assert_eq!(bf.counters(), expected);
+        assert_eq!(bf.clone().counters(), expected);
+
+        bf.reset_counters();
+        assert_eq!(bf.counters(), Counters::default());
+        assert_eq!(Counters::default().positive_rate(), 0.0);
+    }
+
+    #[cfg(feature = "counters")]
+    #[test]
+    fn test_count_novel_is_not_counted() {
+        let mut bf: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        bf.insert(&1);
+        let before = bf.counters();
+        assert_eq!(bf.count_novel([1, 2, 3]), 2);
+        assert_eq!(bf.counters(), before);
+    }
+}
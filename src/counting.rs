@@ -0,0 +1,640 @@
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::approx::{self, ApproximateSet};
+use crate::hashing;
+use crate::params::Geometry;
+use crate::serialize::Crc32;
+use crate::{BloomError, FilterParams, LoadLimits};
+
+/// The bytes every serialized counting filter starts with.
+const MAGIC: [u8; 4] = *b"BLMC";
+
+/// The version of the format written by [`CountingBloomFilter::write_to`].
+const VERSION: u32 = 1;
+
+/// The length of the header: magic, version, counter count, hash count and
+/// escape count.
+const HEADER_BYTES: usize = 28;
+
+/// The 2-bit code of counters of 3 or more, whose value follows the packed
+/// counters.
+const ESCAPE: u8 = 3;
+
+/// The number of packed bytes written or read per I/O call.
+const IO_BYTES: usize = 4096;
+
+/// A counting Bloom Filter supporting concurrent inserts and removals.
+///
+/// Each of the m positions is an 8-bit counter rather than a bit, so items
+/// can be removed again. Every counter is an `AtomicU8` updated with its own
+/// compare-and-swap, so [`insert`](Self::insert) and [`remove`](Self::remove)
+/// take `&self` and scale across threads without a global lock: two threads
+/// only contend when they touch the same counter.
+///
+/// Counters saturate at 255. A saturated counter is never decremented again,
+/// which keeps the no-false-negatives guarantee at the cost of that position
+/// staying set.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::CountingBloomFilter;
+///
+/// let filter = CountingBloomFilter::new(1000, 0.01);
+/// filter.insert("session-1");
+/// assert!(filter.contains("session-1"));
+///
+/// filter.remove("session-1");
+/// assert!(!filter.contains("session-1"));
+/// ```
+#[derive(Debug)]
+pub struct CountingBloomFilter<T: ?Sized> {
+    /// One saturating counter per position.
+    counters: Box<[AtomicU8]>,
+    /// The total number of counters in the filter (m).
+    cell_count: u64,
+    /// The number of hash functions to use (k).
+    hash_fn_count: u32,
+    /// Phantom data to hold the type information without affecting `Send`/`Sync`.
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: ?Sized + Hash> CountingBloomFilter<T> {
+    /// Creates a new counting Bloom Filter optimized for the given expected
+    /// item count and configuration. See [`BloomFilter::new`](crate::BloomFilter::new).
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`](crate::BloomFilter::new).
+    pub fn new(expected_items: usize, params: impl Into<FilterParams>) -> Self {
+        Self::try_new(expected_items, params).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a new counting Bloom Filter, returning an error instead of
+    /// panicking. See [`BloomFilter::try_new`](crate::BloomFilter::try_new).
+    pub fn try_new(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<Self, BloomError> {
+        let geometry = Geometry::optimal::<u8>(expected_items, params.into())?;
+        // Each position takes a whole byte here, not a bit.
+        let max_cells = isize::MAX as u64;
+        if geometry.bit_count > max_cells {
+            return Err(BloomError::CapacityOverflow {
+                bits: geometry.bit_count,
+                max_bits: max_cells,
+            });
+        }
+        let counters = (0..geometry.bit_count).map(|_| AtomicU8::new(0)).collect();
+
+        Ok(CountingBloomFilter {
+            counters,
+            cell_count: geometry.bit_count,
+            hash_fn_count: geometry.hash_count,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Inserts an item, incrementing its k counters.
+    pub fn insert(&self, item: &T) {
+        let (h1, h2) = hashing::hash_pair(item);
+        for i in 0..self.hash_fn_count {
+            let cell = &self.counters[self.get_index(h1, h2, i)];
+            // Err means the counter is saturated, which is left as is.
+            let _ = cell.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                count.checked_add(1)
+            });
+        }
+    }
+
+    /// Removes one occurrence of an item, decrementing its k counters.
+    ///
+    /// Returns `false` (and changes nothing) if the item is definitely not
+    /// present. Removing an item that was never inserted but happens to be a
+    /// false positive corrupts the counters of other items, so only remove
+    /// items known to have been inserted.
+    pub fn remove(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair(item);
+        if !self.contains_hashed(h1, h2) {
+            return false;
+        }
+        for i in 0..self.hash_fn_count {
+            let cell = &self.counters[self.get_index(h1, h2, i)];
+            // Saturated counters have lost their true count and stay put.
+            let _ = cell.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| match count {
+                0 | u8::MAX => None,
+                _ => Some(count - 1),
+            });
+        }
+        true
+    }
+
+    /// Checks if an item might be in the Bloom Filter.
+    ///
+    /// Returns `true` if the item might be present (with a probability of false positive).
+    /// Returns `false` if the item is definitely not present.
+    pub fn contains(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair(item);
+        self.contains_hashed(h1, h2)
+    }
+
+    /// Returns an upper bound on how many times an item was inserted.
+    ///
+    /// This is the minimum of the item's k counters; 0 means the item is
+    /// definitely not present.
+    pub fn estimate_count(&self, item: &T) -> u8 {
+        let (h1, h2) = hashing::hash_pair(item);
+        (0..self.hash_fn_count)
+            .map(|i| self.counters[self.get_index(h1, h2, i)].load(Ordering::Relaxed))
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Resets all counters to zero.
+    ///
+    /// Inserts and removals running concurrently with `clear` may or may not be retained.
+    pub fn clear(&self) {
+        for cell in self.counters.iter() {
+            cell.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the fraction of counters that are non-zero.
+    pub fn fill_ratio(&self) -> f64 {
+        let set = self
+            .counters
+            .iter()
+            .filter(|cell| cell.load(Ordering::Relaxed) > 0)
+            .count();
+        set as f64 / self.cell_count as f64
+    }
+
+    /// Returns how many counters hold each value: entry `v` is the number
+    /// of counters equal to `v`, and entry 255 the number of saturated ones.
+    ///
+    /// The distribution shows how often positions are shared or items
+    /// repeated; counters in the upper range are close to saturating, after
+    /// which they no longer go down on removal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::CountingBloomFilter;
+    ///
+    /// let filter = CountingBloomFilter::new(1000, 0.01);
+    /// filter.insert("once");
+    /// filter.insert("twice");
+    /// filter.insert("twice");
+    ///
+    /// let histogram = filter.counter_histogram();
+    /// assert_eq!(histogram.iter().sum::<u64>(), filter.memory_usage_bytes() as u64);
+    /// // The k counters of "twice" are at 2 or more.
+    /// assert!(histogram[2..].iter().sum::<u64>() >= filter.hash_count() as u64);
+    /// assert_eq!(histogram[255], 0);
+    /// ```
+    pub fn counter_histogram(&self) -> [u64; 256] {
+        let mut histogram = [0u64; 256];
+        for cell in self.counters.iter() {
+            histogram[cell.load(Ordering::Relaxed) as usize] += 1;
+        }
+        histogram
+    }
+
+    /// Returns the approximate memory usage of the counters in bytes.
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.counters.len()
+    }
+
+    /// Returns the number of hash functions (k) being used.
+    pub fn hash_count(&self) -> u32 {
+        self.hash_fn_count
+    }
+
+    fn contains_hashed(&self, h1: u64, h2: u64) -> bool {
+        (0..self.hash_fn_count)
+            .all(|i| self.counters[self.get_index(h1, h2, i)].load(Ordering::Relaxed) > 0)
+    }
+
+    /// Calculates the counter index for the i-th hash function.
+    #[inline]
+    fn get_index(&self, h1: u64, h2: u64, i: u32) -> usize {
+        hashing::bit_index(h1, h2, i, self.cell_count) as usize
+    }
+}
+
+impl<T: ?Sized + Hash> CountingBloomFilter<T> {
+    /// Returns the length of [`to_bytes`](Self::to_bytes) in bytes.
+    pub fn serialized_len(&self) -> usize {
+        HEADER_BYTES + self.counters.len().div_ceil(4) + self.escape_count() + 4
+    }
+
+    /// Serializes the filter into a byte vector.
+    ///
+    /// Counters are packed into 2 bits each, as most are 0, 1 or 2; counters
+    /// of 3 or more are stored in 2 bits plus a byte after the packed
+    /// counters. A filter filled to its expected item count takes about a
+    /// quarter of its in-memory size. The bytes end with a CRC-32, as those
+    /// of [`BloomFilter::to_bytes`](crate::BloomFilter::to_bytes) do.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::CountingBloomFilter;
+    ///
+    /// let filter = CountingBloomFilter::new(1000, 0.01);
+    /// filter.insert("session-1");
+    ///
+    /// let bytes = filter.to_bytes();
+    /// assert!(bytes.len() < filter.memory_usage_bytes() / 3);
+    /// let loaded: CountingBloomFilter<str> = CountingBloomFilter::from_bytes(&bytes).unwrap();
+    /// assert_eq!(loaded.estimate_count("session-1"), 1);
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_len());
+        self.write_to(&mut bytes)
+            .expect("writing to a Vec cannot fail");
+        bytes
+    }
+
+    /// Serializes the filter into `writer`, in the format of
+    /// [`to_bytes`](Self::to_bytes).
+    ///
+    /// Counters changed by concurrent inserts or removals may be written
+    /// before or after the change.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `writer` returns.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let values: Vec<u8> = self
+            .counters
+            .iter()
+            .map(|cell| cell.load(Ordering::Relaxed))
+            .collect();
+        let escapes: Vec<u8> = values
+            .iter()
+            .filter(|&&count| count >= ESCAPE)
+            .map(|&count| count - ESCAPE)
+            .collect();
+
+        let mut header = [0u8; HEADER_BYTES];
+        header[0..4].copy_from_slice(&MAGIC);
+        header[4..8].copy_from_slice(&VERSION.to_le_bytes());
+        header[8..16].copy_from_slice(&self.cell_count.to_le_bytes());
+        header[16..20].copy_from_slice(&self.hash_fn_count.to_le_bytes());
+        header[20..28].copy_from_slice(&(escapes.len() as u64).to_le_bytes());
+        let mut crc = Crc32::new();
+        crc.update(&header);
+        writer.write_all(&header)?;
+
+        let mut packed = Vec::with_capacity(IO_BYTES);
+        for cells in values.chunks(4 * IO_BYTES) {
+            packed.clear();
+            packed.extend(cells.chunks(4).map(|cells| {
+                cells.iter().enumerate().fold(0u8, |byte, (slot, &count)| {
+                    byte | count.min(ESCAPE) << (2 * slot)
+                })
+            }));
+            crc.update(&packed);
+            writer.write_all(&packed)?;
+        }
+        crc.update(&escapes);
+        writer.write_all(&escapes)?;
+        writer.write_all(&crc.finish())
+    }
+
+    /// Deserializes a filter written by [`to_bytes`](Self::to_bytes).
+    ///
+    /// Any size of filter is accepted; use
+    /// [`from_bytes_with_limits`](Self::from_bytes_with_limits) for bytes
+    /// from untrusted sources.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::InvalidFormat`] if `bytes` is not exactly one
+    /// serialized counting filter, and [`BloomError::ZeroHashCount`] or
+    /// [`BloomError::CapacityOverflow`] if the header describes a filter that
+    /// cannot be built on this platform.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BloomError> {
+        Self::from_bytes_with_limits(bytes, &LoadLimits::default())
+    }
+
+    /// Deserializes a filter like [`from_bytes`](Self::from_bytes),
+    /// rejecting filters beyond `limits` with [`BloomError::InvalidFormat`].
+    /// A counter takes one byte of [`LoadLimits::max_bytes`].
+    ///
+    /// The length of `bytes` is checked against the header before anything
+    /// is allocated.
+    pub fn from_bytes_with_limits(bytes: &[u8], limits: &LoadLimits) -> Result<Self, BloomError> {
+        let header: &[u8; HEADER_BYTES] = bytes
+            .get(..HEADER_BYTES)
+            .and_then(|header| header.try_into().ok())
+            .ok_or(BloomError::InvalidFormat("the data is truncated"))?;
+        let (cell_count, _, escape_count) = decode_header(header, limits)?;
+        let expected_len = (HEADER_BYTES as u64 + 4)
+            .checked_add(cell_count.div_ceil(4))
+            .and_then(|len| len.checked_add(escape_count));
+        if expected_len != Some(bytes.len() as u64) {
+            return Err(BloomError::InvalidFormat(
+                "the length does not match the counter count",
+            ));
+        }
+        Self::read_from_with_limits(bytes, limits).map_err(|err| {
+            match err
+                .into_inner()
+                .and_then(|err| err.downcast::<BloomError>().ok())
+            {
+                Some(err) => *err,
+                None => BloomError::InvalidFormat("the data is truncated"),
+            }
+        })
+    }
+
+    /// Deserializes one filter from `reader`, in the format of
+    /// [`to_bytes`](Self::to_bytes). Exactly the filter's bytes are
+    /// consumed.
+    ///
+    /// The counters are allocated as their bytes arrive, so a forged header
+    /// fails at the end of the data rather than on allocation, but any size
+    /// of filter is accepted; use
+    /// [`read_from_with_limits`](Self::read_from_with_limits) for data from
+    /// untrusted sources.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `reader` returns, and an error of kind
+    /// [`io::ErrorKind::InvalidData`] wrapping a [`BloomError`] if the data
+    /// is not a serialized counting filter.
+    pub fn read_from<R: Read>(reader: R) -> io::Result<Self> {
+        Self::read_from_with_limits(reader, &LoadLimits::default())
+    }
+
+    /// Deserializes one filter from `reader` like
+    /// [`read_from`](Self::read_from), rejecting filters beyond `limits`
+    /// after reading the header.
+    pub fn read_from_with_limits<R: Read>(mut reader: R, limits: &LoadLimits) -> io::Result<Self> {
+        let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+        let mut header = [0u8; HEADER_BYTES];
+        reader.read_exact(&mut header)?;
+        let (cell_count, hash_count, escape_count) =
+            decode_header(&header, limits).map_err(invalid)?;
+        let mut crc = Crc32::new();
+        crc.update(&header);
+
+        let mut values = Vec::new();
+        let mut packed = vec![0u8; IO_BYTES];
+        let mut remaining = cell_count.div_ceil(4) as usize;
+        while remaining > 0 {
+            let chunk = &mut packed[..remaining.min(IO_BYTES)];
+            reader.read_exact(chunk)?;
+            crc.update(chunk);
+            remaining -= chunk.len();
+            for &byte in chunk.iter() {
+                values.extend((0..4).map(|slot| byte >> (2 * slot) & ESCAPE));
+            }
+        }
+        if values.drain(cell_count as usize..).any(|count| count != 0) {
+            return Err(invalid(BloomError::InvalidFormat(
+                "counters are set beyond the counter count",
+            )));
+        }
+
+        // Read through `take` so a forged count cannot allocate up front.
+        let mut escapes = Vec::new();
+        reader
+            .by_ref()
+            .take(escape_count)
+            .read_to_end(&mut escapes)?;
+        if escapes.len() as u64 != escape_count {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        crc.update(&escapes);
+        let mut escapes = escapes.into_iter();
+        for count in values.iter_mut().filter(|count| **count == ESCAPE) {
+            let extra = escapes.next().ok_or_else(|| {
+                invalid(BloomError::InvalidFormat(
+                    "the escape count does not match the counters",
+                ))
+            })?;
+            *count = extra
+                .checked_add(ESCAPE)
+                .ok_or_else(|| invalid(BloomError::InvalidFormat("a counter exceeds 255")))?;
+        }
+        if escapes.next().is_some() {
+            return Err(invalid(BloomError::InvalidFormat(
+                "the escape count does not match the counters",
+            )));
+        }
+
+        let mut checksum = [0u8; 4];
+        reader.read_exact(&mut checksum)?;
+        if checksum != crc.finish() {
+            return Err(invalid(BloomError::InvalidFormat(
+                "the checksum does not match",
+            )));
+        }
+        Ok(CountingBloomFilter {
+            counters: values.into_iter().map(AtomicU8::new).collect(),
+            cell_count,
+            hash_fn_count: hash_count,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the number of counters of 3 or more.
+    fn escape_count(&self) -> usize {
+        self.counters
+            .iter()
+            .filter(|cell| cell.load(Ordering::Relaxed) >= ESCAPE)
+            .count()
+    }
+}
+
+/// Decodes a header into the counter count, hash count and escape count,
+/// checking them against `limits`.
+fn decode_header(
+    header: &[u8; HEADER_BYTES],
+    limits: &LoadLimits,
+) -> Result<(u64, u32, u64), BloomError> {
+    if header[0..4] != MAGIC {
+        return Err(BloomError::InvalidFormat("the magic bytes are missing"));
+    }
+    if u32::from_le_bytes(header[4..8].try_into().unwrap()) != VERSION {
+        return Err(BloomError::InvalidFormat(
+            "the format version is unsupported",
+        ));
+    }
+    let cell_count = u64::from_le_bytes(header[8..16].try_into().unwrap());
+    let hash_count = u32::from_le_bytes(header[16..20].try_into().unwrap());
+    let escape_count = u64::from_le_bytes(header[20..28].try_into().unwrap());
+    Geometry::exact::<u8>(cell_count, hash_count)?;
+    if hash_count as u64 > cell_count {
+        return Err(BloomError::InvalidFormat(
+            "the hash count exceeds the bit count",
+        ));
+    }
+    if escape_count > cell_count {
+        return Err(BloomError::InvalidFormat(
+            "the escape count exceeds the counter count",
+        ));
+    }
+    if hash_count > limits.max_hash_count {
+        return Err(BloomError::InvalidFormat(
+            "the hash count exceeds the limit",
+        ));
+    }
+    if cell_count > limits.max_bytes {
+        return Err(BloomError::InvalidFormat(
+            "the counter array exceeds the size limit",
+        ));
+    }
+    Ok((cell_count, hash_count, escape_count))
+}
+
+impl<T: ?Sized + Hash> ApproximateSet<T> for CountingBloomFilter<T> {
+    fn insert(&mut self, item: &T) {
+        CountingBloomFilter::insert(self, item);
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        CountingBloomFilter::contains(self, item)
+    }
+
+    fn estimated_len(&self) -> f64 {
+        approx::estimated_items(self.fill_ratio(), self.cell_count, self.hash_fn_count)
+    }
+
+    fn fp_estimate(&self) -> f64 {
+        self.fill_ratio().powi(self.hash_fn_count as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_insert_and_remove() {
+        let filter = CountingBloomFilter::new(100, 0.01);
+        filter.insert("seen");
+        filter.insert("seen");
+        filter.insert("also seen");
+        assert_eq!(filter.estimate_count("seen"), 2);
+
+        assert!(filter.remove("seen"));
+        assert!(filter.contains("seen"));
+        assert!(filter.remove("seen"));
+        assert!(!filter.contains("seen"));
+        assert!(filter.contains("also seen"));
+        assert!(!filter.remove("unseen"));
+    }
+
+    #[test]
+    fn test_saturated_counters_are_sticky() {
+        let filter = CountingBloomFilter::new(10, 0.01);
+        for _ in 0..300 {
+            filter.insert(&1);
+        }
+        assert_eq!(filter.estimate_count(&1), u8::MAX);
+        let histogram = filter.counter_histogram();
+        assert_eq!(histogram[255], filter.hash_count() as u64);
+        assert_eq!(histogram.iter().sum::<u64>(), filter.cell_count);
+        filter.remove(&1);
+        assert_eq!(filter.estimate_count(&1), u8::MAX);
+    }
+
+    #[test]
+    fn test_packed_round_trip() {
+        let filter = CountingBloomFilter::new(1000, 0.01);
+        for i in 0..1000u64 {
+            for _ in 0..i % 6 {
+                filter.insert(&i);
+            }
+        }
+        (0..300).for_each(|_| filter.insert(&7));
+        let bytes = filter.to_bytes();
+        assert_eq!(bytes.len(), filter.serialized_len());
+
+        let loaded = CountingBloomFilter::<u64>::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.counter_histogram(), filter.counter_histogram());
+        assert!((0..1000).all(|i| loaded.estimate_count(&i) == filter.estimate_count(&i)));
+
+        let mut corrupt = bytes.clone();
+        corrupt[HEADER_BYTES] ^= 1;
+        assert_eq!(
+            CountingBloomFilter::<u64>::from_bytes(&corrupt).unwrap_err(),
+            BloomError::InvalidFormat("the checksum does not match")
+        );
+        for len in [0, HEADER_BYTES, bytes.len() - 1] {
+            assert!(CountingBloomFilter::<u64>::from_bytes(&bytes[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_forged_header_does_not_allocate() {
+        let mut forged = [0u8; HEADER_BYTES + 4];
+        forged[0..4].copy_from_slice(&MAGIC);
+        forged[4..8].copy_from_slice(&VERSION.to_le_bytes());
+        forged[8..16].copy_from_slice(&(1u64 << 62).to_le_bytes());
+        forged[16..20].copy_from_slice(&7u32.to_le_bytes());
+        forged[20..28].copy_from_slice(&(1u64 << 62).to_le_bytes());
+
+        assert_eq!(
+            CountingBloomFilter::<u64>::from_bytes(&forged).unwrap_err(),
+            BloomError::InvalidFormat("the length does not match the counter count")
+        );
+        let err = CountingBloomFilter::<u64>::read_from(&forged[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = CountingBloomFilter::<u64>::read_from_with_limits(
+            &forged[..],
+            &LoadLimits::new(1 << 20),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let bytes = CountingBloomFilter::<u64>::new(1000, 0.01).to_bytes();
+        assert!(
+            CountingBloomFilter::<u64>::from_bytes_with_limits(&bytes, &LoadLimits::new(100))
+                .is_err()
+        );
+        assert!(CountingBloomFilter::<u64>::from_bytes_with_limits(
+            &bytes,
+            &LoadLimits::new(1 << 20)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_concurrent_insert_and_remove() {
+        let filter = CountingBloomFilter::new(4000, 0.01);
+        for i in 0..2000u64 {
+            filter.insert(&i);
+        }
+        thread::scope(|scope| {
+            for t in 0..4u64 {
+                let filter = &filter;
+                scope.spawn(move || {
+                    for i in 0..500 {
+                        // Threads 0-1 remove items 0..1000, 2-3 add the new
+                        // items 2000..3000.
+                        if t < 2 {
+                            filter.remove(&(t * 500 + i));
+                        } else {
+                            filter.insert(&(1000 + t * 500 + i));
+                        }
+                    }
+                });
+            }
+        });
+
+        for i in 1000..3000u64 {
+            assert!(filter.contains(&i));
+        }
+        let remaining = (0..1000u64).filter(|i| filter.contains(i)).count();
+        assert!(remaining < 50, "{} removed items still present", remaining);
+    }
+}
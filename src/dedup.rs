@@ -0,0 +1,329 @@
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "metrics")]
+use crate::FilterMetrics;
+use crate::{BloomError, BloomFilter, FilterParams};
+
+/// The bytes a saved deduplicator starts with.
+const MAGIC: [u8; 4] = *b"BLMD";
+
+/// The version of the format written by [`Deduplicator::write_to`].
+const VERSION: u32 = 1;
+
+/// Drops redelivered messages in at-least-once consumers, e.g. of Kafka or
+/// SQS, by their message IDs.
+///
+/// [`is_duplicate`](Self::is_duplicate) records an ID and reports whether
+/// it was seen before. IDs are kept in two filters, each covering one
+/// `window`: the current one and the one before it, and the older one is
+/// cleared and reused whenever a window ends. An ID is therefore always
+/// recognized for at least `window` after it was seen, and forgotten after
+/// at most twice that, so memory stays fixed however long the consumer
+/// runs. Size `expected_ids` for the busiest window.
+///
+/// Each filter is sized for half of the requested false positive rate, as
+/// an ID is looked up in both: a new message is then dropped as a duplicate
+/// with at most that probability, and a duplicate is never let through
+/// within the window.
+///
+/// The deduplicator can be shared between consumer threads. With
+/// [`write_to`](Self::write_to) and [`read_from`](Self::read_from) it
+/// survives restarts, and with the `metrics` feature
+/// [`with_metrics`](Self::with_metrics) reports through the `metrics`
+/// crate.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use bloomlib::Deduplicator;
+///
+/// let dedup: Deduplicator<str> =
+///     Deduplicator::new(1_000_000, 1e-6, Duration::from_secs(3600));
+/// assert!(!dedup.is_duplicate("msg-1"));
+/// assert!(dedup.is_duplicate("msg-1"));
+/// assert_eq!(dedup.stats().duplicates, 1);
+/// ```
+#[derive(Debug)]
+pub struct Deduplicator<T: ?Sized> {
+    state: Mutex<Windows<T>>,
+    window: Duration,
+    #[cfg(feature = "metrics")]
+    metrics: Option<FilterMetrics>,
+}
+
+/// The filters of the current and the previous window.
+#[derive(Debug)]
+struct Windows<T: ?Sized> {
+    current: BloomFilter<T>,
+    previous: BloomFilter<T>,
+    /// The start of the current window.
+    started: Instant,
+    stats: DedupStats,
+}
+
+/// The activity of a [`Deduplicator`] since it was created or loaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupStats {
+    /// The number of IDs checked.
+    pub checked: u64,
+    /// The number of IDs reported as duplicates.
+    pub duplicates: u64,
+    /// The number of windows that ended.
+    pub rotations: u64,
+}
+
+impl<T: ?Sized + Hash> Deduplicator<T> {
+    /// Creates a deduplicator for up to `expected_ids` distinct IDs per
+    /// `window`, dropping new IDs with the false positive rate or hash
+    /// count in `params`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero, or under the same conditions as
+    /// [`BloomFilter::new`].
+    pub fn new(expected_ids: usize, params: impl Into<FilterParams>, window: Duration) -> Self {
+        Self::try_new(expected_ids, params, window).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a deduplicator, returning an error instead of panicking.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn try_new(
+        expected_ids: usize,
+        params: impl Into<FilterParams>,
+        window: Duration,
+    ) -> Result<Self, BloomError> {
+        assert!(!window.is_zero(), "Window must be greater than 0.");
+        let params = match params.into() {
+            // An invalid rate is passed on as is, for the filter to reject.
+            FilterParams::FalsePositiveRate(rate) if rate > 0.0 && rate < 1.0 => {
+                FilterParams::FalsePositiveRate(rate / 2.0)
+            }
+            params => params,
+        };
+        let current = BloomFilter::try_new(expected_ids, params)?;
+        let previous = current.clone();
+        Ok(Self::from_windows(
+            current,
+            previous,
+            Instant::now(),
+            window,
+        ))
+    }
+
+    fn from_windows(
+        current: BloomFilter<T>,
+        previous: BloomFilter<T>,
+        started: Instant,
+        window: Duration,
+    ) -> Self {
+        Deduplicator {
+            state: Mutex::new(Windows {
+                current,
+                previous,
+                started,
+                stats: DedupStats::default(),
+            }),
+            window,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Reports through the `metrics` crate, under the label
+    /// `filter="{name}"`: checks as queries, duplicates as positives, new
+    /// IDs as inserts, ended windows as rotations, and the current filter's
+    /// fill ratio at every rotation. See [`FilterMetrics`].
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, name: &str) -> Self {
+        let metrics = FilterMetrics::new(name);
+        metrics.record_fill_ratio(self.lock().current.fill_ratio());
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Records `id` and returns `true` if it was seen within the last one
+    /// to two windows, or `false` if it is new.
+    pub fn is_duplicate(&self, id: &T) -> bool {
+        self.is_duplicate_at(id, Instant::now())
+    }
+
+    /// Records `id` as of `now`, like [`is_duplicate`](Self::is_duplicate).
+    pub fn is_duplicate_at(&self, id: &T, now: Instant) -> bool {
+        let mut state = self.lock();
+        self.rotate_if_due(&mut state, now);
+        let duplicate = !state.current.check_and_insert(id) || state.previous.contains(id);
+        state.stats.checked += 1;
+        state.stats.duplicates += duplicate as u64;
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.record_queries(1, duplicate as u64);
+            metrics.record_inserts(!duplicate as u64);
+        }
+        duplicate
+    }
+
+    /// Returns the counts since the deduplicator was created or loaded.
+    pub fn stats(&self) -> DedupStats {
+        self.lock().stats
+    }
+
+    /// Returns the length of a window.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Starts a new window if the current one has ended by `now`, clearing
+    /// both filters if a whole window passed without any check.
+    fn rotate_if_due(&self, state: &mut Windows<T>, now: Instant) {
+        let elapsed = now.saturating_duration_since(state.started);
+        if elapsed < self.window {
+            return;
+        }
+        let Windows {
+            current, previous, ..
+        } = state;
+        std::mem::swap(current, previous);
+        current.clear();
+        if elapsed >= self.window * 2 {
+            previous.clear();
+        }
+        state.started = now;
+        state.stats.rotations += 1;
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.record_rotation();
+            metrics.record_fill_ratio(0.0);
+        }
+    }
+
+    /// Serializes the deduplicator into `writer`: the window, how far into
+    /// it the current one is, and both filters in the format of
+    /// [`BloomFilter::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `writer` returns.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let state = self.lock();
+        let elapsed = Instant::now().saturating_duration_since(state.started);
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.window.as_millis() as u64).to_le_bytes())?;
+        writer.write_all(&(elapsed.as_millis() as u64).to_le_bytes())?;
+        state.current.write_to(&mut writer)?;
+        state.previous.write_to(&mut writer)
+    }
+
+    /// Deserializes a deduplicator written by [`write_to`](Self::write_to),
+    /// resuming its window where it was when written. Time that passed in
+    /// between is not counted, so a consumer that was down does not lose
+    /// the IDs from before it stopped.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `reader` returns, and an error of kind
+    /// [`io::ErrorKind::InvalidData`] wrapping a [`BloomError`] if the data
+    /// is not a serialized deduplicator.
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut header = [0u8; 24];
+        reader.read_exact(&mut header)?;
+        let invalid = |reason| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                BloomError::InvalidFormat(reason),
+            )
+        };
+        if header[0..4] != MAGIC {
+            return Err(invalid("the magic bytes are missing"));
+        }
+        if u32::from_le_bytes(header[4..8].try_into().unwrap()) != VERSION {
+            return Err(invalid("the format version is unsupported"));
+        }
+        let window = Duration::from_millis(u64::from_le_bytes(header[8..16].try_into().unwrap()));
+        let elapsed = Duration::from_millis(u64::from_le_bytes(header[16..24].try_into().unwrap()));
+        if window.is_zero() {
+            return Err(invalid("the window is zero"));
+        }
+        let current = BloomFilter::read_from(&mut reader)?;
+        let previous: BloomFilter<T> = BloomFilter::read_from(&mut reader)?;
+        if (current.bit_count(), current.hash_count())
+            != (previous.bit_count(), previous.hash_count())
+        {
+            return Err(invalid("the windows' filters differ in size"));
+        }
+        let now = Instant::now();
+        let started = now.checked_sub(elapsed.min(window)).unwrap_or(now);
+        Ok(Self::from_windows(current, previous, started, window))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Windows<T>> {
+        // The filters are consistent even if a holder panicked.
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_rotation() {
+        let window = Duration::from_secs(60);
+        let dedup: Deduplicator<u64> = Deduplicator::new(1000, 0.001, window);
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+
+        assert!(!dedup.is_duplicate_at(&1, at(0)));
+        assert!(dedup.is_duplicate_at(&1, at(30)));
+        // Rotated at 70: 1 moves to the previous window.
+        assert!(!dedup.is_duplicate_at(&2, at(70)));
+        assert!(dedup.is_duplicate_at(&1, at(100)));
+        // Rotated at 140: 1 was re-seen at 100, so it is kept, 2 too.
+        assert!(dedup.is_duplicate_at(&2, at(140)));
+        assert!(dedup.is_duplicate_at(&1, at(150)));
+        // A gap of two windows forgets everything.
+        assert!(!dedup.is_duplicate_at(&1, at(400)));
+        assert_eq!(
+            dedup.stats(),
+            DedupStats {
+                checked: 7,
+                duplicates: 4,
+                rotations: 3
+            }
+        );
+
+        let false_positives = (1000..2000u64)
+            .filter(|id| dedup.is_duplicate_at(id, at(400)))
+            .count();
+        assert!(false_positives < 10, "{}", false_positives);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dedup: Deduplicator<str> = Deduplicator::new(1000, 0.01, Duration::from_secs(3600));
+        assert!(!dedup.is_duplicate("a"));
+        let mut bytes = Vec::new();
+        dedup.write_to(&mut bytes).unwrap();
+
+        let loaded: Deduplicator<str> = Deduplicator::read_from(&bytes[..]).unwrap();
+        assert_eq!(loaded.window(), Duration::from_secs(3600));
+        assert!(loaded.is_duplicate("a"));
+        assert!(!loaded.is_duplicate("b"));
+
+        bytes[0] = b'X';
+        let err = Deduplicator::<str>::read_from(&bytes[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        for len in [0, 20, 30, bytes.len() - 1] {
+            assert!(Deduplicator::<str>::read_from(&bytes[1..len.max(1)]).is_err());
+        }
+    }
+}
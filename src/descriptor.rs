@@ -0,0 +1,283 @@
+use std::hash::Hash;
+
+use crate::serialize::{self, Crc32};
+use crate::{BitStore, BloomError, BloomFilter, KeyedBloomFilter, TabulationBloomFilter};
+
+/// The magic bytes that identify a serialized descriptor.
+const MAGIC: [u8; 4] = *b"BLMD";
+
+/// The layout version of serialized descriptors.
+const LAYOUT_VERSION: u32 = 1;
+
+/// The hash function a filter derives its two base hashes from.
+///
+/// In every case `h1` is the hash of the item's bytes and `h2` the hash of
+/// the item's bytes followed by the 8 little-endian bytes of `h1`. The
+/// item's bytes are those its `Hash` implementation writes, e.g. a `str`'s
+/// UTF-8 bytes followed by `0xff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum HashAlgorithm {
+    /// SipHash-1-3 with zero keys, as the standard library's
+    /// `DefaultHasher` currently implements it. The seed is 0.
+    SipHash13 = 1,
+    /// SipHash-2-4 under a secret 128-bit key, as used by
+    /// [`KeyedBloomFilter`]. The seed is the key's public
+    /// [`key_id`](crate::FilterKey::key_id); the key itself is shared
+    /// separately.
+    SipHash24 = 2,
+    /// Tabulation hashing with tables filled from the seed, as used by
+    /// [`TabulationBloomFilter`].
+    Tabulation = 3,
+}
+
+impl HashAlgorithm {
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(HashAlgorithm::SipHash13),
+            2 => Some(HashAlgorithm::SipHash24),
+            3 => Some(HashAlgorithm::Tabulation),
+            _ => None,
+        }
+    }
+}
+
+/// How a filter derives the k bit positions of an item from its two base
+/// hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum ProbeStrategy {
+    /// Double hashing: the i-th position, for i in `0..k`, is
+    /// `g = h1 + i * h2` with wrapping 64-bit arithmetic, reduced onto the
+    /// bit count m as `g & (m - 1)` if m is a power of two and as the top 64
+    /// bits of the 128-bit product `g * m` otherwise. Bit `p` is bit
+    /// `p % 64` of the `p / 64`-th little-endian `u64` word of the bits.
+    DoubleHashing = 1,
+}
+
+impl ProbeStrategy {
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(ProbeStrategy::DoubleHashing),
+            _ => None,
+        }
+    }
+}
+
+/// Everything another implementation needs to query a filter exported by
+/// this crate: the geometry, the hash function and its seed, the probe
+/// sequence and the version of the format the bits are serialized in.
+///
+/// A descriptor is 40 bytes, written by [`to_bytes`](Self::to_bytes)
+/// either before a filter's own bytes or on its own, all integers
+/// little-endian:
+///
+/// | Offset | Size | Field |
+/// |---|---|---|
+/// | 0 | 4 | magic `BLMD` |
+/// | 4 | 4 | descriptor layout version, 1 |
+/// | 8 | 4 | [`version`](Self::version) of the bits format |
+/// | 12 | 8 | bit count m |
+/// | 20 | 4 | hash count k |
+/// | 24 | 1 | [`HashAlgorithm`] id |
+/// | 25 | 1 | [`ProbeStrategy`] id |
+/// | 26 | 2 | zero |
+/// | 28 | 8 | seed |
+/// | 36 | 4 | CRC-32 (IEEE 802.3) of bytes 0 to 35 |
+///
+/// Query-side code checks that it supports the descriptor's algorithm,
+/// probe strategy and version, and that the bits it received
+/// [`match`](BloomFilter::matches) the descriptor, before answering
+/// lookups from them. [`compat::VECTORS`](crate::compat::VECTORS) lists
+/// bits to test a port against.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{BloomFilter, FilterDescriptor};
+///
+/// let mut filter: BloomFilter<str> = BloomFilter::new(1000, 0.01);
+/// filter.insert("alice");
+///
+/// let mut exported = filter.descriptor().to_bytes().to_vec();
+/// exported.extend(filter.to_bytes());
+///
+/// let (descriptor, bits) = exported.split_at(FilterDescriptor::LEN);
+/// let descriptor = FilterDescriptor::from_bytes(descriptor).unwrap();
+/// let filter: BloomFilter<str> = BloomFilter::from_bytes(bits).unwrap();
+/// assert!(filter.matches(&descriptor));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FilterDescriptor {
+    /// The version of the format the bits are serialized in, as in the
+    /// header of [`BloomFilter::to_bytes`].
+    pub version: u32,
+    /// The number of bits (m).
+    pub bit_count: u64,
+    /// The number of hash functions (k).
+    pub hash_count: u32,
+    /// The hash function.
+    pub hash: HashAlgorithm,
+    /// The seed or key id of the hash function.
+    pub seed: u64,
+    /// The probe sequence.
+    pub probe: ProbeStrategy,
+}
+
+impl FilterDescriptor {
+    /// The length of a serialized descriptor in bytes.
+    pub const LEN: usize = 40;
+
+    /// Serializes the descriptor.
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4..8].copy_from_slice(&LAYOUT_VERSION.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.version.to_le_bytes());
+        bytes[12..20].copy_from_slice(&self.bit_count.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.hash_count.to_le_bytes());
+        bytes[24] = self.hash as u8;
+        bytes[25] = self.probe as u8;
+        bytes[28..36].copy_from_slice(&self.seed.to_le_bytes());
+        let mut crc = Crc32::new();
+        crc.update(&bytes[..36]);
+        bytes[36..].copy_from_slice(&crc.finish());
+        bytes
+    }
+
+    /// Deserializes a descriptor written by [`to_bytes`](Self::to_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::InvalidFormat`] if `bytes` is not exactly one
+    /// descriptor, or names an algorithm or strategy this build does not
+    /// know.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BloomError> {
+        let bytes: &[u8; Self::LEN] = bytes
+            .try_into()
+            .map_err(|_| BloomError::InvalidFormat("the descriptor length is wrong"))?;
+        if bytes[0..4] != MAGIC {
+            return Err(BloomError::InvalidFormat("the magic bytes are missing"));
+        }
+        let mut crc = Crc32::new();
+        crc.update(&bytes[..36]);
+        if bytes[36..] != crc.finish() {
+            return Err(BloomError::InvalidFormat("the checksum does not match"));
+        }
+        if u32::from_le_bytes(bytes[4..8].try_into().unwrap()) != LAYOUT_VERSION {
+            return Err(BloomError::InvalidFormat(
+                "the descriptor version is unsupported",
+            ));
+        }
+        Ok(FilterDescriptor {
+            version: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            bit_count: u64::from_le_bytes(bytes[12..20].try_into().unwrap()),
+            hash_count: u32::from_le_bytes(bytes[20..24].try_into().unwrap()),
+            hash: HashAlgorithm::from_id(bytes[24])
+                .ok_or(BloomError::InvalidFormat("the hash algorithm is unknown"))?,
+            probe: ProbeStrategy::from_id(bytes[25])
+                .ok_or(BloomError::InvalidFormat("the probe strategy is unknown"))?,
+            seed: u64::from_le_bytes(bytes[28..36].try_into().unwrap()),
+        })
+    }
+
+    fn new(bit_count: u64, hash_count: u32, hash: HashAlgorithm, seed: u64) -> Self {
+        FilterDescriptor {
+            version: serialize::VERSION,
+            bit_count,
+            hash_count,
+            hash,
+            seed,
+            probe: ProbeStrategy::DoubleHashing,
+        }
+    }
+}
+
+impl<T: ?Sized + Hash, B: BitStore> BloomFilter<T, B> {
+    /// Describes how the filter hashes and probes items, for other
+    /// implementations querying its bytes. See [`FilterDescriptor`].
+    pub fn descriptor(&self) -> FilterDescriptor {
+        FilterDescriptor::new(
+            self.bit_count,
+            self.hash_fn_count,
+            HashAlgorithm::SipHash13,
+            0,
+        )
+    }
+
+    /// Returns `true` if `descriptor` describes this filter: its bits can be
+    /// queried the way the descriptor says.
+    pub fn matches(&self, descriptor: &FilterDescriptor) -> bool {
+        self.descriptor() == *descriptor
+    }
+}
+
+impl<T: ?Sized + Hash> KeyedBloomFilter<T> {
+    /// Describes how the filter hashes and probes items. The descriptor
+    /// carries the key's id, not the key.
+    pub fn descriptor(&self) -> FilterDescriptor {
+        let filter = self.filter();
+        FilterDescriptor::new(
+            filter.bit_count,
+            filter.hash_fn_count,
+            HashAlgorithm::SipHash24,
+            self.key().key_id(),
+        )
+    }
+
+    /// Returns `true` if `descriptor` describes this filter and its key.
+    pub fn matches(&self, descriptor: &FilterDescriptor) -> bool {
+        self.descriptor() == *descriptor
+    }
+}
+
+impl<T: ?Sized + Hash> TabulationBloomFilter<T> {
+    /// Describes how the filter hashes and probes items.
+    pub fn descriptor(&self) -> FilterDescriptor {
+        let filter = self.filter();
+        FilterDescriptor::new(
+            filter.bit_count,
+            filter.hash_fn_count,
+            HashAlgorithm::Tabulation,
+            self.hash().seed(),
+        )
+    }
+
+    /// Returns `true` if `descriptor` describes this filter and its seed.
+    pub fn matches(&self, descriptor: &FilterDescriptor) -> bool {
+        self.descriptor() == *descriptor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FilterKey, TabulationHash};
+
+    #[test]
+    fn test_descriptor_round_trip() {
+        let plain: BloomFilter<str> = BloomFilter::new(1000, 0.01);
+        let keyed: KeyedBloomFilter<str> =
+            KeyedBloomFilter::new(FilterKey::from_bytes([7; 16]), 1000, 0.01);
+        let tabulated: TabulationBloomFilter<str> =
+            TabulationBloomFilter::new(TabulationHash::new(9), 1000, 0.01);
+
+        for descriptor in [
+            plain.descriptor(),
+            keyed.descriptor(),
+            tabulated.descriptor(),
+        ] {
+            let bytes = descriptor.to_bytes();
+            assert_eq!(FilterDescriptor::from_bytes(&bytes), Ok(descriptor));
+            let mut corrupted = bytes;
+            corrupted[13] ^= 1;
+            assert!(FilterDescriptor::from_bytes(&corrupted).is_err());
+        }
+        assert!(plain.matches(&plain.descriptor()));
+        assert!(!plain.matches(&keyed.descriptor()));
+        assert!(!tabulated.matches(
+            &TabulationBloomFilter::<str>::new(TabulationHash::new(10), 1000, 0.01).descriptor()
+        ));
+        assert!(FilterDescriptor::from_bytes(&plain.to_bytes()).is_err());
+    }
+}
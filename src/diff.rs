@@ -0,0 +1,152 @@
+use std::hash::Hash;
+
+use crate::approx::estimated_items;
+use crate::{BitStore, BloomError, BloomFilter};
+
+/// How two filters of the same geometry differ, returned by
+/// [`BloomFilter::diff`].
+///
+/// Replicas that received the same inserts have identical bits, so any
+/// difference points at lost or extra inserts. The bit counts say where
+/// the replicas differ; [`diverging_items`](Self::diverging_items)
+/// estimates how many items that amounts to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilterDiff {
+    /// The number of 64-bit words of the bit array.
+    pub word_count: u64,
+    /// The number of 64-bit words that differ.
+    pub differing_words: u64,
+    /// The number of bits set in the first filter only.
+    pub bits_only_in_self: u64,
+    /// The number of bits set in the second filter only.
+    pub bits_only_in_other: u64,
+    /// The estimated number of items in the first filter.
+    pub estimated_items_self: f64,
+    /// The estimated number of items in the second filter.
+    pub estimated_items_other: f64,
+    /// The estimated number of items in their union.
+    pub estimated_items_union: f64,
+}
+
+impl FilterDiff {
+    /// Returns `true` if the filters have the same bits.
+    pub fn is_identical(&self) -> bool {
+        self.differing_words == 0
+    }
+
+    /// Estimates the number of items inserted into one filter but not the
+    /// other, as `2 n(A ∪ B) - n(A) - n(B)`.
+    ///
+    /// Like [`BloomFilter::estimated_items`], the estimate is only useful
+    /// while the filters are far from full.
+    pub fn diverging_items(&self) -> f64 {
+        let estimate = 2.0 * self.estimated_items_union
+            - self.estimated_items_self
+            - self.estimated_items_other;
+        estimate.max(0.0)
+    }
+}
+
+impl<T: ?Sized + Hash, B: BitStore> BloomFilter<T, B> {
+    /// Compares the bits of this filter with those of `other`, e.g. of two
+    /// replicas that should have received the same inserts.
+    ///
+    /// The storage types may differ.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::IncompatibleFilters`] if the bit counts or hash
+    /// counts differ.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut primary: BloomFilter<u64> = BloomFilter::new(10_000, 0.01);
+    /// let mut replica: BloomFilter<u64> = BloomFilter::new(10_000, 0.01);
+    /// for i in 0..1000 {
+    ///     primary.insert(&i);
+    ///     if i % 10 != 0 {
+    ///         replica.insert(&i);
+    ///     }
+    /// }
+    ///
+    /// let diff = primary.diff(&replica).unwrap();
+    /// assert_eq!(diff.bits_only_in_other, 0);
+    /// assert!((diff.diverging_items() - 100.0).abs() < 20.0);
+    /// ```
+    pub fn diff<C: BitStore>(&self, other: &BloomFilter<T, C>) -> Result<FilterDiff, BloomError> {
+        if (self.bit_count, self.hash_fn_count) != (other.bit_count, other.hash_fn_count) {
+            return Err(BloomError::IncompatibleFilters {
+                expected: (self.bit_count, self.hash_fn_count),
+                found: (other.bit_count, other.hash_fn_count),
+            });
+        }
+        let word_count = self.bit_count.div_ceil(64);
+        let mut diff = FilterDiff {
+            word_count,
+            differing_words: 0,
+            bits_only_in_self: 0,
+            bits_only_in_other: 0,
+            estimated_items_self: 0.0,
+            estimated_items_other: 0.0,
+            estimated_items_union: 0.0,
+        };
+        let (mut set_self, mut set_other, mut set_union) = (0u64, 0u64, 0u64);
+        for (word, other_word) in self
+            .packed_words()
+            .zip(other.packed_words())
+            .take(word_count as usize)
+        {
+            diff.differing_words += (word != other_word) as u64;
+            diff.bits_only_in_self += (word & !other_word).count_ones() as u64;
+            diff.bits_only_in_other += (other_word & !word).count_ones() as u64;
+            set_self += word.count_ones() as u64;
+            set_other += other_word.count_ones() as u64;
+            set_union += (word | other_word).count_ones() as u64;
+        }
+        let estimate = |set_bits: u64| {
+            estimated_items(
+                set_bits as f64 / self.bit_count as f64,
+                self.bit_count,
+                self.hash_fn_count,
+            )
+        };
+        diff.estimated_items_self = estimate(set_self);
+        diff.estimated_items_other = estimate(set_other);
+        diff.estimated_items_union = estimate(set_union);
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_of_replicas() {
+        let mut primary: BloomFilter<u64> = BloomFilter::new(10_000, 0.01);
+        let mut replica: BloomFilter<u64> = BloomFilter::new(10_000, 0.01);
+        for i in 0..5000 {
+            primary.insert(&i);
+            replica.insert(&i);
+        }
+        let diff = primary.diff(&replica).unwrap();
+        assert!(diff.is_identical());
+        assert_eq!(diff.diverging_items(), 0.0);
+        assert_eq!(diff.estimated_items_self, primary.estimated_items());
+
+        (5000..5100).for_each(|i| primary.insert(&i));
+        (6000..6050).for_each(|i| replica.insert(&i));
+        let diff = primary.diff(&replica).unwrap();
+        assert!(diff.differing_words > 0 && diff.differing_words <= 150 * 7);
+        assert!(diff.bits_only_in_self > diff.bits_only_in_other);
+        assert!((diff.diverging_items() - 150.0).abs() < 20.0, "{:?}", diff);
+
+        assert!(matches!(
+            primary.diff(&BloomFilter::<u64>::new(100, 0.01)),
+            Err(BloomError::IncompatibleFilters { .. })
+        ));
+    }
+}
@@ -0,0 +1,305 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::{hashing, AtomicBloomFilter, ShardedBloomFilter};
+
+/// The number of ring positions per shard [`DistributedBloom::new`] uses.
+const DEFAULT_VIRTUAL_NODES: usize = 128;
+
+/// One shard of a [`DistributedBloom`]: a local filter, or a client for a
+/// filter on another machine.
+///
+/// Implemented for the filters whose inserts take `&self`, which cannot
+/// fail, and for references and `Arc`s to any shard. A remote shard, e.g.
+/// over the HTTP API of `bloom serve`, implements it with its client's
+/// error type.
+pub trait BloomShard<T: ?Sized> {
+    /// The error of a failed call, such as a network error.
+    type Error;
+
+    /// Inserts an item.
+    fn insert(&self, item: &T) -> Result<(), Self::Error>;
+
+    /// Checks if an item might be in the shard.
+    fn contains(&self, item: &T) -> Result<bool, Self::Error>;
+}
+
+impl<T: ?Sized + Hash> BloomShard<T> for AtomicBloomFilter<T> {
+    type Error = Infallible;
+
+    fn insert(&self, item: &T) -> Result<(), Infallible> {
+        AtomicBloomFilter::insert(self, item);
+        Ok(())
+    }
+
+    fn contains(&self, item: &T) -> Result<bool, Infallible> {
+        Ok(AtomicBloomFilter::contains(self, item))
+    }
+}
+
+impl<T: ?Sized + Hash> BloomShard<T> for ShardedBloomFilter<T> {
+    type Error = Infallible;
+
+    fn insert(&self, item: &T) -> Result<(), Infallible> {
+        ShardedBloomFilter::insert(self, item);
+        Ok(())
+    }
+
+    fn contains(&self, item: &T) -> Result<bool, Infallible> {
+        Ok(ShardedBloomFilter::contains(self, item))
+    }
+}
+
+impl<T: ?Sized, S: BloomShard<T> + ?Sized> BloomShard<T> for &S {
+    type Error = S::Error;
+
+    fn insert(&self, item: &T) -> Result<(), S::Error> {
+        (**self).insert(item)
+    }
+
+    fn contains(&self, item: &T) -> Result<bool, S::Error> {
+        (**self).contains(item)
+    }
+}
+
+impl<T: ?Sized, S: BloomShard<T> + ?Sized> BloomShard<T> for Arc<S> {
+    type Error = S::Error;
+
+    fn insert(&self, item: &T) -> Result<(), S::Error> {
+        (**self).insert(item)
+    }
+
+    fn contains(&self, item: &T) -> Result<bool, S::Error> {
+        (**self).contains(item)
+    }
+}
+
+/// A filter spread over named shards, local or remote, with each item
+/// routed to one shard by consistent hashing.
+///
+/// Every shard owns many pseudo-random positions on a hash ring, derived
+/// from its name, and an item goes to the shard owning the first position
+/// at or after the item's own. Clients that list the same shard names
+/// therefore route every item identically, in whatever order they list
+/// them, and adding or removing a shard only moves the items of its share
+/// of the ring: about `1 / n` of them, rather than nearly all as with
+/// `hash % n`. Items that moved are not found in their new shard until
+/// they are inserted again, so membership changes suit filters that are
+/// rebuilt or rotated regularly.
+///
+/// Calls go through the [`BloomShard`] trait, so a shard can be a local
+/// [`AtomicBloomFilter`] or a network client.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{AtomicBloomFilter, DistributedBloom};
+///
+/// let filter = DistributedBloom::new(
+///     ["eu-1", "eu-2", "us-1"].map(|name| (name, AtomicBloomFilter::new(10_000, 0.01))),
+/// );
+/// filter.insert("alice").unwrap();
+/// assert!(filter.contains("alice").unwrap());
+/// println!("alice lives on {}", filter.shard_name("alice"));
+/// ```
+pub struct DistributedBloom<T: ?Sized, S> {
+    /// The shards and their names, in the order added.
+    shards: Vec<(String, S)>,
+    /// The ring positions, sorted, each with the index of its shard.
+    ring: Vec<(u64, usize)>,
+    virtual_nodes: usize,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: ?Sized + Hash, S: BloomShard<T>> DistributedBloom<T, S> {
+    /// Creates a filter over `shards`, given as pairs of a name and a shard,
+    /// with 128 ring positions per shard.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no shards or two have the same name.
+    pub fn new<N: Into<String>>(shards: impl IntoIterator<Item = (N, S)>) -> Self {
+        Self::with_virtual_nodes(shards, DEFAULT_VIRTUAL_NODES)
+    }
+
+    /// Creates a filter with `virtual_nodes` ring positions per shard. More
+    /// positions spread items more evenly over the shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no shards, two have the same name, or
+    /// `virtual_nodes` is 0.
+    pub fn with_virtual_nodes<N: Into<String>>(
+        shards: impl IntoIterator<Item = (N, S)>,
+        virtual_nodes: usize,
+    ) -> Self {
+        assert!(virtual_nodes > 0, "Virtual nodes must be greater than 0.");
+        let mut filter = DistributedBloom {
+            shards: Vec::new(),
+            ring: Vec::new(),
+            virtual_nodes,
+            _marker: PhantomData,
+        };
+        for (name, shard) in shards {
+            filter.add_shard(name, shard);
+        }
+        assert!(
+            !filter.shards.is_empty(),
+            "There must be at least one shard."
+        );
+        filter
+    }
+
+    /// Inserts an item into its shard.
+    pub fn insert(&self, item: &T) -> Result<(), S::Error> {
+        self.shards[self.route(item)].1.insert(item)
+    }
+
+    /// Checks if an item might be in its shard.
+    pub fn contains(&self, item: &T) -> Result<bool, S::Error> {
+        self.shards[self.route(item)].1.contains(item)
+    }
+
+    /// Returns the name of the shard that holds `item`.
+    pub fn shard_name(&self, item: &T) -> &str {
+        &self.shards[self.route(item)].0
+    }
+
+    /// Returns the shards with their names, in the order they were added.
+    pub fn shards(&self) -> impl ExactSizeIterator<Item = (&str, &S)> {
+        self.shards
+            .iter()
+            .map(|(name, shard)| (name.as_str(), shard))
+    }
+
+    /// Adds a shard, which takes over about `1 / n` of the items from the
+    /// others.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a shard named `name` already exists.
+    pub fn add_shard(&mut self, name: impl Into<String>, shard: S) {
+        let name = name.into();
+        assert!(
+            self.shards.iter().all(|(existing, _)| *existing != name),
+            "There is already a shard named {:?}.",
+            name
+        );
+        self.shards.push((name, shard));
+        self.build_ring();
+    }
+
+    /// Removes the shard named `name` and returns it, handing its items'
+    /// share of the ring to the remaining shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is the last shard.
+    pub fn remove_shard(&mut self, name: &str) -> Option<S> {
+        let index = self
+            .shards
+            .iter()
+            .position(|(existing, _)| existing == name)?;
+        assert!(self.shards.len() > 1, "The last shard cannot be removed.");
+        let (_, shard) = self.shards.remove(index);
+        self.build_ring();
+        Some(shard)
+    }
+
+    fn build_ring(&mut self) {
+        self.ring = self
+            .shards
+            .iter()
+            .enumerate()
+            .flat_map(|(index, (name, _))| {
+                (0..self.virtual_nodes as u64)
+                    .map(move |node| (hashing::hash_pair(&(name.as_str(), node)).0, index))
+            })
+            .collect();
+        // Ties between shards, however unlikely, are broken by name so that
+        // every client orders the ring the same way.
+        let shards = &self.shards;
+        self.ring
+            .sort_unstable_by(|a, b| (a.0, &shards[a.1].0).cmp(&(b.0, &shards[b.1].0)));
+    }
+
+    /// Returns the index of the shard owning the first ring position at or
+    /// after the item's, wrapping around.
+    fn route(&self, item: &T) -> usize {
+        let position = ring_position(hashing::hash_pair(item).0);
+        let next = self.ring.partition_point(|&(point, _)| point < position);
+        self.ring[next % self.ring.len()].1
+    }
+}
+
+/// Maps an item's first hash onto the ring through the MurmurHash3
+/// finalizer, so that routing does not correlate with the bits the item
+/// probes within its shard, which also derive from that hash.
+fn ring_position(h1: u64) -> u64 {
+    let mut z = h1;
+    z = (z ^ (z >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+    z = (z ^ (z >> 33)).wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    z ^ (z >> 33)
+}
+
+impl<T: ?Sized, S> fmt::Debug for DistributedBloom<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.shards.iter().map(|(name, _)| name.as_str()).collect();
+        f.debug_struct("DistributedBloom")
+            .field("shards", &names)
+            .field("virtual_nodes", &self.virtual_nodes)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shard(name: &str) -> (String, AtomicBloomFilter<u64>) {
+        (name.to_owned(), AtomicBloomFilter::new(10_000, 0.01))
+    }
+
+    #[test]
+    fn test_routing_is_consistent() {
+        let mut filter = DistributedBloom::new(["a", "b", "c", "d"].map(shard));
+        let reordered = DistributedBloom::new(["d", "b", "a", "c"].map(shard));
+        let before: Vec<String> = (0..20_000u64)
+            .map(|i| filter.shard_name(&i).to_owned())
+            .collect();
+        assert!((0..20_000u64).all(|i| reordered.shard_name(&i) == before[i as usize]));
+        for name in ["a", "b", "c", "d"] {
+            let share = before.iter().filter(|owner| *owner == name).count();
+            assert!((3500..6500).contains(&share), "{}: {}", name, share);
+        }
+
+        // A fifth shard takes about a fifth of the items, all from the others.
+        filter.add_shard("e", AtomicBloomFilter::new(10_000, 0.01));
+        let moved: Vec<u64> = (0..20_000u64)
+            .filter(|&i| filter.shard_name(&i) != before[i as usize])
+            .collect();
+        assert!((3000..5000).contains(&moved.len()), "{}", moved.len());
+        assert!(moved.iter().all(|i| filter.shard_name(i) == "e"));
+
+        assert!(filter.remove_shard("e").is_some());
+        assert!(filter.remove_shard("e").is_none());
+        assert!((0..20_000u64).all(|i| filter.shard_name(&i) == before[i as usize]));
+    }
+
+    #[test]
+    fn test_insert_and_contains() {
+        let filter = DistributedBloom::new(["a", "b", "c"].map(shard));
+        (0..3000u64).for_each(|i| filter.insert(&i).unwrap());
+        assert!((0..3000u64).all(|i| filter.contains(&i).unwrap()));
+        let false_positives = (3000..13_000u64)
+            .filter(|i| filter.contains(i).unwrap())
+            .count();
+        assert!(false_positives < 50, "{}", false_positives);
+        for (_, shard) in filter.shards() {
+            assert!((800.0..1200.0).contains(&shard.estimated_items()));
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::fmt;
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, Tag};
+
+use crate::{AllocStore, BitStore, BloomError, BloomFilter};
+
+/// The bytes every encrypted filter starts with.
+const MAGIC: [u8; 4] = *b"BLME";
+
+/// The version of the format written by [`BloomFilter::write_encrypted_to`].
+const VERSION: u32 = 1;
+
+/// The length of the header: magic, version, nonce and ciphertext length.
+/// The header is authenticated along with the ciphertext.
+const HEADER_BYTES: usize = 28;
+
+const NONCE_BYTES: usize = 12;
+const TAG_BYTES: usize = 16;
+
+/// A 256-bit key for encrypting filters at rest with ChaCha20-Poly1305.
+///
+/// Requires the `encryption` feature. The key is supplied by the caller, e.g.
+/// from a key management service; its `Debug` output does not reveal it,
+/// and with the `zeroize` feature it is wiped from memory when dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    /// Creates a key from 32 secret bytes.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        EncryptionKey(bytes)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for EncryptionKey {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// With the `zeroize` feature, the key is wiped from memory when dropped.
+#[cfg(feature = "zeroize")]
+impl Drop for EncryptionKey {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for EncryptionKey {}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+impl<T: ?Sized + Hash, B: BitStore> BloomFilter<T, B> {
+    /// Serializes the filter into `writer`, encrypted and authenticated with
+    /// ChaCha20-Poly1305 (RFC 8439) under `key`, using the RustCrypto
+    /// `chacha20poly1305` crate.
+    ///
+    /// Requires the `encryption` feature. The output is a 28-byte header (the
+    /// magic bytes `BLME`, a format version, a random 96-bit nonce and the
+    /// ciphertext length), the encrypted [`to_bytes`](Self::to_bytes) form of
+    /// the filter, and a 16-byte tag over the header and the ciphertext.
+    /// Nonces are drawn from the operating system, so one key can encrypt
+    /// many filters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::{BloomFilter, EncryptionKey};
+    ///
+    /// let key = EncryptionKey::from_bytes([7; 32]);
+    /// let mut bf: BloomFilter<str> = BloomFilter::new(100, 0.01);
+    /// bf.insert("alice@example.com");
+    ///
+    /// let mut sealed = Vec::new();
+    /// bf.write_encrypted_to(&mut sealed, &key).unwrap();
+    /// let loaded: BloomFilter<str> = BloomFilter::read_encrypted_from(&sealed[..], &key).unwrap();
+    /// assert!(loaded.contains("alice@example.com"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `writer` returns, an error if the operating system
+    /// provides no random nonce, and an error of kind
+    /// [`io::ErrorKind::InvalidInput`] if the serialized filter exceeds the
+    /// 256 GiB one nonce can encrypt.
+    pub fn write_encrypted_to<W: Write>(
+        &self,
+        mut writer: W,
+        key: &EncryptionKey,
+    ) -> io::Result<()> {
+        let mut nonce = [0u8; NONCE_BYTES];
+        getrandom::fill(&mut nonce).map_err(io::Error::other)?;
+        let mut sealed = self.to_bytes();
+        let header = encode_header(&nonce, sealed.len() as u64);
+        let tag = seal(key, &nonce, &header, &mut sealed)?;
+
+        writer.write_all(&header)?;
+        writer.write_all(&sealed)?;
+        writer.write_all(&tag)
+    }
+}
+
+impl<T: ?Sized + Hash, B: AllocStore> BloomFilter<T, B> {
+    /// Deserializes one filter written by
+    /// [`write_encrypted_to`](Self::write_encrypted_to) from `reader`.
+    ///
+    /// Requires the `encryption` feature. Nothing is decrypted or parsed
+    /// until the tag has been verified.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `reader` returns, and an error of kind
+    /// [`io::ErrorKind::InvalidData`] wrapping a [`BloomError`]:
+    /// [`BloomError::AuthenticationFailed`] if the data was modified or
+    /// `key` is not the one it was written with, or another error if the
+    /// data is not an encrypted filter.
+    pub fn read_encrypted_from<R: Read>(mut reader: R, key: &EncryptionKey) -> io::Result<Self> {
+        let mut header = [0u8; HEADER_BYTES];
+        reader.read_exact(&mut header)?;
+        let (nonce, length) = decode_header(&header).map_err(invalid_data)?;
+
+        // Read through `take` so a forged length cannot allocate up front.
+        let mut sealed = Vec::new();
+        reader.by_ref().take(length).read_to_end(&mut sealed)?;
+        if sealed.len() as u64 != length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let mut tag = [0u8; TAG_BYTES];
+        reader.read_exact(&mut tag)?;
+
+        open(key, &nonce, &header, &mut sealed, &tag).map_err(invalid_data)?;
+        let filter = Self::from_bytes(&sealed).map_err(invalid_data);
+        // The decrypted bytes are as sensitive as the filter itself.
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut sealed);
+        filter
+    }
+}
+
+fn encode_header(nonce: &[u8; NONCE_BYTES], length: u64) -> [u8; HEADER_BYTES] {
+    let mut header = [0u8; HEADER_BYTES];
+    header[0..4].copy_from_slice(&MAGIC);
+    header[4..8].copy_from_slice(&VERSION.to_le_bytes());
+    header[8..20].copy_from_slice(nonce);
+    header[20..28].copy_from_slice(&length.to_le_bytes());
+    header
+}
+
+fn decode_header(header: &[u8; HEADER_BYTES]) -> Result<([u8; NONCE_BYTES], u64), BloomError> {
+    if header[0..4] != MAGIC {
+        return Err(BloomError::InvalidFormat("the magic bytes are missing"));
+    }
+    if u32::from_le_bytes(header[4..8].try_into().unwrap()) != VERSION {
+        return Err(BloomError::InvalidFormat(
+            "the format version is unsupported",
+        ));
+    }
+    let nonce = header[8..20].try_into().unwrap();
+    Ok((
+        nonce,
+        u64::from_le_bytes(header[20..28].try_into().unwrap()),
+    ))
+}
+
+fn invalid_data(err: BloomError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Creates the cipher for `key`. The cipher wipes its copy of the key when
+/// dropped.
+fn cipher(key: &EncryptionKey) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(Key::from_slice(&key.0))
+}
+
+/// Encrypts `data` in place, returning the tag over `aad` and the
+/// ciphertext (the AEAD construction of RFC 8439, section 2.8).
+fn seal(
+    key: &EncryptionKey,
+    nonce: &[u8; NONCE_BYTES],
+    aad: &[u8],
+    data: &mut [u8],
+) -> io::Result<[u8; TAG_BYTES]> {
+    let tag = cipher(key)
+        .encrypt_in_place_detached(Nonce::from_slice(nonce), aad, data)
+        .map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the filter exceeds the 256 GiB a ChaCha20 nonce can encrypt",
+            )
+        })?;
+    Ok(tag.into())
+}
+
+/// Verifies the tag, in constant time, and decrypts `data` in place.
+fn open(
+    key: &EncryptionKey,
+    nonce: &[u8; NONCE_BYTES],
+    aad: &[u8],
+    data: &mut [u8],
+    expected: &[u8; TAG_BYTES],
+) -> Result<(), BloomError> {
+    cipher(key)
+        .decrypt_in_place_detached(
+            Nonce::from_slice(nonce),
+            aad,
+            data,
+            Tag::from_slice(expected),
+        )
+        .map_err(|_| BloomError::AuthenticationFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        let digits: Vec<u8> = text.bytes().filter(u8::is_ascii_hexdigit).collect();
+        digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rfc_8439_framing() {
+        // Section 2.8.2.
+        let key = EncryptionKey::from_bytes(
+            hex("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f")[..]
+                .try_into()
+                .unwrap(),
+        );
+        let nonce = hex("070000004041424344454647")[..].try_into().unwrap();
+        let aad = hex("50515253c0c1c2c3c4c5c6c7");
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        let mut data = plaintext.to_vec();
+        let tag = seal(&key, &nonce, &aad, &mut data).unwrap();
+        assert_eq!(data[..16], hex("d31a8d34648e60db7b86afbc53ef7ec2"));
+        assert_eq!(tag.to_vec(), hex("1ae10b594f09e26a7e902ecbd0600691"));
+        open(&key, &nonce, &aad, &mut data, &tag).unwrap();
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let key = EncryptionKey::from_bytes([1; 32]);
+        let mut filter: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        (0..1000).for_each(|i| filter.insert(&i));
+        let mut sealed = Vec::new();
+        filter.write_encrypted_to(&mut sealed, &key).unwrap();
+        assert_eq!(
+            sealed.len(),
+            HEADER_BYTES + filter.serialized_len() + TAG_BYTES
+        );
+
+        let loaded: BloomFilter<u64> = BloomFilter::read_encrypted_from(&sealed[..], &key).unwrap();
+        assert_eq!(loaded.to_bytes(), filter.to_bytes());
+
+        let auth_failed = |bytes: &[u8], key: &EncryptionKey| {
+            let err = BloomFilter::<u64>::read_encrypted_from(bytes, key).unwrap_err();
+            err.into_inner().unwrap().downcast::<BloomError>().unwrap()
+                == Box::new(BloomError::AuthenticationFailed)
+        };
+        assert!(auth_failed(&sealed, &EncryptionKey::from_bytes([2; 32])));
+        for at in [10, HEADER_BYTES + 100, sealed.len() - 1] {
+            let mut modified = sealed.clone();
+            modified[at] ^= 1;
+            assert!(auth_failed(&modified, &key), "byte {}", at);
+        }
+        assert_eq!(format!("{:?}", key), "EncryptionKey(..)");
+    }
+}
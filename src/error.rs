@@ -0,0 +1,79 @@
+use std::fmt;
+
+/// Errors returned when a Bloom Filter cannot be created or loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BloomError {
+    /// The expected item count was 0.
+    ZeroExpectedItems,
+    /// The false positive rate was not between 0.0 and 1.0, exclusive.
+    InvalidFalsePositiveRate(f64),
+    /// The hash count was 0.
+    ZeroHashCount,
+    /// The bit array is too large to be allocated on this platform.
+    ///
+    /// On 32-bit targets a single allocation is limited to `isize::MAX` bytes,
+    /// so filters of a few billion bits cannot be addressed.
+    CapacityOverflow {
+        /// The number of bits (m) the configuration requires.
+        bits: u64,
+        /// The largest number of bits this platform can allocate.
+        max_bits: u64,
+    },
+    /// Two filters could not be combined because their bit counts or hash
+    /// counts differ.
+    IncompatibleFilters {
+        /// The bit count (m) and hash count (k) of the receiving filter.
+        expected: (u64, u32),
+        /// The bit count (m) and hash count (k) of the other filter.
+        found: (u64, u32),
+    },
+    /// Bytes could not be read as a serialized filter.
+    InvalidFormat(&'static str),
+    /// A memory budget, in bytes, was too small for a single word of bits.
+    InsufficientMemory(u64),
+    /// Two keyed filters could not be combined because their keys differ.
+    KeyMismatch,
+    /// Encrypted data was modified, or was encrypted under another key.
+    AuthenticationFailed,
+    /// A bulk operation was stopped by its
+    /// [`CancelToken`](crate::CancelToken) before it completed.
+    Cancelled,
+}
+
+impl fmt::Display for BloomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BloomError::ZeroExpectedItems => write!(f, "Expected items must be greater than 0."),
+            BloomError::InvalidFalsePositiveRate(_) => write!(
+                f,
+                "False positive rate must be between 0.0 and 1.0, exclusive."
+            ),
+            BloomError::ZeroHashCount => write!(f, "Hash count must be greater than 0."),
+            BloomError::CapacityOverflow { bits, max_bits } => write!(
+                f,
+                "Filter requires {} bits, but at most {} bits can be allocated on this platform.",
+                bits, max_bits
+            ),
+            BloomError::IncompatibleFilters { expected, found } => write!(
+                f,
+                "Cannot combine a filter of {} bits and {} hashes with one of {} bits and {} hashes.",
+                expected.0, expected.1, found.0, found.1
+            ),
+            BloomError::InvalidFormat(reason) => {
+                write!(f, "Invalid serialized filter: {}.", reason)
+            }
+            BloomError::InsufficientMemory(bytes) => write!(
+                f,
+                "A filter needs at least 8 bytes, but the budget is {} bytes.",
+                bytes
+            ),
+            BloomError::KeyMismatch => write!(f, "Cannot combine filters with different keys."),
+            BloomError::AuthenticationFailed => {
+                write!(f, "Encrypted filter failed authentication.")
+            }
+            BloomError::Cancelled => write!(f, "The operation was cancelled."),
+        }
+    }
+}
+
+impl std::error::Error for BloomError {}
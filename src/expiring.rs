@@ -0,0 +1,325 @@
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::approx::{self, ApproximateSet};
+use crate::hashing;
+use crate::params::Geometry;
+use crate::{BloomError, FilterParams};
+
+/// The number of clock ticks in one TTL.
+const TICKS_PER_TTL: u32 = 256;
+
+/// A Bloom Filter whose entries stop matching once they are older than a
+/// time-to-live.
+///
+/// Each of the m positions holds the time it was last set instead of a bit,
+/// and an item matches only if all k of its positions were set within the
+/// last `ttl`. Expiry is lazy: [`contains`](Self::contains) ignores stale
+/// positions, so nothing needs to run for entries to expire.
+/// [`purge_expired`](Self::purge_expired) resets stale positions for
+/// periodic cleanup, e.g. to keep [`fill_ratio`](Self::fill_ratio)
+/// meaningful; like every other method it takes `&self` and can run on a
+/// background thread while others insert.
+///
+/// Times are kept at a resolution of 1/256 of the TTL, so an entry expires
+/// between `ttl` and `ttl + ttl / 256` after its insertion. Each position
+/// takes 4 bytes. Size the filter for the number of distinct items inserted
+/// within one TTL.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// use bloomlib::ExpiringBloomFilter;
+///
+/// let seen = ExpiringBloomFilter::new(1000, 0.01, Duration::from_secs(3600));
+/// let start = Instant::now();
+/// seen.insert_at("alice", start);
+///
+/// assert!(seen.contains_at("alice", start + Duration::from_secs(3599)));
+/// assert!(!seen.contains_at("alice", start + Duration::from_secs(3615)));
+/// ```
+#[derive(Debug)]
+pub struct ExpiringBloomFilter<T: ?Sized> {
+    /// The tick each position was last set at, counted from 1; 0 is unset.
+    cells: Box<[AtomicU32]>,
+    /// The total number of positions in the filter (m).
+    cell_count: u64,
+    /// The number of hash functions to use (k).
+    hash_fn_count: u32,
+    ttl: Duration,
+    /// The start of tick 0.
+    epoch: Instant,
+    tick_nanos: u128,
+    /// Phantom data to hold the type information without affecting `Send`/`Sync`.
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: ?Sized + Hash> ExpiringBloomFilter<T> {
+    /// Creates a filter for `expected_items` distinct items per `ttl`. See
+    /// [`BloomFilter::new`](crate::BloomFilter::new) for the other arguments.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ttl` is zero, or under the same conditions as
+    /// [`BloomFilter::new`](crate::BloomFilter::new).
+    pub fn new(expected_items: usize, params: impl Into<FilterParams>, ttl: Duration) -> Self {
+        Self::try_new(expected_items, params, ttl).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates an expiring filter, returning an error instead of panicking.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ttl` is zero.
+    pub fn try_new(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+        ttl: Duration,
+    ) -> Result<Self, BloomError> {
+        assert!(!ttl.is_zero(), "TTL must be greater than 0.");
+        let geometry = Geometry::optimal::<u8>(expected_items, params.into())?;
+        let max_cells = isize::MAX as u64 / 4;
+        if geometry.bit_count > max_cells {
+            return Err(BloomError::CapacityOverflow {
+                bits: geometry.bit_count,
+                max_bits: max_cells,
+            });
+        }
+        let cells = (0..geometry.bit_count).map(|_| AtomicU32::new(0)).collect();
+
+        Ok(ExpiringBloomFilter {
+            cells,
+            cell_count: geometry.bit_count,
+            hash_fn_count: geometry.hash_count,
+            ttl,
+            epoch: Instant::now(),
+            tick_nanos: (ttl.as_nanos() / TICKS_PER_TTL as u128).max(1),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Inserts an item, now.
+    pub fn insert(&self, item: &T) {
+        self.insert_at(item, Instant::now());
+    }
+
+    /// Inserts an item as of `now`, e.g. the time of a replayed event.
+    pub fn insert_at(&self, item: &T, now: Instant) {
+        let tick = self.tick(now);
+        let (h1, h2) = hashing::hash_pair(item);
+        for i in 0..self.hash_fn_count {
+            // A later insert of another item may already have set it.
+            self.cell_at(h1, h2, i).fetch_max(tick, Ordering::Relaxed);
+        }
+    }
+
+    /// Inserts an item, now, returning `true` if it was not already present
+    /// within the last TTL.
+    pub fn check_and_insert(&self, item: &T) -> bool {
+        self.check_and_insert_at(item, Instant::now())
+    }
+
+    /// Inserts an item as of `now`, returning `true` if it was not already
+    /// present within the TTL before `now`.
+    ///
+    /// The check reads the positions as the insert sets them, so of two
+    /// threads inserting the same item one after the other, only the first
+    /// sees it as new. Two inserts that run at the same time may both do.
+    pub fn check_and_insert_at(&self, item: &T, now: Instant) -> bool {
+        let tick = self.tick(now);
+        let (h1, h2) = hashing::hash_pair(item);
+        let mut present = true;
+        for i in 0..self.hash_fn_count {
+            let set_at = self.cell_at(h1, h2, i).fetch_max(tick, Ordering::Relaxed);
+            present &= self.is_live(set_at, tick);
+        }
+        !present
+    }
+
+    /// Checks if an item might have been inserted within the last TTL.
+    ///
+    /// Returns `false` if the item is definitely not present, or only was
+    /// before the TTL.
+    pub fn contains(&self, item: &T) -> bool {
+        self.contains_at(item, Instant::now())
+    }
+
+    /// Checks if an item might have been inserted within the TTL before
+    /// `now`.
+    pub fn contains_at(&self, item: &T, now: Instant) -> bool {
+        let tick = self.tick(now);
+        let (h1, h2) = hashing::hash_pair(item);
+        (0..self.hash_fn_count)
+            .all(|i| self.is_live(self.cell_at(h1, h2, i).load(Ordering::Relaxed), tick))
+    }
+
+    /// Resets every position not set within the last TTL, returning how many
+    /// were reset.
+    pub fn purge_expired(&self) -> usize {
+        self.purge_expired_at(Instant::now())
+    }
+
+    /// Resets every position not set within the TTL before `now`.
+    pub fn purge_expired_at(&self, now: Instant) -> usize {
+        let tick = self.tick(now);
+        self.cells
+            .iter()
+            .filter(|cell| {
+                let set_at = cell.load(Ordering::Relaxed);
+                // An insert racing with the purge wins: the exchange fails.
+                set_at != 0
+                    && !self.is_live(set_at, tick)
+                    && cell
+                        .compare_exchange(set_at, 0, Ordering::Relaxed, Ordering::Relaxed)
+                        .is_ok()
+            })
+            .count()
+    }
+
+    /// Returns the fraction of positions set, expired ones included until
+    /// they are purged.
+    pub fn fill_ratio(&self) -> f64 {
+        let set = self
+            .cells
+            .iter()
+            .filter(|cell| cell.load(Ordering::Relaxed) != 0)
+            .count();
+        set as f64 / self.cell_count as f64
+    }
+
+    /// Resets all positions.
+    ///
+    /// Inserts running concurrently with `clear` may or may not be retained.
+    pub fn clear(&self) {
+        for cell in self.cells.iter() {
+            cell.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the time-to-live of entries.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns the approximate memory usage of the positions in bytes.
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.cells.len() * 4
+    }
+
+    /// Returns the number of hash functions (k) being used.
+    pub fn hash_count(&self) -> u32 {
+        self.hash_fn_count
+    }
+
+    /// Returns the tick of `now`, counted from 1. Times before the filter
+    /// was created count as its first tick.
+    fn tick(&self, now: Instant) -> u32 {
+        let ticks = now.saturating_duration_since(self.epoch).as_nanos() / self.tick_nanos;
+        (ticks + 1).min(u32::MAX as u128) as u32
+    }
+
+    /// Whether a position set at tick `set_at` is still live at tick `now`.
+    fn is_live(&self, set_at: u32, now: u32) -> bool {
+        // A position set after `now` (by a concurrent insert, or one given a
+        // later time) counts as live.
+        set_at != 0 && now.saturating_sub(set_at) < TICKS_PER_TTL
+    }
+
+    /// Returns the position of the i-th hash function.
+    #[inline]
+    fn cell_at(&self, h1: u64, h2: u64, i: u32) -> &AtomicU32 {
+        &self.cells[hashing::bit_index(h1, h2, i, self.cell_count) as usize]
+    }
+}
+
+/// Counts only the positions set within the last TTL, so the estimates
+/// cover the items that still match.
+impl<T: ?Sized + Hash> ApproximateSet<T> for ExpiringBloomFilter<T> {
+    fn insert(&mut self, item: &T) {
+        ExpiringBloomFilter::insert(self, item);
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        ExpiringBloomFilter::contains(self, item)
+    }
+
+    fn estimated_len(&self) -> f64 {
+        approx::estimated_items(self.live_fill_ratio(), self.cell_count, self.hash_fn_count)
+    }
+
+    fn fp_estimate(&self) -> f64 {
+        self.live_fill_ratio().powi(self.hash_fn_count as i32)
+    }
+}
+
+impl<T: ?Sized + Hash> ExpiringBloomFilter<T> {
+    /// Returns the fraction of positions set within the last TTL.
+    fn live_fill_ratio(&self) -> f64 {
+        let tick = self.tick(Instant::now());
+        let live = self
+            .cells
+            .iter()
+            .filter(|cell| self.is_live(cell.load(Ordering::Relaxed), tick))
+            .count();
+        live as f64 / self.cell_count as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_expire() {
+        let ttl = Duration::from_secs(60);
+        let filter = ExpiringBloomFilter::new(1000, 0.01, ttl);
+        let start = Instant::now();
+        for i in 0..500u64 {
+            filter.insert_at(&i, start);
+        }
+        let later = start + Duration::from_secs(50);
+        for i in 500..1000u64 {
+            filter.insert_at(&i, later);
+        }
+
+        let at = |secs| start + Duration::from_secs(secs);
+        assert!((0..1000u64).all(|i| filter.contains_at(&i, at(59))));
+        let stale = (0..500u64)
+            .filter(|i| filter.contains_at(i, at(61)))
+            .count();
+        assert!(stale < 20, "{} expired items still present", stale);
+        assert!((500..1000u64).all(|i| filter.contains_at(&i, at(109))));
+
+        // Reinserting refreshes an entry.
+        assert!(filter.check_and_insert_at(&1, at(100)));
+        assert!(!filter.check_and_insert_at(&1, at(101)));
+        assert!(filter.contains_at(&1, at(150)));
+
+        let fill = filter.fill_ratio();
+        let purged = filter.purge_expired_at(at(200));
+        assert!(purged > 0);
+        assert_eq!(filter.fill_ratio(), 0.0);
+        assert!(fill > 0.0);
+        assert!(!filter.contains_at(&1, at(150)));
+        assert_eq!(filter.ttl(), ttl);
+    }
+
+    #[test]
+    fn test_concurrent_purge() {
+        let filter = ExpiringBloomFilter::new(1000, 0.01, Duration::from_secs(1));
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for _ in 0..100 {
+                    filter.purge_expired();
+                }
+            });
+            for i in 0..1000u64 {
+                filter.insert(&i);
+                assert!(filter.contains(&i));
+            }
+        });
+    }
+}
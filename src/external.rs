@@ -0,0 +1,301 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::Hash;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::counters::OpCounters;
+use crate::params::Geometry;
+use crate::serialize::{encode_header, Crc32};
+use crate::{hashing, BloomError, BloomFilter, FilterParams};
+
+/// The number of words written per I/O call.
+const IO_WORDS: usize = 1024;
+
+/// Distinguishes the spill files of builders in one process.
+static NEXT_BUILD: AtomicU64 = AtomicU64::new(0);
+
+/// Builds a filter larger than the memory it may use, by spilling the
+/// positions its items set to disk.
+///
+/// The bit array is cut into parts of at most `memory_bytes` each. Every
+/// [`insert`](Self::insert) hashes the item and appends the positions of
+/// its bits to one spill file per part, in `spill_dir`. At the end,
+/// [`finish_to`](Self::finish_to) builds the parts one after another from
+/// their spill files and streams each into the output in the format of
+/// [`BloomFilter::to_bytes`], so no more than one part is ever in memory.
+/// The input is read once, in any order, and can be far larger than
+/// memory; the spill files take 8 bytes per inserted bit.
+///
+/// Spill files are removed when the build finishes, or when the builder is
+/// dropped.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use bloomlib::{BloomFilter, ExternalBuilder};
+///
+/// let dir = std::env::temp_dir();
+/// // A filter of about 12 KB, built 4 KB at a time.
+/// let mut builder: ExternalBuilder<str> = ExternalBuilder::new(10_000, 0.01, 4096, &dir).unwrap();
+/// assert_eq!(builder.part_count(), 3);
+/// builder.insert_lines(Cursor::new("apple\nbanana\ncherry\n")).unwrap();
+///
+/// let mut bytes = Vec::new();
+/// builder.finish_to(&mut bytes).unwrap();
+/// let filter: BloomFilter<str> = BloomFilter::from_bytes(&bytes).unwrap();
+/// assert!(filter.contains("banana"));
+/// ```
+pub struct ExternalBuilder<T: ?Sized> {
+    bit_count: u64,
+    hash_fn_count: u32,
+    /// The first 64-bit word of each part, and the word count at the end.
+    bounds: Vec<u64>,
+    spills: Vec<Spill>,
+    items: u64,
+    _marker: PhantomData<fn(&T)>,
+}
+
+/// The spill file of one part, holding bit indices as little-endian `u64`s.
+struct Spill {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl<T: ?Sized + Hash> ExternalBuilder<T> {
+    /// Creates a builder of a filter sized like
+    /// [`BloomFilter::new`]`(expected_items, params)`, keeping at most
+    /// `memory_bytes` of its bit array in memory at once and its spill
+    /// files in `spill_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidInput`] wrapping a
+    /// [`BloomError`] under the same conditions as [`BloomFilter::try_new`],
+    /// or [`BloomError::InsufficientMemory`] if `memory_bytes` is less than
+    /// one 64-bit word; or any error creating the spill files returns.
+    pub fn new(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+        memory_bytes: u64,
+        spill_dir: impl AsRef<Path>,
+    ) -> io::Result<Self> {
+        let invalid = |err| io::Error::new(io::ErrorKind::InvalidInput, err);
+        let geometry = Geometry::optimal::<u64>(expected_items, params.into()).map_err(invalid)?;
+        let part_words = memory_bytes / 8;
+        if part_words == 0 {
+            return Err(invalid(BloomError::InsufficientMemory(memory_bytes)));
+        }
+        let word_count = geometry.word_count as u64;
+        let part_count = word_count.div_ceil(part_words).max(1);
+        let bounds: Vec<u64> = (0..=part_count)
+            .map(|part| (part * part_words).min(word_count))
+            .collect();
+
+        let build = NEXT_BUILD.fetch_add(1, Ordering::Relaxed);
+        let mut builder = ExternalBuilder {
+            bit_count: geometry.bit_count,
+            hash_fn_count: geometry.hash_count,
+            bounds,
+            spills: Vec::with_capacity(part_count as usize),
+            items: 0,
+            _marker: PhantomData,
+        };
+        // Spill files already created are removed by `Drop` on failure.
+        for part in 0..part_count {
+            let name = format!("bloomlib-{}-{}-{}.spill", std::process::id(), build, part);
+            let path = spill_dir.as_ref().join(name);
+            let writer = BufWriter::new(File::create(&path)?);
+            builder.spills.push(Spill { path, writer });
+        }
+        Ok(builder)
+    }
+
+    /// Returns the number of parts the bit array is built in.
+    pub fn part_count(&self) -> u32 {
+        self.spills.len() as u32
+    }
+
+    /// Returns the number of items inserted so far.
+    pub fn len(&self) -> u64 {
+        self.items
+    }
+
+    /// Returns `true` if no items have been inserted.
+    pub fn is_empty(&self) -> bool {
+        self.items == 0
+    }
+
+    /// Records the bits of `item` in the spill files.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error writing to a spill file returns.
+    pub fn insert(&mut self, item: &T) -> io::Result<()> {
+        let (h1, h2) = hashing::hash_pair(item);
+        for i in 0..self.hash_fn_count {
+            let bit_index = hashing::bit_index(h1, h2, i, self.bit_count);
+            let part = self
+                .bounds
+                .partition_point(|&start| start <= bit_index / 64)
+                - 1;
+            self.spills[part]
+                .writer
+                .write_all(&bit_index.to_le_bytes())?;
+        }
+        self.items += 1;
+        Ok(())
+    }
+
+    /// Builds the filter part by part and writes it to `writer`, in the
+    /// format of [`BloomFilter::to_bytes`]. Each spill file is removed as
+    /// soon as its part is written.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reading a spill file or writing to `writer`
+    /// returns.
+    pub fn finish_to<W: Write>(mut self, mut writer: W) -> io::Result<()> {
+        let header = encode_header(self.bit_count, self.hash_fn_count);
+        let mut crc = Crc32::new();
+        crc.update(&header);
+        writer.write_all(&header)?;
+
+        let mut bytes = Vec::with_capacity(IO_WORDS * 8);
+        for part in 0..self.spills.len() {
+            for words in self.build_part(part)?.chunks(IO_WORDS) {
+                bytes.clear();
+                bytes.extend(words.iter().flat_map(|word| word.to_le_bytes()));
+                crc.update(&bytes);
+                writer.write_all(&bytes)?;
+            }
+            fs::remove_file(&self.spills[part].path)?;
+        }
+        self.spills.clear();
+        writer.write_all(&crc.finish())
+    }
+
+    /// Builds the whole filter in memory, for a result that fits in memory
+    /// although its input did not.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reading a spill file returns.
+    pub fn finish(mut self) -> io::Result<BloomFilter<T>> {
+        let mut bit_vec = Vec::with_capacity(*self.bounds.last().unwrap() as usize);
+        for part in 0..self.spills.len() {
+            bit_vec.extend(self.build_part(part)?);
+            fs::remove_file(&self.spills[part].path)?;
+        }
+        self.spills.clear();
+        Ok(BloomFilter {
+            bit_vec,
+            bit_count: self.bit_count,
+            hash_fn_count: self.hash_fn_count,
+            counters: OpCounters::default(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Sets the bits recorded in the spill file of `part`.
+    fn build_part(&mut self, part: usize) -> io::Result<Vec<u64>> {
+        let (first_word, end) = (self.bounds[part], self.bounds[part + 1]);
+        let mut words = vec![0u64; (end - first_word) as usize];
+        let spill = &mut self.spills[part];
+        spill.writer.flush()?;
+        let mut reader = BufReader::new(File::open(&spill.path)?);
+        let mut index = [0u8; 8];
+        loop {
+            match reader.read_exact(&mut index) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            }
+            let bit_index = u64::from_le_bytes(index);
+            words[(bit_index / 64 - first_word) as usize] |= 1 << (bit_index % 64);
+        }
+        Ok(words)
+    }
+}
+
+impl ExternalBuilder<str> {
+    /// Inserts every line of `reader` as a key, and returns the number of
+    /// lines. Call it once per input file.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reading `reader` or writing to a spill file
+    /// returns.
+    pub fn insert_lines<R: BufRead>(&mut self, reader: R) -> io::Result<u64> {
+        let mut count = 0;
+        for line in reader.lines() {
+            self.insert(&line?)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl<T: ?Sized> Drop for ExternalBuilder<T> {
+    fn drop(&mut self) {
+        for spill in &self.spills {
+            // Nothing can be done about a file that cannot be removed.
+            let _ = fs::remove_file(&spill.path);
+        }
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for ExternalBuilder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalBuilder")
+            .field("bit_count", &self.bit_count)
+            .field("hash_fn_count", &self.hash_fn_count)
+            .field("part_count", &self.spills.len())
+            .field("items", &self.items)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_in_memory_build() {
+        let dir = std::env::temp_dir();
+        let mut expected: BloomFilter<u64> = BloomFilter::new(20_000, 0.01);
+        let mut builder: ExternalBuilder<u64> =
+            ExternalBuilder::new(20_000, 0.01, 1000, &dir).unwrap();
+        assert_eq!(
+            builder.part_count(),
+            expected.bit_vec.len().div_ceil(125) as u32
+        );
+        for i in 0..20_000 {
+            expected.insert(&i);
+            builder.insert(&i).unwrap();
+        }
+        assert_eq!(builder.len(), 20_000);
+        let paths: Vec<PathBuf> = builder
+            .spills
+            .iter()
+            .map(|spill| spill.path.clone())
+            .collect();
+
+        let mut bytes = Vec::new();
+        builder.finish_to(&mut bytes).unwrap();
+        assert_eq!(bytes, expected.to_bytes());
+        assert!(paths.iter().all(|path| !path.exists()));
+
+        let mut builder: ExternalBuilder<u64> =
+            ExternalBuilder::new(20_000, 0.01, 1 << 20, &dir).unwrap();
+        assert_eq!(builder.part_count(), 1);
+        (0..20_000).for_each(|i| builder.insert(&i).unwrap());
+        assert_eq!(builder.finish().unwrap().bit_vec, expected.bit_vec);
+
+        let err = ExternalBuilder::<u64>::new(20_000, 0.01, 7, &dir).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
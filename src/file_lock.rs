@@ -0,0 +1,151 @@
+use std::fs::{File, OpenOptions};
+use std::hash::Hash;
+use std::io::{self, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::{AllocStore, BitStore, BloomFilter};
+
+impl<T: ?Sized + Hash, B: BitStore> BloomFilter<T, B> {
+    /// Saves the filter to the file at `path`, in the format of
+    /// [`BloomFilter::to_bytes`], holding an exclusive lock on the file
+    /// while it is written.
+    ///
+    /// The lock is advisory, taken with `flock` on Unix and `LockFileEx` on
+    /// Windows: processes that access the file only through
+    /// [`save_locked`](Self::save_locked),
+    /// [`load_locked`](Self::load_locked) and
+    /// [`merge_into_file`](Self::merge_into_file) never see a half-written
+    /// filter or interleave their writes, but other code can still read or
+    /// write the file at any time. The file is rewritten in place, since a
+    /// lock does not follow a file renamed over it, and this call blocks
+    /// while another process holds a lock on it.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error opening, locking or writing the file returns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let path = std::env::temp_dir().join("bloomlib-locked-example.bloom");
+    /// let mut filter: BloomFilter<str> = BloomFilter::new(1000, 0.01);
+    /// filter.insert("alice");
+    /// filter.save_locked(&path).unwrap();
+    ///
+    /// let loaded: BloomFilter<str> = BloomFilter::load_locked(&path).unwrap();
+    /// assert!(loaded.contains("alice"));
+    /// ```
+    pub fn save_locked(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = open_for_writing(path.as_ref())?;
+        file.lock()?;
+        overwrite(&file, self)
+    }
+}
+
+impl<T: ?Sized + Hash, B: AllocStore> BloomFilter<T, B> {
+    /// Loads a filter saved by [`save_locked`](Self::save_locked), holding
+    /// a shared lock on the file while it is read, so it waits for a save
+    /// in progress to finish. Any number of processes can load the file at
+    /// once.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error opening, locking or reading the file returns, and
+    /// the errors of [`BloomFilter::read_from`].
+    pub fn load_locked(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        file.lock_shared()?;
+        Self::read_from(BufReader::new(&file))
+    }
+
+    /// Adds every item of this filter to the filter saved at `path`, or
+    /// saves this filter there if the file is empty or does not exist.
+    ///
+    /// The file is read, merged and rewritten under one exclusive lock, so
+    /// processes that each add their items to a shared file this way do not
+    /// lose each other's items. See [`save_locked`](Self::save_locked).
+    ///
+    /// # Errors
+    ///
+    /// Returns any error opening, locking, reading or writing the file
+    /// returns, the errors of [`BloomFilter::read_from`], and an error of
+    /// kind [`io::ErrorKind::InvalidData`] wrapping
+    /// [`BloomError::IncompatibleFilters`](crate::BloomError::IncompatibleFilters)
+    /// if the saved filter has another geometry. The file is unchanged on
+    /// error, unless writing it fails.
+    pub fn merge_into_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = open_for_writing(path.as_ref())?;
+        file.lock()?;
+        if file.metadata()?.len() == 0 {
+            return overwrite(&file, self);
+        }
+        let mut saved = Self::read_from(BufReader::new(&file))?;
+        saved
+            .merge(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        file.seek(SeekFrom::Start(0))?;
+        overwrite(&file, &saved)
+    }
+}
+
+/// Opens the file at `path` for reading and writing, creating it if it does
+/// not exist but not truncating it before it is locked.
+fn open_for_writing(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+/// Replaces the contents of the locked `file` with `filter`.
+fn overwrite<T: ?Sized + Hash, B: BitStore>(
+    mut file: &File,
+    filter: &BloomFilter<T, B>,
+) -> io::Result<()> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    let mut writer = BufWriter::new(file);
+    filter.write_to(&mut writer)?;
+    writer.flush()?;
+    file.sync_data()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_merges_keep_every_item() {
+        let path = std::env::temp_dir().join(format!("bloomlib-lock-{}.bloom", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        thread::scope(|scope| {
+            for t in 0..4u64 {
+                let path = &path;
+                scope.spawn(move || {
+                    for round in 0..10 {
+                        let mut filter: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+                        filter.insert(&(t * 100 + round));
+                        filter.merge_into_file(path).unwrap();
+                    }
+                });
+            }
+        });
+
+        let saved: BloomFilter<u64> = BloomFilter::load_locked(&path).unwrap();
+        for t in 0..4u64 {
+            assert!((0..10).all(|round| saved.contains(&(t * 100 + round))));
+        }
+        let other: BloomFilter<u64> = BloomFilter::new(10, 0.01);
+        assert_eq!(
+            other.merge_into_file(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::{hashing, kernels};
+use crate::{BloomError, FrozenBloomFilter};
+
+/// A read-only Bloom Filter whose hash count (k) is the compile-time
+/// constant `K`, produced by [`FrozenBloomFilter::into_fixed_k`].
+///
+/// With k known to the compiler, the probe loop of
+/// [`contains`](Self::contains) is fully unrolled: all `K` words are loaded
+/// and ANDed without a branch per probe, and without bounds checks, since
+/// the bit array was checked to hold the bit count when the filter was
+/// built. That suits serving tiers that always run one configuration, where
+/// the loop overhead of a run-time k shows up on hot lookups.
+///
+/// The bits are those of the frozen filter, so every lookup answers as it
+/// would there. Lookups always take the unrolled scalar path, never the AVX-512
+/// gather kernel of [`FrozenBloomFilter`].
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{BloomFilter, FixedKBloomFilter};
+///
+/// let mut bf: BloomFilter<str> = BloomFilter::new(100_000, 7u32);
+/// bf.insert("seen");
+///
+/// let fixed: FixedKBloomFilter<str, 7> = bf.freeze().into_fixed_k().unwrap();
+/// assert!(fixed.contains("seen"));
+/// assert!(!fixed.contains("unseen"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedKBloomFilter<T: ?Sized, const K: u32> {
+    /// The bit array as u64 words, holding at least `bit_count` bits.
+    bit_vec: Box<[u64]>,
+    /// The total number of bits in the filter (m).
+    bit_count: u64,
+    /// Phantom data to hold the type information without affecting `Send`/`Sync`.
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: ?Sized + Hash> FrozenBloomFilter<T> {
+    /// Converts the filter into a [`FixedKBloomFilter`] with the hash count
+    /// `K` fixed at compile time.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::IncompatibleFilters`] if the filter's hash
+    /// count is not `K`.
+    pub fn into_fixed_k<const K: u32>(self) -> Result<FixedKBloomFilter<T, K>, BloomError> {
+        let (bit_vec, bit_count, hash_count) = self.into_parts();
+        if hash_count != K {
+            return Err(BloomError::IncompatibleFilters {
+                expected: (bit_count, K),
+                found: (bit_count, hash_count),
+            });
+        }
+        assert!(bit_count <= bit_vec.len() as u64 * 64);
+        Ok(FixedKBloomFilter {
+            bit_vec,
+            bit_count,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: ?Sized + Hash, const K: u32> FixedKBloomFilter<T, K> {
+    /// Checks if an item might be in the Bloom Filter.
+    ///
+    /// Returns `true` if the item might be present (with a probability of false positive).
+    /// Returns `false` if the item is definitely not present.
+    #[inline]
+    pub fn contains(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair(item);
+        // SAFETY: `into_fixed_k` checked that `bit_vec` holds `bit_count` bits.
+        unsafe { kernels::contains_fixed::<K>(&self.bit_vec, self.bit_count, h1, h2) }
+    }
+
+    /// Checks a batch of items, returning one result per item in input
+    /// order. Unlike [`FrozenBloomFilter::contains_batch`], this does not
+    /// prefetch; it suits filters that fit in the CPU cache.
+    pub fn contains_batch<Q: Borrow<T>>(&self, items: &[Q]) -> Vec<bool> {
+        items
+            .iter()
+            .map(|item| self.contains(item.borrow()))
+            .collect()
+    }
+
+    /// Returns the approximate memory usage of the bit vector in bytes.
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.bit_vec.len() * 8
+    }
+
+    /// Returns the number of hash functions (k), which is `K`.
+    pub const fn hash_count(&self) -> u32 {
+        K
+    }
+
+    /// Converts the filter back into a [`FrozenBloomFilter`].
+    pub fn into_frozen(self) -> FrozenBloomFilter<T> {
+        FrozenBloomFilter::from_parts(self.bit_vec, self.bit_count, K)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BloomFilter;
+
+    #[test]
+    fn test_fixed_k_matches_frozen() {
+        let mut bf: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        (0..1000u64).for_each(|i| bf.insert(&i));
+        let frozen = bf.freeze();
+        let queries: Vec<u64> = (0..5000).collect();
+        let expected = frozen.contains_batch(&queries);
+
+        assert!(matches!(
+            frozen.clone().into_fixed_k::<6>(),
+            Err(BloomError::IncompatibleFilters {
+                expected: (_, 6),
+                found: (_, 7)
+            })
+        ));
+        let fixed = frozen.into_fixed_k::<7>().unwrap();
+        assert_eq!(fixed.contains_batch(&queries), expected);
+        assert_eq!(fixed.into_frozen().contains_batch(&queries), expected);
+    }
+}
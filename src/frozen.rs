@@ -0,0 +1,168 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::{hashing, kernels};
+use crate::{BitStore, BloomFilter};
+
+/// An immutable, read-optimized Bloom Filter produced by [`BloomFilter::freeze`].
+///
+/// A frozen filter has no mutation API, holds no interior mutability and is
+/// `Send + Sync` for any `T`, so it can be shared freely by a serving tier
+/// that never changes filters after loading them.
+///
+/// Lookups are faster than on [`BloomFilter`]:
+///
+/// * probes index the bit array without bounds checks, since every reduced
+///   index is below the bit count by construction, and use the AVX-512
+///   gather kernel where the CPU supports it;
+/// * the bits live in an exactly-sized boxed slice of `u64` words, whatever
+///   word type the source filter used.
+///
+/// The bit layout itself is kept. Positions are fixed by the probe sequence
+/// used at insert time, so a different layout (such as cache-line blocking)
+/// could only be built from the original keys, not from the bits.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::BloomFilter;
+///
+/// let mut bf: BloomFilter<str> = BloomFilter::new(100, 0.01);
+/// bf.insert("seen");
+///
+/// let frozen = bf.freeze();
+/// assert!(frozen.contains("seen"));
+/// assert!(!frozen.contains("unseen"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrozenBloomFilter<T: ?Sized> {
+    /// The bit array as u64 words.
+    bit_vec: Box<[u64]>,
+    /// The total number of bits in the filter (m).
+    bit_count: u64,
+    /// The number of hash functions to use (k).
+    hash_fn_count: u32,
+    /// Phantom data to hold the type information without affecting `Send`/`Sync`.
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: ?Sized + Hash, B: BitStore> BloomFilter<T, B> {
+    /// Converts the filter into an immutable, read-optimized [`FrozenBloomFilter`].
+    pub fn freeze(self) -> FrozenBloomFilter<T> {
+        let bit_vec = self.packed_words().collect();
+
+        FrozenBloomFilter {
+            bit_vec,
+            bit_count: self.bit_count,
+            hash_fn_count: self.hash_fn_count,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized + Hash> FrozenBloomFilter<T> {
+    /// Checks if an item might be in the Bloom Filter.
+    ///
+    /// Returns `true` if the item might be present (with a probability of false positive).
+    /// Returns `false` if the item is definitely not present.
+    pub fn contains(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair(item);
+        kernels::contains(&self.bit_vec, self.bit_count, self.hash_fn_count, h1, h2)
+    }
+
+    /// Checks a batch of items with prefetching, returning one result per
+    /// item in input order. See [`BloomFilter::contains_batch`].
+    pub fn contains_batch<Q: Borrow<T>>(&self, items: &[Q]) -> Vec<bool> {
+        let mut results = Vec::with_capacity(items.len());
+        kernels::contains_batch(
+            &self.bit_vec,
+            self.bit_count,
+            self.hash_fn_count,
+            items.iter().map(|item| hashing::hash_pair(item.borrow())),
+            &mut results,
+        );
+        results
+    }
+
+    /// Returns the approximate memory usage of the bit vector in bytes.
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.bit_vec.len() * 8
+    }
+
+    /// Returns the number of hash functions (k) being used.
+    pub fn hash_count(&self) -> u32 {
+        self.hash_fn_count
+    }
+
+    /// Splits the filter into its bit array, bit count (m) and hash count (k).
+    pub(crate) fn into_parts(self) -> (Box<[u64]>, u64, u32) {
+        (self.bit_vec, self.bit_count, self.hash_fn_count)
+    }
+
+    /// Reassembles a filter split by [`into_parts`](Self::into_parts).
+    pub(crate) fn from_parts(bit_vec: Box<[u64]>, bit_count: u64, hash_fn_count: u32) -> Self {
+        FrozenBloomFilter {
+            bit_vec,
+            bit_count,
+            hash_fn_count,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the bit array, the bit count (m) and the hash count (k).
+    #[cfg(feature = "gpu")]
+    pub(crate) fn as_parts(&self) -> (&[u64], u64, u32) {
+        (&self.bit_vec, self.bit_count, self.hash_fn_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_frozen_matches_source() {
+        let mut bf = BloomFilter::new(1000, 0.01);
+        for i in 0..1000u64 {
+            bf.insert(&i);
+        }
+        let expected: Vec<bool> = (0..5000u64).map(|i| bf.contains(&i)).collect();
+
+        let frozen = bf.freeze();
+        let actual: Vec<bool> = (0..5000u64).map(|i| frozen.contains(&i)).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_frozen_from_narrow_words() {
+        let mut bf = BloomFilter::<u64, Vec<u8>>::from_params(1000, 0.01);
+        for i in 0..1000u64 {
+            bf.insert(&i);
+        }
+        let expected: Vec<bool> = (0..5000u64).map(|i| bf.contains(&i)).collect();
+
+        let frozen = bf.freeze();
+        let actual: Vec<bool> = (0..5000u64).map(|i| frozen.contains(&i)).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_frozen_contains_batch() {
+        let mut bf = BloomFilter::new(1000, 0.01);
+        for i in (0..1000u64).step_by(3) {
+            bf.insert(&i);
+        }
+        let frozen = bf.freeze();
+        let queries: Vec<u64> = (0..1000).collect();
+        let expected: Vec<bool> = queries.iter().map(|i| frozen.contains(i)).collect();
+        assert_eq!(frozen.contains_batch(&queries), expected);
+    }
+
+    #[test]
+    fn test_frozen_is_sync_for_any_item_type() {
+        fn assert_send_sync<S: Send + Sync>() {}
+        assert_send_sync::<FrozenBloomFilter<Rc<u8>>>();
+    }
+}
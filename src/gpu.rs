@@ -0,0 +1,470 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::mpsc;
+
+use wgpu::util::DeviceExt;
+
+use crate::hashing::{self, IntegerKey};
+use crate::FrozenBloomFilter;
+
+/// The probe kernel. 64-bit values are `vec2<u32>(low, high)` pairs, since
+/// WGSL has no 64-bit integers; the arithmetic reproduces
+/// `hashing::bit_index` exactly.
+const SHADER: &str = r#"
+struct Params {
+    bit_count: vec2<u32>,
+    mask: vec2<u32>,
+    hash_count: u32,
+    item_count: u32,
+    masked: u32,
+    _padding: u32,
+}
+
+@group(0) @binding(0) var<storage, read> bits: array<u32>;
+@group(0) @binding(1) var<storage, read> hashes: array<vec4<u32>>;
+@group(0) @binding(2) var<storage, read_write> results: array<atomic<u32>>;
+@group(0) @binding(3) var<uniform> params: Params;
+
+fn add64(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let low = a.x + b.x;
+    return vec2<u32>(low, a.y + b.y + select(0u, 1u, low < a.x));
+}
+
+// The full 64-bit product of two 32-bit values, from 16-bit halves.
+fn mul32(a: u32, b: u32) -> vec2<u32> {
+    let a0 = a & 0xffffu;
+    let a1 = a >> 16u;
+    let b0 = b & 0xffffu;
+    let b1 = b >> 16u;
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+    let middle = (p00 >> 16u) + (p01 & 0xffffu) + (p10 & 0xffffu);
+    return vec2<u32>((p00 & 0xffffu) | (middle << 16u), p11 + (p01 >> 16u) + (p10 >> 16u) + (middle >> 16u));
+}
+
+// The high 64 bits of the 128-bit product `a * b` (fastrange).
+fn mul_high64(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let low_low = mul32(a.x, b.x);
+    let low_high = mul32(a.x, b.y);
+    let high_low = mul32(a.y, b.x);
+    let high_high = mul32(a.y, b.y);
+    let column = low_low.y + low_high.x;
+    var carry = select(0u, 1u, column < low_low.y);
+    carry += select(0u, 1u, column + high_low.x < column);
+    var result = add64(high_high, vec2<u32>(low_high.y, 0u));
+    result = add64(result, vec2<u32>(high_low.y, 0u));
+    return add64(result, vec2<u32>(carry, 0u));
+}
+
+@compute @workgroup_size(256)
+fn probe(@builtin(global_invocation_id) id: vec3<u32>) {
+    let item = id.x;
+    if (item >= params.item_count) {
+        return;
+    }
+    let pair = hashes[item];
+    var hash = pair.xy;
+    for (var i = 0u; i < params.hash_count; i++) {
+        var index: vec2<u32>;
+        if (params.masked != 0u) {
+            index = hash & params.mask;
+        } else {
+            index = mul_high64(hash, params.bit_count);
+        }
+        let word = (index.y << 27u) | (index.x >> 5u);
+        if ((bits[word] & (1u << (index.x & 31u))) == 0u) {
+            return;
+        }
+        hash = add64(hash, pair.zw);
+    }
+    atomicOr(&results[item / 32u], 1u << (item % 32u));
+}
+"#;
+
+/// The number of threads per workgroup, as declared in [`SHADER`].
+const WORKGROUP_SIZE: usize = 256;
+
+/// The most items sent to the GPU in one dispatch: 64 MiB of hash pairs,
+/// well under the default storage buffer limit.
+const MAX_DISPATCH_ITEMS: usize = 1 << 22;
+
+/// The size of one item's hash pair on the GPU.
+const PAIR_BYTES: usize = 16;
+
+/// Errors returned by [`GpuBloomFilter`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum GpuError {
+    /// No GPU adapter is available.
+    NoAdapter,
+    /// The adapter refused to open a device.
+    DeviceUnavailable(String),
+    /// The bit array is larger than one GPU storage buffer may be.
+    FilterTooLarge {
+        /// The size of the bit array in bytes.
+        bytes: u64,
+        /// The largest storage buffer the device allows.
+        max_bytes: u64,
+    },
+    /// The results could not be read back from the GPU.
+    Readback(String),
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpuError::NoAdapter => write!(f, "No GPU adapter is available."),
+            GpuError::DeviceUnavailable(reason) => {
+                write!(f, "The GPU device could not be opened: {}", reason)
+            }
+            GpuError::FilterTooLarge { bytes, max_bytes } => write!(
+                f,
+                "Filter needs a {} byte GPU buffer, but the device allows at most {} bytes.",
+                bytes, max_bytes
+            ),
+            GpuError::Readback(reason) => {
+                write!(f, "GPU results could not be read back: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GpuError {}
+
+/// A read-only copy of a [`FrozenBloomFilter`] on the GPU, for batch
+/// membership queries far larger than a CPU pass can handle quickly.
+///
+/// The bit array is uploaded once, when the filter is created. Each query
+/// then hashes the items on the CPU (the standard library's hasher does not
+/// run on a GPU), uploads the hash pairs and probes them in a compute shader,
+/// one thread per item. Results come back as a bitmap: bit `i % 64` of word
+/// `i / 64` is set if item `i` may be in the filter, exactly as
+/// [`FrozenBloomFilter::contains`] would report it.
+///
+/// Requires the `gpu` feature. Any backend `wgpu` supports will do (Vulkan,
+/// Metal, DirectX 12 or OpenGL), and the bit array must fit in a single
+/// storage buffer, whose size limit depends on the device.
+///
+/// # Examples
+///
+/// ```no_run
+/// use bloomlib::{BloomFilter, GpuBloomFilter};
+///
+/// let mut bf: BloomFilter<u64> = BloomFilter::new(1_000_000, 0.01);
+/// bf.insert_keys(&[1, 2, 3]);
+///
+/// let gpu = GpuBloomFilter::new(&bf.freeze()).expect("no GPU");
+/// let bitmap = gpu.contains_keys(&[1, 4]).unwrap();
+/// assert_eq!(bitmap[0] & 0b11, 0b01);
+/// ```
+pub struct GpuBloomFilter<T: ?Sized> {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    /// The bit array as `u32` words.
+    bits: wgpu::Buffer,
+    /// The total number of bits in the filter (m).
+    bit_count: u64,
+    /// The number of hash functions to use (k).
+    hash_fn_count: u32,
+    /// The most items per dispatch the device's buffer limits allow.
+    dispatch_items: usize,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: ?Sized> fmt::Debug for GpuBloomFilter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GpuBloomFilter")
+            .field("bit_count", &self.bit_count)
+            .field("hash_fn_count", &self.hash_fn_count)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: ?Sized + Hash> GpuBloomFilter<T> {
+    /// Opens the default GPU and uploads the filter's bit array to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no GPU is available or the bit array does not fit
+    /// in one of its storage buffers.
+    pub fn new(filter: &FrozenBloomFilter<T>) -> Result<Self, GpuError> {
+        let (words, bit_count, hash_fn_count) = filter.as_parts();
+
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .map_err(|_| GpuError::NoAdapter)?;
+
+        let limits = adapter.limits();
+        let max_bytes = limits
+            .max_storage_buffer_binding_size
+            .min(limits.max_buffer_size);
+        let bytes = words.len() as u64 * 8;
+        if bytes > max_bytes {
+            return Err(GpuError::FilterTooLarge { bytes, max_bytes });
+        }
+
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("bloomlib"),
+            required_limits: limits.clone(),
+            ..Default::default()
+        }))
+        .map_err(|err| GpuError::DeviceUnavailable(err.to_string()))?;
+
+        let contents: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let bits = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("bloomlib bits"),
+            contents: &contents,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("bloomlib probe"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("bloomlib probe"),
+            layout: None,
+            module: &module,
+            entry_point: Some("probe"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let dispatch_items = MAX_DISPATCH_ITEMS
+            .min((max_bytes / PAIR_BYTES as u64) as usize)
+            .min(limits.max_compute_workgroups_per_dimension as usize * WORKGROUP_SIZE)
+            / 64
+            * 64;
+
+        Ok(GpuBloomFilter {
+            device,
+            queue,
+            pipeline,
+            bits,
+            bit_count,
+            hash_fn_count,
+            dispatch_items,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Checks a batch of items on the GPU, returning a bitmap with bit
+    /// `i % 64` of word `i / 64` set if `items[i]` may be in the filter.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the results cannot be read back from the GPU.
+    pub fn contains_batch<Q: Borrow<T>>(&self, items: &[Q]) -> Result<Vec<u64>, GpuError> {
+        self.query(
+            items.len(),
+            items.iter().map(|item| hashing::hash_pair(item.borrow())),
+        )
+    }
+
+    /// Returns the number of hash functions (k) being used.
+    pub fn hash_count(&self) -> u32 {
+        self.hash_fn_count
+    }
+
+    /// Probes `item_count` hash pairs, a dispatch at a time.
+    fn query(
+        &self,
+        item_count: usize,
+        mut hashes: impl Iterator<Item = (u64, u64)>,
+    ) -> Result<Vec<u64>, GpuError> {
+        let mut bitmap = Vec::with_capacity(item_count.div_ceil(64));
+        let mut remaining = item_count;
+        let mut pairs = Vec::with_capacity(self.dispatch_items.min(item_count) * PAIR_BYTES);
+        while remaining > 0 {
+            let batch = remaining.min(self.dispatch_items);
+            pairs.clear();
+            for (h1, h2) in hashes.by_ref().take(batch) {
+                pairs.extend_from_slice(&h1.to_le_bytes());
+                pairs.extend_from_slice(&h2.to_le_bytes());
+            }
+            self.dispatch(batch, &pairs, &mut bitmap)?;
+            remaining -= batch;
+        }
+        Ok(bitmap)
+    }
+
+    /// Runs the probe kernel on one batch of hash pairs and appends its
+    /// results to `bitmap`.
+    fn dispatch(&self, batch: usize, pairs: &[u8], bitmap: &mut Vec<u64>) -> Result<(), GpuError> {
+        let device = &self.device;
+        let result_bytes = (batch.div_ceil(64) * 8) as u64;
+
+        let mask = if self.bit_count.is_power_of_two() {
+            self.bit_count - 1
+        } else {
+            0
+        };
+        let params: Vec<u8> = [
+            self.bit_count as u32,
+            (self.bit_count >> 32) as u32,
+            mask as u32,
+            (mask >> 32) as u32,
+            self.hash_fn_count,
+            batch as u32,
+            self.bit_count.is_power_of_two() as u32,
+            0,
+        ]
+        .iter()
+        .flat_map(|field| field.to_le_bytes())
+        .collect();
+
+        let hashes = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("bloomlib hashes"),
+            contents: pairs,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let uniforms = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("bloomlib params"),
+            contents: &params,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let results = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("bloomlib results"),
+            size: result_bytes,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("bloomlib readback"),
+            size: result_bytes,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.bits.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: hashes.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: results.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: uniforms.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(batch.div_ceil(WORKGROUP_SIZE) as u32, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&results, 0, &readback, 0, result_bytes);
+        self.queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = mpsc::channel();
+        readback.map_async(wgpu::MapMode::Read, .., move |mapped| {
+            let _ = sender.send(mapped);
+        });
+        device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|err| GpuError::Readback(err.to_string()))?;
+        receiver
+            .recv()
+            .map_err(|err| GpuError::Readback(err.to_string()))?
+            .map_err(|err| GpuError::Readback(err.to_string()))?;
+
+        let view = readback
+            .get_mapped_range(..)
+            .map_err(|err| GpuError::Readback(err.to_string()))?;
+        bitmap.extend(
+            view.chunks_exact(8)
+                .map(|word| u64::from_le_bytes(word.try_into().unwrap())),
+        );
+        drop(view);
+        readback.unmap();
+        Ok(())
+    }
+}
+
+impl<T: IntegerKey> GpuBloomFilter<T> {
+    /// Checks a batch of integer keys on the GPU, hashing them several at a
+    /// time on the CPU as in [`BloomFilter::contains_keys`](crate::BloomFilter::contains_keys).
+    ///
+    /// The bitmap is laid out as in [`GpuBloomFilter::contains_batch`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the results cannot be read back from the GPU.
+    pub fn contains_keys(&self, keys: &[T]) -> Result<Vec<u64>, GpuError> {
+        self.query(keys.len(), hashing::integer_hash_pairs(keys))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BloomFilter;
+
+    /// Builds a filter and its GPU copy, or `None` when the machine has no GPU.
+    fn gpu_filter(
+        items: usize,
+        power_of_two: bool,
+    ) -> Option<(FrozenBloomFilter<u64>, GpuBloomFilter<u64>)> {
+        let mut bf: BloomFilter<u64> = BloomFilter::builder(items, 0.01)
+            .power_of_two(power_of_two)
+            .build();
+        bf.insert_keys(&(0..items as u64).map(|i| i * 3).collect::<Vec<_>>());
+        let frozen = bf.freeze();
+        match GpuBloomFilter::new(&frozen) {
+            Ok(gpu) => Some((frozen, gpu)),
+            Err(GpuError::NoAdapter) => None,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn test_gpu_matches_cpu() {
+        for power_of_two in [false, true] {
+            let Some((frozen, gpu)) = gpu_filter(10_000, power_of_two) else {
+                return;
+            };
+            let keys: Vec<u64> = (0..30_001).collect();
+            let bitmap = gpu.contains_keys(&keys).unwrap();
+            assert_eq!(bitmap.len(), keys.len().div_ceil(64));
+            for (i, key) in keys.iter().enumerate() {
+                assert_eq!(bitmap[i / 64] >> (i % 64) & 1 == 1, frozen.contains(key));
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let err = GpuError::FilterTooLarge {
+            bytes: 10,
+            max_bytes: 8,
+        };
+        assert_eq!(
+            err.to_string(),
+            "Filter needs a 10 byte GPU buffer, but the device allows at most 8 bytes."
+        );
+        assert_eq!(
+            GpuError::NoAdapter.to_string(),
+            "No GPU adapter is available."
+        );
+    }
+}
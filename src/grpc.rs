@@ -0,0 +1,285 @@
+//! A gRPC service and client for a central filter, with
+//! [tonic](https://crates.io/crates/tonic).
+//!
+//! Requires the `grpc` feature. The API is defined in `proto/bloom.proto`,
+//! from which clients in other languages can be generated: `Check` and
+//! `CheckMulti` look keys up, `Insert` adds them, `Merge` ORs in a filter
+//! serialized by [`BloomFilter::to_bytes`], and `Stats` reports the
+//! geometry and fill. Keys are strings, hashed as `str`.
+//!
+//! [`FilterService`] serves one [`BloomFilter<str>`]; add
+//! [`FilterService::into_server`] to a `tonic::transport::Server`. Rust
+//! clients use the generated [`BloomClient`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use bloomlib::grpc::{pb, BloomClient, FilterService};
+//! use bloomlib::BloomFilter;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let service = FilterService::new(BloomFilter::new(1_000_000, 0.001));
+//! tokio::spawn(
+//!     tonic::transport::Server::builder()
+//!         .add_service(service.into_server())
+//!         .serve("127.0.0.1:50051".parse()?),
+//! );
+//!
+//! let mut client = BloomClient::connect("http://127.0.0.1:50051").await?;
+//! client.insert(pb::InsertRequest { keys: vec!["alice".into()] }).await?;
+//! let response = client.check(pb::CheckRequest { key: "alice".into() }).await?;
+//! assert!(response.into_inner().present);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use tonic::{Request, Response, Status};
+
+use crate::{BloomError, BloomFilter, LoadLimits};
+
+pub use pb::bloom_client::BloomClient;
+pub use pb::bloom_server::{Bloom, BloomServer};
+
+/// The messages and generated stubs of `proto/bloom.proto`.
+pub mod pb {
+    /// Checks whether one key might be in the filter.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CheckRequest {
+        #[prost(string, tag = "1")]
+        pub key: String,
+    }
+
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct CheckResponse {
+        #[prost(bool, tag = "1")]
+        pub present: bool,
+    }
+
+    /// Inserts keys.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InsertRequest {
+        #[prost(string, repeated, tag = "1")]
+        pub keys: Vec<String>,
+    }
+
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct InsertResponse {
+        /// The number of keys that were not (probably) present before.
+        #[prost(uint64, tag = "1")]
+        pub inserted: u64,
+    }
+
+    /// Checks many keys at once.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CheckMultiRequest {
+        #[prost(string, repeated, tag = "1")]
+        pub keys: Vec<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CheckMultiResponse {
+        /// One result per key, in request order.
+        #[prost(bool, repeated, tag = "1")]
+        pub present: Vec<bool>,
+    }
+
+    /// ORs a serialized filter of the same geometry into the filter.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MergeRequest {
+        #[prost(bytes = "vec", tag = "1")]
+        pub filter: Vec<u8>,
+    }
+
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct MergeResponse {}
+
+    /// Asks for the filter's geometry and fill.
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct StatsRequest {}
+
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct StatsResponse {
+        #[prost(uint64, tag = "1")]
+        pub bit_count: u64,
+        #[prost(uint32, tag = "2")]
+        pub hash_count: u32,
+        #[prost(double, tag = "3")]
+        pub fill_ratio: f64,
+        #[prost(double, tag = "4")]
+        pub estimated_items: f64,
+        #[prost(double, tag = "5")]
+        pub false_positive_rate: f64,
+    }
+
+    include!(concat!(env!("OUT_DIR"), "/bloomlib.v1.Bloom.rs"));
+}
+
+/// The gRPC service over one filter of `str` keys.
+///
+/// Lookups share a read lock and inserts and merges take a write lock, held
+/// only while the request's keys are processed.
+#[derive(Debug)]
+pub struct FilterService {
+    filter: RwLock<BloomFilter<str>>,
+}
+
+impl FilterService {
+    /// Serves `filter`.
+    pub fn new(filter: BloomFilter<str>) -> Self {
+        FilterService {
+            filter: RwLock::new(filter),
+        }
+    }
+
+    /// Wraps the service for `tonic::transport::Server::add_service`.
+    pub fn into_server(self) -> BloomServer<Self> {
+        BloomServer::new(self)
+    }
+
+    /// Returns the filter, e.g. to save it at shutdown.
+    pub fn into_inner(self) -> BloomFilter<str> {
+        self.filter
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // The filter is consistent even if a holder panicked: bits are only set.
+    fn read(&self) -> RwLockReadGuard<'_, BloomFilter<str>> {
+        self.filter
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, BloomFilter<str>> {
+        self.filter
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[tonic::async_trait]
+impl Bloom for FilterService {
+    async fn check(
+        &self,
+        request: Request<pb::CheckRequest>,
+    ) -> Result<Response<pb::CheckResponse>, Status> {
+        let present = self.read().contains(&request.get_ref().key);
+        Ok(Response::new(pb::CheckResponse { present }))
+    }
+
+    async fn insert(
+        &self,
+        request: Request<pb::InsertRequest>,
+    ) -> Result<Response<pb::InsertResponse>, Status> {
+        let mut filter = self.write();
+        let inserted = request
+            .get_ref()
+            .keys
+            .iter()
+            .filter(|key| filter.check_and_insert(key))
+            .count();
+        Ok(Response::new(pb::InsertResponse {
+            inserted: inserted as u64,
+        }))
+    }
+
+    async fn check_multi(
+        &self,
+        request: Request<pb::CheckMultiRequest>,
+    ) -> Result<Response<pb::CheckMultiResponse>, Status> {
+        let present = self.read().contains_batch(&request.get_ref().keys);
+        Ok(Response::new(pb::CheckMultiResponse { present }))
+    }
+
+    async fn merge(
+        &self,
+        request: Request<pb::MergeRequest>,
+    ) -> Result<Response<pb::MergeResponse>, Status> {
+        // Anything larger than this filter could not be merged anyway.
+        let limits = LoadLimits::new(self.read().memory_usage_bytes() as u64);
+        let other: BloomFilter<str> =
+            BloomFilter::from_bytes_with_limits(&request.get_ref().filter, &limits)
+                .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        self.write().merge(&other).map_err(|err| match err {
+            BloomError::IncompatibleFilters { .. } => Status::failed_precondition(err.to_string()),
+            _ => Status::internal(err.to_string()),
+        })?;
+        Ok(Response::new(pb::MergeResponse {}))
+    }
+
+    async fn stats(
+        &self,
+        _request: Request<pb::StatsRequest>,
+    ) -> Result<Response<pb::StatsResponse>, Status> {
+        let filter = self.read();
+        let fill_ratio = filter.fill_ratio();
+        Ok(Response::new(pb::StatsResponse {
+            bit_count: filter.bit_count(),
+            hash_count: filter.hash_count(),
+            fill_ratio,
+            estimated_items: filter.estimated_items(),
+            false_positive_rate: fill_ratio.powi(filter.hash_count() as i32),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::transport::server::TcpIncoming;
+    use tonic::transport::Server;
+
+    #[tokio::test]
+    async fn test_client_and_server() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = FilterService::new(BloomFilter::new(1000, 0.01));
+        tokio::spawn(
+            Server::builder()
+                .add_service(service.into_server())
+                .serve_with_incoming(TcpIncoming::from(listener)),
+        );
+
+        let mut client = BloomClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        let keys = vec!["apple".to_owned(), "banana".to_owned(), "apple".to_owned()];
+        let response = client.insert(pb::InsertRequest { keys }).await.unwrap();
+        assert_eq!(response.into_inner().inserted, 2);
+        let response = client
+            .check(pb::CheckRequest {
+                key: "apple".into(),
+            })
+            .await
+            .unwrap();
+        assert!(response.into_inner().present);
+
+        let mut other: BloomFilter<str> = BloomFilter::new(1000, 0.01);
+        other.insert("cherry");
+        let filter = other.to_bytes();
+        client.merge(pb::MergeRequest { filter }).await.unwrap();
+        let keys = vec!["cherry".to_owned(), "durian".to_owned()];
+        let response = client
+            .check_multi(pb::CheckMultiRequest { keys })
+            .await
+            .unwrap();
+        assert_eq!(response.into_inner().present, [true, false]);
+
+        let stats = client
+            .stats(pb::StatsRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(stats.hash_count, 7);
+        assert!((stats.estimated_items - 3.0).abs() < 0.5);
+
+        let filter = BloomFilter::<str>::new(10, 0.01).to_bytes();
+        let status = client.merge(pb::MergeRequest { filter }).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        let filter = b"BLMF".to_vec();
+        let status = client.merge(pb::MergeRequest { filter }).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+use std::sync::OnceLock;
+
+/// Computes two 64-bit hashes for the item.
+pub(crate) fn hash_pair<T: ?Sized + Hash>(item: &T) -> (u64, u64) {
+    hash_pair_with(&BuildHasherDefault::<DefaultHasher>::default(), item)
+}
+
+/// Computes two 64-bit hashes for the item with hashers from `build_hasher`:
+/// `h1` hashes the item, `h2` hashes the item followed by `h1`.
+pub(crate) fn hash_pair_with<S: BuildHasher, T: ?Sized + Hash>(
+    build_hasher: &S,
+    item: &T,
+) -> (u64, u64) {
+    let h1 = build_hasher.hash_one(item);
+
+    let mut hasher2 = build_hasher.build_hasher();
+    item.hash(&mut hasher2);
+    h1.hash(&mut hasher2);
+    let h2 = hasher2.finish();
+
+    (h1, h2)
+}
+
+/// Calculates the bit index for the i-th hash function using Double Hashing.
+///
+/// A power-of-two `bit_count` is reduced with a mask. Any other size uses
+/// Lemire's multiply-shift "fastrange", which maps the hash onto
+/// `0..bit_count` as evenly as a modulo but without a 64-bit division.
+///
+/// This is a `const fn` so that [`ConstBloomFilter`](crate::ConstBloomFilter)
+/// probes the same positions.
+#[inline]
+pub(crate) const fn bit_index(h1: u64, h2: u64, i: u32, bit_count: u64) -> u64 {
+    let offset = h2.wrapping_mul(i as u64);
+    let hash = h1.wrapping_add(offset);
+
+    if bit_count.is_power_of_two() {
+        hash & (bit_count - 1)
+    } else {
+        fastrange(hash, bit_count)
+    }
+}
+
+/// Maps `index` in `0..count` onto `0..range`, keeping order: equal slices
+/// of `0..count` map to each value.
+#[inline]
+pub(crate) fn fastrange_of(index: u64, count: u64, range: u64) -> u64 {
+    (index as u128 * range as u128 / count as u128) as u64
+}
+
+/// Maps `hash` onto `0..range` with a multiply-shift: the top 64 bits of
+/// the 128-bit product `hash * range`.
+#[inline]
+pub(crate) const fn fastrange(hash: u64, range: u64) -> u64 {
+    ((hash as u128 * range as u128) >> 64) as u64
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A primitive integer key that batch APIs hash several at a time.
+///
+/// Implemented for `u32`, `u64`, `i32` and `i64`. The batch hashes are the
+/// same as those of [`Hash`], so keys inserted one way are found the other.
+pub trait IntegerKey: Hash + Copy + sealed::Sealed {
+    /// The size of the key's `Hash` input in bytes.
+    #[doc(hidden)]
+    const BYTES: u64;
+
+    /// Returns the key's `Hash` input, read as a little-endian word the way
+    /// SipHash reads its message.
+    #[doc(hidden)]
+    fn message(self) -> u64;
+}
+
+macro_rules! impl_integer_key {
+    ($($t:ty => $unsigned:ty),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+
+            impl IntegerKey for $t {
+                const BYTES: u64 = std::mem::size_of::<$t>() as u64;
+
+                #[inline]
+                fn message(self) -> u64 {
+                    <$unsigned>::from_le_bytes(self.to_ne_bytes()) as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_key!(u32 => u32, u64 => u64, i32 => u32, i64 => u64);
+
+/// The number of keys [`integer_hash_pairs`] hashes together.
+const LANES: usize = 8;
+
+/// One 64-bit value per key of a batch.
+type Lanes = [u64; LANES];
+
+/// Computes [`hash_pair`] for a slice of integer keys, [`LANES`] at a time.
+///
+/// `DefaultHasher` is SipHash-1-3 with zero keys. Running its rounds on
+/// [`LANES`] keys side by side compiles to vector instructions, where hashing
+/// one key at a time is a chain of dependent scalar operations; on x86-64 the
+/// lanes are compiled for AVX-512 and AVX2 and picked at run time. The standard
+/// library does not promise that algorithm, so the lane version is checked
+/// against `DefaultHasher` once, and the keys are hashed one by one if the two
+/// ever disagree.
+pub(crate) fn integer_hash_pairs<K: IntegerKey>(
+    keys: &[K],
+) -> impl Iterator<Item = (u64, u64)> + '_ {
+    let wide = lanes_match_default_hasher();
+    keys.chunks(LANES).flat_map(move |chunk| {
+        let mut pairs = [(0, 0); LANES];
+        if wide {
+            let mut messages = [0; LANES];
+            for (message, key) in messages.iter_mut().zip(chunk) {
+                *message = key.message();
+            }
+            let (h1, h2) = sip_lanes_widest::<K>(&messages);
+            for (lane, pair) in pairs.iter_mut().enumerate() {
+                *pair = (h1[lane], h2[lane]);
+            }
+        } else {
+            for (pair, key) in pairs.iter_mut().zip(chunk) {
+                *pair = hash_pair(key);
+            }
+        }
+        pairs.into_iter().take(chunk.len())
+    })
+}
+
+/// Returns whether [`sip_lanes`] agrees with [`hash_pair`], checking once.
+fn lanes_match_default_hasher() -> bool {
+    static MATCHES: OnceLock<bool> = OnceLock::new();
+    *MATCHES.get_or_init(|| {
+        let samples: [u64; LANES] = [0, 1, 2, 255, 1 << 32, 0xdead_beef, u64::MAX - 1, u64::MAX];
+        let (h1, h2) = sip_lanes::<u64>(&samples.map(IntegerKey::message));
+        let wide_ok = (0..LANES).all(|lane| hash_pair(&samples[lane]) == (h1[lane], h2[lane]));
+
+        let narrow = samples.map(|sample| sample as u32);
+        let (h1, h2) = sip_lanes::<u32>(&narrow.map(IntegerKey::message));
+        let narrow_ok = (0..LANES).all(|lane| hash_pair(&narrow[lane]) == (h1[lane], h2[lane]));
+
+        wide_ok && narrow_ok
+    })
+}
+
+/// Runs [`sip_lanes`] built for the widest vector extension the CPU supports.
+#[inline]
+fn sip_lanes_widest<K: IntegerKey>(messages: &Lanes) -> (Lanes, Lanes) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx512f") {
+            // SAFETY: AVX-512F support was just checked.
+            return unsafe { sip_lanes_avx512::<K>(messages) };
+        }
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support was just checked.
+            return unsafe { sip_lanes_avx2::<K>(messages) };
+        }
+    }
+    sip_lanes::<K>(messages)
+}
+
+/// [`sip_lanes`] compiled for AVX-512F, which has 64-bit lane rotates.
+///
+/// # Safety
+///
+/// The CPU must support AVX-512F.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+unsafe fn sip_lanes_avx512<K: IntegerKey>(messages: &Lanes) -> (Lanes, Lanes) {
+    sip_lanes::<K>(messages)
+}
+
+/// [`sip_lanes`] compiled for AVX2.
+///
+/// # Safety
+///
+/// The CPU must support AVX2.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn sip_lanes_avx2<K: IntegerKey>(messages: &Lanes) -> (Lanes, Lanes) {
+    sip_lanes::<K>(messages)
+}
+
+/// Computes `hash_pair` for [`LANES`] keys of type `K` given as messages.
+///
+/// `h1` hashes the key bytes; `h2` hashes the key bytes followed by `h1`.
+#[inline(always)]
+fn sip_lanes<K: IntegerKey>(messages: &Lanes) -> (Lanes, Lanes) {
+    let len_tag = |bytes: u64| bytes << 56;
+    let h1_bytes = |h1: u64| u64::from_le_bytes(h1.to_ne_bytes());
+
+    if K::BYTES == 8 {
+        let mut state = Sip::new();
+        state.compress(messages);
+        let h1 = state.finish(&[len_tag(8); LANES]);
+
+        let mut state = Sip::new();
+        state.compress(messages);
+        state.compress(&h1.map(h1_bytes));
+        let h2 = state.finish(&[len_tag(16); LANES]);
+        (h1, h2)
+    } else {
+        // Four key bytes: the whole key is the final, partial block.
+        let mut state = Sip::new();
+        let h1 = state.finish(&messages.map(|message| message | len_tag(4)));
+
+        // The key and the low half of `h1` fill a block; the high half of
+        // `h1` is the final, partial block.
+        let mut state = Sip::new();
+        let mut block = [0; LANES];
+        let mut last = [0; LANES];
+        for lane in 0..LANES {
+            block[lane] = messages[lane] | h1_bytes(h1[lane]) << 32;
+            last[lane] = h1_bytes(h1[lane]) >> 32 | len_tag(12);
+        }
+        state.compress(&block);
+        (h1, state.finish(&last))
+    }
+}
+
+/// SipHash-1-3 state with zero keys, for [`LANES`] messages at once.
+struct Sip {
+    v: [Lanes; 4],
+}
+
+impl Sip {
+    #[inline(always)]
+    fn new() -> Self {
+        Sip {
+            v: [
+                [0x736f_6d65_7073_6575; LANES],
+                [0x646f_7261_6e64_6f6d; LANES],
+                [0x6c79_6765_6e65_7261; LANES],
+                [0x7465_6462_7974_6573; LANES],
+            ],
+        }
+    }
+
+    /// Absorbs one 8-byte block per lane.
+    #[inline(always)]
+    fn compress(&mut self, block: &Lanes) {
+        for (v3, word) in self.v[3].iter_mut().zip(block) {
+            *v3 ^= word;
+        }
+        self.round();
+        for (v0, word) in self.v[0].iter_mut().zip(block) {
+            *v0 ^= word;
+        }
+    }
+
+    /// Absorbs the final block (the trailing bytes and the length tag) and
+    /// returns the hashes.
+    #[inline(always)]
+    fn finish(&mut self, last: &Lanes) -> Lanes {
+        self.compress(last);
+        for v2 in &mut self.v[2] {
+            *v2 ^= 0xff;
+        }
+        for _ in 0..3 {
+            self.round();
+        }
+        let [v0, v1, v2, v3] = &self.v;
+        std::array::from_fn(|lane| v0[lane] ^ v1[lane] ^ v2[lane] ^ v3[lane])
+    }
+
+    #[inline(always)]
+    fn round(&mut self) {
+        let [v0, v1, v2, v3] = &mut self.v;
+        for lane in 0..LANES {
+            v0[lane] = v0[lane].wrapping_add(v1[lane]);
+            v1[lane] = v1[lane].rotate_left(13) ^ v0[lane];
+            v0[lane] = v0[lane].rotate_left(32);
+            v2[lane] = v2[lane].wrapping_add(v3[lane]);
+            v3[lane] = v3[lane].rotate_left(16) ^ v2[lane];
+            v0[lane] = v0[lane].wrapping_add(v3[lane]);
+            v3[lane] = v3[lane].rotate_left(21) ^ v0[lane];
+            v2[lane] = v2[lane].wrapping_add(v1[lane]);
+            v1[lane] = v1[lane].rotate_left(17) ^ v2[lane];
+            v2[lane] = v2[lane].rotate_left(32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fastrange_stays_in_range() {
+        let range = 1_000_003;
+        for hash in [0, 1, 999, 1 << 63, u64::MAX - 1, u64::MAX] {
+            assert!(fastrange(hash, range) < range);
+        }
+        assert_eq!(fastrange(u64::MAX, range), range - 1);
+    }
+
+    #[test]
+    fn test_fastrange_spreads_evenly() {
+        let mut buckets = [0u32; 10];
+        for i in 0..10_000u64 {
+            let (h1, _) = hash_pair(&i);
+            buckets[fastrange(h1, 10) as usize] += 1;
+        }
+        for count in buckets {
+            assert!(
+                (850..1150).contains(&count),
+                "uneven buckets: {:?}",
+                buckets
+            );
+        }
+    }
+
+    #[test]
+    fn test_integer_hash_pairs_match_hash_pair() {
+        assert!(lanes_match_default_hasher());
+
+        let keys: Vec<u64> = (0..37u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .collect();
+        let expected: Vec<_> = keys.iter().map(hash_pair).collect();
+        assert_eq!(integer_hash_pairs(&keys).collect::<Vec<_>>(), expected);
+
+        let keys: Vec<i32> = (-20..17).collect();
+        let expected: Vec<_> = keys.iter().map(hash_pair).collect();
+        assert_eq!(integer_hash_pairs(&keys).collect::<Vec<_>>(), expected);
+    }
+}
@@ -0,0 +1,279 @@
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::{BitStore, BloomFilter};
+
+/// A lifecycle event reported to [`Hooks`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum FilterEvent {
+    /// The fill ratio of a [`HookedBloomFilter`] rose past a threshold
+    /// registered with [`Hooks::fill_threshold`].
+    FillRatioCrossed {
+        /// The threshold that was crossed.
+        threshold: f64,
+        /// The estimated fill ratio just after the crossing.
+        fill_ratio: f64,
+    },
+    /// A [`ScalableBloomFilter`](crate::ScalableBloomFilter) added a slice.
+    SliceAdded {
+        /// The number of slices, including the new one.
+        slice_count: usize,
+        /// The number of items the new slice was sized for.
+        capacity: usize,
+    },
+    /// A [`HookedBloomFilter`] was replaced by a new filter.
+    Rotated,
+}
+
+type Callback = Arc<dyn Fn(&FilterEvent) + Send + Sync>;
+
+/// Callbacks run synchronously when a filter reports a [`FilterEvent`].
+///
+/// Callbacks run on the thread that caused the event, inside the insert
+/// that caused it, so they see the event as it happens rather than at the
+/// next poll. They should be quick: hand slow work such as persisting the
+/// filter to another thread.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::mpsc;
+/// use bloomlib::{BloomFilter, FilterEvent, HookedBloomFilter, Hooks};
+///
+/// let (events, received) = mpsc::channel();
+/// let hooks = Hooks::new()
+///     .fill_threshold(0.5)
+///     .on_event(move |event| events.send(*event).unwrap());
+///
+/// let mut filter = HookedBloomFilter::new(BloomFilter::<u64>::new(1000, 0.01), hooks);
+/// for i in 0..2000 {
+///     filter.insert(&i);
+/// }
+/// assert!(matches!(
+///     received.try_recv(),
+///     Ok(FilterEvent::FillRatioCrossed { threshold: 0.5, .. })
+/// ));
+/// ```
+#[derive(Clone, Default)]
+pub struct Hooks {
+    callbacks: Vec<Callback>,
+    /// Fill ratio thresholds, in ascending order.
+    fill_thresholds: Vec<f64>,
+}
+
+impl Hooks {
+    /// Creates an empty set of hooks.
+    pub fn new() -> Self {
+        Hooks::default()
+    }
+
+    /// Adds a callback for every event.
+    pub fn on_event(mut self, callback: impl Fn(&FilterEvent) + Send + Sync + 'static) -> Self {
+        self.callbacks.push(Arc::new(callback));
+        self
+    }
+
+    /// Reports [`FilterEvent::FillRatioCrossed`] when the fill ratio rises
+    /// past `threshold`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threshold` is not between 0.0 and 1.0, exclusive.
+    pub fn fill_threshold(mut self, threshold: f64) -> Self {
+        assert!(
+            threshold > 0.0 && threshold < 1.0,
+            "Fill threshold must be between 0.0 and 1.0, exclusive."
+        );
+        let index = self.fill_thresholds.partition_point(|&t| t < threshold);
+        self.fill_thresholds.insert(index, threshold);
+        self
+    }
+
+    /// Runs every callback on `event`.
+    pub(crate) fn emit(&self, event: FilterEvent) {
+        for callback in &self.callbacks {
+            callback(&event);
+        }
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("callbacks", &self.callbacks.len())
+            .field("fill_thresholds", &self.fill_thresholds)
+            .finish()
+    }
+}
+
+/// A [`BloomFilter`] that reports fill thresholds and rotations to
+/// [`Hooks`] as they happen.
+///
+/// The fill ratio is tracked as an estimate rather than by counting bits:
+/// each new item leaves a given bit unset with probability `(1 - 1/m)^k`,
+/// so the unset fraction is multiplied by that factor on every insert of an
+/// item that was not already present. This costs one multiplication per
+/// insert, and the filter's actual [`fill_ratio`](BloomFilter::fill_ratio)
+/// is only measured when the wrapper is created or rotated.
+///
+/// See [`Hooks`] for an example.
+#[derive(Debug)]
+pub struct HookedBloomFilter<T: ?Sized, B = Vec<u64>> {
+    filter: BloomFilter<T, B>,
+    hooks: Hooks,
+    /// The estimated fraction of bits still unset.
+    unset: f64,
+    /// The factor `unset` shrinks by with each new item.
+    decay: f64,
+    /// The index of the lowest threshold not yet crossed.
+    next_threshold: usize,
+}
+
+impl<T: ?Sized + Hash, B: BitStore> HookedBloomFilter<T, B> {
+    /// Wraps `filter`. Thresholds it has already crossed are not reported.
+    pub fn new(filter: BloomFilter<T, B>, hooks: Hooks) -> Self {
+        let mut hooked = HookedBloomFilter {
+            hooks,
+            unset: 0.0,
+            decay: 0.0,
+            next_threshold: 0,
+            filter,
+        };
+        hooked.reset_tracking();
+        hooked
+    }
+
+    /// Inserts an item, returning `true` if it was new, and reports any fill
+    /// threshold the insert crossed.
+    pub fn insert(&mut self, item: &T) -> bool {
+        if !self.filter.check_and_insert(item) {
+            return false;
+        }
+        self.unset *= self.decay;
+        while let Some(&threshold) = self.hooks.fill_thresholds.get(self.next_threshold) {
+            let fill_ratio = 1.0 - self.unset;
+            if fill_ratio < threshold {
+                break;
+            }
+            self.next_threshold += 1;
+            self.hooks.emit(FilterEvent::FillRatioCrossed {
+                threshold,
+                fill_ratio,
+            });
+        }
+        true
+    }
+
+    /// Checks if an item might be in the filter.
+    pub fn contains(&self, item: &T) -> bool {
+        self.filter.contains(item)
+    }
+
+    /// Returns the estimated fill ratio.
+    pub fn estimated_fill_ratio(&self) -> f64 {
+        1.0 - self.unset
+    }
+
+    /// Replaces the filter with `next`, reporting [`FilterEvent::Rotated`],
+    /// and returns the previous one.
+    pub fn rotate(&mut self, next: BloomFilter<T, B>) -> BloomFilter<T, B> {
+        let previous = std::mem::replace(&mut self.filter, next);
+        self.reset_tracking();
+        self.hooks.emit(FilterEvent::Rotated);
+        previous
+    }
+
+    /// Returns the wrapped filter.
+    pub fn filter(&self) -> &BloomFilter<T, B> {
+        &self.filter
+    }
+
+    /// Unwraps the filter.
+    pub fn into_inner(self) -> BloomFilter<T, B> {
+        self.filter
+    }
+
+    /// Measures the filter's fill and skips the thresholds it is past.
+    fn reset_tracking(&mut self) {
+        let fill_ratio = self.filter.fill_ratio();
+        let (m, k) = (self.filter.bit_count() as f64, self.filter.hash_count());
+        self.unset = 1.0 - fill_ratio;
+        self.decay = (1.0 - 1.0 / m).powi(k as i32);
+        self.next_threshold = self
+            .hooks
+            .fill_thresholds
+            .partition_point(|&threshold| threshold <= fill_ratio);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    fn recorder() -> (Hooks, Arc<Mutex<Vec<FilterEvent>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let hooks = Hooks::new().on_event(move |event| sink.lock().unwrap().push(*event));
+        (hooks, events)
+    }
+
+    #[test]
+    fn test_fill_thresholds() {
+        let (hooks, events) = recorder();
+        let hooks = hooks.fill_threshold(0.5).fill_threshold(0.25);
+        let mut filter = HookedBloomFilter::new(BloomFilter::<u64>::new(1000, 0.01), hooks);
+
+        for i in 0..1000 {
+            filter.insert(&i);
+            // Duplicates do not move the estimate.
+            filter.insert(&i);
+        }
+        let estimate = filter.estimated_fill_ratio();
+        let actual = filter.filter().fill_ratio();
+        assert!(
+            (estimate - actual).abs() < 0.02,
+            "{} vs {}",
+            estimate,
+            actual
+        );
+
+        let thresholds: Vec<f64> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| match event {
+                FilterEvent::FillRatioCrossed {
+                    threshold,
+                    fill_ratio,
+                } => {
+                    assert!(fill_ratio >= threshold);
+                    *threshold
+                }
+                event => panic!("unexpected {:?}", event),
+            })
+            .collect();
+        assert_eq!(thresholds, [0.25, 0.5]);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let (hooks, events) = recorder();
+        let mut full: BloomFilter<u64> = BloomFilter::new(100, 0.01);
+        (0..1000).for_each(|i| full.insert(&i));
+
+        // Thresholds already crossed are skipped, on creation and rotation.
+        let mut filter = HookedBloomFilter::new(full.clone(), hooks.fill_threshold(0.5));
+        filter.rotate(full);
+        assert_eq!(*events.lock().unwrap(), [FilterEvent::Rotated]);
+
+        filter.rotate(BloomFilter::new(100, 0.01));
+        (0..100).for_each(|i| {
+            filter.insert(&i);
+        });
+        assert_eq!(events.lock().unwrap().len(), 3);
+    }
+}
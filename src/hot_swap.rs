@@ -0,0 +1,222 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use arc_swap::{ArcSwap, Guard};
+
+use crate::{trace, AllocStore, BloomError, BloomFilter, FilterParams};
+
+/// A filter slot whose contents can be replaced while readers keep running.
+///
+/// Requires the `arc-swap` feature. Readers take a lock-free snapshot with
+/// [`load`](Self::load) and query it; a writer (typically a background
+/// thread that has finished building a replacement) installs the new filter
+/// with [`swap`](Self::swap) in a single atomic pointer exchange. Readers
+/// holding the previous snapshot finish against it undisturbed, and the old
+/// filter is dropped once the last snapshot is released.
+///
+/// Works with any filter type, e.g. [`BloomFilter`](crate::BloomFilter) or
+/// a read-optimized [`FrozenBloomFilter`](crate::FrozenBloomFilter).
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use bloomlib::{BloomFilter, HotSwapFilter};
+///
+/// let mut first: BloomFilter<str> = BloomFilter::new(100, 0.01);
+/// first.insert("old");
+/// let slot = Arc::new(HotSwapFilter::new(first));
+///
+/// // Build the replacement off the serving path, then swap it in.
+/// let builder = {
+///     let slot = Arc::clone(&slot);
+///     thread::spawn(move || {
+///         let mut next: BloomFilter<str> = BloomFilter::new(100, 0.01);
+///         next.insert("new");
+///         slot.swap(next);
+///     })
+/// };
+/// builder.join().unwrap();
+///
+/// assert!(slot.load().contains("new"));
+/// assert!(!slot.load().contains("old"));
+/// ```
+#[derive(Debug)]
+pub struct HotSwapFilter<F> {
+    /// The current generation of the filter.
+    current: ArcSwap<F>,
+}
+
+impl<F> HotSwapFilter<F> {
+    /// Creates a slot holding `filter`.
+    pub fn new(filter: F) -> Self {
+        HotSwapFilter {
+            current: ArcSwap::from_pointee(filter),
+        }
+    }
+
+    /// Returns a lock-free snapshot of the current filter.
+    ///
+    /// The snapshot is meant to be short-lived (e.g. for one request); use
+    /// [`load_full`](Self::load_full) to keep a filter for longer.
+    pub fn load(&self) -> Guard<Arc<F>> {
+        self.current.load()
+    }
+
+    /// Returns an owned handle to the current filter.
+    pub fn load_full(&self) -> Arc<F> {
+        self.current.load_full()
+    }
+
+    /// Atomically replaces the filter, returning the previous generation.
+    pub fn swap(&self, filter: F) -> Arc<F> {
+        trace::timed_span!("bloom.rotate");
+        self.current.swap(Arc::new(filter))
+    }
+
+    /// Atomically replaces the filter with one already behind an `Arc`,
+    /// returning the previous generation.
+    pub fn swap_arc(&self, filter: Arc<F>) -> Arc<F> {
+        trace::timed_span!("bloom.rotate");
+        self.current.swap(filter)
+    }
+
+    /// Replaces the filter with one derived from the current generation.
+    ///
+    /// `update` may run more than once if other writers swap concurrently,
+    /// so it should not have side effects. Returns the generation that was
+    /// replaced.
+    pub fn update(&self, mut update: impl FnMut(&F) -> F) -> Arc<F> {
+        trace::timed_span!("bloom.rotate");
+        self.current.rcu(|current| Arc::new(update(current)))
+    }
+}
+
+impl<T: ?Sized + Hash, B: AllocStore> HotSwapFilter<BloomFilter<T, B>> {
+    /// Rebuilds the filter with new parameters from `keys` and swaps it in,
+    /// returning the generation that was replaced.
+    ///
+    /// The replacement is sized like
+    /// [`BloomFilter::from_params`]`(expected_items, params)`, so a new
+    /// false positive rate or capacity takes effect without downtime.
+    /// Readers keep querying the current filter while the replacement is
+    /// built, and see either the old or the new filter, never a partial
+    /// one. The bits of a filter cannot be mapped to a different bit count,
+    /// so `keys` must yield every key of the filter again, e.g. from the
+    /// table or append-only log it was built from. Keys inserted into the
+    /// current generation during the rebuild are only in the replacement if
+    /// `keys` yields them too.
+    ///
+    /// Call it from a background thread to keep the caller responsive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as
+    /// [`BloomFilter::try_new`], leaving the current filter in place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use bloomlib::{BloomFilter, HotSwapFilter};
+    ///
+    /// let keys: Vec<String> = (0..1000).map(|i| format!("user-{}", i)).collect();
+    /// let mut filter: BloomFilter<str> = BloomFilter::new(1000, 0.05);
+    /// keys.iter().for_each(|key| filter.insert(key));
+    /// let slot = Arc::new(HotSwapFilter::new(filter));
+    ///
+    /// // Tighten the false positive rate while readers keep serving.
+    /// let rebuild = {
+    ///     let slot = Arc::clone(&slot);
+    ///     thread::spawn(move || slot.reparameterize(keys.iter().map(String::as_str), 1000, 0.001))
+    /// };
+    /// assert!(slot.load().contains("user-7"));
+    /// let previous = rebuild.join().unwrap().unwrap();
+    /// assert!(slot.load().bit_count() > previous.bit_count());
+    /// assert!(slot.load().contains("user-7"));
+    /// ```
+    pub fn reparameterize<I>(
+        &self,
+        keys: I,
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<Arc<BloomFilter<T, B>>, BloomError>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<T>,
+    {
+        let mut filter = BloomFilter::try_from_params(expected_items, params)?;
+        for key in keys {
+            filter.insert(key.borrow());
+        }
+        Ok(self.swap(filter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BloomFilter;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    #[test]
+    fn test_readers_continue_during_swaps() {
+        let mut initial = BloomFilter::new(1000, 0.01);
+        initial.insert(&0u64);
+        let slot = HotSwapFilter::new(initial);
+        let done = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    while !done.load(Ordering::Relaxed) {
+                        // Every generation contains 0, so readers never miss it.
+                        assert!(slot.load().contains(&0));
+                    }
+                });
+            }
+            for generation in 1..50u64 {
+                let mut next = BloomFilter::new(1000, 0.01);
+                next.insert(&0u64);
+                next.insert(&generation);
+                slot.swap(next);
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+
+        assert!(slot.load().contains(&49));
+    }
+
+    #[test]
+    fn test_update_derives_from_current() {
+        let slot = HotSwapFilter::new(BloomFilter::<u64>::new(100, 0.01));
+        let previous = slot.update(|current| {
+            let mut next = current.clone();
+            next.insert(&7);
+            next
+        });
+        assert!(!previous.contains(&7));
+        assert!(slot.load().contains(&7));
+    }
+
+    #[test]
+    fn test_reparameterize_keeps_keys() {
+        let mut filter = BloomFilter::<u64>::new(100, 0.1);
+        (0..100).for_each(|i| filter.insert(&i));
+        let slot = HotSwapFilter::new(filter);
+
+        let previous = slot.reparameterize(0..100u64, 100, 0.001).unwrap();
+        let current = slot.load_full();
+        assert!(current.bit_count() > previous.bit_count());
+        assert!(current.hash_count() > previous.hash_count());
+        assert!((0..100).all(|i| current.contains(&i)));
+
+        let err = slot.reparameterize(0..100u64, 0, 0.01).unwrap_err();
+        assert_eq!(err, BloomError::ZeroExpectedItems);
+        assert!(Arc::ptr_eq(&slot.load_full(), &current));
+    }
+}
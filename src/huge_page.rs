@@ -0,0 +1,239 @@
+use std::alloc::{handle_alloc_error, Layout};
+use std::ffi::{c_int, c_void};
+use std::ptr::NonNull;
+
+use crate::{AllocStore, BitStore};
+
+/// The huge page size on x86-64 and 4K-page AArch64 Linux.
+const HUGE_PAGE_BYTES: usize = 2 << 20;
+
+const PROT_READ: c_int = 0x1;
+const PROT_WRITE: c_int = 0x2;
+const MAP_PRIVATE: c_int = 0x02;
+const MAP_ANONYMOUS: c_int = 0x20;
+const MAP_HUGETLB: c_int = 0x40000;
+const MADV_HUGEPAGE: c_int = 14;
+const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+extern "C" {
+    fn mmap(
+        addr: *mut c_void,
+        len: usize,
+        prot: c_int,
+        flags: c_int,
+        fd: c_int,
+        offset: i64,
+    ) -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    fn madvise(addr: *mut c_void, len: usize, advice: c_int) -> c_int;
+}
+
+/// A [`BitStore`] of `u64` words backed by 2 MiB huge pages (Linux only).
+///
+/// A multi-gigabyte filter on 4 KiB pages needs hundreds of thousands of TLB
+/// entries, and since probes land on random pages nearly every lookup misses
+/// the TLB. Huge pages cut the number of pages 512-fold.
+///
+/// The store first asks for explicit huge pages (`MAP_HUGETLB`), which only
+/// succeeds when the administrator has reserved them (`vm.nr_hugepages`).
+/// Otherwise it maps ordinary memory aligned to 2 MiB and advises the kernel
+/// to back it with transparent huge pages (`MADV_HUGEPAGE`). The size is
+/// rounded up to whole huge pages, so this store is meant for large filters.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{BloomFilter, HugePageStore};
+///
+/// let mut bf = BloomFilter::<str, HugePageStore>::from_params(1_000_000, 0.01);
+/// bf.insert("seen");
+/// assert!(bf.contains("seen"));
+/// ```
+#[derive(Debug)]
+pub struct HugePageStore {
+    /// The start of the mapping, aligned to `HUGE_PAGE_BYTES`.
+    ptr: NonNull<u64>,
+    /// The number of words in use.
+    word_count: usize,
+    /// The length of the mapping in bytes, a multiple of `HUGE_PAGE_BYTES`.
+    mapped_bytes: usize,
+    /// Whether the mapping uses explicit (hugetlbfs) pages.
+    explicit: bool,
+}
+
+// SAFETY: the store exclusively owns its mapping, like a `Box<[u64]>`.
+unsafe impl Send for HugePageStore {}
+// SAFETY: shared access only reads the mapping.
+unsafe impl Sync for HugePageStore {}
+
+impl HugePageStore {
+    /// Returns `true` if the store got explicit huge pages, or `false` if it
+    /// relies on transparent huge pages.
+    pub fn is_explicit(&self) -> bool {
+        self.explicit
+    }
+
+    /// Returns the words as one slice.
+    pub fn as_slice(&self) -> &[u64] {
+        // SAFETY: the mapping holds at least `word_count` zero-initialized words.
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.word_count) }
+    }
+
+    /// Returns the words as one mutable slice.
+    pub fn as_mut_slice(&mut self) -> &mut [u64] {
+        // SAFETY: as in `as_slice`, and `&mut self` makes the access exclusive.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.word_count) }
+    }
+}
+
+/// Maps `bytes` of zeroed, 2 MiB-aligned memory, returning the mapping and
+/// whether it uses explicit huge pages.
+fn map_huge(bytes: usize) -> Option<(NonNull<u64>, bool)> {
+    let prot = PROT_READ | PROT_WRITE;
+    let flags = MAP_PRIVATE | MAP_ANONYMOUS;
+
+    // SAFETY: anonymous mappings touch no existing memory.
+    let explicit = unsafe {
+        mmap(
+            std::ptr::null_mut(),
+            bytes,
+            prot,
+            flags | MAP_HUGETLB,
+            -1,
+            0,
+        )
+    };
+    if explicit != MAP_FAILED {
+        return NonNull::new(explicit.cast()).map(|ptr| (ptr, true));
+    }
+
+    // Over-map by one huge page, then trim both ends to an aligned range.
+    let padded = bytes.checked_add(HUGE_PAGE_BYTES)?;
+    // SAFETY: as above.
+    let raw = unsafe { mmap(std::ptr::null_mut(), padded, prot, flags, -1, 0) };
+    if raw == MAP_FAILED {
+        return None;
+    }
+    let head = (raw as usize).next_multiple_of(HUGE_PAGE_BYTES) - raw as usize;
+    let tail = padded - head - bytes;
+    // SAFETY: the head and tail lie inside the mapping just created, and
+    // the aligned middle is advised only; failing to advise is harmless.
+    unsafe {
+        let aligned = raw.cast::<u8>().add(head);
+        if head > 0 {
+            munmap(raw, head);
+        }
+        if tail > 0 {
+            munmap(aligned.add(bytes).cast(), tail);
+        }
+        madvise(aligned.cast(), bytes, MADV_HUGEPAGE);
+        NonNull::new(aligned.cast()).map(|ptr| (ptr, false))
+    }
+}
+
+impl BitStore for HugePageStore {
+    type Word = u64;
+
+    #[inline]
+    fn word_count(&self) -> usize {
+        self.word_count
+    }
+
+    #[inline]
+    fn word(&self, index: usize) -> u64 {
+        self.as_slice()[index]
+    }
+
+    #[inline]
+    fn set_bits(&mut self, index: usize, mask: u64) {
+        self.as_mut_slice()[index] |= mask;
+    }
+
+    fn clear(&mut self) {
+        self.as_mut_slice().fill(0);
+    }
+
+    fn memory_usage_bytes(&self) -> usize {
+        self.mapped_bytes
+    }
+
+    #[inline]
+    fn as_u64_words(&self) -> Option<&[u64]> {
+        Some(self.as_slice())
+    }
+
+    #[inline]
+    fn as_u64_words_mut(&mut self) -> Option<&mut [u64]> {
+        Some(self.as_mut_slice())
+    }
+}
+
+impl AllocStore for HugePageStore {
+    /// Maps `word_count` zeroed words.
+    ///
+    /// Aborts through [`handle_alloc_error`] if the memory cannot be mapped,
+    /// like a failed `Vec` allocation.
+    fn zeroed(word_count: usize) -> Self {
+        let mapped_bytes = word_count
+            .max(1)
+            .checked_mul(8)
+            .and_then(|bytes| bytes.checked_next_multiple_of(HUGE_PAGE_BYTES))
+            .expect("capacity overflow");
+        let (ptr, explicit) = map_huge(mapped_bytes).unwrap_or_else(|| {
+            handle_alloc_error(Layout::from_size_align(mapped_bytes, HUGE_PAGE_BYTES).unwrap())
+        });
+
+        HugePageStore {
+            ptr,
+            word_count,
+            mapped_bytes,
+            explicit,
+        }
+    }
+}
+
+impl Clone for HugePageStore {
+    fn clone(&self) -> Self {
+        let mut copy = Self::zeroed(self.word_count);
+        copy.as_mut_slice().copy_from_slice(self.as_slice());
+        copy
+    }
+}
+
+impl Drop for HugePageStore {
+    fn drop(&mut self) {
+        // SAFETY: the mapping was created by `map_huge` with this length.
+        unsafe {
+            munmap(self.ptr.as_ptr().cast(), self.mapped_bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BloomFilter;
+
+    #[test]
+    fn test_huge_page_store_layout() {
+        let store = HugePageStore::zeroed(1000);
+        assert_eq!(store.word_count(), 1000);
+        assert_eq!(store.memory_usage_bytes(), HUGE_PAGE_BYTES);
+        assert_eq!(store.as_slice().as_ptr() as usize % HUGE_PAGE_BYTES, 0);
+        assert!(store.as_slice().iter().all(|&word| word == 0));
+    }
+
+    #[test]
+    fn test_huge_page_store_matches_vec() {
+        let mut huge = BloomFilter::<u64, HugePageStore>::from_params(100_000, 0.01);
+        let mut plain = BloomFilter::<u64>::new(100_000, 0.01);
+        for i in 0..100_000u64 {
+            huge.insert(&i);
+            plain.insert(&i);
+        }
+        let copy = huge.clone();
+        for i in 0..200_000u64 {
+            assert_eq!(copy.contains(&i), plain.contains(&i));
+        }
+    }
+}
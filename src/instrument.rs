@@ -0,0 +1,230 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+
+use metrics::{counter, gauge, Counter, Gauge};
+
+use crate::{trace, BitStore, BloomFilter};
+
+/// The metrics of one filter, registered once with the global recorder.
+///
+/// Requires the `metrics` feature. Every metric carries a `filter` label
+/// with the name given to [`new`](Self::new):
+///
+/// | Metric                  | Kind    | Meaning                                |
+/// |-------------------------|---------|----------------------------------------|
+/// | `bloom_inserts_total`   | counter | items inserted                         |
+/// | `bloom_queries_total`   | counter | items looked up                        |
+/// | `bloom_positives_total` | counter | lookups that reported the item present |
+/// | `bloom_fill_ratio`      | gauge   | fraction of bits set, as last reported |
+/// | `bloom_rotations_total` | counter | times the filter was replaced          |
+///
+/// The positive ratio approaches the false positive rate when most lookups
+/// are for absent items, and the fill ratio predicts it as `fill^k`.
+#[derive(Debug, Clone)]
+pub struct FilterMetrics {
+    inserts: Counter,
+    queries: Counter,
+    positives: Counter,
+    fill_ratio: Gauge,
+    rotations: Counter,
+}
+
+impl FilterMetrics {
+    /// Registers the metrics of the filter called `name`.
+    pub fn new(name: &str) -> Self {
+        let name = name.to_owned();
+        FilterMetrics {
+            inserts: counter!("bloom_inserts_total", "filter" => name.clone()),
+            queries: counter!("bloom_queries_total", "filter" => name.clone()),
+            positives: counter!("bloom_positives_total", "filter" => name.clone()),
+            fill_ratio: gauge!("bloom_fill_ratio", "filter" => name.clone()),
+            rotations: counter!("bloom_rotations_total", "filter" => name),
+        }
+    }
+
+    /// Records `count` inserts.
+    pub fn record_inserts(&self, count: u64) {
+        self.inserts.increment(count);
+    }
+
+    /// Records `count` lookups, of which `positives` found the item.
+    pub fn record_queries(&self, count: u64, positives: u64) {
+        self.queries.increment(count);
+        self.positives.increment(positives);
+    }
+
+    /// Records that the filter was replaced by a new one, e.g. after
+    /// swapping a `HotSwapFilter`.
+    pub fn record_rotation(&self) {
+        self.rotations.increment(1);
+    }
+
+    /// Sets the fill ratio gauge.
+    pub fn record_fill_ratio(&self, fill_ratio: f64) {
+        self.fill_ratio.set(fill_ratio);
+    }
+}
+
+/// A [`BloomFilter`] that reports its activity through the `metrics` crate.
+///
+/// Requires the `metrics` feature. Counters are updated on every insert and
+/// lookup; the fill ratio takes a pass over the bit array, so it is only
+/// updated by [`report`](Self::report) and [`rotate`](Self::rotate), which
+/// a service typically calls from its scrape or housekeeping loop. See
+/// [`FilterMetrics`] for the metric names.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{BloomFilter, InstrumentedBloomFilter};
+///
+/// let filter: BloomFilter<str> = BloomFilter::new(1000, 0.01);
+/// let mut seen = InstrumentedBloomFilter::new("sessions", filter);
+/// seen.insert("alice");
+/// assert!(seen.contains("alice"));
+/// seen.report();
+/// ```
+#[derive(Debug)]
+pub struct InstrumentedBloomFilter<T: ?Sized, B = Vec<u64>> {
+    filter: BloomFilter<T, B>,
+    metrics: FilterMetrics,
+}
+
+impl<T: ?Sized + Hash, B: BitStore> InstrumentedBloomFilter<T, B> {
+    /// Wraps `filter`, reporting under the label `filter="{name}"`.
+    pub fn new(name: &str, filter: BloomFilter<T, B>) -> Self {
+        let metrics = FilterMetrics::new(name);
+        metrics.record_fill_ratio(filter.fill_ratio());
+        InstrumentedBloomFilter { filter, metrics }
+    }
+
+    /// Inserts an item and counts it.
+    pub fn insert(&mut self, item: &T) {
+        self.filter.insert(item);
+        self.metrics.record_inserts(1);
+    }
+
+    /// Looks up an item, counting the query and a positive result.
+    pub fn contains(&self, item: &T) -> bool {
+        let found = self.filter.contains(item);
+        self.metrics.record_queries(1, found as u64);
+        found
+    }
+
+    /// Looks up a batch of items, counting them with one update per metric.
+    pub fn contains_batch<Q: Borrow<T>>(&self, items: &[Q]) -> Vec<bool> {
+        let found = self.filter.contains_batch(items);
+        let positives = found.iter().filter(|&&found| found).count();
+        self.metrics
+            .record_queries(items.len() as u64, positives as u64);
+        found
+    }
+
+    /// Updates the fill ratio gauge.
+    pub fn report(&self) {
+        self.metrics.record_fill_ratio(self.filter.fill_ratio());
+    }
+
+    /// Replaces the filter with `next`, counting a rotation, and returns the
+    /// previous one.
+    pub fn rotate(&mut self, next: BloomFilter<T, B>) -> BloomFilter<T, B> {
+        trace::timed_span!("bloom.rotate", bits = next.bit_count());
+        let previous = std::mem::replace(&mut self.filter, next);
+        self.metrics.record_rotation();
+        self.report();
+        previous
+    }
+
+    /// Returns the wrapped filter, for the methods not instrumented here.
+    pub fn filter(&self) -> &BloomFilter<T, B> {
+        &self.filter
+    }
+
+    /// Returns the metric handles, e.g. to count work done on
+    /// [`filter`](Self::filter) directly.
+    pub fn metrics(&self) -> &FilterMetrics {
+        &self.metrics
+    }
+
+    /// Unwraps the filter.
+    pub fn into_inner(self) -> BloomFilter<T, B> {
+        self.filter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use metrics::{Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+
+    use super::*;
+
+    /// Keeps every counter and gauge as an atomic, by key.
+    #[derive(Default)]
+    struct TestRecorder {
+        values: Mutex<HashMap<Key, Arc<AtomicU64>>>,
+    }
+
+    impl TestRecorder {
+        fn value(&self, key: &Key) -> Arc<AtomicU64> {
+            Arc::clone(self.values.lock().unwrap().entry(key.clone()).or_default())
+        }
+
+        fn counter(&self, name: &'static str) -> u64 {
+            let key = Key::from_parts(name, vec![metrics::Label::new("filter", "test")]);
+            self.value(&key).load(Ordering::Relaxed)
+        }
+
+        fn gauge(&self, name: &'static str) -> f64 {
+            f64::from_bits(self.counter(name))
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.value(key))
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::from_arc(self.value(key))
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn test_metrics_are_reported() {
+        let recorder = TestRecorder::default();
+        let mut filter = metrics::with_local_recorder(&recorder, || {
+            InstrumentedBloomFilter::new("test", BloomFilter::<str>::new(100, 0.01))
+        });
+        assert_eq!(recorder.gauge("bloom_fill_ratio"), 0.0);
+
+        filter.insert("a");
+        filter.insert("b");
+        assert!(filter.contains("a"));
+        assert_eq!(filter.contains_batch(&["a", "b", "c"]), [true, true, false]);
+        assert_eq!(recorder.counter("bloom_inserts_total"), 2);
+        assert_eq!(recorder.counter("bloom_queries_total"), 4);
+        assert_eq!(recorder.counter("bloom_positives_total"), 3);
+
+        filter.report();
+        assert_eq!(
+            recorder.gauge("bloom_fill_ratio"),
+            filter.filter().fill_ratio()
+        );
+        let previous = filter.rotate(BloomFilter::new(100, 0.01));
+        assert!(previous.contains("a"));
+        assert_eq!(recorder.counter("bloom_rotations_total"), 1);
+        assert_eq!(recorder.gauge("bloom_fill_ratio"), 0.0);
+    }
+}
@@ -0,0 +1,118 @@
+use std::hash::Hash;
+
+use crate::{BloomError, BloomFilter, FilterParams};
+
+/// Adds [`approx_unique`](ApproxUniqueExt::approx_unique) to every iterator
+/// of hashable items.
+pub trait ApproxUniqueExt: Iterator + Sized
+where
+    Self::Item: Hash,
+{
+    /// Yields only the first occurrence of each item, remembering the items
+    /// seen in a [`BloomFilter`] sized for `expected_items` distinct items.
+    ///
+    /// Memory stays fixed however long the iterator runs, at the cost of
+    /// dropping a new item as if it were a repeat with the filter's false
+    /// positive rate. Repeats are never let through.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::ApproxUniqueExt;
+    ///
+    /// let words = ["apple", "banana", "apple", "cherry", "banana"];
+    /// let unique: Vec<_> = words.into_iter().approx_unique(100, 0.001).collect();
+    /// assert_eq!(unique, ["apple", "banana", "cherry"]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    fn approx_unique(
+        self,
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> ApproxUnique<Self> {
+        self.try_approx_unique(expected_items, params)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`approx_unique`](ApproxUniqueExt::approx_unique), but returns
+    /// an error instead of panicking on invalid parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`BloomFilter::try_new`].
+    fn try_approx_unique(
+        self,
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<ApproxUnique<Self>, BloomError> {
+        Ok(ApproxUnique {
+            iter: self,
+            seen: BloomFilter::try_new(expected_items, params)?,
+        })
+    }
+}
+
+impl<I: Iterator> ApproxUniqueExt for I where I::Item: Hash {}
+
+/// An iterator over the first occurrences of items, created by
+/// [`ApproxUniqueExt::approx_unique`].
+#[derive(Debug, Clone)]
+pub struct ApproxUnique<I: Iterator> {
+    iter: I,
+    seen: BloomFilter<I::Item>,
+}
+
+impl<I: Iterator> ApproxUnique<I>
+where
+    I::Item: Hash,
+{
+    /// Returns the filter of the items seen so far, e.g. to save it and
+    /// resume deduplication later.
+    pub fn filter(&self) -> &BloomFilter<I::Item> {
+        &self.seen
+    }
+
+    /// Unwraps the iterator and the filter of the items seen so far.
+    pub fn into_parts(self) -> (I, BloomFilter<I::Item>) {
+        (self.iter, self.seen)
+    }
+}
+
+impl<I: Iterator> Iterator for ApproxUnique<I>
+where
+    I::Item: Hash,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let seen = &mut self.seen;
+        self.iter.find(|item| seen.check_and_insert(item))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approx_unique() {
+        let items = (0..10_000u64).chain(0..10_000).chain(5_000..15_000);
+        let unique: Vec<u64> = items.approx_unique(15_000, 0.0001).collect();
+        // A few new items may be taken for repeats, but no repeat gets through.
+        assert!(unique.len() > 14_990 && unique.len() <= 15_000);
+        let mut sorted = unique.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), unique.len());
+
+        let err = [1].into_iter().try_approx_unique(0, 0.01).unwrap_err();
+        assert_eq!(err, BloomError::ZeroExpectedItems);
+    }
+}
@@ -0,0 +1,249 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{BloomError, BloomFilter, FilterParams};
+
+/// Pruning statistics of a [`BloomJoin`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JoinStats {
+    /// The number of probe-side rows checked against the filter.
+    pub probed: u64,
+    /// The number of rows that passed, i.e. whose key may be on the build
+    /// side.
+    pub passed: u64,
+}
+
+impl JoinStats {
+    /// Returns the number of rows dropped before the join.
+    pub fn pruned(&self) -> u64 {
+        self.probed - self.passed
+    }
+
+    /// Returns the fraction of rows dropped before the join, or 0 if none
+    /// were probed.
+    pub fn prune_rate(&self) -> f64 {
+        if self.probed == 0 {
+            0.0
+        } else {
+            self.pruned() as f64 / self.probed as f64
+        }
+    }
+}
+
+/// The classic bloom join: a filter over the join keys of the smaller
+/// (build) side, used to drop rows of the larger (probe) side that cannot
+/// match before they are shuffled, spilled or looked up.
+///
+/// Every row whose key is on the build side passes; a row whose key is not
+/// passes with the filter's false positive rate and is dropped by the join
+/// itself. The filter is small enough to ship to wherever the probe side
+/// lives, e.g. with [`BloomFilter::to_bytes`], and to rebuild there with
+/// [`from_filter`](Self::from_filter).
+///
+/// Each [`prune`](Self::prune) adapter counts the rows it probed and passed,
+/// and adds them to the join's [`stats`](Self::stats) when dropped, so the
+/// win can be checked once the probe side has been read.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::BloomJoin;
+///
+/// let customers = ["alice", "bob"];
+/// let orders = [("alice", 10), ("carol", 20), ("bob", 30), ("dave", 40)];
+///
+/// let join: BloomJoin<str> = BloomJoin::build(customers.len(), 0.01, customers);
+/// let candidates: Vec<_> = join.prune_by(orders, |order| order.0).collect();
+/// assert_eq!(candidates, [("alice", 10), ("bob", 30)]);
+///
+/// let stats = join.stats();
+/// assert_eq!((stats.probed, stats.passed), (4, 2));
+/// assert_eq!(stats.prune_rate(), 0.5);
+/// ```
+#[derive(Debug)]
+pub struct BloomJoin<K: ?Sized> {
+    filter: BloomFilter<K>,
+    probed: AtomicU64,
+    passed: AtomicU64,
+}
+
+impl<K: ?Sized + Hash> BloomJoin<K> {
+    /// Creates an empty join sized for `expected_items` build-side keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn new(expected_items: usize, params: impl Into<FilterParams>) -> Self {
+        Self::try_new(expected_items, params).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates an empty join, returning an error instead of panicking.
+    pub fn try_new(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<Self, BloomError> {
+        Ok(Self::from_filter(BloomFilter::try_new(
+            expected_items,
+            params,
+        )?))
+    }
+
+    /// Creates a join over all `keys` of the build side.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn build<Q: Borrow<K>>(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+        keys: impl IntoIterator<Item = Q>,
+    ) -> Self {
+        let mut join = Self::new(expected_items, params);
+        for key in keys {
+            join.insert(key.borrow());
+        }
+        join
+    }
+
+    /// Adds a build-side key.
+    pub fn insert(&mut self, key: &K) {
+        self.filter.insert(key);
+    }
+
+    /// Drops the probe-side keys that are not on the build side.
+    pub fn prune<I>(&self, keys: I) -> Prune<'_, K, I::IntoIter, BorrowKey<I::Item, K>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<K>,
+    {
+        self.prune_by(keys, Borrow::borrow)
+    }
+
+    /// Drops the probe-side rows whose key, as extracted by `key`, is not on
+    /// the build side.
+    pub fn prune_by<I, F>(&self, rows: I, key: F) -> Prune<'_, K, I::IntoIter, F>
+    where
+        I: IntoIterator,
+        F: FnMut(&I::Item) -> &K,
+    {
+        Prune {
+            join: self,
+            rows: rows.into_iter(),
+            key,
+            stats: JoinStats::default(),
+        }
+    }
+
+    /// Returns the statistics of every finished [`Prune`] adapter.
+    pub fn stats(&self) -> JoinStats {
+        JoinStats {
+            probed: self.probed.load(Ordering::Relaxed),
+            passed: self.passed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<K: ?Sized> BloomJoin<K> {
+    /// Creates a join over a filter of build-side keys, e.g. one received
+    /// from another node.
+    pub fn from_filter(filter: BloomFilter<K>) -> Self {
+        BloomJoin {
+            filter,
+            probed: AtomicU64::new(0),
+            passed: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the filter of build-side keys.
+    pub fn filter(&self) -> &BloomFilter<K> {
+        &self.filter
+    }
+
+    /// Unwraps the filter of build-side keys.
+    pub fn into_filter(self) -> BloomFilter<K> {
+        self.filter
+    }
+}
+
+/// The key extractor of [`BloomJoin::prune`].
+pub type BorrowKey<Q, K> = fn(&Q) -> &K;
+
+/// An iterator over the probe-side rows that may join, created by
+/// [`BloomJoin::prune`] or [`BloomJoin::prune_by`].
+#[derive(Debug)]
+pub struct Prune<'a, K: ?Sized, I, F> {
+    join: &'a BloomJoin<K>,
+    rows: I,
+    key: F,
+    stats: JoinStats,
+}
+
+impl<K: ?Sized, I, F> Prune<'_, K, I, F> {
+    /// Returns the statistics of this adapter so far.
+    pub fn stats(&self) -> JoinStats {
+        self.stats
+    }
+}
+
+impl<K: ?Sized + Hash, I, F> Iterator for Prune<'_, K, I, F>
+where
+    I: Iterator,
+    F: FnMut(&I::Item) -> &K,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let Prune {
+            join,
+            rows,
+            key,
+            stats,
+        } = self;
+        rows.find(|row| {
+            let passed = join.filter.contains(key(row));
+            stats.probed += 1;
+            stats.passed += passed as u64;
+            passed
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.rows.size_hint().1)
+    }
+}
+
+impl<K: ?Sized, I, F> Drop for Prune<'_, K, I, F> {
+    fn drop(&mut self) {
+        self.join
+            .probed
+            .fetch_add(self.stats.probed, Ordering::Relaxed);
+        self.join
+            .passed
+            .fetch_add(self.stats.passed, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune() {
+        let join: BloomJoin<u64> = BloomJoin::build(1000, 0.001, (0..1000u64).map(|i| i * 10));
+
+        let mut pruned = join.prune(0..10_000u64);
+        let passed: Vec<u64> = pruned.by_ref().take(10).collect();
+        assert_eq!(passed, (0..10).map(|i| i * 10).collect::<Vec<_>>());
+        assert_eq!(pruned.stats().passed, 10);
+        assert_eq!(join.stats(), JoinStats::default());
+
+        // Every matching row passes; few others do.
+        let rest = pruned.filter(|key| key % 10 != 0).count();
+        assert!(rest < 30, "{} false passes", rest);
+        let stats = join.stats();
+        assert_eq!(stats.probed, 10_000);
+        assert_eq!(stats.passed, 1000 + rest as u64);
+        assert!(stats.prune_rate() > 0.89);
+    }
+}
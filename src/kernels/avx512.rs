@@ -0,0 +1,237 @@
+//! AVX-512F kernels: eight probes per iteration.
+//!
+//! The eight probe hashes `h1 + i * h2` are kept in one vector and reduced
+//! together, either with the power-of-two mask or with a multiply-shift built
+//! from 32-bit partial products (AVX-512F has no 64-bit high multiply).
+//! `contains` then gathers the eight target words and tests them at once;
+//! `insert` stores the indices and sets the bits one by one, since a scatter
+//! would lose bits when two probes land in the same word.
+//!
+//! CPUs without AVX-512F use the scalar kernels.
+
+use std::arch::x86_64::*;
+
+use super::scalar;
+
+/// The number of 64-bit lanes per vector.
+const LANES: u32 = 8;
+
+/// Returns `true` if the running CPU supports AVX-512F.
+#[inline]
+fn available() -> bool {
+    is_x86_feature_detected!("avx512f")
+}
+
+/// Runs [`super::contains`] with AVX-512F if the CPU supports it.
+///
+/// # Safety
+///
+/// `words` must hold at least `bit_count` bits.
+#[inline]
+pub(super) unsafe fn contains(
+    words: &[u64],
+    bit_count: u64,
+    hash_count: u32,
+    h1: u64,
+    h2: u64,
+) -> bool {
+    // SAFETY: AVX-512F support is checked first; the caller keeps every
+    // probe inside `words`.
+    unsafe {
+        if available() {
+            contains_avx512(words, bit_count, hash_count, h1, h2)
+        } else {
+            scalar::contains(words, bit_count, hash_count, h1, h2)
+        }
+    }
+}
+
+/// Runs [`super::insert`] with AVX-512F if the CPU supports it.
+#[inline]
+pub(super) fn insert(words: &mut [u64], bit_count: u64, hash_count: u32, h1: u64, h2: u64) {
+    if available() {
+        // SAFETY: AVX-512F support was just checked.
+        unsafe { insert_avx512(words, bit_count, hash_count, h1, h2) }
+    } else {
+        scalar::insert(words, bit_count, hash_count, h1, h2)
+    }
+}
+
+/// AVX-512 version of [`super::contains`].
+///
+/// # Safety
+///
+/// The CPU must support AVX-512F and `words` must hold at least `bit_count` bits.
+#[target_feature(enable = "avx512f")]
+unsafe fn contains_avx512(
+    words: &[u64],
+    bit_count: u64,
+    hash_count: u32,
+    h1: u64,
+    h2: u64,
+) -> bool {
+    let mut probes = Probes::new(bit_count, h1, h2);
+    let one = _mm512_set1_epi64(1);
+    let mut i = 0;
+    while i < hash_count {
+        let lanes = lane_mask(hash_count - i);
+        let indices = probes.next_indices();
+
+        let offsets = _mm512_srli_epi64::<6>(indices);
+        let bits = _mm512_sllv_epi64(one, _mm512_and_si512(indices, _mm512_set1_epi64(63)));
+        // SAFETY: every active offset is below `bit_count / 64 <= words.len()`.
+        let targets = unsafe {
+            _mm512_mask_i64gather_epi64::<8>(
+                _mm512_setzero_si512(),
+                lanes,
+                offsets,
+                words.as_ptr().cast(),
+            )
+        };
+        if _mm512_mask_test_epi64_mask(lanes, targets, bits) != lanes {
+            return false;
+        }
+        i += LANES;
+    }
+    true
+}
+
+/// AVX-512 version of [`super::insert`].
+///
+/// # Safety
+///
+/// The CPU must support AVX-512F and `words` must hold at least `bit_count` bits.
+#[target_feature(enable = "avx512f")]
+unsafe fn insert_avx512(words: &mut [u64], bit_count: u64, hash_count: u32, h1: u64, h2: u64) {
+    let mut probes = Probes::new(bit_count, h1, h2);
+    let mut indices = [0u64; LANES as usize];
+    let mut i = 0;
+    while i < hash_count {
+        // SAFETY: `indices` is exactly one vector wide.
+        unsafe { _mm512_storeu_si512(indices.as_mut_ptr().cast(), probes.next_indices()) };
+        for &bit_index in indices.iter().take((hash_count - i) as usize) {
+            words[(bit_index / 64) as usize] |= 1 << (bit_index % 64);
+        }
+        i += LANES;
+    }
+}
+
+/// Generates probe indices eight at a time.
+struct Probes {
+    /// The hashes of the next eight probes.
+    hashes: __m512i,
+    /// `8 * h2`, the distance to the following eight probes.
+    step: __m512i,
+    /// `bit_count` broadcast to every lane.
+    range: __m512i,
+    /// Whether `bit_count` is a power of two.
+    masked: bool,
+}
+
+impl Probes {
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    fn new(bit_count: u64, h1: u64, h2: u64) -> Self {
+        let first: [u64; LANES as usize] =
+            std::array::from_fn(|lane| h1.wrapping_add(h2.wrapping_mul(lane as u64)));
+        let masked = bit_count.is_power_of_two();
+        Probes {
+            // SAFETY: `first` is exactly one vector wide.
+            hashes: unsafe { _mm512_loadu_si512(first.as_ptr().cast()) },
+            step: _mm512_set1_epi64(h2.wrapping_mul(LANES as u64) as i64),
+            range: _mm512_set1_epi64(if masked { bit_count - 1 } else { bit_count } as i64),
+            masked,
+        }
+    }
+
+    /// Returns the bit indices of the next eight probes, matching
+    /// [`hashing::bit_index`](crate::hashing::bit_index) lane by lane.
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    fn next_indices(&mut self) -> __m512i {
+        let indices = if self.masked {
+            _mm512_and_si512(self.hashes, self.range)
+        } else {
+            mul_high(self.hashes, self.range)
+        };
+        self.hashes = _mm512_add_epi64(self.hashes, self.step);
+        indices
+    }
+}
+
+/// Returns the high 64 bits of each 64x64-bit lane product.
+#[inline]
+#[target_feature(enable = "avx512f")]
+fn mul_high(a: __m512i, b: __m512i) -> __m512i {
+    let low_32 = _mm512_set1_epi64(0xffff_ffff);
+    let a_high = _mm512_srli_epi64::<32>(a);
+    let b_high = _mm512_srli_epi64::<32>(b);
+
+    // `_mm512_mul_epu32` multiplies the low 32 bits of each lane.
+    let low_low = _mm512_mul_epu32(a, b);
+    let low_high = _mm512_mul_epu32(a, b_high);
+    let high_low = _mm512_mul_epu32(a_high, b);
+    let high_high = _mm512_mul_epu32(a_high, b_high);
+
+    // Sum the middle 32-bit column; at most 34 bits, so it cannot overflow.
+    let middle = _mm512_add_epi64(
+        _mm512_add_epi64(
+            _mm512_srli_epi64::<32>(low_low),
+            _mm512_and_si512(low_high, low_32),
+        ),
+        _mm512_and_si512(high_low, low_32),
+    );
+    _mm512_add_epi64(
+        _mm512_add_epi64(high_high, _mm512_srli_epi64::<32>(middle)),
+        _mm512_add_epi64(
+            _mm512_srli_epi64::<32>(low_high),
+            _mm512_srli_epi64::<32>(high_low),
+        ),
+    )
+}
+
+/// Returns the lane mask for the first `remaining` (at most eight) probes.
+#[inline]
+fn lane_mask(remaining: u32) -> __mmask8 {
+    if remaining >= LANES {
+        0xff
+    } else {
+        (1u8 << remaining) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashing;
+
+    #[test]
+    fn test_mul_high_matches_u128() {
+        if !available() {
+            return;
+        }
+        let a = [
+            0,
+            1,
+            u64::MAX,
+            1 << 63,
+            0x9e37_79b9_7f4a_7c15,
+            12345,
+            u64::MAX - 1,
+            7,
+        ];
+        let b = 1_000_003u64;
+        let mut out = [0u64; 8];
+        // SAFETY: AVX-512F was detected and all buffers are one vector wide.
+        unsafe {
+            let high = mul_high(
+                _mm512_loadu_si512(a.as_ptr().cast()),
+                _mm512_set1_epi64(b as i64),
+            );
+            _mm512_storeu_si512(out.as_mut_ptr().cast(), high);
+        }
+        for (lane, &value) in a.iter().enumerate() {
+            assert_eq!(out[lane], hashing::fastrange(value, b));
+        }
+    }
+}
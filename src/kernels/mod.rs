@@ -0,0 +1,239 @@
+//! Probe kernels for bit arrays stored as contiguous `u64` words.
+//!
+//! Each kernel computes the k double-hashing probe positions of one item and
+//! either tests or sets them. Every build selects one set of kernels, and
+//! results never depend on which one was taken:
+//!
+//! * with the `nightly-simd` feature: `std::simd`, on every architecture;
+//! * x86-64: AVX-512F, detected at run time, falling back to scalar;
+//! * AArch64: NEON, which every AArch64 CPU has;
+//! * wasm32 built with `+simd128`: WebAssembly SIMD;
+//! * anywhere else: the scalar loop.
+
+#[cfg(all(target_arch = "x86_64", not(feature = "nightly-simd")))]
+mod avx512;
+#[cfg(all(target_arch = "aarch64", not(feature = "nightly-simd")))]
+mod neon;
+#[cfg(feature = "nightly-simd")]
+mod portable;
+// The reference implementation; some builds only use it in tests.
+#[cfg_attr(
+    any(
+        target_arch = "aarch64",
+        all(target_arch = "wasm32", target_feature = "simd128"),
+        feature = "nightly-simd"
+    ),
+    allow(dead_code)
+)]
+mod scalar;
+#[cfg(all(
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(feature = "nightly-simd")
+))]
+mod wasm;
+
+#[cfg(all(target_arch = "x86_64", not(feature = "nightly-simd")))]
+use avx512 as dispatch;
+#[cfg(all(target_arch = "aarch64", not(feature = "nightly-simd")))]
+use neon as dispatch;
+#[cfg(feature = "nightly-simd")]
+use portable as dispatch;
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    all(target_arch = "wasm32", target_feature = "simd128"),
+    feature = "nightly-simd"
+)))]
+use scalar as dispatch;
+#[cfg(all(
+    target_arch = "wasm32",
+    target_feature = "simd128",
+    not(feature = "nightly-simd")
+))]
+use wasm as dispatch;
+
+use crate::hashing;
+
+/// How many items ahead of the current lookup [`contains_batch`] hashes and
+/// prefetches. Enough to cover a DRAM miss with the hashing of later items.
+const PREFETCH_DISTANCE: usize = 8;
+
+/// Tests whether all k probe bits of `(h1, h2)` are set.
+///
+/// # Panics
+///
+/// Panics if `words` holds fewer than `bit_count` bits.
+#[inline]
+pub(crate) fn contains(words: &[u64], bit_count: u64, hash_count: u32, h1: u64, h2: u64) -> bool {
+    assert!(bit_count <= words.len() as u64 * 64);
+    // SAFETY: the assertion above keeps every probe inside `words`.
+    unsafe { dispatch::contains(words, bit_count, hash_count, h1, h2) }
+}
+
+/// Tests whether all `K` probe bits of `(h1, h2)` are set, with the probe
+/// loop unrolled for a hash count known at compile time.
+///
+/// # Safety
+///
+/// `words` must hold at least `bit_count` bits.
+#[inline(always)]
+pub(crate) unsafe fn contains_fixed<const K: u32>(
+    words: &[u64],
+    bit_count: u64,
+    h1: u64,
+    h2: u64,
+) -> bool {
+    // SAFETY: same contract.
+    unsafe { scalar::contains_fixed::<K>(words, bit_count, h1, h2) }
+}
+
+/// Sets all k probe bits of `(h1, h2)`.
+///
+/// # Panics
+///
+/// Panics if `words` holds fewer than `bit_count` bits.
+#[inline]
+pub(crate) fn insert(words: &mut [u64], bit_count: u64, hash_count: u32, h1: u64, h2: u64) {
+    assert!(bit_count <= words.len() as u64 * 64);
+    dispatch::insert(words, bit_count, hash_count, h1, h2)
+}
+
+/// Tests a batch of items, given as hash pairs, appending one result per
+/// item to `results`.
+///
+/// Each item's k target cache lines are prefetched [`PREFETCH_DISTANCE`]
+/// items before it is tested, so on filters much larger than the cache the
+/// memory latency overlaps with the work on the items in between.
+///
+/// # Panics
+///
+/// Panics if `words` holds fewer than `bit_count` bits.
+pub(crate) fn contains_batch(
+    words: &[u64],
+    bit_count: u64,
+    hash_count: u32,
+    hashes: impl IntoIterator<Item = (u64, u64)>,
+    results: &mut Vec<bool>,
+) {
+    assert!(bit_count <= words.len() as u64 * 64);
+    let mut hashes = hashes.into_iter();
+
+    // A ring of the hashed but not yet tested items; `oldest` is next to test.
+    let mut window = [(0u64, 0u64); PREFETCH_DISTANCE];
+    let mut pending = 0;
+    for (slot, (h1, h2)) in window.iter_mut().zip(&mut hashes) {
+        prefetch(words, bit_count, hash_count, h1, h2);
+        *slot = (h1, h2);
+        pending += 1;
+    }
+
+    let mut oldest = 0;
+    for (h1, h2) in hashes {
+        prefetch(words, bit_count, hash_count, h1, h2);
+        let (t1, t2) = std::mem::replace(&mut window[oldest], (h1, h2));
+        // SAFETY: the assertion above keeps every probe inside `words`.
+        results.push(unsafe { dispatch::contains(words, bit_count, hash_count, t1, t2) });
+        oldest = (oldest + 1) % PREFETCH_DISTANCE;
+    }
+
+    for offset in 0..pending {
+        let (t1, t2) = window[(oldest + offset) % PREFETCH_DISTANCE];
+        // SAFETY: as above.
+        results.push(unsafe { dispatch::contains(words, bit_count, hash_count, t1, t2) });
+    }
+}
+
+/// Hints the CPU to start loading the k words probed for `(h1, h2)`.
+#[inline]
+fn prefetch(words: &[u64], bit_count: u64, hash_count: u32, h1: u64, h2: u64) {
+    for i in 0..hash_count {
+        let bit_index = hashing::bit_index(h1, h2, i, bit_count);
+        prefetch_word(words.as_ptr().wrapping_add((bit_index / 64) as usize));
+    }
+}
+
+/// Prefetches the cache line holding `word` into all cache levels.
+///
+/// A prefetch is only a hint: it never faults and has no visible effect,
+/// so it is a no-op on targets without a stable prefetch instruction.
+#[inline(always)]
+fn prefetch_word(word: *const u64) {
+    #[cfg(target_arch = "x86_64")]
+    // SAFETY: prefetching does not access memory architecturally.
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(word.cast());
+    }
+    #[cfg(target_arch = "aarch64")]
+    // SAFETY: prefetching does not access memory architecturally.
+    unsafe {
+        std::arch::asm!(
+            "prfm pldl1keep, [{0}]",
+            in(reg) word,
+            options(nostack, readonly, preserves_flags),
+        );
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let _ = word;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap deterministic hash pairs for comparing kernels.
+    fn hash_pairs() -> impl Iterator<Item = (u64, u64)> {
+        (0..500u64).map(|i| hashing::hash_pair(&i))
+    }
+
+    #[test]
+    fn test_kernels_match_scalar() {
+        for bit_count in [64u64, 640, 1_000_000, 1 << 20] {
+            for hash_count in [1, 3, 7, 8, 9, 16, 23] {
+                let word_count = bit_count.div_ceil(64) as usize;
+                let mut fast = vec![0u64; word_count];
+                let mut reference = vec![0u64; word_count];
+                for (h1, h2) in hash_pairs().take(100) {
+                    insert(&mut fast, bit_count, hash_count, h1, h2);
+                    scalar::insert(&mut reference, bit_count, hash_count, h1, h2);
+                }
+                assert_eq!(fast, reference);
+
+                for (h1, h2) in hash_pairs() {
+                    // SAFETY: `reference` holds `bit_count` bits.
+                    let expected =
+                        unsafe { scalar::contains(&reference, bit_count, hash_count, h1, h2) };
+                    assert_eq!(contains(&fast, bit_count, hash_count, h1, h2), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch_matches_single() {
+        let bit_count = 10_000;
+        let mut words = vec![0u64; 157];
+        for (h1, h2) in hash_pairs().step_by(2) {
+            insert(&mut words, bit_count, 7, h1, h2);
+        }
+
+        // Batches shorter and longer than the prefetch window.
+        for len in [0, 1, PREFETCH_DISTANCE - 1, PREFETCH_DISTANCE, 500] {
+            let pairs: Vec<_> = hash_pairs().take(len).collect();
+            let mut results = Vec::new();
+            contains_batch(&words, bit_count, 7, pairs.iter().copied(), &mut results);
+            let expected: Vec<bool> = pairs
+                .iter()
+                .map(|&(h1, h2)| contains(&words, bit_count, 7, h1, h2))
+                .collect();
+            assert_eq!(results, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_short_words_rejected() {
+        contains(&[0u64; 2], 256, 7, 1, 2);
+    }
+}
@@ -0,0 +1,131 @@
+//! NEON kernels: two probes per vector, four vectors per iteration.
+//!
+//! NEON is part of the AArch64 baseline, so these kernels need no run-time
+//! detection. The probe hashes are reduced in vectors of two lanes, with the
+//! power-of-two mask or with a multiply-shift assembled from `vmull_u32`
+//! partial products. NEON has no gather, so the target words are then loaded
+//! one by one; the lookups of eight probes are issued before any of them is
+//! tested, which lets the loads overlap.
+
+use std::arch::aarch64::*;
+
+/// The number of probes handled per iteration.
+const BATCH: usize = 8;
+
+/// NEON version of [`super::contains`].
+///
+/// # Safety
+///
+/// `words` must hold at least `bit_count` bits.
+pub(super) unsafe fn contains(
+    words: &[u64],
+    bit_count: u64,
+    hash_count: u32,
+    h1: u64,
+    h2: u64,
+) -> bool {
+    let mut probes = Probes::new(bit_count, h1, h2);
+    let mut indices = [0u64; BATCH];
+    let mut remaining = hash_count as usize;
+    while remaining > 0 {
+        probes.next_batch(&mut indices);
+        let active = remaining.min(BATCH);
+        let mut all_set = 1;
+        for &bit_index in &indices[..active] {
+            // SAFETY: `bit_index` is below `bit_count`, which the caller
+            // guarantees fits in `words`.
+            let word = unsafe { *words.get_unchecked((bit_index / 64) as usize) };
+            all_set &= word >> (bit_index % 64);
+        }
+        if all_set & 1 == 0 {
+            return false;
+        }
+        remaining -= active;
+    }
+    true
+}
+
+/// NEON version of [`super::insert`].
+pub(super) fn insert(words: &mut [u64], bit_count: u64, hash_count: u32, h1: u64, h2: u64) {
+    let mut probes = Probes::new(bit_count, h1, h2);
+    let mut indices = [0u64; BATCH];
+    let mut remaining = hash_count as usize;
+    while remaining > 0 {
+        probes.next_batch(&mut indices);
+        let active = remaining.min(BATCH);
+        for &bit_index in &indices[..active] {
+            words[(bit_index / 64) as usize] |= 1 << (bit_index % 64);
+        }
+        remaining -= active;
+    }
+}
+
+/// Generates probe indices a batch at a time.
+struct Probes {
+    /// The hashes of the next two probes.
+    hashes: uint64x2_t,
+    /// `2 * h2`, the distance to the following two probes.
+    step: uint64x2_t,
+    /// `bit_count - 1` for a power-of-two `bit_count`.
+    mask: uint64x2_t,
+    /// The low and high 32 bits of `bit_count`, for the multiply-shift.
+    range_low: uint32x2_t,
+    range_high: uint32x2_t,
+    /// Whether `bit_count` is a power of two.
+    masked: bool,
+}
+
+impl Probes {
+    #[inline]
+    fn new(bit_count: u64, h1: u64, h2: u64) -> Self {
+        let first = [h1, h1.wrapping_add(h2)];
+        Probes {
+            // SAFETY: `first` holds two lanes.
+            hashes: unsafe { vld1q_u64(first.as_ptr()) },
+            step: vdupq_n_u64(h2.wrapping_mul(2)),
+            mask: vdupq_n_u64(bit_count.wrapping_sub(1)),
+            range_low: vdup_n_u32(bit_count as u32),
+            range_high: vdup_n_u32((bit_count >> 32) as u32),
+            masked: bit_count.is_power_of_two(),
+        }
+    }
+
+    /// Writes the bit indices of the next eight probes, matching
+    /// [`hashing::bit_index`](crate::hashing::bit_index) one by one.
+    #[inline]
+    fn next_batch(&mut self, indices: &mut [u64; BATCH]) {
+        for pair in indices.chunks_exact_mut(2) {
+            let reduced = if self.masked {
+                vandq_u64(self.hashes, self.mask)
+            } else {
+                self.mul_high(self.hashes)
+            };
+            // SAFETY: `pair` holds two lanes.
+            unsafe { vst1q_u64(pair.as_mut_ptr(), reduced) };
+            self.hashes = vaddq_u64(self.hashes, self.step);
+        }
+    }
+
+    /// Returns the high 64 bits of each lane multiplied by `bit_count`.
+    #[inline]
+    fn mul_high(&self, hashes: uint64x2_t) -> uint64x2_t {
+        let low_32 = vdupq_n_u64(0xffff_ffff);
+        let hash_low = vmovn_u64(hashes);
+        let hash_high = vshrn_n_u64::<32>(hashes);
+
+        let low_low = vmull_u32(hash_low, self.range_low);
+        let low_high = vmull_u32(hash_low, self.range_high);
+        let high_low = vmull_u32(hash_high, self.range_low);
+        let high_high = vmull_u32(hash_high, self.range_high);
+
+        // Sum the middle 32-bit column; at most 34 bits, so it cannot overflow.
+        let middle = vaddq_u64(
+            vaddq_u64(vshrq_n_u64::<32>(low_low), vandq_u64(low_high, low_32)),
+            vandq_u64(high_low, low_32),
+        );
+        vaddq_u64(
+            vaddq_u64(high_high, vshrq_n_u64::<32>(middle)),
+            vaddq_u64(vshrq_n_u64::<32>(low_high), vshrq_n_u64::<32>(high_low)),
+        )
+    }
+}
@@ -0,0 +1,150 @@
+//! `std::simd` kernels: eight probes per vector on any architecture.
+//!
+//! Requires the `nightly-simd` feature. The kernels mirror the AVX-512 ones
+//! (vector reduction, gather-and-test lookups, scalar bit setting on insert)
+//! but leave instruction selection to the compiler, so every target gets
+//! whatever vector unit it has from one code path.
+
+use std::simd::cmp::SimdPartialEq;
+use std::simd::num::SimdUint;
+use std::simd::{u64x8, Mask};
+
+/// The number of 64-bit lanes per vector.
+const LANES: usize = 8;
+
+/// Portable SIMD version of [`super::contains`].
+///
+/// # Safety
+///
+/// `words` must hold at least `bit_count` bits.
+pub(super) unsafe fn contains(
+    words: &[u64],
+    bit_count: u64,
+    hash_count: u32,
+    h1: u64,
+    h2: u64,
+) -> bool {
+    let mut probes = Probes::new(bit_count, h1, h2);
+    let zero = u64x8::splat(0);
+    let mut remaining = hash_count as usize;
+    while remaining > 0 {
+        let lanes = Mask::from_bitmask(lane_bits(remaining));
+        let indices = probes.next_indices();
+
+        let offsets = (indices >> 6).cast::<usize>();
+        let bits = u64x8::splat(1) << (indices & u64x8::splat(63));
+        // SAFETY: every active offset is below `bit_count / 64 <= words.len()`.
+        let targets = unsafe { u64x8::gather_select_unchecked(words, lanes, offsets, zero) };
+        if !((targets & bits).simd_ne(zero) | !lanes.cast()).all() {
+            return false;
+        }
+        remaining = remaining.saturating_sub(LANES);
+    }
+    true
+}
+
+/// Portable SIMD version of [`super::insert`].
+pub(super) fn insert(words: &mut [u64], bit_count: u64, hash_count: u32, h1: u64, h2: u64) {
+    let mut probes = Probes::new(bit_count, h1, h2);
+    let mut remaining = hash_count as usize;
+    while remaining > 0 {
+        let indices = probes.next_indices().to_array();
+        for &bit_index in indices.iter().take(remaining) {
+            words[(bit_index / 64) as usize] |= 1 << (bit_index % 64);
+        }
+        remaining = remaining.saturating_sub(LANES);
+    }
+}
+
+/// Generates probe indices eight at a time.
+struct Probes {
+    /// The hashes of the next eight probes.
+    hashes: u64x8,
+    /// `8 * h2`, the distance to the following eight probes.
+    step: u64x8,
+    /// `bit_count` broadcast to every lane.
+    range: u64x8,
+    /// Whether `bit_count` is a power of two.
+    masked: bool,
+}
+
+impl Probes {
+    #[inline]
+    fn new(bit_count: u64, h1: u64, h2: u64) -> Self {
+        let masked = bit_count.is_power_of_two();
+        Probes {
+            hashes: u64x8::from_array(std::array::from_fn(|lane| {
+                h1.wrapping_add(h2.wrapping_mul(lane as u64))
+            })),
+            step: u64x8::splat(h2.wrapping_mul(LANES as u64)),
+            range: u64x8::splat(if masked { bit_count - 1 } else { bit_count }),
+            masked,
+        }
+    }
+
+    /// Returns the bit indices of the next eight probes, matching
+    /// [`hashing::bit_index`](crate::hashing::bit_index) lane by lane.
+    #[inline]
+    fn next_indices(&mut self) -> u64x8 {
+        let indices = if self.masked {
+            self.hashes & self.range
+        } else {
+            mul_high(self.hashes, self.range)
+        };
+        self.hashes += self.step;
+        indices
+    }
+}
+
+/// Returns the high 64 bits of each 64x64-bit lane product, from four
+/// 32x32-bit partial products that each fit a 64-bit lane.
+#[inline]
+fn mul_high(a: u64x8, b: u64x8) -> u64x8 {
+    let low_32 = u64x8::splat(0xffff_ffff);
+    let (a_low, a_high) = (a & low_32, a >> 32);
+    let (b_low, b_high) = (b & low_32, b >> 32);
+
+    let low_low = a_low * b_low;
+    let low_high = a_low * b_high;
+    let high_low = a_high * b_low;
+    let high_high = a_high * b_high;
+
+    // Sum the middle 32-bit column; at most 34 bits, so it cannot overflow.
+    let middle = (low_low >> 32) + (low_high & low_32) + (high_low & low_32);
+    high_high + (middle >> 32) + (low_high >> 32) + (high_low >> 32)
+}
+
+/// Returns the bitmask of the first `remaining` (capped at eight) lanes.
+#[inline]
+fn lane_bits(remaining: usize) -> u64 {
+    if remaining >= LANES {
+        0xff
+    } else {
+        (1 << remaining) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashing;
+
+    #[test]
+    fn test_mul_high_matches_u128() {
+        let a = [
+            0,
+            1,
+            u64::MAX,
+            1 << 63,
+            0x9e37_79b9_7f4a_7c15,
+            12345,
+            u64::MAX - 1,
+            7,
+        ];
+        let b = 1_000_003u64;
+        let high = mul_high(u64x8::from_array(a), u64x8::splat(b)).to_array();
+        for (lane, &value) in a.iter().enumerate() {
+            assert_eq!(high[lane], hashing::fastrange(value, b));
+        }
+    }
+}
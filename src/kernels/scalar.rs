@@ -0,0 +1,116 @@
+//! The portable reference kernels: one probe at a time.
+
+use crate::hashing;
+
+/// Scalar version of [`super::contains`].
+///
+/// # Safety
+///
+/// `words` must hold at least `bit_count` bits.
+pub(super) unsafe fn contains(
+    words: &[u64],
+    bit_count: u64,
+    hash_count: u32,
+    h1: u64,
+    h2: u64,
+) -> bool {
+    // SAFETY: every arm has the same contract as this function.
+    unsafe {
+        match hash_count {
+            1 => contains_fixed::<1>(words, bit_count, h1, h2),
+            2 => contains_fixed::<2>(words, bit_count, h1, h2),
+            3 => contains_fixed::<3>(words, bit_count, h1, h2),
+            4 => contains_fixed::<4>(words, bit_count, h1, h2),
+            5 => contains_fixed::<5>(words, bit_count, h1, h2),
+            6 => contains_fixed::<6>(words, bit_count, h1, h2),
+            7 => contains_fixed::<7>(words, bit_count, h1, h2),
+            8 => contains_fixed::<8>(words, bit_count, h1, h2),
+            _ => contains_early_exit(words, bit_count, hash_count, h1, h2),
+        }
+    }
+}
+
+/// Tests exactly `K` probes without branching on any of them.
+///
+/// All `K` words are loaded and ANDed together, so on a filter larger than
+/// the cache their misses are in flight at once. The early-exit loop instead
+/// waits for each probe's word before deciding to load the next, which
+/// serializes the misses of a negative lookup until it hits a zero bit.
+///
+/// # Safety
+///
+/// `words` must hold at least `bit_count` bits.
+#[inline(always)]
+pub(super) unsafe fn contains_fixed<const K: u32>(
+    words: &[u64],
+    bit_count: u64,
+    h1: u64,
+    h2: u64,
+) -> bool {
+    let mut all_set = 1;
+    for i in 0..K {
+        let bit_index = hashing::bit_index(h1, h2, i, bit_count);
+        // SAFETY: `bit_index` is below `bit_count`, which the caller guarantees
+        // fits in `words`.
+        let word = unsafe { *words.get_unchecked((bit_index / 64) as usize) };
+        all_set &= word >> (bit_index % 64);
+    }
+    all_set & 1 != 0
+}
+
+/// Tests the probes in order, stopping at the first unset bit. Used for more
+/// than eight probes, where skipping the remaining ones pays off.
+///
+/// # Safety
+///
+/// `words` must hold at least `bit_count` bits.
+unsafe fn contains_early_exit(
+    words: &[u64],
+    bit_count: u64,
+    hash_count: u32,
+    h1: u64,
+    h2: u64,
+) -> bool {
+    (0..hash_count).all(|i| {
+        let bit_index = hashing::bit_index(h1, h2, i, bit_count);
+        // SAFETY: `bit_index` is below `bit_count`, which the caller guarantees
+        // fits in `words`.
+        let word = unsafe { *words.get_unchecked((bit_index / 64) as usize) };
+        word & (1 << (bit_index % 64)) != 0
+    })
+}
+
+/// Scalar version of [`super::insert`].
+pub(super) fn insert(words: &mut [u64], bit_count: u64, hash_count: u32, h1: u64, h2: u64) {
+    for i in 0..hash_count {
+        let bit_index = hashing::bit_index(h1, h2, i, bit_count);
+        words[(bit_index / 64) as usize] |= 1 << (bit_index % 64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branchless_matches_early_exit() {
+        let bit_count = 5000;
+        let mut words = vec![0u64; 79];
+        for i in 0..300u64 {
+            let (h1, h2) = hashing::hash_pair(&i);
+            insert(&mut words, bit_count, 4, h1, h2);
+        }
+        for hash_count in 1..=8 {
+            for i in 0..1000u64 {
+                let (h1, h2) = hashing::hash_pair(&i);
+                // SAFETY: `words` holds more than `bit_count` bits.
+                unsafe {
+                    assert_eq!(
+                        contains(&words, bit_count, hash_count, h1, h2),
+                        contains_early_exit(&words, bit_count, hash_count, h1, h2)
+                    );
+                }
+            }
+        }
+    }
+}
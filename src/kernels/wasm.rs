@@ -0,0 +1,125 @@
+//! WebAssembly SIMD128 kernels: two probes per vector, four vectors per batch.
+//!
+//! Selected at compile time when building for `wasm32` with the `simd128`
+//! target feature (`-C target-feature=+simd128`). The probe hashes are reduced
+//! in vectors of two lanes, with the power-of-two mask or with a multiply-shift
+//! assembled from 32x32-bit partial products. WebAssembly has no gather, so
+//! the target words are then loaded one by one, eight probes per batch.
+
+use std::arch::wasm32::*;
+
+/// The number of probes handled per batch.
+const BATCH: usize = 8;
+
+/// SIMD128 version of [`super::contains`].
+///
+/// # Safety
+///
+/// `words` must hold at least `bit_count` bits.
+pub(super) unsafe fn contains(
+    words: &[u64],
+    bit_count: u64,
+    hash_count: u32,
+    h1: u64,
+    h2: u64,
+) -> bool {
+    let mut probes = Probes::new(bit_count, h1, h2);
+    let mut indices = [0u64; BATCH];
+    let mut remaining = hash_count as usize;
+    while remaining > 0 {
+        probes.next_batch(&mut indices);
+        let active = remaining.min(BATCH);
+        let mut all_set = 1;
+        for &bit_index in &indices[..active] {
+            // SAFETY: `bit_index` is below `bit_count`, which the caller
+            // guarantees fits in `words`.
+            let word = unsafe { *words.get_unchecked((bit_index / 64) as usize) };
+            all_set &= word >> (bit_index % 64);
+        }
+        if all_set & 1 == 0 {
+            return false;
+        }
+        remaining -= active;
+    }
+    true
+}
+
+/// SIMD128 version of [`super::insert`].
+pub(super) fn insert(words: &mut [u64], bit_count: u64, hash_count: u32, h1: u64, h2: u64) {
+    let mut probes = Probes::new(bit_count, h1, h2);
+    let mut indices = [0u64; BATCH];
+    let mut remaining = hash_count as usize;
+    while remaining > 0 {
+        probes.next_batch(&mut indices);
+        let active = remaining.min(BATCH);
+        for &bit_index in &indices[..active] {
+            words[(bit_index / 64) as usize] |= 1 << (bit_index % 64);
+        }
+        remaining -= active;
+    }
+}
+
+/// Generates probe indices a batch at a time.
+struct Probes {
+    /// The hashes of the next two probes.
+    hashes: v128,
+    /// `2 * h2`, the distance to the following two probes.
+    step: v128,
+    /// `bit_count - 1` for a power-of-two `bit_count`, else `bit_count`.
+    range: v128,
+    /// Whether `bit_count` is a power of two.
+    masked: bool,
+}
+
+impl Probes {
+    #[inline]
+    fn new(bit_count: u64, h1: u64, h2: u64) -> Self {
+        let masked = bit_count.is_power_of_two();
+        Probes {
+            hashes: u64x2(h1, h1.wrapping_add(h2)),
+            step: u64x2_splat(h2.wrapping_mul(2)),
+            range: u64x2_splat(if masked { bit_count - 1 } else { bit_count }),
+            masked,
+        }
+    }
+
+    /// Writes the bit indices of the next eight probes, matching
+    /// [`hashing::bit_index`](crate::hashing::bit_index) one by one.
+    #[inline]
+    fn next_batch(&mut self, indices: &mut [u64; BATCH]) {
+        for pair in indices.chunks_exact_mut(2) {
+            let reduced = if self.masked {
+                v128_and(self.hashes, self.range)
+            } else {
+                mul_high(self.hashes, self.range)
+            };
+            pair[0] = u64x2_extract_lane::<0>(reduced);
+            pair[1] = u64x2_extract_lane::<1>(reduced);
+            self.hashes = u64x2_add(self.hashes, self.step);
+        }
+    }
+}
+
+/// Returns the high 64 bits of each 64x64-bit lane product, from four
+/// 32x32-bit partial products that each fit a 64-bit lane.
+#[inline]
+fn mul_high(a: v128, b: v128) -> v128 {
+    let low_32 = u64x2_splat(0xffff_ffff);
+    let (a_low, a_high) = (v128_and(a, low_32), u64x2_shr(a, 32));
+    let (b_low, b_high) = (v128_and(b, low_32), u64x2_shr(b, 32));
+
+    let low_low = u64x2_mul(a_low, b_low);
+    let low_high = u64x2_mul(a_low, b_high);
+    let high_low = u64x2_mul(a_high, b_low);
+    let high_high = u64x2_mul(a_high, b_high);
+
+    // Sum the middle 32-bit column; at most 34 bits, so it cannot overflow.
+    let middle = u64x2_add(
+        u64x2_add(u64x2_shr(low_low, 32), v128_and(low_high, low_32)),
+        v128_and(high_low, low_32),
+    );
+    u64x2_add(
+        u64x2_add(high_high, u64x2_shr(middle, 32)),
+        u64x2_add(u64x2_shr(low_high, 32), u64x2_shr(high_low, 32)),
+    )
+}
@@ -0,0 +1,400 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use siphasher::sip::SipHasher24;
+
+use crate::{hashing, ApproximateSet, BloomError, BloomFilter, FilterParams};
+
+/// A 128-bit secret key for [`KeyedBloomFilter`].
+///
+/// Items are hashed with SipHash-2-4 under this key, a pseudorandom
+/// function, so without the key the filter's bits say nothing about which
+/// items it holds, and nobody can test a guessed item against it.
+///
+/// In a private set intersection protocol both parties derive the same key,
+/// e.g. by running the OPRF output through a KDF, and pass the bytes to
+/// [`from_bytes`](Self::from_bytes). [`key_id`](Self::key_id) lets them
+/// confirm they hold the same key without revealing it. The key is not
+/// printed by `Debug`, and with the `zeroize` feature it is wiped from
+/// memory when dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct FilterKey {
+    k0: u64,
+    k1: u64,
+}
+
+impl FilterKey {
+    /// Creates a key from 16 secret bytes.
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        let (low, high) = bytes.split_at(8);
+        FilterKey {
+            k0: u64::from_le_bytes(low.try_into().unwrap()),
+            k1: u64::from_le_bytes(high.try_into().unwrap()),
+        }
+    }
+
+    /// Returns a public fingerprint of the key: the key's hash of a fixed
+    /// label, from which the key cannot be recovered.
+    pub fn key_id(&self) -> u64 {
+        let mut hasher = self.build_hasher();
+        hasher.write(b"bloomlib key id");
+        hasher.finish()
+    }
+}
+
+impl fmt::Debug for FilterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilterKey")
+            .field("key_id", &format_args!("{:016x}", self.key_id()))
+            .finish()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for FilterKey {
+    fn zeroize(&mut self) {
+        self.k0.zeroize();
+        self.k1.zeroize();
+    }
+}
+
+/// With the `zeroize` feature, the key is wiped from memory when dropped.
+#[cfg(feature = "zeroize")]
+impl Drop for FilterKey {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for FilterKey {}
+
+impl BuildHasher for FilterKey {
+    type Hasher = KeyedHasher;
+
+    fn build_hasher(&self) -> KeyedHasher {
+        KeyedHasher::new(self.k0, self.k1)
+    }
+}
+
+/// SipHash-2-4 under a [`FilterKey`], from the `siphasher` crate.
+///
+/// Integers are written as their native-endian bytes, as by the standard
+/// library's hashers.
+#[derive(Clone)]
+pub struct KeyedHasher(SipHasher24);
+
+impl KeyedHasher {
+    fn new(k0: u64, k1: u64) -> Self {
+        KeyedHasher(SipHasher24::new_with_keys(k0, k1))
+    }
+}
+
+/// The state, from which the key could be recovered, is not printed.
+impl fmt::Debug for KeyedHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedHasher").finish_non_exhaustive()
+    }
+}
+
+impl Hasher for KeyedHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+}
+
+/// With the `zeroize` feature, the state, which holds the key, is wiped
+/// when the hasher is dropped.
+#[cfg(feature = "zeroize")]
+impl Drop for KeyedHasher {
+    fn drop(&mut self) {
+        // SAFETY: `SipHasher24` holds only integers and a `PhantomData`, so it
+        // has no drop glue, no pointers, and is valid when all zero.
+        unsafe { zeroize::zeroize_flat_type(&mut self.0) }
+    }
+}
+
+/// A [`BloomFilter`] whose items are hashed under a secret [`FilterKey`],
+/// the filter side of simple private set intersection protocols.
+///
+/// Each party builds a filter of its set under the shared key and sends it
+/// to the other, which can then find the items the two sets (probably)
+/// share with [`intersection`](Self::intersection), or only estimate how
+/// many they share with
+/// [`estimated_intersection_size`](Self::estimated_intersection_size).
+/// Filters travel as plain [`BloomFilter`] bytes, and are rebuilt on
+/// arrival with [`from_filter`](Self::from_filter).
+///
+/// Both parties must hash items identically, so the item type should hash
+/// the same on every platform: strings and byte strings do, while `usize`
+/// does not.
+///
+/// The filter hides which items it holds, not how many: its fill ratio
+/// reveals the set's approximate size. Items the receiver holds can still
+/// be tested against it, with the filter's false positive rate, which is
+/// the point of the exchange; size the filter for a low rate accordingly.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{BloomFilter, FilterKey, KeyedBloomFilter};
+///
+/// // Both parties derive this from a key agreement.
+/// let key = FilterKey::from_bytes(*b"shared secret 16");
+///
+/// let mut alice: KeyedBloomFilter<str> = KeyedBloomFilter::new(key.clone(), 1000, 1e-6);
+/// for contact in ["bob", "carol", "dave"] {
+///     alice.insert(contact);
+/// }
+/// let sent = alice.filter().to_bytes();
+///
+/// // Bob receives Alice's filter and checks his own contacts against it.
+/// let received: KeyedBloomFilter<str> =
+///     KeyedBloomFilter::from_filter(key, BloomFilter::from_bytes(&sent).unwrap());
+/// let mutual: Vec<_> = received.intersection(["alice", "carol", "erin"]).collect();
+/// assert_eq!(mutual, ["carol"]);
+/// ```
+#[derive(Debug)]
+pub struct KeyedBloomFilter<T: ?Sized> {
+    filter: BloomFilter<T>,
+    key: FilterKey,
+}
+
+impl<T: ?Sized> Clone for KeyedBloomFilter<T> {
+    fn clone(&self) -> Self {
+        KeyedBloomFilter {
+            filter: self.filter.clone(),
+            key: self.key.clone(),
+        }
+    }
+}
+
+impl<T: ?Sized + Hash> KeyedBloomFilter<T> {
+    /// Creates an empty filter keyed with `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn new(key: FilterKey, expected_items: usize, params: impl Into<FilterParams>) -> Self {
+        Self::try_new(key, expected_items, params).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates an empty keyed filter, returning an error instead of
+    /// panicking.
+    pub fn try_new(
+        key: FilterKey,
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<Self, BloomError> {
+        Ok(KeyedBloomFilter {
+            filter: BloomFilter::try_new(expected_items, params)?,
+            key,
+        })
+    }
+
+    /// Treats `filter` as built under `key`, e.g. a filter received from the
+    /// other party.
+    pub fn from_filter(key: FilterKey, filter: BloomFilter<T>) -> Self {
+        KeyedBloomFilter { filter, key }
+    }
+
+    /// Inserts an item.
+    pub fn insert(&mut self, item: &T) {
+        let (h1, h2) = hashing::hash_pair_with(&self.key, item);
+        self.filter.insert_hashes(h1, h2);
+        self.filter.counters.record_inserts(1);
+    }
+
+    /// Checks if an item might be in the filter.
+    pub fn contains(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair_with(&self.key, item);
+        let found = self.filter.contains_hashes(h1, h2);
+        self.filter.counters.record_query(found);
+        found
+    }
+
+    /// Checks if an item might be in the filter, in time that does not
+    /// depend on the answer. See [`BloomFilter::contains_constant_time`].
+    pub fn contains_constant_time(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair_with(&self.key, item);
+        let found = self.filter.contains_hashes_constant_time(h1, h2);
+        self.filter.counters.record_query(found);
+        found
+    }
+
+    /// Yields the `items` that are (probably) in this filter: checked against
+    /// the other party's filter, the intersection of the two sets.
+    pub fn intersection<'a, I>(&'a self, items: I) -> impl Iterator<Item = I::Item> + 'a
+    where
+        I: IntoIterator + 'a,
+        I::Item: Borrow<T>,
+    {
+        items
+            .into_iter()
+            .filter(move |item| self.contains((*item).borrow()))
+    }
+
+    /// Estimates the number of items the two filters share, from their fill
+    /// alone, as `n(A) + n(B) - n(A ∪ B)` with each `n` estimated by
+    /// [`BloomFilter::estimated_items`].
+    ///
+    /// Neither side learns which items are shared. The estimate is only
+    /// useful while the filters are far from full.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::KeyMismatch`] if the filters have different
+    /// keys, or [`BloomError::IncompatibleFilters`] if their bit counts or
+    /// hash counts differ.
+    pub fn estimated_intersection_size(&self, other: &Self) -> Result<f64, BloomError> {
+        let mut union = self.clone();
+        union.union(other)?;
+        let estimate = self.filter.estimated_items() + other.filter.estimated_items()
+            - union.filter.estimated_items();
+        Ok(estimate.max(0.0))
+    }
+
+    /// Adds every item of `other` to this filter.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as
+    /// [`estimated_intersection_size`](Self::estimated_intersection_size).
+    pub fn union(&mut self, other: &Self) -> Result<(), BloomError> {
+        self.check_key(other)?;
+        self.filter.merge(&other.filter)
+    }
+
+    /// Keeps only the bits set in both filters. Every item inserted into
+    /// both filters is still found, and the false positive rate is at most
+    /// that of either filter. It can still be higher than that of a filter
+    /// built from the true intersection: bits set by different items in
+    /// each filter survive, so the result is fuller than the intersection's
+    /// keys alone would make it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as
+    /// [`estimated_intersection_size`](Self::estimated_intersection_size).
+    pub fn intersect(&mut self, other: &Self) -> Result<(), BloomError> {
+        self.check_key(other)?;
+        let (this, that) = (&self.filter, &other.filter);
+        if (this.bit_count, this.hash_fn_count) != (that.bit_count, that.hash_fn_count) {
+            return Err(BloomError::IncompatibleFilters {
+                expected: (this.bit_count, this.hash_fn_count),
+                found: (that.bit_count, that.hash_fn_count),
+            });
+        }
+        for (word, other_word) in self.filter.bit_vec.iter_mut().zip(&other.filter.bit_vec) {
+            *word &= other_word;
+        }
+        Ok(())
+    }
+
+    fn check_key(&self, other: &Self) -> Result<(), BloomError> {
+        if self.key != other.key {
+            return Err(BloomError::KeyMismatch);
+        }
+        Ok(())
+    }
+}
+
+impl<T: ?Sized> KeyedBloomFilter<T> {
+    /// Returns the key.
+    pub fn key(&self) -> &FilterKey {
+        &self.key
+    }
+
+    /// Returns the underlying filter, e.g. to serialize it for the other
+    /// party. Its own `insert` and `contains` do not use the key.
+    pub fn filter(&self) -> &BloomFilter<T> {
+        &self.filter
+    }
+
+    /// Unwraps the underlying filter.
+    pub fn into_inner(self) -> BloomFilter<T> {
+        self.filter
+    }
+}
+
+impl<T: ?Sized + Hash> ApproximateSet<T> for KeyedBloomFilter<T> {
+    fn insert(&mut self, item: &T) {
+        KeyedBloomFilter::insert(self, item);
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        KeyedBloomFilter::contains(self, item)
+    }
+
+    fn estimated_len(&self) -> f64 {
+        self.filter.estimated_len()
+    }
+
+    fn fp_estimate(&self) -> f64 {
+        self.filter.fp_estimate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(seed: u8) -> FilterKey {
+        FilterKey::from_bytes(std::array::from_fn(|i| seed.wrapping_add(i as u8)))
+    }
+
+    #[test]
+    fn test_siphash_reference_vectors() {
+        // From the SipHash paper: key 00..0f, messages 00..(len - 1).
+        let key = key(0);
+        let expected = [
+            (0, 0x726f_db47_dd0e_0e31),
+            (1, 0x74f8_39c5_93dc_67fd),
+            (8, 0x93f5_f579_9a93_2462),
+            (15, 0xa129_ca61_49be_45e5),
+        ];
+        for (len, hash) in expected {
+            let message: Vec<u8> = (0..len).collect();
+            let mut hasher = key.build_hasher();
+            hasher.write(&message);
+            assert_eq!(hasher.finish(), hash, "length {}", len);
+
+            // Split writes hash the same as one.
+            let mut hasher = key.build_hasher();
+            for chunk in message.chunks(3) {
+                hasher.write(chunk);
+            }
+            assert_eq!(hasher.finish(), hash, "length {} in chunks", len);
+        }
+    }
+
+    #[test]
+    fn test_keyed_filters() {
+        let mut alice = KeyedBloomFilter::new(key(1), 1000, 0.001);
+        let mut bob = KeyedBloomFilter::new(key(1), 1000, 0.001);
+        (0..600u64).for_each(|i| alice.insert(&i));
+        (400..1000u64).for_each(|i| bob.insert(&i));
+
+        // Another key puts the items on other bits.
+        let mut plain: BloomFilter<u64> = BloomFilter::new(1000, 0.001);
+        (0..600u64).for_each(|i| plain.insert(&i));
+        assert_ne!(alice.filter().bit_vec, plain.bit_vec);
+        assert_ne!(key(1).key_id(), key(2).key_id());
+
+        let shared = alice.estimated_intersection_size(&bob).unwrap();
+        assert!((shared - 200.0).abs() < 20.0, "{}", shared);
+        let mutual = bob.intersection(0..600u64).count();
+        assert!((200..205).contains(&mutual), "{}", mutual);
+
+        alice.intersect(&bob).unwrap();
+        assert!((400..600u64).all(|i| alice.contains(&i)));
+
+        let stranger = KeyedBloomFilter::new(key(2), 1000, 0.001);
+        assert_eq!(alice.union(&stranger), Err(BloomError::KeyMismatch));
+    }
+}
@@ -0,0 +1,255 @@
+//! Canonical k-mers of DNA sequences.
+//!
+//! A k-mer is a substring of `k` bases. Since DNA is double-stranded, a
+//! k-mer and its reverse complement (`ACG` and `CGT`) are the same piece of
+//! sequence read from opposite strands, so genomic filters store the
+//! *canonical* form: the smaller of the two. [`canonical_kmers`] packs each
+//! k-mer into a `u64` at 2 bits per base (`A`=0, `C`=1, `G`=2, `T`=3) and
+//! slides over the sequence updating both strands in constant time per base,
+//! without allocating.
+//!
+//! Bases may be upper or lower case. Any other byte, such as the `N` of an
+//! unknown base, cannot be encoded, so the k-mers overlapping it are skipped.
+//!
+//! [`insert`] and [`query`] apply this to a `BloomFilter<u64>`.
+//!
+//! # Examples
+//!
+//! ```
+//! use bloomlib::{kmer, BloomFilter};
+//!
+//! let mut filter: BloomFilter<u64> = BloomFilter::new(10_000, 0.001);
+//! kmer::insert(&mut filter, b"ACGTTGCANNACGTAGCTAG", 5);
+//!
+//! // The reverse complement of a read is found just like the read.
+//! let hits = kmer::query(&filter, b"CAACGT", 5);
+//! assert_eq!((hits.present, hits.total), (2, 2));
+//! ```
+
+use std::slice;
+
+use crate::{BitStore, BloomFilter};
+
+/// The largest `k` whose k-mers fit in a `u64`.
+pub const MAX_K: usize = 32;
+
+/// The number of k-mers [`insert`] hashes together.
+const BATCH_LEN: usize = 256;
+
+/// Returns the 2-bit code of a base, or `None` for any other byte.
+#[inline]
+fn encode_base(base: u8) -> Option<u64> {
+    match base {
+        b'A' | b'a' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'G' | b'g' => Some(2),
+        b'T' | b't' => Some(3),
+        _ => None,
+    }
+}
+
+/// Returns the canonical 2-bit encoding of a single k-mer, or `None` if it
+/// contains a byte other than a base.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::kmer;
+///
+/// // CGT is the reverse complement of ACG.
+/// assert_eq!(kmer::encode(b"ACG"), kmer::encode(b"CGT"));
+/// assert_eq!(kmer::encode(b"ACG"), Some(0b00_01_10));
+/// assert_eq!(kmer::encode(b"ANG"), None);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `kmer` is empty or longer than [`MAX_K`].
+pub fn encode(kmer: &[u8]) -> Option<u64> {
+    let mut kmers = canonical_kmers(kmer, kmer.len());
+    let encoded = kmers.next();
+    // The only k-mer is missing exactly when a byte is not a base.
+    debug_assert!(kmers.next().is_none());
+    encoded
+}
+
+/// Returns the canonical encodings of all k-mers of `sequence`, in order.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::kmer;
+///
+/// let kmers: Vec<u64> = kmer::canonical_kmers(b"ACGTN", 3).collect();
+/// assert_eq!(kmers, [kmer::encode(b"ACG").unwrap(), kmer::encode(b"CGT").unwrap()]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `k` is 0 or greater than [`MAX_K`].
+pub fn canonical_kmers(sequence: &[u8], k: usize) -> CanonicalKmers<'_> {
+    assert!(
+        (1..=MAX_K).contains(&k),
+        "k must be between 1 and {}.",
+        MAX_K
+    );
+    CanonicalKmers {
+        bases: sequence.iter(),
+        k,
+        mask: u64::MAX >> (64 - 2 * k),
+        forward: 0,
+        reverse: 0,
+        valid: 0,
+    }
+}
+
+/// An iterator over the canonical k-mers of a sequence, created by
+/// [`canonical_kmers`].
+#[derive(Debug, Clone)]
+pub struct CanonicalKmers<'a> {
+    bases: slice::Iter<'a, u8>,
+    k: usize,
+    /// The low `2k` bits.
+    mask: u64,
+    /// The last `k` bases, the latest in the low bits.
+    forward: u64,
+    /// The reverse complement of `forward`.
+    reverse: u64,
+    /// The number of bases since the last byte that was not a base.
+    valid: usize,
+}
+
+impl Iterator for CanonicalKmers<'_> {
+    type Item = u64;
+
+    #[inline]
+    fn next(&mut self) -> Option<u64> {
+        for &base in self.bases.by_ref() {
+            let Some(code) = encode_base(base) else {
+                self.valid = 0;
+                continue;
+            };
+            self.forward = ((self.forward << 2) | code) & self.mask;
+            self.reverse = (self.reverse >> 2) | ((3 - code) << (2 * (self.k - 1)));
+            self.valid += 1;
+            if self.valid >= self.k {
+                return Some(self.forward.min(self.reverse));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let warm_up = (self.k - 1).saturating_sub(self.valid);
+        (0, Some(self.bases.len().saturating_sub(warm_up)))
+    }
+}
+
+/// The result of [`query`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KmerHits {
+    /// The number of k-mers the filter reported as present.
+    pub present: usize,
+    /// The number of k-mers looked up.
+    pub total: usize,
+}
+
+impl KmerHits {
+    /// Returns the fraction of k-mers present, or 0 if the sequence had
+    /// none.
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.present as f64 / self.total as f64
+        }
+    }
+}
+
+/// Inserts the canonical k-mers of `sequence`, returning how many there
+/// were.
+///
+/// The k-mers are inserted in batches through
+/// [`BloomFilter::insert_keys`], from a buffer on the stack.
+///
+/// # Panics
+///
+/// Panics if `k` is 0 or greater than [`MAX_K`].
+pub fn insert<B: BitStore>(filter: &mut BloomFilter<u64, B>, sequence: &[u8], k: usize) -> usize {
+    let mut kmers = canonical_kmers(sequence, k);
+    let mut batch = [0; BATCH_LEN];
+    let mut count = 0;
+    loop {
+        let len = batch
+            .iter_mut()
+            .zip(kmers.by_ref())
+            .map(|(slot, kmer)| *slot = kmer)
+            .count();
+        filter.insert_keys(&batch[..len]);
+        count += len;
+        if len < BATCH_LEN {
+            return count;
+        }
+    }
+}
+
+/// Looks up the canonical k-mers of `sequence`, counting those present.
+///
+/// # Panics
+///
+/// Panics if `k` is 0 or greater than [`MAX_K`].
+pub fn query<B: BitStore>(filter: &BloomFilter<u64, B>, sequence: &[u8], k: usize) -> KmerHits {
+    canonical_kmers(sequence, k).fold(KmerHits::default(), |hits, kmer| KmerHits {
+        present: hits.present + filter.contains(&kmer) as usize,
+        total: hits.total + 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes the canonical form the slow way, from both strings.
+    fn naive(kmer: &[u8]) -> Option<u64> {
+        fn pack(bases: impl Iterator<Item = u8>) -> Option<u64> {
+            bases
+                .map(encode_base)
+                .try_fold(0, |packed, code| Some(packed << 2 | code?))
+        }
+        let complement = |base: &u8| match base.to_ascii_uppercase() {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'T' => b'A',
+            other => other,
+        };
+        let forward = pack(kmer.iter().copied())?;
+        let reverse = pack(kmer.iter().rev().map(complement))?;
+        Some(forward.min(reverse))
+    }
+
+    #[test]
+    fn test_canonical_kmers_match_naive() {
+        let sequence = b"ACGTTGCAnnAcgtagctagGGATCCTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTACGGT";
+        for k in [1, 3, 7, 31, 32] {
+            let expected: Vec<u64> = sequence.windows(k).filter_map(naive).collect();
+            let kmers = canonical_kmers(sequence, k);
+            assert!(kmers.size_hint().1.unwrap() >= expected.len());
+            assert_eq!(kmers.collect::<Vec<_>>(), expected, "k = {}", k);
+        }
+        assert_eq!(canonical_kmers(b"ACG", 4).next(), None);
+    }
+
+    #[test]
+    fn test_insert_and_query() {
+        let sequence: Vec<u8> = (0..2000u32)
+            .map(|i| b"ACGT"[(i.wrapping_mul(2_654_435_761) >> 30) as usize])
+            .collect();
+        let mut filter: BloomFilter<u64> = BloomFilter::new(4000, 0.001);
+        assert_eq!(insert(&mut filter, &sequence, 21), 2000 - 20);
+
+        let hits = query(&filter, &sequence[500..700], 21);
+        assert_eq!((hits.present, hits.total), (180, 180));
+        assert_eq!(query(&filter, b"ACG", 21).fraction(), 0.0);
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+type Loader<F, E> = Box<dyn FnMut() -> Result<F, E> + Send>;
+
+/// A filter built by a loader closure the first time it is used.
+///
+/// Services often hold filters, e.g. per-region blocklists, that a given
+/// process may never query. A `LazyBloom` defers loading or building such a
+/// filter to the first [`get`](Self::get), and afterwards returns the same
+/// filter without locking. Concurrent first calls run the loader once; the
+/// others wait for it.
+///
+/// If the loader fails, the error is returned to that caller, and by
+/// default the next call tries again. A filter created with
+/// [`poison_on_error`](Self::poison_on_error) instead fails every later
+/// call with [`LazyError::Poisoned`], for loaders that should not be
+/// retried, such as ones reading a file that is known to be gone.
+///
+/// The loader blocks the calling thread; async services call
+/// [`get`](Self::get) from a blocking task the first time, e.g. through
+/// `tokio::task::spawn_blocking` on an `Arc<LazyBloom>`, and can serve
+/// from [`get_if_loaded`](Self::get_if_loaded) meanwhile.
+///
+/// # Examples
+///
+/// ```
+/// use std::io;
+/// use bloomlib::{BloomFilter, LazyBloom};
+///
+/// let blocklist = LazyBloom::new(|| -> io::Result<_> {
+///     let mut filter: BloomFilter<str> = BloomFilter::new(1000, 0.01);
+///     filter.insert("spam.example");
+///     Ok(filter)
+/// });
+/// assert!(!blocklist.is_loaded());
+/// assert!(blocklist.get().unwrap().contains("spam.example"));
+/// assert!(blocklist.is_loaded());
+/// ```
+pub struct LazyBloom<F, E> {
+    filter: OnceLock<F>,
+    state: Mutex<State<F, E>>,
+    poison: bool,
+}
+
+enum State<F, E> {
+    /// Not loaded yet, or the last attempt failed and may be retried.
+    Pending(Loader<F, E>),
+    /// Loaded; the loader has been dropped.
+    Loaded,
+    /// A load failed and the filter does not retry.
+    Poisoned,
+}
+
+/// The error returned by [`LazyBloom::get`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum LazyError<E> {
+    /// The loader failed on this call.
+    Failed(E),
+    /// The loader failed on an earlier call, and the filter was created with
+    /// [`LazyBloom::poison_on_error`].
+    Poisoned,
+}
+
+impl<E: fmt::Display> fmt::Display for LazyError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LazyError::Failed(err) => write!(f, "The filter could not be loaded: {}", err),
+            LazyError::Poisoned => write!(f, "An earlier load of the filter failed."),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for LazyError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LazyError::Failed(err) => Some(err),
+            LazyError::Poisoned => None,
+        }
+    }
+}
+
+impl<F, E> LazyBloom<F, E> {
+    /// Creates a filter that `loader` builds on first use, retrying on
+    /// later calls if it fails.
+    pub fn new(loader: impl FnMut() -> Result<F, E> + Send + 'static) -> Self {
+        LazyBloom {
+            filter: OnceLock::new(),
+            state: Mutex::new(State::Pending(Box::new(loader))),
+            poison: false,
+        }
+    }
+
+    /// Makes a failed load final: every later call returns
+    /// [`LazyError::Poisoned`] without running the loader again.
+    pub fn poison_on_error(mut self) -> Self {
+        self.poison = true;
+        self
+    }
+
+    /// Returns the filter, running the loader if this is the first call, or
+    /// if earlier calls failed and the filter retries.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LazyError::Failed`] with the loader's error if it fails on
+    /// this call, and [`LazyError::Poisoned`] if it failed on an earlier
+    /// call of a filter created with
+    /// [`poison_on_error`](Self::poison_on_error).
+    pub fn get(&self) -> Result<&F, LazyError<E>> {
+        if let Some(filter) = self.filter.get() {
+            return Ok(filter);
+        }
+        // Holding the lock while loading makes concurrent callers wait for
+        // this load instead of starting their own. A loader that panicked
+        // left the state as it was, so the lock is recovered.
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let loader = match &mut *state {
+            State::Pending(loader) => loader,
+            State::Loaded => return Ok(self.filter.get().expect("the filter is loaded")),
+            State::Poisoned => return Err(LazyError::Poisoned),
+        };
+        match loader() {
+            Ok(filter) => {
+                *state = State::Loaded;
+                Ok(self.filter.get_or_init(|| filter))
+            }
+            Err(err) => {
+                if self.poison {
+                    *state = State::Poisoned;
+                }
+                Err(LazyError::Failed(err))
+            }
+        }
+    }
+
+    /// Returns the filter if it has been loaded, without loading it.
+    pub fn get_if_loaded(&self) -> Option<&F> {
+        self.filter.get()
+    }
+
+    /// Returns `true` if the filter has been loaded.
+    pub fn is_loaded(&self) -> bool {
+        self.filter.get().is_some()
+    }
+
+    /// Returns the filter if it has been loaded, consuming the wrapper.
+    pub fn into_inner(self) -> Option<F> {
+        self.filter.into_inner()
+    }
+}
+
+impl<F: fmt::Debug, E> fmt::Debug for LazyBloom<F, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyBloom")
+            .field("filter", &self.filter.get())
+            .field("poison", &self.poison)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BloomFilter;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn counting_loader(
+        failures: usize,
+        calls: &Arc<AtomicUsize>,
+    ) -> impl FnMut() -> Result<BloomFilter<u64>, String> + Send + 'static {
+        let calls = Arc::clone(calls);
+        move || {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            if call < failures {
+                return Err(format!("attempt {} failed", call));
+            }
+            let mut filter = BloomFilter::new(100, 0.01);
+            filter.insert(&7);
+            Ok(filter)
+        }
+    }
+
+    #[test]
+    fn test_loads_once() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let lazy = LazyBloom::new(counting_loader(0, &calls));
+        assert!(lazy.get_if_loaded().is_none());
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| assert!(lazy.get().unwrap().contains(&7)));
+            }
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(lazy.into_inner().unwrap().contains(&7));
+    }
+
+    #[test]
+    fn test_retry_and_poison() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let retrying = LazyBloom::new(counting_loader(2, &calls));
+        assert_eq!(
+            retrying.get().unwrap_err(),
+            LazyError::Failed("attempt 0 failed".into())
+        );
+        assert!(retrying.get().is_err());
+        assert!(retrying.get().unwrap().contains(&7));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let poisoning = LazyBloom::new(counting_loader(1, &calls)).poison_on_error();
+        assert!(matches!(poisoning.get(), Err(LazyError::Failed(_))));
+        assert_eq!(poisoning.get().unwrap_err(), LazyError::Poisoned);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,33 +1,149 @@
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+#![cfg_attr(feature = "nightly-simd", feature(portable_simd))]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
+use std::borrow::Borrow;
+use std::hash::Hash;
 use std::marker::PhantomData;
 
-/// Configuration parameter for creating a Bloom Filter.
-///
-/// Specify either the desired false positive rate (f64)
-/// or the specific number of hash functions (u32) to use.
-#[derive(Debug, Clone, Copy)]
-pub enum FilterParams {
-    /// Target false positive rate (between 0.0 and 1.0).
-    /// The filter will calculate optimal bits (m) and hashes (k).
-    FalsePositiveRate(f64),
-    /// Target number of hash functions (k).
-    /// The filter will calculate the optimal bits (m) to satisfy the
-    /// 50% fill rate assumption for this k (p = 2^-k).
-    HashCount(u32),
-}
+use counters::OpCounters;
 
-impl From<f64> for FilterParams {
-    fn from(rate: f64) -> Self {
-        FilterParams::FalsePositiveRate(rate)
-    }
-}
+#[cfg(feature = "allocator_api")]
+mod alloc_api;
+mod approx;
+mod atomic;
+mod buffered;
+mod builder;
+mod cached;
+mod cancel;
+mod cardinality;
+mod cell;
+mod collections;
+pub mod compat;
+mod const_filter;
+mod counters;
+mod counting;
+mod dedup;
+mod descriptor;
+mod diff;
+mod distributed;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod error;
+mod expiring;
+mod external;
+mod file_lock;
+mod fixed_k;
+mod frozen;
+#[cfg(feature = "gpu")]
+mod gpu;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod hashing;
+mod hooks;
+#[cfg(feature = "arc-swap")]
+mod hot_swap;
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod huge_page;
+#[cfg(feature = "metrics")]
+mod instrument;
+mod iter;
+mod join;
+mod kernels;
+mod keyed;
+pub mod kmer;
+mod lazy;
+#[cfg(feature = "tower")]
+mod middleware;
+pub mod migrate;
+pub mod ngram;
+#[cfg(feature = "rayon")]
+mod parallel;
+pub mod params;
+mod pipeline;
+mod pool;
+pub mod privacy;
+mod record;
+mod registry;
+mod rotating;
+mod scalable;
+#[cfg(feature = "serde")]
+pub mod serde_b64;
+mod serialize;
+mod sharded;
+pub mod simhash;
+mod split;
+pub mod stats;
+mod storage;
+mod tabulation;
+mod trace;
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(feature = "zeroize")]
+mod zeroizing;
 
-impl From<u32> for FilterParams {
-    fn from(hashes: u32) -> Self {
-        FilterParams::HashCount(hashes)
-    }
-}
+pub use approx::ApproximateSet;
+pub use atomic::AtomicBloomFilter;
+pub use buffered::InsertBuffer;
+pub use builder::BloomFilterBuilder;
+pub use cached::CachedBloomFilter;
+pub use cancel::{CancelToken, Progress, ProgressUnit};
+pub use cardinality::CardinalityBloomFilter;
+pub use cell::CellBloomFilter;
+pub use const_filter::{const_word_count, ConstBloomFilter, StaticBloom};
+#[cfg(feature = "counters")]
+pub use counters::Counters;
+pub use counting::CountingBloomFilter;
+pub use dedup::{DedupStats, Deduplicator};
+pub use descriptor::{FilterDescriptor, HashAlgorithm, ProbeStrategy};
+pub use diff::FilterDiff;
+pub use distributed::{BloomShard, DistributedBloom};
+#[cfg(feature = "encryption")]
+pub use encryption::EncryptionKey;
+pub use error::BloomError;
+pub use expiring::ExpiringBloomFilter;
+pub use external::ExternalBuilder;
+pub use fixed_k::FixedKBloomFilter;
+pub use frozen::FrozenBloomFilter;
+#[cfg(feature = "gpu")]
+pub use gpu::{GpuBloomFilter, GpuError};
+pub use hashing::IntegerKey;
+pub use hooks::{FilterEvent, HookedBloomFilter, Hooks};
+#[cfg(feature = "arc-swap")]
+pub use hot_swap::HotSwapFilter;
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub use huge_page::HugePageStore;
+#[cfg(feature = "metrics")]
+pub use instrument::{FilterMetrics, InstrumentedBloomFilter};
+pub use iter::{ApproxUnique, ApproxUniqueExt};
+pub use join::{BloomJoin, BorrowKey, JoinStats, Prune};
+pub use keyed::{FilterKey, KeyedBloomFilter, KeyedHasher};
+pub use lazy::{LazyBloom, LazyError};
+#[cfg(feature = "tower")]
+pub use middleware::{Dedup, DedupFuture, DedupLayer};
+pub use params::FilterParams;
+pub use pool::FilterPool;
+pub use record::{OpLog, RecordedOp, RecordingBloomFilter, ReplayStats};
+pub use registry::{FilterRegistry, FilterUsage, RegistryError, SharedFilter};
+pub use rotating::RotatingBloomFilter;
+pub use scalable::ScalableBloomFilter;
+pub use serialize::LoadLimits;
+pub use sharded::ShardedBloomFilter;
+pub use split::FilterPart;
+pub use storage::{AlignedStore, AllocStore, BitStore, ChunkedStore, Word};
+pub use tabulation::{TabulationBloomFilter, TabulationHash, TabulationHasher};
+#[cfg(feature = "watch")]
+pub use watch::FilterWatcher;
+#[cfg(feature = "zeroize")]
+pub use zeroizing::ZeroizingStore;
+
+#[cfg(feature = "macros")]
+pub use bloomlib_macros::bloom;
 
 /// A space and time efficient Bloom Filter implementation.
 ///
@@ -37,18 +153,35 @@ impl From<u32> for FilterParams {
 ///
 /// # Type Parameters
 /// * `T`: The type of values to be stored. Must implement `Hash`.
-#[derive(Debug, Clone)]
-pub struct BloomFilter<T: ?Sized> {
-    /// The bit array stored as a vector of u64s to maximize cache efficiency.
-    bit_vec: Vec<u64>,
+/// * `B`: The bit array storage. Defaults to `Vec<u64>`; `Vec<u8>`, `Vec<u16>`
+///   and `Vec<u32>` trade word width for cheaper operations on small targets.
+#[derive(Debug)]
+pub struct BloomFilter<T: ?Sized, B = Vec<u64>> {
+    /// The bit array, stored as u64 words by default to maximize cache efficiency.
+    bit_vec: B,
     /// The total number of bits in the filter (m).
     bit_count: u64,
     /// The number of hash functions to use (k).
     hash_fn_count: u32,
+    /// Insert and lookup counts, empty without the `counters` feature.
+    counters: OpCounters,
     /// Phantom data to hold the type information.
     _marker: PhantomData<T>,
 }
 
+// Implemented by hand so that cloning does not require `T: Clone` (e.g. for `str`).
+impl<T: ?Sized, B: Clone> Clone for BloomFilter<T, B> {
+    fn clone(&self) -> Self {
+        BloomFilter {
+            bit_vec: self.bit_vec.clone(),
+            bit_count: self.bit_count,
+            hash_fn_count: self.hash_fn_count,
+            counters: self.counters.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
 impl<T: ?Sized + Hash> BloomFilter<T> {
     /// Creates a new Bloom Filter optimized for the given expected item count
     /// and configuration (either false positive rate or hash count).
@@ -85,56 +218,151 @@ impl<T: ?Sized + Hash> BloomFilter<T> {
     /// Panics if `expected_items` is 0, or if configuration parameters are invalid
     /// (e.g., rate <= 0.0, rate >= 1.0, or hashes == 0).
     pub fn new(expected_items: usize, params: impl Into<FilterParams>) -> Self {
-        assert!(expected_items > 0, "Expected items must be greater than 0.");
-
-        let ln2 = std::f64::consts::LN_2;
-        let params = params.into();
-
-        let (m, k) = match params {
-            FilterParams::FalsePositiveRate(p) => {
-                assert!(
-                    p > 0.0 && p < 1.0,
-                    "False positive rate must be between 0.0 and 1.0, exclusive."
-                );
-                // m = - (n * ln(p)) / (ln(2)^2)
-                let numerator = -(expected_items as f64) * p.ln();
-                let denominator = ln2 * ln2;
-                let m = (numerator / denominator).ceil() as u64;
-
-                // k = (m / n) * ln(2)
-                let k = ((m as f64 / expected_items as f64) * ln2).ceil() as u32;
-                (m, k)
-            }
-            FilterParams::HashCount(k) => {
-                assert!(k > 0, "Hash count must be greater than 0.");
-                // If k is fixed, assume optimal fill rate (50%), where p = 2^-k.
-                // Derived from k = (m/n) * ln(2) -> m = (k * n) / ln(2)
-                let m = ((k as f64 * expected_items as f64) / ln2).ceil() as u64;
-                (m, k)
-            }
-        };
+        Self::from_params(expected_items, params)
+    }
 
-        // Round up m to the nearest multiple of 64 for valid u64 storage
-        let num_u64s = m.div_ceil(64) as usize;
-        let bit_vec = vec![0; num_u64s];
+    /// Creates a new Bloom Filter like [`BloomFilter::new`], returning an
+    /// error instead of panicking if the configuration is invalid or the
+    /// filter is too large to allocate on this platform.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::{BloomError, BloomFilter};
+    ///
+    /// let bf: Result<BloomFilter<str>, _> = BloomFilter::try_new(1000, 0.01);
+    /// assert!(bf.is_ok());
+    ///
+    /// let bf: Result<BloomFilter<str>, _> = BloomFilter::try_new(0, 0.01);
+    /// assert_eq!(bf.unwrap_err(), BloomError::ZeroExpectedItems);
+    /// ```
+    pub fn try_new(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<Self, BloomError> {
+        Self::try_from_params(expected_items, params)
+    }
+}
 
-        // Recalculate true bit count based on vector size
-        let true_bit_count = (num_u64s * 64) as u64;
+impl<T: ?Sized + Hash, B: AllocStore> BloomFilter<T, B> {
+    /// Creates a new Bloom Filter backed by storage `B`, optimized for the
+    /// given expected item count and configuration.
+    ///
+    /// This is [`BloomFilter::new`] for a non-default store, such as a
+    /// narrower word type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// // Store the bits in bytes rather than 64-bit words
+    /// let mut bf = BloomFilter::<str, Vec<u8>>::from_params(100, 0.01);
+    /// bf.insert("seen");
+    /// assert!(bf.contains("seen"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn from_params(expected_items: usize, params: impl Into<FilterParams>) -> Self {
+        Self::try_from_params(expected_items, params).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a new Bloom Filter backed by storage `B`, returning an error
+    /// instead of panicking. See [`BloomFilter::try_new`].
+    pub fn try_from_params(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<Self, BloomError> {
+        BloomFilterBuilder::new(expected_items, params).try_build()
+    }
+}
 
+impl<T: ?Sized, W: Word, const N: usize> BloomFilter<T, [W; N]> {
+    /// Creates an empty filter of `N` words of type `W` using `hash_count`
+    /// hash functions, with its bits inline rather than on the heap.
+    ///
+    /// This is a `const fn`, so a filter can be a `static`, e.g. behind a
+    /// `Mutex` on a microcontroller with a fixed memory map. The filter has
+    /// all `N * W::BITS` bits, and hashes and probes exactly like one built
+    /// by [`BloomFilter::new`] with that geometry. Size it with
+    /// [`const_word_count`], which counts `u64` words, or [`params::size`].
+    ///
+    /// # Panics
+    ///
+    /// Panics (or fails const evaluation) if `N` or `hash_count` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Mutex;
+    /// use bloomlib::{const_word_count, BloomFilter};
+    ///
+    /// const WORDS: usize = const_word_count(1000, 7);
+    /// static SEEN: Mutex<BloomFilter<u32, [u64; WORDS]>> = Mutex::new(BloomFilter::with_array(7));
+    ///
+    /// SEEN.lock().unwrap().insert(&0x2a);
+    /// assert!(SEEN.lock().unwrap().contains(&0x2a));
+    /// ```
+    pub const fn with_array(hash_count: u32) -> Self {
+        assert!(N > 0, "Word count must be greater than 0.");
+        assert!(hash_count > 0, "Hash count must be greater than 0.");
         BloomFilter {
-            bit_vec,
-            bit_count: true_bit_count,
-            hash_fn_count: k,
+            bit_vec: [W::ZERO; N],
+            bit_count: N as u64 * W::BITS as u64,
+            hash_fn_count: hash_count,
+            counters: OpCounters::new(),
             _marker: PhantomData,
         }
     }
+}
 
+impl<T: ?Sized + Hash, B: BitStore> BloomFilter<T, B> {
     /// Inserts an item into the Bloom Filter.
     pub fn insert(&mut self, item: &T) {
         let (h1, h2) = self.get_hashes(item);
+        self.insert_hashes(h1, h2);
+        self.counters.record_inserts(1);
+    }
+
+    /// Inserts an item, returning `true` if it was new, or `false` if it was
+    /// (probably) already present.
+    ///
+    /// This hashes the item once, so it is cheaper than `contains` followed
+    /// by `insert`. A false positive makes a new item look present, so it is
+    /// reported as a duplicate with the filter's false positive rate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut seen: BloomFilter<str> = BloomFilter::new(100, 0.01);
+    /// assert!(seen.check_and_insert("apple"));
+    /// assert!(!seen.check_and_insert("apple"));
+    /// ```
+    pub fn check_and_insert(&mut self, item: &T) -> bool {
+        let (h1, h2) = self.get_hashes(item);
+        let found = self.contains_hashes(h1, h2);
+        self.counters.record_query(found);
+        if found {
+            return false;
+        }
+        self.insert_hashes(h1, h2);
+        self.counters.record_inserts(1);
+        true
+    }
+
+    /// Sets the k bits of an item's hash pair.
+    fn insert_hashes(&mut self, h1: u64, h2: u64) {
+        let (bit_count, hash_count) = (self.bit_count, self.hash_fn_count);
+        if let Some(words) = self.bit_vec.as_u64_words_mut() {
+            return kernels::insert(words, bit_count, hash_count, h1, h2);
+        }
         for i in 0..self.hash_fn_count {
             let (vec_index, mask) = self.get_bit(h1, h2, i);
-            self.bit_vec[vec_index] |= mask;
+            self.bit_vec.set_bits(vec_index, mask);
         }
     }
 
@@ -144,70 +372,560 @@ impl<T: ?Sized + Hash> BloomFilter<T> {
     /// Returns `false` if the item is definitely not present.
     pub fn contains(&self, item: &T) -> bool {
         let (h1, h2) = self.get_hashes(item);
+        let found = self.contains_hashes(h1, h2);
+        self.counters.record_query(found);
+        found
+    }
+
+    /// Tests the k bits of an item's hash pair.
+    fn contains_hashes(&self, h1: u64, h2: u64) -> bool {
+        if let Some(words) = self.bit_vec.as_u64_words() {
+            return kernels::contains(words, self.bit_count, self.hash_fn_count, h1, h2);
+        }
         for i in 0..self.hash_fn_count {
             let (vec_index, mask) = self.get_bit(h1, h2, i);
-            if (self.bit_vec[vec_index] & mask) == 0 {
+            if (self.bit_vec.word(vec_index) & mask) == B::Word::ZERO {
                 return false;
             }
         }
         true
     }
 
+    /// Checks if an item might be in the filter, in time that does not
+    /// depend on the answer.
+    ///
+    /// [`contains`](Self::contains) stops at the first unset bit, so how long
+    /// it takes tells an observer how many of an item's bits are set. This
+    /// always tests all k bits and combines them without branching, so
+    /// lookups of present and absent items run the same instructions. Use it
+    /// where response timing must not reveal membership, e.g. next to
+    /// authentication; with a [`KeyedBloomFilter`], use
+    /// [`KeyedBloomFilter::contains_constant_time`].
+    ///
+    /// The memory addresses read still depend on the item, so an attacker
+    /// who can observe the CPU cache, e.g. from the same machine, may learn
+    /// which bits were probed. Hashing takes time proportional to the item's
+    /// length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut revoked: BloomFilter<str> = BloomFilter::new(1000, 0.001);
+    /// revoked.insert("token-1");
+    /// assert!(revoked.contains_constant_time("token-1"));
+    /// assert!(!revoked.contains_constant_time("token-2"));
+    /// ```
+    pub fn contains_constant_time(&self, item: &T) -> bool {
+        let (h1, h2) = self.get_hashes(item);
+        let found = self.contains_hashes_constant_time(h1, h2);
+        self.counters.record_query(found);
+        found
+    }
+
+    /// Tests all k bits of an item's hash pair without branching on them.
+    fn contains_hashes_constant_time(&self, h1: u64, h2: u64) -> bool {
+        let mut found = 1u64;
+        for i in 0..self.hash_fn_count {
+            let (vec_index, mask) = self.get_bit(h1, h2, i);
+            let bit = (self.bit_vec.word(vec_index) & mask).to_u64();
+            // 1 if the bit is set, 0 otherwise, by arithmetic rather than a
+            // comparison; `black_box` keeps the loop from exiting early once
+            // `found` is 0.
+            found = std::hint::black_box(found & ((bit | bit.wrapping_neg()) >> 63));
+        }
+        found == 1
+    }
+
+    /// Checks if an item might be in the filter by testing only the first
+    /// `probes` of its k bits, or all of them if `probes` exceeds k.
+    ///
+    /// Fewer probes make lookups cheaper and raise the false positive rate to
+    /// [`fill_ratio`](Self::fill_ratio) to the power of `probes`, but never
+    /// miss an inserted item. Use [`adaptive_probes`](Self::adaptive_probes)
+    /// to choose `probes` for a target rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `probes` is 0.
+    pub fn contains_with_probes(&self, item: &T, probes: u32) -> bool {
+        assert!(probes > 0, "Probes must be greater than 0.");
+        let (h1, h2) = self.get_hashes(item);
+        let found = (0..probes.min(self.hash_fn_count)).all(|i| {
+            let (vec_index, mask) = self.get_bit(h1, h2, i);
+            (self.bit_vec.word(vec_index) & mask) != B::Word::ZERO
+        });
+        self.counters.record_query(found);
+        found
+    }
+
+    /// Returns the fewest probes that keep the false positive rate at the
+    /// filter's current fill at or below `fp_rate`, for
+    /// [`contains_with_probes`](Self::contains_with_probes). See
+    /// [`params::probes_for_fill`].
+    ///
+    /// This counts the set bits, so compute it once per batch of lookups,
+    /// or after inserts, rather than per lookup.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fp_rate` is not between 0.0 and 1.0, exclusive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut bf: BloomFilter<u64> = BloomFilter::new(10_000, 0.01);
+    /// (0..500).for_each(|i| bf.insert(&i));
+    ///
+    /// // A twentieth full, the filter needs 2 of its 7 probes for 1%.
+    /// let probes = bf.adaptive_probes(0.01);
+    /// assert!(probes < bf.hash_count());
+    /// assert!((0..500).all(|i| bf.contains_with_probes(&i, probes)));
+    /// ```
+    pub fn adaptive_probes(&self, fp_rate: f64) -> u32 {
+        params::probes_for_fill(self.fill_ratio(), self.hash_fn_count, fp_rate)
+    }
+
+    /// Checks a batch of items, returning one result per item in input order.
+    ///
+    /// Each result means the same as [`BloomFilter::contains`]. The batch is
+    /// pipelined: items are hashed a few positions ahead and the cache lines
+    /// they will probe are prefetched, so on filters much larger than the CPU
+    /// cache the memory stalls of different items overlap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut bf: BloomFilter<str> = BloomFilter::new(100, 0.01);
+    /// bf.insert("seen");
+    ///
+    /// assert_eq!(bf.contains_batch(&["seen", "unseen"]), vec![true, false]);
+    /// ```
+    pub fn contains_batch<Q: Borrow<T>>(&self, items: &[Q]) -> Vec<bool> {
+        let mut results = Vec::with_capacity(items.len());
+        match self.bit_vec.as_u64_words() {
+            Some(words) => kernels::contains_batch(
+                words,
+                self.bit_count,
+                self.hash_fn_count,
+                items.iter().map(|item| self.get_hashes(item.borrow())),
+                &mut results,
+            ),
+            None => results.extend(items.iter().map(|item| {
+                let (h1, h2) = self.get_hashes(item.borrow());
+                self.contains_hashes(h1, h2)
+            })),
+        }
+        self.counters.record_queries(&results);
+        results
+    }
+
+    /// Returns how many items of `items` appear to be new, i.e. are not in
+    /// the filter, without inserting them.
+    ///
+    /// This is a lower bound on the batch's new items: a false positive makes
+    /// a new item look present. Nothing is inserted, so an item occurring
+    /// several times in the batch is counted each time. As a read-only probe
+    /// it leaves the filter's operation counters unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut seen: BloomFilter<str> = BloomFilter::new(100, 0.01);
+    /// seen.insert("apple");
+    ///
+    /// assert_eq!(seen.count_novel(["apple", "banana", "cherry"]), 2);
+    /// assert!(!seen.contains("banana"));
+    /// ```
+    pub fn count_novel<I>(&self, items: I) -> usize
+    where
+        I: IntoIterator,
+        I::Item: Borrow<T>,
+    {
+        items
+            .into_iter()
+            .filter(|item| {
+                let (h1, h2) = self.get_hashes(item.borrow());
+                !self.contains_hashes(h1, h2)
+            })
+            .count()
+    }
+
     /// Clears all bits in the filter.
     pub fn clear(&mut self) {
-        for slot in self.bit_vec.iter_mut() {
-            *slot = 0;
+        self.bit_vec.clear();
+    }
+
+    /// Adds every item of `other` to this filter, by OR-ing its bits in.
+    ///
+    /// Afterwards the filter contains the union of both sets, exactly as if
+    /// all items had been inserted into it directly. The two filters must
+    /// have the same bit count and hash count, i.e. be created with the same
+    /// parameters; the storage types may differ.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::IncompatibleFilters`] if the geometries differ.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut left: BloomFilter<str> = BloomFilter::new(100, 0.01);
+    /// let mut right: BloomFilter<str> = BloomFilter::new(100, 0.01);
+    /// left.insert("apple");
+    /// right.insert("banana");
+    ///
+    /// left.merge(&right).unwrap();
+    /// assert!(left.contains("apple") && left.contains("banana"));
+    /// ```
+    pub fn merge<C: BitStore<Word = B::Word>>(
+        &mut self,
+        other: &BloomFilter<T, C>,
+    ) -> Result<(), BloomError> {
+        self.merge_until(other, None)
+    }
+
+    /// Adds every item of `other` to this filter like
+    /// [`merge`](Self::merge), stopping early if `cancel` is cancelled.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::IncompatibleFilters`] if the geometries differ,
+    /// and [`BloomError::Cancelled`] if the token is cancelled before the
+    /// merge completes. Only some of `other`'s bits are then merged in: the
+    /// filter still contains every item it contained before, and some of
+    /// `other`'s items may be found, but not all. Merging again completes
+    /// it.
+    pub fn merge_cancellable<C: BitStore<Word = B::Word>>(
+        &mut self,
+        other: &BloomFilter<T, C>,
+        cancel: &CancelToken,
+    ) -> Result<(), BloomError> {
+        self.merge_until(other, Some(cancel))
+    }
+
+    fn merge_until<C: BitStore<Word = B::Word>>(
+        &mut self,
+        other: &BloomFilter<T, C>,
+        cancel: Option<&CancelToken>,
+    ) -> Result<(), BloomError> {
+        trace::timed_span!(
+            "bloom.merge",
+            bits = self.bit_count,
+            hashes = self.hash_fn_count
+        );
+        if (self.bit_count, self.hash_fn_count) != (other.bit_count, other.hash_fn_count) {
+            return Err(BloomError::IncompatibleFilters {
+                expected: (self.bit_count, self.hash_fn_count),
+                found: (other.bit_count, other.hash_fn_count),
+            });
+        }
+        let checkpoint = |done: usize, total: usize| {
+            cancel.map_or(Ok(()), |cancel| {
+                cancel.checkpoint(Progress::bytes(done, total))
+            })
+        };
+        let total = if let (Some(words), Some(other_words)) = (
+            self.bit_vec.as_u64_words_mut(),
+            other.bit_vec.as_u64_words(),
+        ) {
+            let total = words.len() * 8;
+            for (chunk, (words, other_words)) in words
+                .chunks_mut(cancel::CHECK_INTERVAL)
+                .zip(other_words.chunks(cancel::CHECK_INTERVAL))
+                .enumerate()
+            {
+                checkpoint(chunk * cancel::CHECK_INTERVAL * 8, total)?;
+                for (word, other_word) in words.iter_mut().zip(other_words) {
+                    *word |= other_word;
+                }
+            }
+            total
+        } else {
+            // Stores may pad differently (e.g. to whole cache lines), so only
+            // the words holding the `bit_count` bits are merged.
+            let word_bytes = B::Word::BITS as usize / 8;
+            let total = self.used_words() * word_bytes;
+            for index in 0..self.used_words() {
+                if index % cancel::CHECK_INTERVAL == 0 {
+                    checkpoint(index * word_bytes, total)?;
+                }
+                self.bit_vec.set_bits(index, other.bit_vec.word(index));
+            }
+            total
+        };
+        if let Some(cancel) = cancel {
+            cancel.report(Progress::bytes(total, total));
         }
+        Ok(())
     }
 
     /// Computes two 64-bit hashes for the item.
     fn get_hashes(&self, item: &T) -> (u64, u64) {
-        let mut hasher1 = DefaultHasher::new();
-        item.hash(&mut hasher1);
-        let h1 = hasher1.finish();
-
-        let mut hasher2 = DefaultHasher::new();
-        item.hash(&mut hasher2);
-        h1.hash(&mut hasher2);
-        let h2 = hasher2.finish();
-
-        (h1, h2)
+        hashing::hash_pair(item)
     }
 
     /// Calculates the bit index for the i-th hash function using Double Hashing.
     #[inline]
     fn get_index(&self, h1: u64, h2: u64, i: u32) -> u64 {
-        let offset = h2.wrapping_mul(i as u64);
-        let hash = h1.wrapping_add(offset);
-
-        hash % self.bit_count
+        hashing::bit_index(h1, h2, i, self.bit_count)
     }
 
     /// Computes the vector index and bit mask for the i-th hash position.
     #[inline]
-    fn get_bit(&self, h1: u64, h2: u64, i: u32) -> (usize, u64) {
+    fn get_bit(&self, h1: u64, h2: u64, i: u32) -> (usize, B::Word) {
         let bit_index = self.get_index(h1, h2, i);
-        let vec_index = (bit_index / 64) as usize;
-        let bit_offset = 1u64 << (bit_index % 64);
+        let word_bits = B::Word::BITS as u64;
+        let vec_index = (bit_index / word_bits) as usize;
+        let bit_offset = B::Word::bit((bit_index % word_bits) as u32);
 
         (vec_index, bit_offset)
     }
 
     /// Returns the approximate memory usage of the bit vector in bytes.
     pub fn memory_usage_bytes(&self) -> usize {
-        self.bit_vec.capacity() * 8
+        self.bit_vec.memory_usage_bytes()
     }
 
     /// Returns the number of hash functions (k) being used.
     pub fn hash_count(&self) -> u32 {
         self.hash_fn_count
     }
+
+    /// Returns the number of bits (m) in the filter.
+    pub fn bit_count(&self) -> u64 {
+        self.bit_count
+    }
+
+    /// Returns the fraction of the filter's bits that are set.
+    ///
+    /// A lookup of an item that was never inserted is a false positive with
+    /// probability of about `fill_ratio().powi(k)`. See
+    /// [`stats::saturation`] for how evenly the set bits are spread.
+    pub fn fill_ratio(&self) -> f64 {
+        let set_bits: u64 = (0..self.used_words())
+            .map(|index| self.bit_vec.word(index).to_u64().count_ones() as u64)
+            .sum();
+        set_bits as f64 / self.bit_count as f64
+    }
+
+    /// Estimates the number of distinct items inserted so far from the
+    /// fraction of set bits, as `-(m / k) * ln(1 - fill_ratio)`.
+    ///
+    /// The estimate is accurate while the filter is not close to full, and
+    /// infinite once every bit is set.
+    pub fn estimated_items(&self) -> f64 {
+        let (m, k) = (self.bit_count as f64, self.hash_fn_count as f64);
+        -(m / k) * (1.0 - self.fill_ratio()).ln()
+    }
+
+    /// Projects the false positive rate after inserting `additional_items`
+    /// more distinct items, starting from the filter's current fill.
+    ///
+    /// Each new item leaves a given bit unset with probability
+    /// `(1 - 1/m)^k`, so the unset fraction shrinks to about
+    /// `(1 - fill) * e^(-kn/m)`. Because it starts from the bits actually
+    /// set, the projection holds however the current items got there.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut bf: BloomFilter<u64> = BloomFilter::new(100_000, 0.01);
+    /// for i in 0..60_000 {
+    ///     bf.insert(&i);
+    /// }
+    ///
+    /// // Tomorrow's batch of 40,000 fits; one of 100,000 does not.
+    /// assert!(bf.expected_fp_after(40_000) < 0.011);
+    /// assert!(bf.expected_fp_after(100_000) > 0.05);
+    /// ```
+    pub fn expected_fp_after(&self, additional_items: usize) -> f64 {
+        let k = self.hash_fn_count as f64;
+        let unset = (1.0 - self.fill_ratio())
+            * (-k * additional_items as f64 / self.bit_count as f64).exp();
+        (1.0 - unset).powf(k)
+    }
+
+    /// Returns the store's words packed into `u64`s.
+    ///
+    /// Narrower words are packed little-endian, so bit i stays at word
+    /// i / 64, bit i % 64, whatever the word type.
+    pub(crate) fn packed_words(&self) -> impl Iterator<Item = u64> + '_ {
+        let word_bits = B::Word::BITS as usize;
+        let words_per_u64 = 64 / word_bits;
+        let word_count = self.bit_vec.word_count();
+
+        (0..word_count.div_ceil(words_per_u64)).map(move |chunk| {
+            (0..words_per_u64)
+                .map(|offset| chunk * words_per_u64 + offset)
+                .take_while(|&index| index < word_count)
+                .fold(0u64, |packed, index| {
+                    let shift = (index % words_per_u64) * word_bits;
+                    packed | (self.bit_vec.word(index).to_u64() << shift)
+                })
+        })
+    }
+
+    /// Returns the number of words holding the `bit_count` bits.
+    #[inline]
+    fn used_words(&self) -> usize {
+        self.bit_count.div_ceil(B::Word::BITS as u64) as usize
+    }
+}
+
+impl<T: IntegerKey, B: BitStore> BloomFilter<T, B> {
+    /// Inserts a batch of integer keys.
+    ///
+    /// Equivalent to calling [`BloomFilter::insert`] on each key, but the keys
+    /// are hashed several at a time with vector instructions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut bf: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+    /// bf.insert_keys(&[1, 2, 3]);
+    /// assert!(bf.contains(&2));
+    /// ```
+    pub fn insert_keys(&mut self, keys: &[T]) {
+        for (h1, h2) in hashing::integer_hash_pairs(keys) {
+            self.insert_hashes(h1, h2);
+        }
+        self.counters.record_inserts(keys.len());
+    }
+
+    /// Checks a batch of integer keys, returning one result per key in input
+    /// order.
+    ///
+    /// Equivalent to [`BloomFilter::contains_batch`], but the keys are hashed
+    /// several at a time with vector instructions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut bf: BloomFilter<u32> = BloomFilter::new(1000, 0.01);
+    /// bf.insert(&7);
+    /// assert_eq!(bf.contains_keys(&[7, 8]), vec![true, false]);
+    /// ```
+    pub fn contains_keys(&self, keys: &[T]) -> Vec<bool> {
+        let mut results = Vec::with_capacity(keys.len());
+        let hashes = hashing::integer_hash_pairs(keys);
+        match self.bit_vec.as_u64_words() {
+            Some(words) => kernels::contains_batch(
+                words,
+                self.bit_count,
+                self.hash_fn_count,
+                hashes,
+                &mut results,
+            ),
+            None => results.extend(hashes.map(|(h1, h2)| self.contains_hashes(h1, h2))),
+        }
+        self.counters.record_queries(&results);
+        results
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_expected_fp_after() {
+        let mut bf: BloomFilter<u64> = BloomFilter::new(20_000, 0.01);
+        let (m, k) = (bf.bit_count() as f64, bf.hash_count() as f64);
+        let formula = (1.0 - (-k * 20_000.0 / m).exp()).powf(k);
+        assert!((bf.expected_fp_after(20_000) - formula).abs() < 1e-12);
+        assert_eq!(bf.expected_fp_after(0), 0.0);
+
+        for i in 0..10_000u64 {
+            bf.insert(&i);
+        }
+        let projected = bf.expected_fp_after(10_000);
+        for i in 10_000..20_000u64 {
+            bf.insert(&i);
+        }
+        let actual = bf.fill_ratio().powf(k);
+        assert!((bf.estimated_items() - 20_000.0).abs() < 400.0);
+        assert!(
+            (projected - actual).abs() < actual * 0.05,
+            "{projected} vs {actual}"
+        );
+    }
+
+    #[test]
+    fn test_check_and_insert() {
+        let mut wide: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        let mut narrow: BloomFilter<u64, Vec<u8>> = BloomFilter::from_params(1000, 0.01);
+        for i in 0..1000u64 {
+            let present = (wide.contains(&i), narrow.contains(&i));
+            assert_eq!(wide.check_and_insert(&i), !present.0);
+            assert_eq!(narrow.check_and_insert(&i), !present.1);
+            assert!(wide.contains(&i) && !wide.check_and_insert(&i));
+            assert!(narrow.contains(&i) && !narrow.check_and_insert(&i));
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut left: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        let mut right: BloomFilter<u64, AlignedStore> = BloomFilter::from_params(1000, 0.01);
+        let mut both: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        for i in 0..1000u64 {
+            if i % 2 == 0 {
+                left.insert(&i);
+            } else {
+                right.insert(&i);
+            }
+            both.insert(&i);
+        }
+        left.merge(&right).unwrap();
+        assert_eq!(left.bit_vec, both.bit_vec);
+
+        let mut narrow: BloomFilter<u64, Vec<u8>> = BloomFilter::from_params(1000, 0.01);
+        let mut narrow_right: BloomFilter<u64, Vec<u8>> = BloomFilter::from_params(1000, 0.01);
+        narrow_right.insert(&7);
+        narrow.merge(&narrow_right).unwrap();
+        assert!(narrow.contains(&7));
+
+        let other: BloomFilter<u64> = BloomFilter::new(1000, 0.001);
+        assert_eq!(
+            left.merge(&other),
+            Err(BloomError::IncompatibleFilters {
+                expected: (left.bit_count(), left.hash_count()),
+                found: (other.bit_count(), other.hash_count()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_integer_keys_match_single() {
+        let keys: Vec<i64> = (-500..500).map(|i| i * 7919).collect();
+        let mut batch: BloomFilter<i64, Vec<u32>> = BloomFilter::from_params(500, 0.01);
+        let mut single: BloomFilter<i64, Vec<u32>> = BloomFilter::from_params(500, 0.01);
+        batch.insert_keys(&keys[..500]);
+        for key in &keys[..500] {
+            single.insert(key);
+        }
+        assert_eq!(batch.bit_vec, single.bit_vec);
+
+        let expected: Vec<bool> = keys.iter().map(|key| single.contains(key)).collect();
+        assert_eq!(batch.contains_keys(&keys), expected);
+    }
+
     #[test]
     fn test_initialization_with_rate() {
         let bf: BloomFilter<str> = BloomFilter::new(100, 0.01);
@@ -268,6 +986,24 @@ mod tests {
         assert!(!bf.contains(&other_user));
     }
 
+    #[test]
+    fn test_narrow_word_storage() {
+        let mut bf8 = BloomFilter::<str, Vec<u8>>::from_params(100, 0.01);
+        let mut bf16 = BloomFilter::<str, Vec<u16>>::from_params(100, 0.01);
+        let mut bf32 = BloomFilter::<str, Vec<u32>>::from_params(100, 0.01);
+
+        // m = 959 bits, rounded up to the word size
+        assert_eq!(bf8.bit_vec.len(), 120);
+        assert_eq!(bf16.bit_vec.len(), 60);
+        assert_eq!(bf32.bit_vec.len(), 30);
+
+        bf8.insert("seen");
+        bf16.insert("seen");
+        bf32.insert("seen");
+        assert!(bf8.contains("seen") && bf16.contains("seen") && bf32.contains("seen"));
+        assert!(!bf8.contains("unseen") && !bf16.contains("unseen") && !bf32.contains("unseen"));
+    }
+
     #[test]
     #[should_panic(expected = "Expected items must be greater than 0.")]
     fn test_panic_on_zero_items() {
@@ -279,4 +1015,71 @@ mod tests {
     fn test_panic_on_zero_hashes() {
         BloomFilter::<i32>::new(100, 0u32);
     }
+
+    #[test]
+    fn test_try_new_errors() {
+        assert_eq!(
+            BloomFilter::<i32>::try_new(100, 1.5).unwrap_err(),
+            BloomError::InvalidFalsePositiveRate(1.5)
+        );
+        assert_eq!(
+            BloomFilter::<i32>::try_new(100, 0u32).unwrap_err(),
+            BloomError::ZeroHashCount
+        );
+    }
+
+    #[test]
+    fn test_try_new_capacity_overflow() {
+        // m far exceeds what a single allocation can address on any platform.
+        let err = BloomFilter::<i32>::try_new(usize::MAX, 1000u32).unwrap_err();
+        assert!(matches!(err, BloomError::CapacityOverflow { .. }));
+
+        let err = BloomFilter::<i32, Vec<u8>>::try_from_params(usize::MAX, 1e-300).unwrap_err();
+        assert!(matches!(err, BloomError::CapacityOverflow { .. }));
+    }
+
+    #[test]
+    fn test_contains_batch_matches_contains() {
+        let mut wide = BloomFilter::new(500, 0.01);
+        let mut narrow = BloomFilter::<u64, Vec<u16>>::from_params(500, 0.01);
+        for i in (0..1000u64).step_by(2) {
+            wide.insert(&i);
+            narrow.insert(&i);
+        }
+
+        let queries: Vec<u64> = (0..1000).collect();
+        let expected: Vec<bool> = queries.iter().map(|i| wide.contains(i)).collect();
+        assert_eq!(wide.contains_batch(&queries), expected);
+
+        let expected: Vec<bool> = queries.iter().map(|i| narrow.contains(i)).collect();
+        assert_eq!(narrow.contains_batch(&queries), expected);
+    }
+
+    #[test]
+    fn test_array_store_matches_vec() {
+        let mut array = BloomFilter::<u64, [u16; 64]>::with_array(5);
+        assert_eq!(array.bit_count(), 1024);
+        (0..100).for_each(|i| array.insert(&i));
+
+        // The same bits in a heap-allocated filter answer the same.
+        let vec: BloomFilter<u64> = BloomFilter::from_bytes(&array.to_bytes()).unwrap();
+        assert!((0..1000).all(|i| vec.contains(&i) == array.contains(&i)));
+        assert!((0..100).all(|i| array.contains(&i)));
+        array.clear();
+        assert!(!array.contains(&1));
+    }
+
+    #[test]
+    fn test_constant_time_matches_contains() {
+        let mut wide = BloomFilter::new(500, 0.1);
+        let mut narrow = BloomFilter::<u64, Vec<u8>>::from_params(500, 0.1);
+        for i in (0..1000u64).step_by(2) {
+            wide.insert(&i);
+            narrow.insert(&i);
+        }
+        for i in 0..1000u64 {
+            assert_eq!(wide.contains_constant_time(&i), wide.contains(&i));
+            assert_eq!(narrow.contains_constant_time(&i), narrow.contains(&i));
+        }
+    }
 }
@@ -0,0 +1,232 @@
+use std::fmt;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::ExpiringBloomFilter;
+
+/// A tower [`Layer`] that short-circuits requests whose idempotency key was
+/// seen within the filter's TTL.
+///
+/// Requires the `tower` feature. For each request, `key` extracts the
+/// idempotency key, e.g. from an `Idempotency-Key` header; requests without
+/// one pass through. A request whose key is new is recorded in the
+/// [`ExpiringBloomFilter`] and passed to the inner service. A request whose
+/// key was recently seen never reaches the inner service: `on_duplicate`
+/// answers it instead, with a response such as `409 Conflict` or an error.
+///
+/// The filter's TTL is the deduplication window, and the filter is shared by
+/// every service the layer creates, e.g. one per connection. A new key is
+/// taken for a repeat with the filter's false positive rate, so size it for
+/// the number of keys expected per TTL.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use bloomlib::{DedupLayer, ExpiringBloomFilter};
+///
+/// struct Request {
+///     idempotency_key: Option<String>,
+/// }
+///
+/// let filter: Arc<ExpiringBloomFilter<String>> =
+///     Arc::new(ExpiringBloomFilter::new(100_000, 1e-6, Duration::from_secs(3600)));
+/// let layer = DedupLayer::new(
+///     filter,
+///     |request: &Request| request.idempotency_key.clone(),
+///     |_request: Request| Err::<String, _>("duplicate request"),
+/// );
+/// // let service = tower::ServiceBuilder::new().layer(layer).service(inner);
+/// ```
+pub struct DedupLayer<K, F, D> {
+    filter: Arc<ExpiringBloomFilter<K>>,
+    key: F,
+    on_duplicate: D,
+}
+
+impl<K, F, D> DedupLayer<K, F, D> {
+    /// Creates a layer recording keys in `filter`. See the type
+    /// documentation for `key` and `on_duplicate`.
+    pub fn new(filter: Arc<ExpiringBloomFilter<K>>, key: F, on_duplicate: D) -> Self {
+        DedupLayer {
+            filter,
+            key,
+            on_duplicate,
+        }
+    }
+
+    /// Returns the filter of recently seen keys.
+    pub fn filter(&self) -> &Arc<ExpiringBloomFilter<K>> {
+        &self.filter
+    }
+}
+
+impl<K, F: Clone, D: Clone> Clone for DedupLayer<K, F, D> {
+    fn clone(&self) -> Self {
+        DedupLayer {
+            filter: Arc::clone(&self.filter),
+            key: self.key.clone(),
+            on_duplicate: self.on_duplicate.clone(),
+        }
+    }
+}
+
+impl<K, F, D> fmt::Debug for DedupLayer<K, F, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DedupLayer").finish_non_exhaustive()
+    }
+}
+
+impl<S, K, F: Clone, D: Clone> Layer<S> for DedupLayer<K, F, D> {
+    type Service = Dedup<S, K, F, D>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Dedup {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// The service created by [`DedupLayer`].
+pub struct Dedup<S, K, F, D> {
+    inner: S,
+    layer: DedupLayer<K, F, D>,
+}
+
+impl<S: Clone, K, F: Clone, D: Clone> Clone for Dedup<S, K, F, D> {
+    fn clone(&self) -> Self {
+        Dedup {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S: fmt::Debug, K, F, D> fmt::Debug for Dedup<S, K, F, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dedup")
+            .field("inner", &self.inner)
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+impl<S, K, F, D, R> Service<R> for Dedup<S, K, F, D>
+where
+    S: Service<R>,
+    K: Hash,
+    F: Fn(&R) -> Option<K>,
+    D: Fn(R) -> Result<S::Response, S::Error>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = DedupFuture<S::Future, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let duplicate =
+            (self.layer.key)(&request).is_some_and(|key| !self.layer.filter.check_and_insert(&key));
+        if duplicate {
+            DedupFuture::Duplicate(Some((self.layer.on_duplicate)(request)))
+        } else {
+            DedupFuture::Inner(self.inner.call(request))
+        }
+    }
+}
+
+/// The future returned by [`Dedup`]: the inner service's response, or the
+/// duplicate response.
+#[derive(Debug)]
+pub enum DedupFuture<F, R> {
+    /// The request was passed to the inner service.
+    Inner(F),
+    /// The request was a duplicate; taken when first polled.
+    Duplicate(Option<R>),
+}
+
+impl<F: Future<Output = R>, R> Future for DedupFuture<F, R> {
+    type Output = R;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        // SAFETY: the inner future is never moved out of the pinned enum,
+        // and the duplicate response is not structurally pinned.
+        match unsafe { self.get_unchecked_mut() } {
+            DedupFuture::Inner(future) => unsafe { Pin::new_unchecked(future) }.poll(cx),
+            DedupFuture::Duplicate(response) => Poll::Ready(
+                response
+                    .take()
+                    .expect("DedupFuture polled after completion"),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::{ready, Ready};
+    use std::task::Waker;
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Echoes requests, counting them.
+    struct Echo(usize);
+
+    impl Service<(Option<u64>, &'static str)> for Echo {
+        type Response = String;
+        type Error = String;
+        type Future = Ready<Result<String, String>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), String>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, (_, body): (Option<u64>, &'static str)) -> Self::Future {
+            self.0 += 1;
+            ready(Ok(body.to_owned()))
+        }
+    }
+
+    #[test]
+    fn test_duplicates_are_short_circuited() {
+        let filter = Arc::new(ExpiringBloomFilter::new(
+            1000,
+            0.001,
+            Duration::from_secs(60),
+        ));
+        let layer = DedupLayer::new(
+            filter,
+            |request: &(Option<u64>, &str)| request.0,
+            |request: (Option<u64>, &str)| Err(format!("duplicate {:?}", request.0)),
+        );
+        let mut service = layer.layer(Echo(0));
+
+        let mut call = |key, body| {
+            let mut future = std::pin::pin!(service.call((key, body)));
+            match future
+                .as_mut()
+                .poll(&mut Context::from_waker(Waker::noop()))
+            {
+                Poll::Ready(result) => result,
+                Poll::Pending => unreachable!(),
+            }
+        };
+        assert_eq!(call(Some(1), "a"), Ok("a".to_owned()));
+        assert_eq!(call(Some(2), "b"), Ok("b".to_owned()));
+        assert_eq!(call(Some(1), "c"), Err("duplicate Some(1)".to_owned()));
+        assert_eq!(call(None, "d"), Ok("d".to_owned()));
+        assert_eq!(call(None, "e"), Ok("e".to_owned()));
+        assert_eq!(service.inner.0, 4);
+    }
+}
@@ -0,0 +1,167 @@
+//! Rebuilding filters of other Bloom Filter crates as filters of this one,
+//! from their keys.
+//!
+//! No other crate's format is read or converted: this module only answers
+//! lookups from the old filter, through a closure calling that crate, while
+//! its keys are inserted into the new one.
+//!
+//! A filter's bits are only meaningful to the hashing scheme that set them.
+//! This crate hashes a key with SipHash-1-3 under zero keys through
+//! `DefaultHasher`, derives its k positions by double hashing and reduces
+//! them onto the bit count as recorded in [`compat`](crate::compat). Crates
+//! such as `bloomfilter`, `growable-bloom-filter` and
+//! `probabilistic-collections` each use schemes of their own, with their own
+//! hash functions, seeds and probe sequences, so none of their bit arrays
+//! can be reinterpreted as a [`BloomFilter`], even where the bit count and
+//! hash count match: lookups would miss inserted keys. Their serialized
+//! formats are also private to each crate and version.
+//!
+//! Migrating therefore keeps the existing filter readable through its own
+//! crate, which the caller depends on and loads, and rebuilds the new one
+//! from keys the caller supplies. [`MigratingFilter`] answers
+//! lookups from both while the keys are replayed, so a service can switch
+//! over before the rebuild has finished without any false negatives:
+//!
+//! | Source | Bits preserved | Migration |
+//! |---|---|---|
+//! | `bloomlib` filters written by [`BloomFilter::to_bytes`] | yes | [`BloomFilter::from_bytes`] |
+//! | `bloomfilter` | no | rebuild from keys |
+//! | `growable-bloom-filter` | no | rebuild from keys |
+//! | `probabilistic-collections` | no | rebuild from keys |
+//!
+//! # Examples
+//!
+//! ```
+//! use std::collections::HashSet;
+//! use bloomlib::migrate::MigratingFilter;
+//! use bloomlib::BloomFilter;
+//!
+//! // Stands in for a filter of another crate, loaded with that crate.
+//! let legacy: HashSet<String> = ["alice", "bob"].into_iter().map(String::from).collect();
+//!
+//! let mut migrating = MigratingFilter::new(
+//!     BloomFilter::<str>::new(1000, 0.01),
+//!     move |key: &str| legacy.contains(key),
+//! );
+//! // Found through the legacy filter before the keys are replayed.
+//! assert!(migrating.contains("bob"));
+//!
+//! migrating.replay(["alice", "bob"]);
+//! let filter = migrating.finish();
+//! assert!(filter.contains("bob"));
+//! ```
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::{BitStore, BloomFilter};
+
+/// A filter being rebuilt from keys, which keeps answering lookups from the
+/// filter it replaces until the rebuild is finished.
+///
+/// `legacy` is the lookup of the filter being replaced, e.g. a closure
+/// calling `check` on a filter of another crate, loaded with that crate;
+/// nothing here reads its bytes. Lookups return `true` if
+/// either filter might contain the item, so the false positive rate during
+/// the migration is up to the sum of both rates. Inserts go to the new
+/// filter only; [`replay`](Self::replay) the keys of the old one, then
+/// [`finish`](Self::finish) to drop it.
+pub struct MigratingFilter<T: ?Sized, L, B = Vec<u64>> {
+    filter: BloomFilter<T, B>,
+    legacy: L,
+    replayed: u64,
+}
+
+impl<T, L, B> MigratingFilter<T, L, B>
+where
+    T: ?Sized + Hash,
+    L: Fn(&T) -> bool,
+    B: BitStore,
+{
+    /// Starts migrating from `legacy` into `filter`, which should be sized
+    /// for every key of the old filter and the keys still to come.
+    pub fn new(filter: BloomFilter<T, B>, legacy: L) -> Self {
+        MigratingFilter {
+            filter,
+            legacy,
+            replayed: 0,
+        }
+    }
+
+    /// Inserts an item into the new filter.
+    pub fn insert(&mut self, item: &T) {
+        self.filter.insert(item);
+    }
+
+    /// Inserts keys of the old filter into the new one, and returns how
+    /// many. It can be called several times, e.g. once per batch read from
+    /// the source of the keys.
+    pub fn replay<I>(&mut self, keys: I) -> u64
+    where
+        I: IntoIterator,
+        I::Item: Borrow<T>,
+    {
+        let mut count = 0;
+        for key in keys {
+            self.filter.insert(key.borrow());
+            count += 1;
+        }
+        self.replayed += count;
+        count
+    }
+
+    /// Checks if an item might be in the new filter or the old one.
+    pub fn contains(&self, item: &T) -> bool {
+        self.filter.contains(item) || (self.legacy)(item)
+    }
+
+    /// Returns the number of keys replayed so far.
+    pub fn replayed(&self) -> u64 {
+        self.replayed
+    }
+
+    /// Returns the new filter.
+    pub fn filter(&self) -> &BloomFilter<T, B> {
+        &self.filter
+    }
+
+    /// Ends the migration once every key of the old filter has been
+    /// replayed, dropping the old filter and returning the new one.
+    pub fn finish(self) -> BloomFilter<T, B> {
+        self.filter
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, L, B: fmt::Debug> fmt::Debug for MigratingFilter<T, L, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MigratingFilter")
+            .field("filter", &self.filter)
+            .field("replayed", &self.replayed)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives_during_migration() {
+        // A filter with a different hashing scheme, as another crate's would be.
+        let legacy_keys: Vec<u64> = (0..1000).collect();
+        let legacy = |key: &u64| legacy_keys.binary_search(key).is_ok();
+        let mut migrating = MigratingFilter::new(BloomFilter::<u64>::new(2000, 0.01), legacy);
+
+        for batch in legacy_keys.chunks(100) {
+            assert!(legacy_keys.iter().all(|key| migrating.contains(key)));
+            migrating.replay(batch);
+            migrating.insert(&(5000 + batch[0]));
+        }
+        assert_eq!(migrating.replayed(), 1000);
+        assert!(migrating.contains(&5000));
+
+        let filter = migrating.finish();
+        assert!(legacy_keys.iter().all(|key| filter.contains(key)));
+    }
+}
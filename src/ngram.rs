@@ -0,0 +1,266 @@
+//! Byte n-grams hashed with a rolling hash.
+//!
+//! Substring prefilters, e.g. for near-duplicate or plagiarism detection,
+//! store every n-byte window (n-gram, or shingle) of a corpus and look up the
+//! windows of a candidate text. [`rolling_hashes`] hashes the windows of a
+//! byte slice Rabin–Karp style: a polynomial hash modulo the prime
+//! 2^61 - 1 that is updated in constant time as the window slides, whatever
+//! `n` is, instead of rehashing each window from scratch. The hash of a
+//! window depends only on its bytes, so the same n-gram hashes the same
+//! wherever, and in whichever input, it occurs.
+//!
+//! [`insert`] and [`query`] apply this to a `BloomFilter<u64>`. To process a
+//! stream in chunks, overlap consecutive chunks by `n - 1` bytes so that no
+//! window is lost.
+//!
+//! # Examples
+//!
+//! ```
+//! use bloomlib::{ngram, BloomFilter};
+//!
+//! let mut corpus: BloomFilter<u64> = BloomFilter::new(10_000, 0.001);
+//! ngram::insert(&mut corpus, b"the quick brown fox jumps over the lazy dog", 8);
+//!
+//! let copied = ngram::query(&corpus, b"a quick brown fox jumps", 8);
+//! assert!(copied.fraction() > 0.8);
+//! let original = ngram::query(&corpus, b"an entirely different text", 8);
+//! assert!(original.fraction() < 0.2);
+//! ```
+
+use std::slice;
+
+use crate::{BitStore, BloomFilter};
+
+/// The Mersenne prime 2^61 - 1, the modulus of the hash.
+const MODULUS: u64 = (1 << 61) - 1;
+
+/// The base of the polynomial, a fixed residue so that hashes agree across
+/// processes.
+const BASE: u64 = 0x0d6e_8fed_1f0e_56a1;
+
+/// The number of n-grams [`insert`] hashes together.
+const BATCH_LEN: usize = 256;
+
+/// Returns `a * b` modulo 2^61 - 1, for `a, b < 2^61`.
+#[inline]
+fn mul_mod(a: u64, b: u64) -> u64 {
+    let product = a as u128 * b as u128;
+    let sum = (product as u64 & MODULUS) + (product >> 61) as u64;
+    if sum >= MODULUS {
+        sum - MODULUS
+    } else {
+        sum
+    }
+}
+
+/// Returns `a + b` modulo 2^61 - 1, for `a, b < 2^61 - 1`.
+#[inline]
+fn add_mod(a: u64, b: u64) -> u64 {
+    let sum = a + b;
+    if sum >= MODULUS {
+        sum - MODULUS
+    } else {
+        sum
+    }
+}
+
+/// Returns `a - b` modulo 2^61 - 1, for `a, b < 2^61 - 1`.
+#[inline]
+fn sub_mod(a: u64, b: u64) -> u64 {
+    if a >= b {
+        a - b
+    } else {
+        a + MODULUS - b
+    }
+}
+
+/// Returns the digit a byte contributes: 1 to 256, so that zero bytes count.
+#[inline]
+fn digit(byte: u8) -> u64 {
+    byte as u64 + 1
+}
+
+/// Returns the hash of a single n-gram, as [`rolling_hashes`] computes it
+/// for a window of the same bytes.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::ngram;
+///
+/// let hashes: Vec<u64> = ngram::rolling_hashes(b"abcab", 2).collect();
+/// assert_eq!(hashes[0], ngram::hash(b"ab"));
+/// assert_eq!(hashes[3], ngram::hash(b"ab"));
+/// ```
+pub fn hash(ngram: &[u8]) -> u64 {
+    ngram
+        .iter()
+        .fold(0, |hash, &byte| add_mod(mul_mod(hash, BASE), digit(byte)))
+}
+
+/// Returns the hashes of all `n`-byte windows of `data`, in order.
+///
+/// # Panics
+///
+/// Panics if `n` is 0.
+pub fn rolling_hashes(data: &[u8], n: usize) -> RollingHashes<'_> {
+    assert!(n > 0, "n must be greater than 0.");
+    // BASE^(n - 1), the weight of the byte leaving the window.
+    let mut leading_weight = 1;
+    for _ in 1..n {
+        leading_weight = mul_mod(leading_weight, BASE);
+    }
+    RollingHashes {
+        incoming: data.iter(),
+        outgoing: data.iter(),
+        n,
+        leading_weight,
+        hash: 0,
+        filled: 0,
+    }
+}
+
+/// An iterator over the rolling hashes of the n-grams of a byte slice,
+/// created by [`rolling_hashes`].
+#[derive(Debug, Clone)]
+pub struct RollingHashes<'a> {
+    /// The bytes still to enter the window.
+    incoming: slice::Iter<'a, u8>,
+    /// The bytes still to leave the window, `n` behind `incoming` once it
+    /// is full.
+    outgoing: slice::Iter<'a, u8>,
+    n: usize,
+    leading_weight: u64,
+    /// The hash of the current window.
+    hash: u64,
+    /// The number of bytes in the window, up to `n`.
+    filled: usize,
+}
+
+impl Iterator for RollingHashes<'_> {
+    type Item = u64;
+
+    #[inline]
+    fn next(&mut self) -> Option<u64> {
+        while self.filled < self.n {
+            let &byte = self.incoming.next()?;
+            self.hash = add_mod(mul_mod(self.hash, BASE), digit(byte));
+            self.filled += 1;
+            if self.filled == self.n {
+                return Some(self.hash);
+            }
+        }
+        let &byte = self.incoming.next()?;
+        let &leaving = self.outgoing.next().expect("the window trails the input");
+        let hash = sub_mod(self.hash, mul_mod(digit(leaving), self.leading_weight));
+        self.hash = add_mod(mul_mod(hash, BASE), digit(byte));
+        Some(self.hash)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // A full window has already been returned.
+        let windows = if self.filled == self.n {
+            self.incoming.len()
+        } else {
+            (self.filled + self.incoming.len() + 1).saturating_sub(self.n)
+        };
+        (windows, Some(windows))
+    }
+}
+
+impl ExactSizeIterator for RollingHashes<'_> {}
+
+/// The result of [`query`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NgramHits {
+    /// The number of n-grams the filter reported as present.
+    pub present: usize,
+    /// The number of n-grams looked up.
+    pub total: usize,
+}
+
+impl NgramHits {
+    /// Returns the fraction of n-grams present, or 0 if the data had none.
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.present as f64 / self.total as f64
+        }
+    }
+}
+
+/// Inserts the hashes of the `n`-grams of `data`, returning how many there
+/// were.
+///
+/// The hashes are inserted in batches through
+/// [`BloomFilter::insert_keys`], from a buffer on the stack.
+///
+/// # Panics
+///
+/// Panics if `n` is 0.
+pub fn insert<B: BitStore>(filter: &mut BloomFilter<u64, B>, data: &[u8], n: usize) -> usize {
+    let mut hashes = rolling_hashes(data, n);
+    let mut batch = [0; BATCH_LEN];
+    let mut count = 0;
+    loop {
+        let len = batch
+            .iter_mut()
+            .zip(hashes.by_ref())
+            .map(|(slot, hash)| *slot = hash)
+            .count();
+        filter.insert_keys(&batch[..len]);
+        count += len;
+        if len < BATCH_LEN {
+            return count;
+        }
+    }
+}
+
+/// Looks up the `n`-grams of `data`, counting those present.
+///
+/// # Panics
+///
+/// Panics if `n` is 0.
+pub fn query<B: BitStore>(filter: &BloomFilter<u64, B>, data: &[u8], n: usize) -> NgramHits {
+    rolling_hashes(data, n).fold(NgramHits::default(), |hits, hash| NgramHits {
+        present: hits.present + filter.contains(&hash) as usize,
+        total: hits.total + 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_hashes_match_direct() {
+        let data: Vec<u8> = (0..500u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .chain([0, 0, 0, 255, 255, 255])
+            .collect();
+        for n in [1, 2, 5, 64, 506] {
+            let expected: Vec<u64> = data.windows(n).map(hash).collect();
+            let hashes = rolling_hashes(&data, n);
+            assert_eq!(hashes.len(), expected.len());
+            assert_eq!(hashes.collect::<Vec<_>>(), expected, "n = {}", n);
+        }
+        assert_eq!(rolling_hashes(b"abc", 4).len(), 0);
+        assert_eq!(rolling_hashes(b"abc", 4).next(), None);
+        assert_ne!(hash(b"\0a"), hash(b"a"));
+    }
+
+    #[test]
+    fn test_insert_and_query() {
+        let corpus: Vec<u8> = (0..5000u32)
+            .map(|i| b'a' + (i.wrapping_mul(2_654_435_761) >> 28) as u8)
+            .collect();
+        let mut filter: BloomFilter<u64> = BloomFilter::new(5000, 0.001);
+        assert_eq!(insert(&mut filter, &corpus, 12), 5000 - 11);
+
+        let hits = query(&filter, &corpus[1000..1100], 12);
+        assert_eq!((hits.present, hits.total), (89, 89));
+        assert_eq!(query(&filter, b"short", 12), NgramHits::default());
+        assert!(query(&filter, b"zzzzzzzzzzzzzzzzzzzz", 12).fraction() < 0.1);
+    }
+}
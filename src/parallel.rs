@@ -0,0 +1,182 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::mem;
+use std::slice;
+use std::sync::atomic::AtomicU64;
+
+use rayon::iter::{IntoParallelIterator, ParallelExtend, ParallelIterator};
+use rayon::slice::ParallelSlice;
+
+use crate::{atomic, hashing, trace, BitStore, BloomFilter, FilterParams};
+
+/// The number of items each rayon task checks in one prefetching batch.
+const PAR_BATCH_LEN: usize = 1024;
+
+impl<T: ?Sized + Hash> BloomFilter<T> {
+    /// Creates a new Bloom Filter and inserts all items of a parallel iterator.
+    ///
+    /// Requires the `rayon` feature. See [`BloomFilter::new`] for the sizing
+    /// arguments and `par_extend` for how items are inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    /// use rayon::prelude::*;
+    ///
+    /// let keys: Vec<u64> = (0..10_000).collect();
+    /// let bf = BloomFilter::from_par_iter(keys.len(), 0.01, keys.par_iter());
+    /// assert!(bf.contains(&42));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn from_par_iter<I>(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+        items: I,
+    ) -> Self
+    where
+        Self: ParallelExtend<I::Item>,
+        I: IntoParallelIterator,
+    {
+        let mut filter = Self::new(expected_items, params);
+        filter.par_extend(items);
+        filter
+    }
+}
+
+impl<T: ?Sized + Hash + Sync, B: BitStore + Sync> BloomFilter<T, B> {
+    /// Checks a batch of items on all rayon worker threads.
+    ///
+    /// Requires the `rayon` feature. Returns one result per item, in input
+    /// order, with the same meaning as [`BloomFilter::contains`]. The batch is
+    /// split across threads by rayon, so callers need no chunking of their own;
+    /// each thread runs its share through the prefetching
+    /// [`BloomFilter::contains_batch`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut bf: BloomFilter<str> = BloomFilter::new(100, 0.01);
+    /// bf.insert("seen");
+    ///
+    /// let results = bf.par_contains_batch(&["seen", "unseen"]);
+    /// assert_eq!(results, vec![true, false]);
+    /// ```
+    pub fn par_contains_batch<Q>(&self, items: &[Q]) -> Vec<bool>
+    where
+        Q: Borrow<T> + Sync,
+    {
+        items
+            .par_chunks(PAR_BATCH_LEN)
+            .flat_map_iter(|chunk| self.contains_batch(chunk))
+            .collect()
+    }
+}
+
+impl<T: ?Sized> BloomFilter<T> {
+    /// Returns the bit array as atomic words, or `None` if it is not aligned
+    /// for atomic access, as it need not be on some 32-bit targets.
+    ///
+    /// Every rayon worker then writes into the one array in place with
+    /// `fetch_or`, rather than into a copy: moving it into an
+    /// [`AtomicBloomFilter`](crate::AtomicBloomFilter) would allocate a second array, and building
+    /// (and later ORing) one per worker would multiply peak memory by the
+    /// thread count. A panicking worker leaves the bits set so far.
+    fn atomic_words(&mut self) -> Option<&[AtomicU64]> {
+        let words = self.bit_vec.as_mut_slice();
+        if words.as_ptr().align_offset(mem::align_of::<AtomicU64>()) != 0 {
+            return None;
+        }
+        // SAFETY: `AtomicU64` has the size and bit validity of `u64`, the
+        // words are aligned for it, and the view borrows `self` exclusively,
+        // so the words are accessed only through it while it lives.
+        Some(unsafe { slice::from_raw_parts(words.as_mut_ptr().cast(), words.len()) })
+    }
+}
+
+impl<'a, T: ?Sized + Hash + Sync + 'a> ParallelExtend<&'a T> for BloomFilter<T> {
+    fn par_extend<I>(&mut self, items: I)
+    where
+        I: IntoParallelIterator<Item = &'a T>,
+    {
+        trace::timed_span!(
+            "bloom.par_extend",
+            bits = self.bit_count,
+            hashes = self.hash_fn_count
+        );
+        let (bit_count, hash_count) = (self.bit_count, self.hash_fn_count);
+        let inserted = match self.atomic_words() {
+            Some(words) => items
+                .into_par_iter()
+                .map(|item| {
+                    let (h1, h2) = hashing::hash_pair(item);
+                    atomic::insert_hashed(words, bit_count, hash_count, h1, h2);
+                })
+                .count(),
+            // Only the hashing runs in parallel.
+            None => {
+                let hashes: Vec<(u64, u64)> =
+                    items.into_par_iter().map(hashing::hash_pair).collect();
+                for &(h1, h2) in &hashes {
+                    self.insert_hashes(h1, h2);
+                }
+                hashes.len()
+            }
+        };
+        self.counters.record_inserts(inserted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::iter::IntoParallelRefIterator;
+
+    #[test]
+    fn test_par_extend_matches_sequential() {
+        let keys: Vec<u64> = (0..20_000).collect();
+
+        let mut sequential = BloomFilter::new(keys.len(), 0.01);
+        for key in &keys {
+            sequential.insert(key);
+        }
+
+        let mut parallel = BloomFilter::new(keys.len(), 0.01);
+        parallel.insert(&keys[0]);
+        let words = parallel.bit_vec.as_ptr();
+        parallel.par_extend(keys[1..].par_iter());
+        assert_eq!(parallel.bit_vec, sequential.bit_vec);
+        // The bits were set in place.
+        assert_eq!(parallel.bit_vec.as_ptr(), words);
+    }
+
+    #[test]
+    fn test_from_par_iter_unsized() {
+        let words = vec!["alpha", "beta", "gamma"];
+        let bf: BloomFilter<str> =
+            BloomFilter::from_par_iter(words.len(), 0.01, words.par_iter().copied());
+        assert!(bf.contains("beta"));
+        assert!(!bf.contains("delta"));
+    }
+
+    #[test]
+    fn test_par_contains_batch_preserves_order() {
+        let mut bf = BloomFilter::new(1000, 0.001);
+        for i in (0..1000u64).step_by(2) {
+            bf.insert(&i);
+        }
+
+        let queries: Vec<u64> = (0..1000).collect();
+        let results = bf.par_contains_batch(&queries);
+        assert_eq!(results.len(), queries.len());
+        for (query, result) in queries.iter().zip(&results) {
+            assert_eq!(*result, bf.contains(query));
+        }
+        assert!(results.iter().step_by(2).all(|&r| r));
+    }
+}
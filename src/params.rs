@@ -0,0 +1,314 @@
+//! Filter sizing.
+//!
+//! [`size`] computes the bit count, hash count and memory a filter built
+//! with [`BloomFilter::new`](crate::BloomFilter::new) will have, without
+//! allocating it. [`size_for_memory`] answers the inverse question: how
+//! accurate a filter of a given size can be for a given number of items.
+//!
+//! # Examples
+//!
+//! ```
+//! use bloomlib::params;
+//!
+//! let sizing = params::size(1_000_000, 0.01).unwrap();
+//! assert_eq!(sizing.hash_count, 7);
+//! assert!(sizing.memory_bytes() < 1_200_000);
+//!
+//! let budget = params::size_for_memory(1_000_000, 1 << 20).unwrap();
+//! assert!(budget.false_positive_rate < 0.02);
+//! ```
+
+use crate::{BloomError, Word};
+
+/// The false positive rate of filters built by conversions that take no
+/// parameters, such as `BloomFilter::from(&HashSet<T>)`.
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Configuration parameter for creating a Bloom Filter.
+///
+/// Specify either the desired false positive rate (f64)
+/// or the specific number of hash functions (u32) to use.
+#[derive(Debug, Clone, Copy)]
+pub enum FilterParams {
+    /// Target false positive rate (between 0.0 and 1.0).
+    /// The filter will calculate optimal bits (m) and hashes (k).
+    FalsePositiveRate(f64),
+    /// Target number of hash functions (k).
+    /// The filter will calculate the optimal bits (m) to satisfy the
+    /// 50% fill rate assumption for this k (p = 2^-k).
+    HashCount(u32),
+}
+
+impl From<f64> for FilterParams {
+    fn from(rate: f64) -> Self {
+        FilterParams::FalsePositiveRate(rate)
+    }
+}
+
+impl From<u32> for FilterParams {
+    fn from(hashes: u32) -> Self {
+        FilterParams::HashCount(hashes)
+    }
+}
+
+/// The size and predicted accuracy of a filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sizing {
+    /// The number of bits (m).
+    pub bit_count: u64,
+    /// The number of hash functions (k).
+    pub hash_count: u32,
+    /// The false positive rate predicted once the expected items are
+    /// inserted, `(1 - e^(-kn/m))^k`.
+    pub false_positive_rate: f64,
+}
+
+impl Sizing {
+    /// The memory taken by the bit array, in bytes.
+    pub fn memory_bytes(&self) -> u64 {
+        self.bit_count.div_ceil(8)
+    }
+}
+
+/// Returns the size of the filter [`BloomFilter::new`](crate::BloomFilter::new)
+/// builds for `expected_items` and `params`.
+///
+/// # Errors
+///
+/// Returns the same errors as [`BloomFilter::try_new`](crate::BloomFilter::try_new).
+pub fn size(expected_items: usize, params: impl Into<FilterParams>) -> Result<Sizing, BloomError> {
+    let geometry = Geometry::optimal::<u64>(expected_items, params.into())?;
+    Ok(Sizing {
+        bit_count: geometry.bit_count,
+        hash_count: geometry.hash_count,
+        false_positive_rate: false_positive_rate(
+            geometry.bit_count,
+            geometry.hash_count,
+            expected_items as u64,
+        ),
+    })
+}
+
+/// Returns the most accurate filter for `expected_items` that fits in
+/// `memory_bytes`.
+///
+/// The bit count is the budget rounded down to whole `u64` words, and the
+/// hash count the one that minimizes the false positive rate for it,
+/// `k = (m / n) ln 2` rounded to the nearest integer.
+///
+/// # Errors
+///
+/// Returns [`BloomError::ZeroExpectedItems`] if `expected_items` is 0, and
+/// [`BloomError::InsufficientMemory`] if the budget is less than one word.
+pub fn size_for_memory(expected_items: usize, memory_bytes: u64) -> Result<Sizing, BloomError> {
+    if expected_items == 0 {
+        return Err(BloomError::ZeroExpectedItems);
+    }
+    let bit_count = (memory_bytes / 8).saturating_mul(64);
+    if bit_count == 0 {
+        return Err(BloomError::InsufficientMemory(memory_bytes));
+    }
+    let optimal = bit_count as f64 / expected_items as f64 * std::f64::consts::LN_2;
+    let hash_count = (optimal.round() as u32).max(1);
+    Ok(Sizing {
+        bit_count,
+        hash_count,
+        false_positive_rate: false_positive_rate(bit_count, hash_count, expected_items as u64),
+    })
+}
+
+/// Returns the false positive rate predicted for a filter of `bit_count`
+/// bits and `hash_count` hashes holding `items` distinct items,
+/// `(1 - e^(-kn/m))^k`.
+pub fn false_positive_rate(bit_count: u64, hash_count: u32, items: u64) -> f64 {
+    let (m, k) = (bit_count as f64, hash_count as f64);
+    (1.0 - (-k * items as f64 / m).exp()).powf(k)
+}
+
+/// Returns the fewest of a filter's `hash_count` probes that keep its false
+/// positive rate at or below `fp_rate` while `fill_ratio` of its bits are
+/// set, `ceil(ln(fp_rate) / ln(fill_ratio))` clamped to `1..=hash_count`.
+///
+/// Testing only the first probes of an item cannot miss an inserted item,
+/// since all of its bits are set; it only raises the false positive rate,
+/// to `fill_ratio` to the power of the probes. A filter far below its
+/// design fill, e.g. just after a rotation, meets its target with fewer
+/// probes, and lookups get cheaper accordingly.
+///
+/// # Panics
+///
+/// Panics if `fp_rate` is not between 0.0 and 1.0, exclusive.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::params;
+///
+/// assert_eq!(params::probes_for_fill(0.05, 7, 0.01), 2);
+/// assert_eq!(params::probes_for_fill(0.5, 7, 0.01), 7);
+/// ```
+pub fn probes_for_fill(fill_ratio: f64, hash_count: u32, fp_rate: f64) -> u32 {
+    assert!(
+        fp_rate > 0.0 && fp_rate < 1.0,
+        "False positive rate must be between 0.0 and 1.0, exclusive."
+    );
+    let probes = (fp_rate.ln() / fill_ratio.ln()).ceil();
+    if probes.is_nan() {
+        return hash_count;
+    }
+    (probes.min(hash_count as f64) as u32).clamp(1, hash_count.max(1))
+}
+
+/// The sized bit array and hash count of a filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Geometry {
+    /// The number of storage words.
+    pub(crate) word_count: usize,
+    /// The total number of bits (m), a whole number of words unless read
+    /// back from a serialized filter.
+    pub(crate) bit_count: u64,
+    /// The number of hash functions (k).
+    pub(crate) hash_count: u32,
+}
+
+impl Geometry {
+    /// Computes the optimal geometry for `expected_items` stored in words of type `W`.
+    pub(crate) fn optimal<W: Word>(
+        expected_items: usize,
+        params: FilterParams,
+    ) -> Result<Geometry, BloomError> {
+        if expected_items == 0 {
+            return Err(BloomError::ZeroExpectedItems);
+        }
+
+        let ln2 = std::f64::consts::LN_2;
+
+        let (m, k) = match params {
+            FilterParams::FalsePositiveRate(p) => {
+                if !(p > 0.0 && p < 1.0) {
+                    return Err(BloomError::InvalidFalsePositiveRate(p));
+                }
+                // m = - (n * ln(p)) / (ln(2)^2)
+                let numerator = -(expected_items as f64) * p.ln();
+                let denominator = ln2 * ln2;
+                let m = (numerator / denominator).ceil();
+
+                // k = (m / n) * ln(2)
+                let k = ((m / expected_items as f64) * ln2).ceil() as u32;
+                (m, k)
+            }
+            FilterParams::HashCount(k) => {
+                if k == 0 {
+                    return Err(BloomError::ZeroHashCount);
+                }
+                // If k is fixed, assume optimal fill rate (50%), where p = 2^-k.
+                // Derived from k = (m/n) * ln(2) -> m = (k * n) / ln(2)
+                let m = ((k as f64 * expected_items as f64) / ln2).ceil();
+                (m, k)
+            }
+        };
+
+        if m >= u64::MAX as f64 {
+            return Err(overflow::<W>(u64::MAX));
+        }
+        Self::with_bits::<W>(m as u64, k)
+    }
+
+    /// Rounds the bit count up to a power of two, so that probe indices can
+    /// be reduced with a mask instead of a multiply-shift. The hash count is kept,
+    /// which only lowers the false positive rate.
+    pub(crate) fn to_power_of_two<W: Word>(self) -> Result<Geometry, BloomError> {
+        let bits = self
+            .bit_count
+            .checked_next_power_of_two()
+            .ok_or(overflow::<W>(self.bit_count))?;
+        Self::with_bits::<W>(bits, self.hash_count)
+    }
+
+    /// Returns the geometry of a filter of exactly `bit_count` bits, as read
+    /// back from a serialized filter.
+    pub(crate) fn exact<W: Word>(bit_count: u64, hash_count: u32) -> Result<Geometry, BloomError> {
+        if hash_count == 0 {
+            return Err(BloomError::ZeroHashCount);
+        }
+        if bit_count == 0 {
+            return Err(BloomError::InvalidFormat("the bit count is 0"));
+        }
+        let num_words = bit_count.div_ceil(W::BITS as u64);
+        if num_words > max_words::<W>() {
+            return Err(overflow::<W>(bit_count));
+        }
+
+        Ok(Geometry {
+            word_count: num_words as usize,
+            bit_count,
+            hash_count,
+        })
+    }
+
+    /// Sizes storage for at least `m` bits and `k` hashes.
+    fn with_bits<W: Word>(m: u64, k: u32) -> Result<Geometry, BloomError> {
+        // Round up m to the nearest multiple of the word size for valid storage,
+        // doing all arithmetic in u64 so nothing truncates on 32-bit targets.
+        let word_bits = W::BITS as u64;
+        let num_words = m.div_ceil(word_bits);
+        if num_words > max_words::<W>() {
+            return Err(overflow::<W>(m));
+        }
+
+        // Recalculate true bit count based on vector size
+        let bit_count = num_words.checked_mul(word_bits).ok_or(overflow::<W>(m))?;
+
+        Ok(Geometry {
+            word_count: num_words as usize,
+            bit_count,
+            hash_count: k,
+        })
+    }
+}
+
+/// The most words of type `W` a single allocation can hold.
+fn max_words<W: Word>() -> u64 {
+    (isize::MAX as u64) / std::mem::size_of::<W>() as u64
+}
+
+/// The error for a filter of `bits` bits that cannot be allocated.
+fn overflow<W: Word>(bits: u64) -> BloomError {
+    BloomError::CapacityOverflow {
+        bits,
+        max_bits: max_words::<W>().saturating_mul(W::BITS as u64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BloomFilter;
+
+    #[test]
+    fn test_size_matches_filter() {
+        let sizing = size(10_000, 0.001).unwrap();
+        let filter: BloomFilter<u64> = BloomFilter::new(10_000, 0.001);
+        assert_eq!(sizing.bit_count, filter.bit_count());
+        assert_eq!(sizing.hash_count, filter.hash_count());
+        assert!(sizing.false_positive_rate < 0.0011);
+        assert_eq!(size(0, 0.01), Err(BloomError::ZeroExpectedItems));
+    }
+
+    #[test]
+    fn test_size_for_memory() {
+        let sizing = size_for_memory(10_000, 12_000).unwrap();
+        assert_eq!(sizing.bit_count, 96_000);
+        assert_eq!(sizing.hash_count, 7);
+        assert!(sizing.memory_bytes() <= 12_000);
+
+        // The optimal hash count beats its neighbours.
+        for k in [sizing.hash_count - 1, sizing.hash_count + 1] {
+            assert!(false_positive_rate(96_000, k, 10_000) > sizing.false_positive_rate);
+        }
+        assert_eq!(
+            size_for_memory(10_000, 7),
+            Err(BloomError::InsufficientMemory(7))
+        );
+    }
+}
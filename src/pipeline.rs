@@ -0,0 +1,127 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::{hashing, trace, BitStore, BloomFilter};
+
+/// The number of items a hashing thread takes at a time.
+const BATCH_LEN: usize = 1024;
+
+impl<T: ?Sized + Hash, B: BitStore> BloomFilter<T, B> {
+    /// Inserts every item of `items`, hashing them on `threads` worker
+    /// threads while the calling thread alone sets the bits.
+    ///
+    /// One thread reads `items` and hands them out in batches; the workers
+    /// hash each batch and pass the hash pairs to the calling thread, which
+    /// inserts them. Both hand-offs go through bounded channels, so only a
+    /// few batches are in flight however long the input is. For items that
+    /// are expensive to hash, such as long strings, this parallelizes the
+    /// hashing without sharing the bit array, so it works with every store
+    /// and gives the same bits as inserting the items one by one. Batches
+    /// are inserted in the order they are hashed, not read.
+    ///
+    /// Cheap items are faster to insert directly; with the `rayon` feature,
+    /// `par_extend` also parallelizes setting the bits of a `Vec<u64>`
+    /// store.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let urls = (0..10_000).map(|i| format!("https://example.com/articles/{}", i));
+    /// let mut seen: BloomFilter<str> = BloomFilter::new(10_000, 0.01);
+    /// seen.extend_pipelined(urls, 4);
+    /// assert!(seen.contains("https://example.com/articles/42"));
+    /// ```
+    pub fn extend_pipelined<I>(&mut self, items: I, threads: usize)
+    where
+        I: IntoIterator,
+        I::IntoIter: Send,
+        I::Item: Borrow<T> + Send,
+    {
+        assert!(threads > 0, "Threads must be greater than 0.");
+        trace::timed_span!(
+            "bloom.extend_pipelined",
+            bits = self.bit_count,
+            hashes = self.hash_fn_count,
+            threads = threads
+        );
+        let items = items.into_iter();
+        let (batch_tx, batch_rx) = mpsc::sync_channel::<Vec<I::Item>>(2 * threads);
+        let (hash_tx, hash_rx) = mpsc::sync_channel::<Vec<(u64, u64)>>(2 * threads);
+        let batch_rx = Mutex::new(batch_rx);
+
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                let mut items = items;
+                loop {
+                    let batch: Vec<I::Item> = items.by_ref().take(BATCH_LEN).collect();
+                    // The workers are gone only if one panicked.
+                    if batch.is_empty() || batch_tx.send(batch).is_err() {
+                        break;
+                    }
+                }
+            });
+            for _ in 0..threads {
+                let (batch_rx, hash_tx) = (&batch_rx, hash_tx.clone());
+                scope.spawn(move || {
+                    while let Some(batch) = next_batch(batch_rx) {
+                        let hashes = batch
+                            .iter()
+                            .map(|item| hashing::hash_pair(item.borrow()))
+                            .collect();
+                        if hash_tx.send(hashes).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            // The channel closes once every worker has dropped its sender.
+            drop(hash_tx);
+            for hashes in hash_rx {
+                for &(h1, h2) in &hashes {
+                    self.insert_hashes(h1, h2);
+                }
+                self.counters.record_inserts(hashes.len());
+            }
+        });
+    }
+}
+
+/// Takes the next batch, or `None` once the reader has finished.
+fn next_batch<U>(batches: &Mutex<Receiver<U>>) -> Option<U> {
+    // A worker that panicked holding the lock left the receiver intact.
+    let batches = batches
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    batches.recv().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_matches_sequential() {
+        let keys: Vec<String> = (0..50_000).map(|i| format!("key-{}", i)).collect();
+        let mut sequential: BloomFilter<str> = BloomFilter::new(keys.len(), 0.01);
+        keys.iter().for_each(|key| sequential.insert(key));
+
+        for threads in [1, 3, 8] {
+            let mut pipelined: BloomFilter<str> = BloomFilter::new(keys.len(), 0.01);
+            pipelined.extend_pipelined(keys.iter().cloned(), threads);
+            assert_eq!(pipelined.bit_vec, sequential.bit_vec);
+        }
+
+        let mut narrow = BloomFilter::<str, Vec<u8>>::from_params(keys.len(), 0.01);
+        narrow.extend_pipelined(keys.iter().map(String::as_str), 2);
+        assert!(keys.iter().all(|key| narrow.contains(key)));
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::counters::OpCounters;
+use crate::params::Geometry;
+use crate::{AllocStore, BloomError, BloomFilter, FilterParams};
+
+/// A pool of cleared filters of one geometry, for services that replace
+/// their filters regularly.
+///
+/// [`take`](Self::take) hands out an empty filter, reusing a returned one
+/// when there is one, and [`recycle`](Self::recycle) clears a filter that is
+/// no longer needed and keeps it for the next `take`. Rotating large filters
+/// through a pool avoids allocating (and having the OS zero) new pages every
+/// epoch; clearing happens on the thread that recycles, off the path that
+/// takes the replacement. At most `max_idle` filters are kept.
+///
+/// The pool can be shared between threads; `take` and `recycle` only hold
+/// its lock to move a filter in or out.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::FilterPool;
+///
+/// let pool: FilterPool<str> = FilterPool::new(1_000_000, 0.01, 2);
+/// let mut current = pool.take();
+/// current.insert("seen");
+///
+/// // At the end of the epoch, swap in a fresh filter.
+/// let previous = std::mem::replace(&mut current, pool.take());
+/// pool.recycle(previous).unwrap();
+/// assert_eq!(pool.idle_count(), 1);
+/// assert!(!pool.take().contains("seen"));
+/// ```
+pub struct FilterPool<T: ?Sized, B = Vec<u64>> {
+    geometry: Geometry,
+    /// Cleared filters ready to be taken.
+    idle: Mutex<Vec<BloomFilter<T, B>>>,
+    max_idle: usize,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: ?Sized + Hash, B: AllocStore> FilterPool<T, B> {
+    /// Creates an empty pool of filters sized like
+    /// [`BloomFilter::new`]`(expected_items, params)`, keeping at most
+    /// `max_idle` filters between uses.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn new(expected_items: usize, params: impl Into<FilterParams>, max_idle: usize) -> Self {
+        Self::try_new(expected_items, params, max_idle).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a pool, returning an error instead of panicking.
+    pub fn try_new(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+        max_idle: usize,
+    ) -> Result<Self, BloomError> {
+        Ok(FilterPool {
+            geometry: Geometry::optimal::<B::Word>(expected_items, params.into())?,
+            idle: Mutex::new(Vec::with_capacity(max_idle)),
+            max_idle,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns an empty filter, reusing an idle one if there is one.
+    pub fn take(&self) -> BloomFilter<T, B> {
+        if let Some(filter) = self.lock().pop() {
+            return filter;
+        }
+        BloomFilter {
+            bit_vec: B::zeroed(self.geometry.word_count),
+            bit_count: self.geometry.bit_count,
+            hash_fn_count: self.geometry.hash_count,
+            counters: OpCounters::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Clears `filter` and keeps it for a later [`take`](Self::take), or
+    /// drops it if `max_idle` filters are already kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::IncompatibleFilters`] if `filter` does not have
+    /// the pool's bit count and hash count; it is dropped.
+    pub fn recycle(&self, mut filter: BloomFilter<T, B>) -> Result<(), BloomError> {
+        let expected = (self.geometry.bit_count, self.geometry.hash_count);
+        let found = (filter.bit_count, filter.hash_fn_count);
+        if found != expected {
+            return Err(BloomError::IncompatibleFilters { expected, found });
+        }
+        if self.lock().len() >= self.max_idle {
+            return Ok(());
+        }
+        // Clear outside the lock, then check again: other threads may have
+        // filled the pool meanwhile.
+        filter.clear();
+        filter.counters = OpCounters::default();
+        let mut idle = self.lock();
+        if idle.len() < self.max_idle {
+            idle.push(filter);
+        }
+        Ok(())
+    }
+
+    /// Returns the number of cleared filters waiting to be taken.
+    pub fn idle_count(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<BloomFilter<T, B>>> {
+        // The vector is consistent even if a holder panicked.
+        self.idle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<T: ?Sized, B> fmt::Debug for FilterPool<T, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilterPool")
+            .field("bit_count", &self.geometry.bit_count)
+            .field("hash_count", &self.geometry.hash_count)
+            .field("max_idle", &self.max_idle)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_are_reused_cleared() {
+        let pool: FilterPool<u64> = FilterPool::new(1000, 0.01, 1);
+        let mut filter = pool.take();
+        assert_eq!(
+            filter.to_bytes(),
+            BloomFilter::<u64>::new(1000, 0.01).to_bytes()
+        );
+        (0..1000).for_each(|i| filter.insert(&i));
+        let words = filter.bit_vec.as_ptr();
+
+        pool.recycle(filter).unwrap();
+        pool.recycle(pool.take()).unwrap();
+        let reused = pool.take();
+        assert_eq!(reused.bit_vec.as_ptr(), words);
+        assert_eq!(reused.fill_ratio(), 0.0);
+        assert_eq!(pool.idle_count(), 0);
+
+        pool.recycle(reused).unwrap();
+        pool.recycle(pool.take()).unwrap();
+        pool.recycle(BloomFilter::new(1000, 0.01)).unwrap();
+        assert_eq!(pool.idle_count(), 1);
+        assert!(matches!(
+            pool.recycle(BloomFilter::new(10, 0.01)),
+            Err(BloomError::IncompatibleFilters { .. })
+        ));
+    }
+}
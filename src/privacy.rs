@@ -0,0 +1,283 @@
+//! Randomized response for filters collected from clients.
+//!
+//! In the RAPPOR scheme each client reports a Bloom filter of its value, but
+//! only after every bit has been replaced by a noisy one: a set bit is
+//! reported as 1 with probability `q`, an unset bit with probability `p`. A
+//! single report then says little about its client, since any bit could be
+//! noise, while the noise averages out over many reports:
+//! [`Aggregate`] estimates how many clients' filters contain an item.
+//!
+//! # Examples
+//!
+//! ```
+//! use bloomlib::privacy::{Aggregate, RandomizedResponse};
+//! use bloomlib::BloomFilter;
+//!
+//! let response = RandomizedResponse::symmetric(0.5);
+//! let mut aggregate = Aggregate::new(response);
+//!
+//! for client in 0..10_000u64 {
+//!     let mut filter: BloomFilter<str> = BloomFilter::new(10, 0.01);
+//!     filter.insert(if client % 4 == 0 { "dark mode" } else { "light mode" });
+//!     // On the client: only the noisy filter is sent.
+//!     let report = response.apply(&filter);
+//!     aggregate.add(&report).unwrap();
+//! }
+//!
+//! let dark = aggregate.estimate("dark mode");
+//! assert!((dark - 0.25).abs() < 0.05);
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+use crate::stats::SplitMix64;
+use crate::{hashing, BloomError, BloomFilter};
+
+/// The probabilities with which a client reports each bit of its filter as
+/// set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RandomizedResponse {
+    /// The probability that an unset bit is reported as 1.
+    p: f64,
+    /// The probability that a set bit is reported as 1.
+    q: f64,
+}
+
+impl RandomizedResponse {
+    /// Reports an unset bit as 1 with probability `p` and a set bit as 1
+    /// with probability `q`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `0.0 <= p < q <= 1.0`.
+    pub fn new(p: f64, q: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&p) && (0.0..=1.0).contains(&q) && p < q,
+            "Response probabilities must satisfy 0.0 <= p < q <= 1.0."
+        );
+        RandomizedResponse { p, q }
+    }
+
+    /// Replaces each bit by a fair coin flip with probability `f`, and keeps
+    /// it otherwise, as in RAPPOR's permanent randomized response. This is
+    /// `p = f / 2` and `q = 1 - f / 2`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `0.0 <= f < 1.0`.
+    pub fn symmetric(f: f64) -> Self {
+        assert!(
+            (0.0..1.0).contains(&f),
+            "Noise level must be between 0.0, inclusive, and 1.0, exclusive."
+        );
+        Self::new(f / 2.0, 1.0 - f / 2.0)
+    }
+
+    /// Returns the probability that an unset bit is reported as 1.
+    pub fn p(&self) -> f64 {
+        self.p
+    }
+
+    /// Returns the probability that a set bit is reported as 1.
+    pub fn q(&self) -> f64 {
+        self.q
+    }
+
+    /// Returns the differential privacy level ε of one report of a filter
+    /// holding a single item, with `hash_count` hashes.
+    ///
+    /// Replacing the item changes at most `2k` bits, and each changes the
+    /// likelihood of a report by at most `max(q / p, (1 - p) / (1 - q))`.
+    /// The result is infinite when `p` is 0 or `q` is 1. Clients that report
+    /// repeatedly must reuse the same noisy filter (memoize it) to keep this
+    /// bound, since fresh noise on every report averages out.
+    pub fn epsilon(&self, hash_count: u32) -> f64 {
+        let ratio = (self.q / self.p).max((1.0 - self.p) / (1.0 - self.q));
+        2.0 * hash_count as f64 * ratio.ln()
+    }
+
+    /// Returns a noisy copy of `filter`, drawing the noise from a generator
+    /// seeded differently on each call.
+    ///
+    /// The generator is fast but not cryptographic; where an adversary might
+    /// predict its seed, pass one from the operating system's secure source
+    /// to [`apply_with_seed`](Self::apply_with_seed).
+    pub fn apply<T: ?Sized>(&self, filter: &BloomFilter<T>) -> BloomFilter<T> {
+        self.apply_with_seed(filter, RandomState::new().hash_one(()))
+    }
+
+    /// Returns a noisy copy of `filter`, drawing the noise from a generator
+    /// seeded with `seed`.
+    pub fn apply_with_seed<T: ?Sized>(&self, filter: &BloomFilter<T>, seed: u64) -> BloomFilter<T> {
+        let mut rng = SplitMix64(seed);
+        // A 64-bit draw is below `threshold(x)` with probability `x`.
+        let threshold = |probability: f64| (probability * 2f64.powi(64)) as u128;
+        let (p, q) = (threshold(self.p), threshold(self.q));
+
+        let mut noisy = filter.clone();
+        for (index, word) in noisy.bit_vec.iter_mut().enumerate() {
+            let first_bit = index as u64 * 64;
+            let bits = filter.bit_count.saturating_sub(first_bit).min(64);
+            let mut reported = 0;
+            for bit in 0..bits {
+                let set = (*word >> bit) & 1 == 1;
+                let draw = rng.next_u64() as u128;
+                reported |= ((draw < if set { q } else { p }) as u64) << bit;
+            }
+            *word = reported;
+        }
+        noisy
+    }
+}
+
+/// Estimates, from many noisy reports, the fraction of clients whose filter
+/// contains an item.
+///
+/// All reports must share one bit count and hash count, taken from the first
+/// report [`add`](Self::add)ed.
+#[derive(Debug, Clone)]
+pub struct Aggregate<T: ?Sized> {
+    response: RandomizedResponse,
+    bit_count: u64,
+    hash_count: u32,
+    reports: u64,
+    /// The number of reports with each bit set.
+    ones: Vec<u64>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: ?Sized + Hash> Aggregate<T> {
+    /// Creates an empty aggregate of reports made with `response`.
+    pub fn new(response: RandomizedResponse) -> Self {
+        Aggregate {
+            response,
+            bit_count: 0,
+            hash_count: 0,
+            reports: 0,
+            ones: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Adds a noisy report.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::IncompatibleFilters`] if the report's bit count
+    /// or hash count differs from those of the first report.
+    pub fn add(&mut self, report: &BloomFilter<T>) -> Result<(), BloomError> {
+        if self.reports == 0 {
+            self.bit_count = report.bit_count;
+            self.hash_count = report.hash_fn_count;
+            self.ones = vec![0; report.bit_count as usize];
+        } else if (self.bit_count, self.hash_count) != (report.bit_count, report.hash_fn_count) {
+            return Err(BloomError::IncompatibleFilters {
+                expected: (self.bit_count, self.hash_count),
+                found: (report.bit_count, report.hash_fn_count),
+            });
+        }
+        for (bit, ones) in self.ones.iter_mut().enumerate() {
+            *ones += (report.bit_vec[bit / 64] >> (bit % 64)) & 1;
+        }
+        self.reports += 1;
+        Ok(())
+    }
+
+    /// Returns the number of reports added.
+    pub fn report_count(&self) -> u64 {
+        self.reports
+    }
+
+    /// Returns, for each bit, the estimated fraction of clients whose filter
+    /// had it set before the noise, clamped to `0.0..=1.0`.
+    ///
+    /// With `c` of `N` reports showing a bit, the unbiased estimate is
+    /// `(c / N - p) / (q - p)`.
+    pub fn bit_frequencies(&self) -> Vec<f64> {
+        (0..self.ones.len())
+            .map(|bit| self.bit_frequency(bit))
+            .collect()
+    }
+
+    /// Returns the estimated fraction of clients whose filter contains
+    /// `item`, or 0 if no reports were added.
+    ///
+    /// Every client holding the item sets all of its bits, so this is the
+    /// lowest estimated frequency among them. Other items sharing all those
+    /// bits push the estimate up, as false positives do in a single filter.
+    pub fn estimate(&self, item: &T) -> f64 {
+        if self.reports == 0 {
+            return 0.0;
+        }
+        let (h1, h2) = hashing::hash_pair(item);
+        (0..self.hash_count)
+            .map(|i| self.bit_frequency(hashing::bit_index(h1, h2, i, self.bit_count) as usize))
+            .fold(1.0, f64::min)
+    }
+
+    fn bit_frequency(&self, bit: usize) -> f64 {
+        let observed = self.ones[bit] as f64 / self.reports as f64;
+        let RandomizedResponse { p, q } = self.response;
+        ((observed - p) / (q - p)).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_rates() {
+        let mut filter: BloomFilter<u64> = BloomFilter::new(10_000, 0.01);
+        (0..10_000).for_each(|i| filter.insert(&i));
+        let response = RandomizedResponse::new(0.1, 0.8);
+
+        let noisy = response.apply_with_seed(&filter, 7);
+        let (mut set, mut kept, mut unset, mut flipped) = (0, 0, 0, 0);
+        for (word, noisy_word) in filter.bit_vec.iter().zip(&noisy.bit_vec) {
+            set += word.count_ones();
+            kept += (word & noisy_word).count_ones();
+            unset += (!word).count_ones();
+            flipped += (!word & noisy_word).count_ones();
+        }
+        assert_eq!(filter.bit_count % 64, 0);
+        assert!((kept as f64 / set as f64 - 0.8).abs() < 0.01);
+        assert!((flipped as f64 / unset as f64 - 0.1).abs() < 0.01);
+        assert_eq!(noisy.bit_vec, response.apply_with_seed(&filter, 7).bit_vec);
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let response = RandomizedResponse::symmetric(0.5);
+        assert_eq!((response.p(), response.q()), (0.25, 0.75));
+        assert!((response.epsilon(2) - 4.0 * 3f64.ln()).abs() < 1e-12);
+
+        let mut aggregate = Aggregate::new(response);
+        assert_eq!(aggregate.estimate(&1), 0.0);
+        for client in 0..10_000u64 {
+            let mut filter: BloomFilter<u64> = BloomFilter::new(10, 0.01);
+            filter.insert(&(client % 10 / 5 + client % 10 / 8));
+            aggregate
+                .add(&response.apply_with_seed(&filter, client))
+                .unwrap();
+        }
+        assert_eq!(aggregate.report_count(), 10_000);
+        for (item, frequency) in [(0, 0.5), (1, 0.3), (2, 0.2), (3, 0.0)] {
+            let estimate = aggregate.estimate(&item);
+            assert!(
+                (estimate - frequency).abs() < 0.05,
+                "{}: {}",
+                item,
+                estimate
+            );
+        }
+
+        let other: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        assert!(matches!(
+            aggregate.add(&other),
+            Err(BloomError::IncompatibleFilters { .. })
+        ));
+    }
+}
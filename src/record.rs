@@ -0,0 +1,395 @@
+use std::fmt;
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+
+use crate::{BitStore, BloomError, BloomFilter};
+
+/// The bytes every operation log starts with.
+const MAGIC: [u8; 4] = *b"BLMR";
+
+/// The version of the log format.
+const VERSION: u32 = 1;
+
+/// The length of the header: magic, version, bit count and hash count.
+const HEADER_BYTES: usize = 20;
+
+/// The length of a record: its tag and the two hashes.
+const RECORD_BYTES: usize = 17;
+
+const TAG_INSERT: u8 = 1;
+const TAG_MISS: u8 = 2;
+const TAG_HIT: u8 = 3;
+
+/// A [`BloomFilter`] that logs every insert, and optionally every lookup,
+/// to a writer, so the traffic can later be replayed with
+/// [`BloomFilter::replay`] or read back with [`OpLog`].
+///
+/// Each operation is logged as the item's two 64-bit hashes, from which
+/// the filter derives its bit positions, and a tag: 17 bytes whatever the
+/// item. Replaying the log into an empty filter of the same geometry
+/// reproduces the recorded filter bit for bit, and every lookup with its
+/// recorded answer, which turns a false positive rate anomaly seen in
+/// production into a deterministic test case. Replaying into filters of
+/// other sizes shows what they would have answered. The items themselves
+/// cannot be recovered from the log.
+///
+/// Records are written as operations happen, so pass a buffered writer. A
+/// failed write does not fail the operation: it stops the recording, and
+/// the error is returned by [`flush`](Self::flush) and
+/// [`into_parts`](Self::into_parts).
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{BloomFilter, RecordingBloomFilter};
+///
+/// let mut recording =
+///     RecordingBloomFilter::new(BloomFilter::<str>::new(1000, 0.01), Vec::new())
+///         .unwrap()
+///         .with_queries();
+/// recording.insert("alice");
+/// assert!(recording.contains("alice"));
+/// let (recorded, log) = recording.into_parts().unwrap();
+///
+/// let mut replayed: BloomFilter<str> = BloomFilter::new(1000, 0.01);
+/// let stats = replayed.replay(&log[..]).unwrap();
+/// assert_eq!((stats.inserts, stats.queries, stats.diverged), (1, 1, 0));
+/// assert_eq!(replayed.to_bytes(), recorded.to_bytes());
+/// ```
+pub struct RecordingBloomFilter<T: ?Sized, W, B = Vec<u64>> {
+    filter: BloomFilter<T, B>,
+    log: Mutex<LogWriter<W>>,
+    queries: bool,
+}
+
+/// The writer of a [`RecordingBloomFilter`] and the first error it returned.
+struct LogWriter<W> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> LogWriter<W> {
+    fn record(&mut self, tag: u8, (h1, h2): (u64, u64)) {
+        if self.error.is_some() {
+            return;
+        }
+        let mut record = [0u8; RECORD_BYTES];
+        record[0] = tag;
+        record[1..9].copy_from_slice(&h1.to_le_bytes());
+        record[9..17].copy_from_slice(&h2.to_le_bytes());
+        if let Err(err) = self.writer.write_all(&record) {
+            self.error = Some(err);
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+impl<T: ?Sized + Hash, W: Write, B: BitStore> RecordingBloomFilter<T, W, B> {
+    /// Starts recording the operations on `filter` to `writer`, writing the
+    /// log's header, which holds the filter's geometry.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error writing the header returns.
+    pub fn new(filter: BloomFilter<T, B>, mut writer: W) -> io::Result<Self> {
+        let mut header = [0u8; HEADER_BYTES];
+        header[0..4].copy_from_slice(&MAGIC);
+        header[4..8].copy_from_slice(&VERSION.to_le_bytes());
+        header[8..16].copy_from_slice(&filter.bit_count.to_le_bytes());
+        header[16..20].copy_from_slice(&filter.hash_fn_count.to_le_bytes());
+        writer.write_all(&header)?;
+        Ok(RecordingBloomFilter {
+            filter,
+            log: Mutex::new(LogWriter {
+                writer,
+                error: None,
+            }),
+            queries: false,
+        })
+    }
+
+    /// Also records every lookup and its answer.
+    pub fn with_queries(mut self) -> Self {
+        self.queries = true;
+        self
+    }
+
+    /// Inserts an item and records it.
+    pub fn insert(&mut self, item: &T) {
+        let (h1, h2) = self.filter.get_hashes(item);
+        self.filter.insert_hashes(h1, h2);
+        self.filter.counters.record_inserts(1);
+        self.log_mut().record(TAG_INSERT, (h1, h2));
+    }
+
+    /// Checks if an item might be in the filter, recording the lookup if
+    /// lookups are recorded.
+    pub fn contains(&self, item: &T) -> bool {
+        let (h1, h2) = self.filter.get_hashes(item);
+        let found = self.filter.contains_hashes(h1, h2);
+        self.filter.counters.record_query(found);
+        if self.queries {
+            let tag = if found { TAG_HIT } else { TAG_MISS };
+            self.lock_log().record(tag, (h1, h2));
+        }
+        found
+    }
+
+    /// Returns the filter.
+    pub fn filter(&self) -> &BloomFilter<T, B> {
+        &self.filter
+    }
+
+    /// Flushes the writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error that stopped the recording, if a write failed, or
+    /// any error flushing returns.
+    pub fn flush(&self) -> io::Result<()> {
+        self.lock_log().flush()
+    }
+
+    /// Stops recording, flushing the writer, and returns the filter and the
+    /// writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`flush`](Self::flush).
+    pub fn into_parts(self) -> io::Result<(BloomFilter<T, B>, W)> {
+        let mut log = self
+            .log
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        log.flush()?;
+        Ok((self.filter, log.writer))
+    }
+
+    fn log_mut(&mut self) -> &mut LogWriter<W> {
+        self.log
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_log(&self) -> std::sync::MutexGuard<'_, LogWriter<W>> {
+        // A panic while writing leaves at worst a torn record, which the
+        // error it caused or the reader reports.
+        self.log
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, W, B: fmt::Debug> fmt::Debug for RecordingBloomFilter<T, W, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordingBloomFilter")
+            .field("filter", &self.filter)
+            .field("queries", &self.queries)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An operation read from a log written by a [`RecordingBloomFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordedOp {
+    /// An item with hashes `h1` and `h2` was inserted.
+    Insert {
+        /// The first hash of the item.
+        h1: u64,
+        /// The second hash of the item.
+        h2: u64,
+    },
+    /// An item with hashes `h1` and `h2` was looked up.
+    Query {
+        /// The first hash of the item.
+        h1: u64,
+        /// The second hash of the item.
+        h2: u64,
+        /// The answer the filter gave.
+        found: bool,
+    },
+}
+
+/// Reads the operations of a log written by a [`RecordingBloomFilter`].
+///
+/// A log cut short by a crash ends with a partial record, which is
+/// reported as an error after the complete ones.
+#[derive(Debug)]
+pub struct OpLog<R> {
+    reader: R,
+    bit_count: u64,
+    hash_count: u32,
+}
+
+impl<R: Read> OpLog<R> {
+    /// Reads the log's header from `reader`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `reader` returns, and an error of kind
+    /// [`io::ErrorKind::InvalidData`] wrapping a [`BloomError`] if the data
+    /// is not an operation log.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0u8; HEADER_BYTES];
+        reader.read_exact(&mut header)?;
+        if header[0..4] != MAGIC {
+            return Err(invalid_data("the magic bytes are missing"));
+        }
+        if u32::from_le_bytes(header[4..8].try_into().unwrap()) != VERSION {
+            return Err(invalid_data("the format version is unsupported"));
+        }
+        Ok(OpLog {
+            reader,
+            bit_count: u64::from_le_bytes(header[8..16].try_into().unwrap()),
+            hash_count: u32::from_le_bytes(header[16..20].try_into().unwrap()),
+        })
+    }
+
+    /// Returns the bit count (m) of the recorded filter.
+    pub fn bit_count(&self) -> u64 {
+        self.bit_count
+    }
+
+    /// Returns the hash count (k) of the recorded filter.
+    pub fn hash_count(&self) -> u32 {
+        self.hash_count
+    }
+
+    fn next_op(&mut self) -> io::Result<Option<RecordedOp>> {
+        let mut record = [0u8; RECORD_BYTES];
+        let mut filled = 0;
+        while filled < RECORD_BYTES {
+            match self.reader.read(&mut record[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(invalid_data("the last record is truncated")),
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        let h1 = u64::from_le_bytes(record[1..9].try_into().unwrap());
+        let h2 = u64::from_le_bytes(record[9..17].try_into().unwrap());
+        match record[0] {
+            TAG_INSERT => Ok(Some(RecordedOp::Insert { h1, h2 })),
+            TAG_MISS | TAG_HIT => Ok(Some(RecordedOp::Query {
+                h1,
+                h2,
+                found: record[0] == TAG_HIT,
+            })),
+            _ => Err(invalid_data("a record has an unknown tag")),
+        }
+    }
+}
+
+impl<R: Read> Iterator for OpLog<R> {
+    type Item = io::Result<RecordedOp>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_op().transpose()
+    }
+}
+
+/// The outcome of [`BloomFilter::replay`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplayStats {
+    /// The number of inserts replayed.
+    pub inserts: u64,
+    /// The number of lookups replayed.
+    pub queries: u64,
+    /// The number of replayed lookups whose answer differs from the
+    /// recorded one. It is 0 when replaying into an empty filter of the
+    /// recorded geometry.
+    pub diverged: u64,
+}
+
+impl<T: ?Sized + Hash, B: BitStore> BloomFilter<T, B> {
+    /// Replays a log written by a [`RecordingBloomFilter`] into this filter:
+    /// inserts the recorded items and repeats the recorded lookups,
+    /// counting those that answer differently.
+    ///
+    /// The filter may have any geometry: replaying into an empty filter of
+    /// the recorded one reproduces the recorded filter and every answer,
+    /// and replaying into a larger one, e.g., shows how many of the
+    /// recorded positives would have gone away.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`OpLog`]. The operations before the error
+    /// have been replayed.
+    pub fn replay<R: Read>(&mut self, reader: R) -> io::Result<ReplayStats> {
+        let mut stats = ReplayStats::default();
+        for op in OpLog::new(reader)? {
+            match op? {
+                RecordedOp::Insert { h1, h2 } => {
+                    self.insert_hashes(h1, h2);
+                    self.counters.record_inserts(1);
+                    stats.inserts += 1;
+                }
+                RecordedOp::Query { h1, h2, found } => {
+                    let replayed = self.contains_hashes(h1, h2);
+                    self.counters.record_query(replayed);
+                    stats.queries += 1;
+                    stats.diverged += (replayed != found) as u64;
+                }
+            }
+        }
+        Ok(stats)
+    }
+}
+
+fn invalid_data(reason: &'static str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        BloomError::InvalidFormat(reason),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_reproduces_filter_and_answers() {
+        let filter: BloomFilter<u64> = BloomFilter::new(1000, 0.05);
+        let mut recording = RecordingBloomFilter::new(filter, Vec::new())
+            .unwrap()
+            .with_queries();
+        for i in 0..1000 {
+            recording.insert(&i);
+        }
+        let positives = (1000..11_000).filter(|i| recording.contains(i)).count() as u64;
+        assert!(positives > 0);
+        let (recorded, log) = recording.into_parts().unwrap();
+        assert_eq!(log.len(), HEADER_BYTES + 11_000 * RECORD_BYTES);
+
+        let mut replayed: BloomFilter<u64> = BloomFilter::new(1000, 0.05);
+        let stats = replayed.replay(&log[..]).unwrap();
+        assert_eq!(
+            stats,
+            ReplayStats {
+                inserts: 1000,
+                queries: 10_000,
+                diverged: 0
+            }
+        );
+        assert_eq!(replayed.bit_vec, recorded.bit_vec);
+
+        // A larger filter loses most of the false positives.
+        let mut larger: BloomFilter<u64> = BloomFilter::new(1000, 1e-6);
+        assert!(larger.replay(&log[..]).unwrap().diverged > positives / 2);
+
+        let ops = OpLog::new(&log[..log.len() - 1]).unwrap();
+        assert_eq!(ops.bit_count(), recorded.bit_count());
+        let ops: Vec<_> = ops.collect();
+        assert_eq!(ops.len(), 11_000);
+        assert!(ops[..10_999].iter().all(Result::is_ok));
+        assert!(ops[10_999].is_err());
+    }
+}
@@ -0,0 +1,804 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::Hash;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::params::Geometry;
+use crate::{BloomError, BloomFilter, FilterParams};
+
+/// A filter held by a [`FilterRegistry`], shared with the callers of
+/// [`FilterRegistry::get`].
+pub type SharedFilter<T> = Arc<RwLock<BloomFilter<T>>>;
+
+/// Errors returned by [`FilterRegistry`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryError {
+    /// A filter with the name already exists.
+    FilterExists(String),
+    /// No filter has the name.
+    NoSuchFilter(String),
+    /// The filter could not be created.
+    Filter(BloomError),
+    /// The filter would take the registry, or its tenant, over its memory
+    /// budget.
+    QuotaExceeded {
+        /// The tenant whose quota was exceeded, or `None` for the registry's
+        /// overall budget.
+        tenant: Option<String>,
+        /// The memory the filter needs, in bytes.
+        requested: u64,
+        /// The memory left in the budget, in bytes.
+        available: u64,
+    },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::FilterExists(name) => write!(f, "A filter named {:?} exists.", name),
+            RegistryError::NoSuchFilter(name) => write!(f, "No filter is named {:?}.", name),
+            RegistryError::Filter(err) => err.fmt(f),
+            RegistryError::QuotaExceeded {
+                tenant,
+                requested,
+                available,
+            } => {
+                match tenant {
+                    Some(tenant) => write!(f, "Tenant {:?}", tenant)?,
+                    None => write!(f, "The registry")?,
+                }
+                write!(
+                    f,
+                    " has {} bytes left, but the filter needs {} bytes.",
+                    available, requested
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RegistryError::Filter(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<BloomError> for RegistryError {
+    fn from(err: BloomError) -> Self {
+        RegistryError::Filter(err)
+    }
+}
+
+/// A set of named filters, each with its own parameters: the in-process
+/// equivalent of a filter server such as bloomd.
+///
+/// Filters are created, looked up and dropped by name, and items inserted
+/// and checked through the registry by filter name. Every filter has its
+/// own lock, so work on one filter never waits for another, and the
+/// registry's own lock is only held to find a filter by name. For several
+/// operations on one filter, [`get`](Self::get) it once and lock it
+/// directly.
+///
+/// The registry can be shared between threads; `bloom serve` serves one
+/// over HTTP.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::FilterRegistry;
+///
+/// let registry: FilterRegistry = FilterRegistry::new();
+/// registry.create("users", 1_000_000, 0.001).unwrap();
+/// registry.create("sessions", 10_000, 0.01).unwrap();
+///
+/// registry.insert("users", "alice").unwrap();
+/// assert!(registry.check("users", "alice").unwrap());
+/// assert!(!registry.check("sessions", "alice").unwrap());
+///
+/// assert!(registry.remove("sessions").is_some());
+/// assert_eq!(registry.names(), ["users"]);
+/// ```
+pub struct FilterRegistry<T: ?Sized = str> {
+    state: RwLock<State<T>>,
+    /// The most memory all filters together may use, in bytes.
+    budget: Option<u64>,
+}
+
+/// The filters and the tenants' quotas, behind the registry's lock.
+struct State<T: ?Sized> {
+    filters: HashMap<String, Entry<T>>,
+    quotas: HashMap<String, u64>,
+}
+
+struct Entry<T: ?Sized> {
+    filter: SharedFilter<T>,
+    tenant: Option<String>,
+    /// The memory the filter used when added, in bytes.
+    bytes: u64,
+}
+
+/// The first line of a manifest written by [`FilterRegistry::save_manifest`].
+const MANIFEST_HEADER: &str = "bloomlib-registry 1";
+
+/// The memory used by one filter of a [`FilterRegistry`], returned by
+/// [`FilterRegistry::memory_usage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterUsage {
+    /// The filter's name.
+    pub name: String,
+    /// The tenant the filter belongs to, if any.
+    pub tenant: Option<String>,
+    /// The memory the filter uses, in bytes.
+    pub bytes: u64,
+}
+
+impl<T: ?Sized + Hash> FilterRegistry<T> {
+    /// Creates an empty registry with no memory budget.
+    pub fn new() -> Self {
+        FilterRegistry {
+            state: RwLock::new(State {
+                filters: HashMap::new(),
+                quotas: HashMap::new(),
+            }),
+            budget: None,
+        }
+    }
+
+    /// Creates an empty registry whose filters together may use at most
+    /// `bytes` of memory. Creating or adding a filter that does not fit
+    /// fails with [`RegistryError::QuotaExceeded`].
+    pub fn with_memory_budget(bytes: u64) -> Self {
+        FilterRegistry {
+            budget: Some(bytes),
+            ..Self::new()
+        }
+    }
+
+    /// Creates an empty filter named `name`, sized like
+    /// [`BloomFilter::new`]`(expected_items, params)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RegistryError::FilterExists`] if the name is taken,
+    /// [`RegistryError::QuotaExceeded`] if the filter does not fit the
+    /// registry's memory budget, or [`RegistryError::Filter`] under the same
+    /// conditions as [`BloomFilter::try_new`].
+    pub fn create(
+        &self,
+        name: &str,
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<SharedFilter<T>, RegistryError> {
+        self.create_entry(None, name, expected_items, params.into())
+    }
+
+    /// Creates an empty filter named `name` that counts against the quota
+    /// of `tenant`, as well as the registry's budget.
+    ///
+    /// # Errors
+    ///
+    /// As [`create`](Self::create), and [`RegistryError::QuotaExceeded`]
+    /// also if the filter does not fit the tenant's quota.
+    pub fn create_for_tenant(
+        &self,
+        tenant: &str,
+        name: &str,
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<SharedFilter<T>, RegistryError> {
+        self.create_entry(Some(tenant), name, expected_items, params.into())
+    }
+
+    /// Adds an existing filter, e.g. one loaded from disk, named `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RegistryError::FilterExists`] if the name is taken, or
+    /// [`RegistryError::QuotaExceeded`] if the filter does not fit the
+    /// registry's memory budget.
+    pub fn add(
+        &self,
+        name: &str,
+        filter: BloomFilter<T>,
+    ) -> Result<SharedFilter<T>, RegistryError> {
+        self.add_entry(None, name, filter)
+    }
+
+    /// Adds an existing filter named `name` that counts against the quota
+    /// of `tenant`.
+    ///
+    /// # Errors
+    ///
+    /// As [`add`](Self::add), and [`RegistryError::QuotaExceeded`] also if
+    /// the filter does not fit the tenant's quota.
+    pub fn add_for_tenant(
+        &self,
+        tenant: &str,
+        name: &str,
+        filter: BloomFilter<T>,
+    ) -> Result<SharedFilter<T>, RegistryError> {
+        self.add_entry(Some(tenant), name, filter)
+    }
+
+    fn create_entry(
+        &self,
+        tenant: Option<&str>,
+        name: &str,
+        expected_items: usize,
+        params: FilterParams,
+    ) -> Result<SharedFilter<T>, RegistryError> {
+        // Checked first so that a filter that cannot be added is never
+        // allocated; `add_entry` checks again under the write lock.
+        let bytes = Geometry::optimal::<u64>(expected_items, params)?.word_count as u64 * 8;
+        self.check_admission(&self.read(), tenant, name, bytes)?;
+        self.add_entry(tenant, name, BloomFilter::try_new(expected_items, params)?)
+    }
+
+    fn add_entry(
+        &self,
+        tenant: Option<&str>,
+        name: &str,
+        filter: BloomFilter<T>,
+    ) -> Result<SharedFilter<T>, RegistryError> {
+        let bytes = filter.memory_usage_bytes() as u64;
+        let mut state = self.write();
+        self.check_admission(&state, tenant, name, bytes)?;
+        let filter = Arc::new(RwLock::new(filter));
+        state.filters.insert(
+            name.to_owned(),
+            Entry {
+                filter: Arc::clone(&filter),
+                tenant: tenant.map(str::to_owned),
+                bytes,
+            },
+        );
+        Ok(filter)
+    }
+
+    /// Checks that the name is free and that `bytes` more fit the budgets.
+    fn check_admission(
+        &self,
+        state: &State<T>,
+        tenant: Option<&str>,
+        name: &str,
+        bytes: u64,
+    ) -> Result<(), RegistryError> {
+        if state.filters.contains_key(name) {
+            return Err(RegistryError::FilterExists(name.to_owned()));
+        }
+        let exceeded = |limit: u64, used: u64, tenant: Option<&str>| {
+            let available = limit.saturating_sub(used);
+            (bytes > available).then(|| RegistryError::QuotaExceeded {
+                tenant: tenant.map(str::to_owned),
+                requested: bytes,
+                available,
+            })
+        };
+        if let Some(tenant) = tenant {
+            if let Some(&quota) = state.quotas.get(tenant) {
+                if let Some(err) = exceeded(quota, state.tenant_bytes(tenant), Some(tenant)) {
+                    return Err(err);
+                }
+            }
+        }
+        if let Some(budget) = self.budget {
+            if let Some(err) = exceeded(budget, state.total_bytes(), None) {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the filter named `name`, if there is one.
+    pub fn get(&self, name: &str) -> Option<SharedFilter<T>> {
+        self.read()
+            .filters
+            .get(name)
+            .map(|entry| Arc::clone(&entry.filter))
+    }
+
+    /// Removes the filter named `name` and returns it. Callers that still
+    /// hold it from [`get`](Self::get) keep using it, unregistered, but its
+    /// memory no longer counts against any budget.
+    pub fn remove(&self, name: &str) -> Option<SharedFilter<T>> {
+        self.write().filters.remove(name).map(|entry| entry.filter)
+    }
+
+    /// Inserts an item into the filter named `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RegistryError::NoSuchFilter`] if there is no such filter.
+    pub fn insert(&self, name: &str, item: &T) -> Result<(), RegistryError> {
+        let filter = self.find(name)?;
+        let mut filter = filter
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        filter.insert(item);
+        Ok(())
+    }
+
+    /// Checks if an item might be in the filter named `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RegistryError::NoSuchFilter`] if there is no such filter.
+    pub fn check(&self, name: &str, item: &T) -> Result<bool, RegistryError> {
+        let filter = self.find(name)?;
+        let filter = filter
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(filter.contains(item))
+    }
+
+    /// Returns the names of the filters, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.read().filters.keys().cloned().collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns the number of filters.
+    pub fn len(&self) -> usize {
+        self.read().filters.len()
+    }
+
+    /// Returns `true` if there are no filters.
+    pub fn is_empty(&self) -> bool {
+        self.read().filters.is_empty()
+    }
+
+    /// Limits the filters of `tenant` to `bytes` of memory together, or
+    /// lifts the limit with `None`.
+    ///
+    /// Filters the tenant already has are kept even if they exceed the new
+    /// quota; only new ones are rejected.
+    pub fn set_quota(&self, tenant: &str, bytes: Option<u64>) {
+        let quotas = &mut self.write().quotas;
+        match bytes {
+            Some(bytes) => quotas.insert(tenant.to_owned(), bytes),
+            None => quotas.remove(tenant),
+        };
+    }
+
+    /// Returns the quota of `tenant`, if it has one.
+    pub fn quota(&self, tenant: &str) -> Option<u64> {
+        self.read().quotas.get(tenant).copied()
+    }
+
+    /// Returns the registry's overall memory budget, if it has one.
+    pub fn memory_budget(&self) -> Option<u64> {
+        self.budget
+    }
+
+    /// Returns the memory used by all filters, in bytes.
+    pub fn memory_usage_bytes(&self) -> u64 {
+        self.read().total_bytes()
+    }
+
+    /// Returns the memory used by the filters of `tenant`, in bytes.
+    pub fn tenant_memory_usage_bytes(&self, tenant: &str) -> u64 {
+        self.read().tenant_bytes(tenant)
+    }
+
+    /// Returns the memory used by each filter, sorted by name.
+    ///
+    /// Usage is accounted when a filter is added; a filter replaced through
+    /// the lock returned by [`get`](Self::get) keeps the size of the one it
+    /// replaced.
+    pub fn memory_usage(&self) -> Vec<FilterUsage> {
+        let mut usage: Vec<FilterUsage> = self
+            .read()
+            .filters
+            .iter()
+            .map(|(name, entry)| FilterUsage {
+                name: name.clone(),
+                tenant: entry.tenant.clone(),
+                bytes: entry.bytes,
+            })
+            .collect();
+        usage.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        usage
+    }
+
+    fn find(&self, name: &str) -> Result<SharedFilter<T>, RegistryError> {
+        self.get(name)
+            .ok_or_else(|| RegistryError::NoSuchFilter(name.to_owned()))
+    }
+
+    // The state is consistent even if a holder panicked.
+    fn read(&self) -> RwLockReadGuard<'_, State<T>> {
+        self.state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, State<T>> {
+        self.state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<T: ?Sized> State<T> {
+    fn total_bytes(&self) -> u64 {
+        self.filters.values().map(|entry| entry.bytes).sum()
+    }
+
+    fn tenant_bytes(&self, tenant: &str) -> u64 {
+        self.filters
+            .values()
+            .filter(|entry| entry.tenant.as_deref() == Some(tenant))
+            .map(|entry| entry.bytes)
+            .sum()
+    }
+}
+
+impl<T: ?Sized + Hash> FilterRegistry<T> {
+    /// Saves every filter and a manifest listing them, so that
+    /// [`load_manifest`](Self::load_manifest) can restore the registry
+    /// after a restart.
+    ///
+    /// Each filter is written in the format of [`BloomFilter::to_bytes`]
+    /// to its own file next to the manifest, named after the filter with
+    /// characters other than ASCII letters, digits, `-`, `_` and `.`
+    /// percent-encoded, with the extension `.bloom`. The manifest is a text
+    /// file with one tab-separated line per filter: its name, tenant, bit
+    /// count, hash count and file name, and one per tenant quota. Every
+    /// file is written to a temporary file first and renamed into place,
+    /// the manifest last, so a crash leaves the previous manifest valid.
+    ///
+    /// Each filter is locked for reading only while it is written, so
+    /// inserts may continue meanwhile, and files of filters removed since
+    /// an earlier save are left in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error creating, writing or renaming a file returns, and
+    /// an error of kind [`io::ErrorKind::InvalidInput`] if a name contains
+    /// a tab or line break.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::FilterRegistry;
+    ///
+    /// let dir = std::env::temp_dir().join("bloomlib-manifest-example");
+    /// std::fs::create_dir_all(&dir).unwrap();
+    ///
+    /// let registry: FilterRegistry = FilterRegistry::new();
+    /// registry.create("users", 10_000, 0.01).unwrap();
+    /// registry.insert("users", "alice").unwrap();
+    /// registry.save_manifest(dir.join("filters.manifest")).unwrap();
+    ///
+    /// // After a restart:
+    /// let registry: FilterRegistry = FilterRegistry::new();
+    /// registry.load_manifest(dir.join("filters.manifest")).unwrap();
+    /// assert!(registry.check("users", "alice").unwrap());
+    /// ```
+    pub fn save_manifest(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let (mut entries, mut quotas) = {
+            let state = self.read();
+            let entries: Vec<(String, Option<String>, SharedFilter<T>)> = state
+                .filters
+                .iter()
+                .map(|(name, entry)| {
+                    (
+                        name.clone(),
+                        entry.tenant.clone(),
+                        Arc::clone(&entry.filter),
+                    )
+                })
+                .collect();
+            let quotas: Vec<(String, u64)> = state
+                .quotas
+                .iter()
+                .map(|(tenant, &bytes)| (tenant.clone(), bytes))
+                .collect();
+            (entries, quotas)
+        };
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        quotas.sort_unstable();
+
+        let mut manifest = format!("{}\n", MANIFEST_HEADER);
+        for (tenant, bytes) in &quotas {
+            check_field(tenant)?;
+            manifest.push_str(&format!("quota\t{}\t{}\n", tenant, bytes));
+        }
+        for (name, tenant, filter) in &entries {
+            check_field(name)?;
+            let tenant = tenant.as_deref().unwrap_or("");
+            check_field(tenant)?;
+            let file_name = format!("{}.bloom", encode_file_name(name));
+            let (bit_count, hash_count) = write_atomically(&dir.join(&file_name), |writer| {
+                let filter = filter
+                    .read()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                filter.write_to(writer)?;
+                Ok((filter.bit_count(), filter.hash_count()))
+            })?;
+            manifest.push_str(&format!(
+                "filter\t{}\t{}\t{}\t{}\t{}\n",
+                name, tenant, bit_count, hash_count, file_name
+            ));
+        }
+        write_atomically(path, |writer| writer.write_all(manifest.as_bytes()))
+    }
+
+    /// Loads the filters and quotas listed in a manifest written by
+    /// [`save_manifest`](Self::save_manifest) into this registry, and
+    /// returns the number of filters loaded.
+    ///
+    /// Quotas are set before any filter is added, and every filter counts
+    /// against the registry's memory budget and its tenant's quota as if it
+    /// were added with [`add_for_tenant`](Self::add_for_tenant).
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reading a file returns; an error of kind
+    /// [`io::ErrorKind::InvalidData`] if the manifest is malformed, or a
+    /// filter file is not a serialized filter or does not have the bit
+    /// count and hash count the manifest lists; and an error wrapping a
+    /// [`RegistryError`] if a filter's name is taken or it does not fit a
+    /// budget. Filters loaded before the error stay in the registry.
+    pub fn load_manifest(&self, path: impl AsRef<Path>) -> io::Result<usize> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let invalid = |reason| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                BloomError::InvalidFormat(reason),
+            )
+        };
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        if lines.next().transpose()?.as_deref() != Some(MANIFEST_HEADER) {
+            return Err(invalid("the manifest header is missing"));
+        }
+
+        let mut filters = Vec::new();
+        for line in lines {
+            let line = line?;
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[..] {
+                ["quota", tenant, bytes] => {
+                    let bytes = bytes
+                        .parse()
+                        .map_err(|_| invalid("a quota is not a number"))?;
+                    self.set_quota(tenant, Some(bytes));
+                }
+                ["filter", name, tenant, bit_count, hash_count, file_name] => {
+                    let geometry = bit_count
+                        .parse::<u64>()
+                        .ok()
+                        .zip(hash_count.parse::<u32>().ok());
+                    let geometry =
+                        geometry.ok_or_else(|| invalid("a filter geometry is not a number"))?;
+                    let tenant = (!tenant.is_empty()).then(|| tenant.to_owned());
+                    filters.push((name.to_owned(), tenant, geometry, file_name.to_owned()));
+                }
+                [""] => {}
+                _ => return Err(invalid("a manifest line is malformed")),
+            }
+        }
+
+        for (name, tenant, expected, file_name) in &filters {
+            let file = File::open(dir.join(file_name))?;
+            let filter: BloomFilter<T> = BloomFilter::read_from(BufReader::new(file))?;
+            let found = (filter.bit_count(), filter.hash_count());
+            if found != *expected {
+                let err = BloomError::IncompatibleFilters {
+                    expected: *expected,
+                    found,
+                };
+                return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+            }
+            self.add_entry(tenant.as_deref(), name, filter)
+                .map_err(io::Error::other)?;
+        }
+        Ok(filters.len())
+    }
+}
+
+/// Rejects names and tenants that would break the manifest's lines.
+fn check_field(field: &str) -> io::Result<()> {
+    if field.contains(['\t', '\n', '\r']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?} contains a tab or line break", field),
+        ));
+    }
+    Ok(())
+}
+
+/// Percent-encodes the bytes of `name` that are not safe in a file name.
+fn encode_file_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric()
+            || b"-_".contains(&byte)
+            || (byte == b'.' && !encoded.is_empty())
+        {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Writes a file through `write` into a temporary file next to `path`,
+/// then renames it into place.
+fn write_atomically<R>(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<R>,
+) -> io::Result<R> {
+    let mut temporary = OsString::from(path.as_os_str());
+    temporary.push(".tmp");
+    let mut writer = BufWriter::new(File::create(&temporary)?);
+    let result = write(&mut writer)?;
+    writer.into_inner()?.sync_all()?;
+    fs::rename(&temporary, path)?;
+    Ok(result)
+}
+
+impl<T: ?Sized + Hash> Default for FilterRegistry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for FilterRegistry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self
+            .state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut names: Vec<&String> = state.filters.keys().collect();
+        names.sort_unstable();
+        f.debug_struct("FilterRegistry")
+            .field("filters", &names)
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_filters() {
+        let registry: FilterRegistry<u64> = FilterRegistry::new();
+        assert!(registry.is_empty());
+        let small = registry.create("small", 100, 0.1).unwrap();
+        registry.create("large", 100_000, 1e-6).unwrap();
+        assert_eq!(
+            registry.create("small", 10, 0.5).unwrap_err(),
+            RegistryError::FilterExists("small".into())
+        );
+        assert_eq!(
+            registry.create("bad", 0, 0.5).unwrap_err(),
+            RegistryError::Filter(BloomError::ZeroExpectedItems)
+        );
+        assert!(
+            registry.get("large").unwrap().read().unwrap().bit_count()
+                > 1000 * small.read().unwrap().bit_count()
+        );
+
+        registry.insert("small", &1).unwrap();
+        assert!(registry.check("small", &1).unwrap());
+        assert!(small.read().unwrap().contains(&1));
+        assert!(!registry.check("large", &1).unwrap());
+        assert_eq!(
+            registry.insert("missing", &1).unwrap_err(),
+            RegistryError::NoSuchFilter("missing".into())
+        );
+
+        assert_eq!(registry.names(), ["large", "small"]);
+        assert!(Arc::ptr_eq(&registry.remove("small").unwrap(), &small));
+        assert!(registry.remove("small").is_none());
+        assert_eq!(registry.len(), 1);
+        registry.add("small", BloomFilter::new(10, 0.1)).unwrap();
+        assert!(!registry.check("small", &1).unwrap());
+    }
+
+    #[test]
+    fn test_memory_budget_and_quotas() {
+        let bytes = BloomFilter::<u64>::new(10_000, 0.01).memory_usage_bytes() as u64;
+        let registry: FilterRegistry<u64> = FilterRegistry::with_memory_budget(5 * bytes);
+        registry.set_quota("acme", Some(2 * bytes));
+        registry
+            .create_for_tenant("acme", "a1", 10_000, 0.01)
+            .unwrap();
+        registry
+            .create_for_tenant("acme", "a2", 10_000, 0.01)
+            .unwrap();
+        assert_eq!(
+            registry
+                .create_for_tenant("acme", "a3", 10_000, 0.01)
+                .unwrap_err(),
+            RegistryError::QuotaExceeded {
+                tenant: Some("acme".into()),
+                requested: bytes,
+                available: 0
+            }
+        );
+        assert_eq!(registry.tenant_memory_usage_bytes("acme"), 2 * bytes);
+
+        registry
+            .create_for_tenant("other", "o1", 10_000, 0.01)
+            .unwrap();
+        registry
+            .add("shared", BloomFilter::new(10_000, 0.01))
+            .unwrap();
+        assert_eq!(
+            registry.create("big", 20_000, 0.01).unwrap_err(),
+            RegistryError::QuotaExceeded {
+                tenant: None,
+                requested: 2 * bytes,
+                available: bytes
+            }
+        );
+        assert_eq!(registry.memory_usage_bytes(), 4 * bytes);
+
+        registry.remove("a1");
+        registry
+            .create_for_tenant("acme", "a3", 10_000, 0.01)
+            .unwrap();
+        let usage = registry.memory_usage();
+        let names: Vec<&str> = usage.iter().map(|usage| usage.name.as_str()).collect();
+        assert_eq!(names, ["a2", "a3", "o1", "shared"]);
+        assert_eq!(usage[2].tenant.as_deref(), Some("other"));
+        assert!(usage.iter().all(|usage| usage.bytes == bytes));
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir = std::env::temp_dir().join(format!("bloomlib-registry-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("filters.manifest");
+
+        let registry: FilterRegistry<str> = FilterRegistry::new();
+        registry.set_quota("acme", Some(1 << 20));
+        registry
+            .create_for_tenant("acme", "acme/users", 1000, 0.01)
+            .unwrap();
+        registry.create("..", 100, 4u32).unwrap();
+        registry.insert("acme/users", "alice").unwrap();
+        registry.insert("..", "bob").unwrap();
+        registry.save_manifest(&manifest).unwrap();
+        assert!(dir.join("acme%2Fusers.bloom").exists());
+        assert!(dir.join("%2E..bloom").exists());
+
+        let restored: FilterRegistry<str> = FilterRegistry::new();
+        assert_eq!(restored.load_manifest(&manifest).unwrap(), 2);
+        assert_eq!(restored.names(), registry.names());
+        assert_eq!(restored.memory_usage(), registry.memory_usage());
+        assert_eq!(restored.quota("acme"), Some(1 << 20));
+        assert!(restored.check("acme/users", "alice").unwrap());
+        assert!(restored.check("..", "bob").unwrap());
+        assert!(!restored.check("..", "alice").unwrap());
+
+        // Loading twice finds the names taken.
+        assert!(restored.load_manifest(&manifest).is_err());
+        fs::write(
+            dir.join("%2E..bloom"),
+            BloomFilter::<str>::new(100, 0.5).to_bytes(),
+        )
+        .unwrap();
+        let err = FilterRegistry::<str>::new()
+            .load_manifest(&manifest)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use crate::{params, BitStore, BloomFilter};
+
+/// A [`BloomFilter`] that rotates to a fresh generation once its estimated
+/// false positive rate passes a threshold.
+///
+/// The estimate is tracked as in [`HookedBloomFilter`](crate::HookedBloomFilter):
+/// each new item leaves a given bit unset with probability `(1 - 1/m)^k`,
+/// and the false positive rate is the set fraction to the power k. When an
+/// insert takes it past `max_fp_rate`, the filter is replaced by a cleared
+/// one of the same geometry.
+///
+/// Dropping the full generation at once makes every key inserted before the
+/// rotation a false negative. With
+/// [`with_grace_period`](Self::with_grace_period), the previous generation
+/// is kept and queried as well until the grace period after its rotation
+/// has passed, e.g. for a deduplication window of that length. Its memory
+/// is reused for the generation after next.
+///
+/// With [`with_adaptive_probes`](Self::with_adaptive_probes), lookups in the
+/// current generation test only as many of an item's k bits as its
+/// estimated fill needs for a target false positive rate: right after a
+/// rotation, one or two instead of k.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use bloomlib::{BloomFilter, RotatingBloomFilter};
+///
+/// let mut seen = RotatingBloomFilter::new(BloomFilter::<u64>::new(1000, 0.01), 0.02)
+///     .with_grace_period(Duration::from_secs(60));
+/// for i in 0..5000 {
+///     seen.insert(&i);
+/// }
+/// assert!(seen.rotation_count() > 0);
+/// // Keys of the previous generation are still found.
+/// assert!(seen.contains(&4999));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RotatingBloomFilter<T: ?Sized, B = Vec<u64>> {
+    current: BloomFilter<T, B>,
+    /// The previous generation and the time it stops being queried.
+    previous: Option<(BloomFilter<T, B>, Instant)>,
+    max_fp_rate: f64,
+    grace_period: Option<Duration>,
+    /// The estimated fraction of bits of `current` still unset.
+    unset: f64,
+    /// The factor `unset` shrinks by with each new item.
+    decay: f64,
+    rotations: u64,
+    /// The false positive rate lookups in `current` are held to, if they
+    /// adapt their probes.
+    probe_target: Option<f64>,
+    /// The probes lookups in `current` test.
+    probes: u32,
+}
+
+impl<T: ?Sized + Hash, B: BitStore + Clone> RotatingBloomFilter<T, B> {
+    /// Wraps `filter`, rotating whenever its estimated false positive rate
+    /// passes `max_fp_rate`. Later generations are cleared copies of it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_fp_rate` is not between 0.0 and 1.0, exclusive.
+    pub fn new(filter: BloomFilter<T, B>, max_fp_rate: f64) -> Self {
+        assert!(
+            max_fp_rate > 0.0 && max_fp_rate < 1.0,
+            "Maximum false positive rate must be between 0.0 and 1.0, exclusive."
+        );
+        let (m, k) = (filter.bit_count() as f64, filter.hash_count());
+        RotatingBloomFilter {
+            unset: 1.0 - filter.fill_ratio(),
+            decay: (1.0 - 1.0 / m).powi(k as i32),
+            current: filter,
+            previous: None,
+            max_fp_rate,
+            grace_period: None,
+            rotations: 0,
+            probe_target: None,
+            probes: k,
+        }
+    }
+
+    /// Keeps each retired generation queryable for `grace_period` after its
+    /// rotation.
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = Some(grace_period);
+        self
+    }
+
+    /// Tests only as many of an item's bits in the current generation as
+    /// keep its false positive rate at or below `fp_rate`, recomputed from
+    /// the estimated fill as items are inserted. See
+    /// [`params::probes_for_fill`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fp_rate` is not between 0.0 and 1.0, exclusive.
+    pub fn with_adaptive_probes(mut self, fp_rate: f64) -> Self {
+        self.probe_target = Some(fp_rate);
+        self.update_probes();
+        self
+    }
+
+    /// Returns the number of an item's bits lookups in the current
+    /// generation test.
+    pub fn probe_count(&self) -> u32 {
+        self.probes
+    }
+
+    /// Inserts an item, now, rotating first if the filter is past its
+    /// threshold. Returns `true` if the item was new to the current
+    /// generation.
+    pub fn insert(&mut self, item: &T) -> bool {
+        self.insert_at(item, Instant::now())
+    }
+
+    /// Inserts an item as of `now`, e.g. the time of a replayed event.
+    pub fn insert_at(&mut self, item: &T, now: Instant) -> bool {
+        if self.estimated_fp_rate() > self.max_fp_rate {
+            self.rotate_at(now);
+        }
+        if !self.current.check_and_insert(item) {
+            return false;
+        }
+        self.unset *= self.decay;
+        self.update_probes();
+        true
+    }
+
+    /// Checks if an item might be in the current generation, or in the
+    /// previous one if it is within its grace period.
+    pub fn contains(&self, item: &T) -> bool {
+        self.contains_at(item, Instant::now())
+    }
+
+    /// Checks if an item might be present as of `now`.
+    pub fn contains_at(&self, item: &T, now: Instant) -> bool {
+        self.current.contains_with_probes(item, self.probes)
+            || self
+                .previous
+                .as_ref()
+                .is_some_and(|(previous, until)| now < *until && previous.contains(item))
+    }
+
+    /// Returns the estimated false positive rate of the current generation.
+    ///
+    /// Rotation happens on the insert after the estimate passes the
+    /// threshold, so it may briefly exceed it.
+    pub fn estimated_fp_rate(&self) -> f64 {
+        (1.0 - self.unset).powi(self.current.hash_count() as i32)
+    }
+
+    /// Rotates to a fresh generation now, regardless of the estimate.
+    pub fn rotate(&mut self) {
+        self.rotate_at(Instant::now());
+    }
+
+    /// Rotates to a fresh generation as of `now`.
+    pub fn rotate_at(&mut self, now: Instant) {
+        let mut next = match self.previous.take() {
+            Some((mut previous, _)) => {
+                previous.clear();
+                previous
+            }
+            None => {
+                let mut next = self.current.clone();
+                next.clear();
+                next
+            }
+        };
+        std::mem::swap(&mut self.current, &mut next);
+        if let Some(grace_period) = self.grace_period {
+            self.previous = Some((next, now + grace_period));
+        }
+        self.unset = 1.0;
+        self.rotations += 1;
+        self.update_probes();
+    }
+
+    /// Returns the number of rotations so far.
+    pub fn rotation_count(&self) -> u64 {
+        self.rotations
+    }
+
+    /// Returns the current generation.
+    pub fn current(&self) -> &BloomFilter<T, B> {
+        &self.current
+    }
+
+    /// Returns the previous generation if it is within its grace period as
+    /// of `now`.
+    pub fn previous_at(&self, now: Instant) -> Option<&BloomFilter<T, B>> {
+        self.previous
+            .as_ref()
+            .filter(|(_, until)| now < *until)
+            .map(|(previous, _)| previous)
+    }
+
+    fn update_probes(&mut self) {
+        let k = self.current.hash_count();
+        self.probes = match self.probe_target {
+            Some(fp_rate) => params::probes_for_fill(1.0 - self.unset, k, fp_rate),
+            None => k,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotates_at_threshold() {
+        let start = Instant::now();
+        let mut filter = RotatingBloomFilter::new(BloomFilter::<u64>::new(1000, 0.01), 0.02)
+            .with_grace_period(Duration::from_secs(10));
+        for i in 0..1000 {
+            filter.insert_at(&i, start);
+        }
+        assert_eq!(filter.rotation_count(), 0);
+        let mut inserted = 1000;
+        while filter.rotation_count() == 0 {
+            filter.insert_at(&inserted, start);
+            inserted += 1;
+        }
+        assert!(filter.estimated_fp_rate() < 0.02);
+
+        // Keys of the retired generation are found until the grace period ends.
+        assert!((0..inserted).all(|i| filter.contains_at(&i, start + Duration::from_secs(9))));
+        assert!(filter.previous_at(start).is_some());
+        assert!(!filter.contains_at(&0, start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_without_grace_period() {
+        let mut filter = RotatingBloomFilter::new(BloomFilter::<u64>::new(100, 0.01), 0.5);
+        filter.insert(&1);
+        filter.rotate();
+        assert!(!filter.contains(&1));
+        assert!(filter.previous_at(Instant::now()).is_none());
+        assert_eq!(filter.current().fill_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_adaptive_probes() {
+        let mut filter = RotatingBloomFilter::new(BloomFilter::<u64>::new(10_000, 0.01), 0.02)
+            .with_adaptive_probes(0.01);
+        assert_eq!(filter.probe_count(), 1);
+        for i in 0..10_000 {
+            filter.insert(&i);
+            assert!(filter.contains(&i));
+            let (probes, k) = (filter.probe_count(), filter.current().hash_count());
+            if probes < k {
+                assert!(filter.estimated_fp_rate().powf(probes as f64 / k as f64) <= 0.01);
+            }
+        }
+        assert_eq!(filter.probe_count(), filter.current().hash_count());
+        filter.rotate();
+        assert_eq!(filter.probe_count(), 1);
+    }
+}
@@ -0,0 +1,367 @@
+use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use crate::approx::ApproximateSet;
+use crate::hashing;
+use crate::{AtomicBloomFilter, BloomError, FilterEvent, FilterParams, Hooks};
+
+/// The maximum number of slices. Growth factors are at least 2, so the last
+/// slice would hold at least 2^63 times the initial capacity: the slices
+/// hold more items than a `usize` counts, and allocating them runs out of
+/// memory long before this is reached.
+const MAX_SLICES: usize = 64;
+
+/// One fixed-size slice of a [`ScalableBloomFilter`].
+#[derive(Debug)]
+struct Slice<T: ?Sized> {
+    filter: AtomicBloomFilter<T>,
+    /// The number of items this slice was sized for.
+    capacity: usize,
+    /// Insert attempts claimed against `capacity`; may overshoot it.
+    claimed: AtomicUsize,
+}
+
+/// An auto-growing Bloom Filter that is safe for concurrent inserts.
+///
+/// Implements the scalable Bloom Filter of Almeida et al.: items go into the
+/// newest of a series of [`AtomicBloomFilter`] slices, and once a slice has
+/// absorbed its capacity a larger one with a tighter false positive rate is
+/// added. The compound false positive rate stays below the configured rate
+/// however many items arrive, which makes the filter suitable for
+/// deduplicating unbounded streams.
+///
+/// All methods take `&self`. Lookups and inserts are lock-free; adding a
+/// slice is coordinated through a `OnceLock`, so when a slice fills up
+/// exactly one thread allocates the next one while the others briefly wait
+/// for it.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::ScalableBloomFilter;
+///
+/// let filter = ScalableBloomFilter::new(100, 0.01);
+/// for i in 0..1000u64 {
+///     filter.insert(&i);
+/// }
+/// assert!(filter.contains(&999));
+/// assert!(filter.slice_count() > 1);
+/// ```
+#[derive(Debug)]
+pub struct ScalableBloomFilter<T: ?Sized> {
+    /// Slots for the slices; the first `slice_count` are initialized.
+    slices: Box<[OnceLock<Slice<T>>]>,
+    /// The number of published slices.
+    slice_count: AtomicUsize,
+    /// The capacity of the first slice.
+    initial_capacity: usize,
+    /// The false positive rate of the first slice.
+    initial_fp_rate: f64,
+    /// The capacity multiplier from one slice to the next.
+    growth_factor: usize,
+    /// The false positive rate multiplier from one slice to the next.
+    tightening_ratio: f64,
+    /// Callbacks for added slices.
+    hooks: Hooks,
+}
+
+impl<T: ?Sized + Hash> ScalableBloomFilter<T> {
+    /// Creates a scalable filter whose compound false positive rate stays
+    /// below `fp_rate`, starting with room for `initial_capacity` items.
+    ///
+    /// Uses a growth factor of 2 and a tightening ratio of 0.85.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial_capacity` is 0 or `fp_rate` is not between 0.0 and 1.0, exclusive.
+    pub fn new(initial_capacity: usize, fp_rate: f64) -> Self {
+        Self::with_growth(initial_capacity, fp_rate, 2, 0.85)
+    }
+
+    /// Creates a scalable filter with a custom growth policy.
+    ///
+    /// Each new slice holds `growth_factor` times the items of the previous
+    /// one, at `tightening_ratio` times its false positive rate. The first
+    /// slice gets `fp_rate * (1 - tightening_ratio)`, so the geometric series
+    /// over all slices sums to at most `fp_rate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial_capacity` is 0, `growth_factor` is less than 2, if
+    /// `fp_rate` is not between 0.0 and 1.0, or if `tightening_ratio` is not
+    /// between 0.0 and 1.0, exclusive.
+    pub fn with_growth(
+        initial_capacity: usize,
+        fp_rate: f64,
+        growth_factor: usize,
+        tightening_ratio: f64,
+    ) -> Self {
+        Self::try_with_growth(initial_capacity, fp_rate, growth_factor, tightening_ratio)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a scalable filter with a custom growth policy, returning an
+    /// error instead of panicking on invalid sizing parameters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `growth_factor` is less than 2, as slices that do not grow
+    /// would run out after 64 times the initial capacity, or if
+    /// `tightening_ratio` is not between 0.0 and 1.0, exclusive.
+    pub fn try_with_growth(
+        initial_capacity: usize,
+        fp_rate: f64,
+        growth_factor: usize,
+        tightening_ratio: f64,
+    ) -> Result<Self, BloomError> {
+        assert!(growth_factor >= 2, "Growth factor must be at least 2.");
+        assert!(
+            tightening_ratio > 0.0 && tightening_ratio < 1.0,
+            "Tightening ratio must be between 0.0 and 1.0, exclusive."
+        );
+        if !(fp_rate > 0.0 && fp_rate < 1.0) {
+            return Err(BloomError::InvalidFalsePositiveRate(fp_rate));
+        }
+
+        let filter = ScalableBloomFilter {
+            slices: (0..MAX_SLICES).map(|_| OnceLock::new()).collect(),
+            slice_count: AtomicUsize::new(1),
+            initial_capacity,
+            initial_fp_rate: fp_rate * (1.0 - tightening_ratio),
+            growth_factor,
+            tightening_ratio,
+            hooks: Hooks::new(),
+        };
+        let first = filter.new_slice(0)?;
+        let _ = filter.slices[0].set(first);
+        Ok(filter)
+    }
+
+    /// Reports [`FilterEvent::SliceAdded`] to `hooks` whenever a slice is
+    /// added, e.g. to persist the filter or alert on unexpected growth.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use bloomlib::{Hooks, ScalableBloomFilter};
+    ///
+    /// let added = Arc::new(AtomicUsize::new(0));
+    /// let counter = Arc::clone(&added);
+    /// let hooks = Hooks::new().on_event(move |_| {
+    ///     counter.fetch_add(1, Ordering::Relaxed);
+    /// });
+    ///
+    /// let filter = ScalableBloomFilter::new(100, 0.01).with_hooks(hooks);
+    /// for i in 0..1000u64 {
+    ///     filter.insert(&i);
+    /// }
+    /// assert_eq!(added.load(Ordering::Relaxed), filter.slice_count() - 1);
+    /// ```
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Inserts an item, adding a slice first if the current one is full.
+    ///
+    /// Returns `true` if the item was new, or `false` if it was (probably)
+    /// already present, in which case nothing is changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a new slice is needed but cannot be allocated.
+    pub fn insert(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair(item);
+        let mut count = self.slice_count.load(Ordering::Acquire);
+        if self
+            .published(count)
+            .any(|slice| slice.filter.contains_hashed(h1, h2))
+        {
+            return false;
+        }
+
+        loop {
+            let slice = self.slice(count - 1);
+            if slice.claimed.fetch_add(1, Ordering::Relaxed) < slice.capacity {
+                slice.filter.insert_hashed(h1, h2);
+                return true;
+            }
+            count = self.grow(count);
+        }
+    }
+
+    /// Checks if an item might be in the Bloom Filter.
+    ///
+    /// Returns `true` if the item might be present (with a probability of false positive).
+    /// Returns `false` if the item is definitely not present.
+    pub fn contains(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair(item);
+        let count = self.slice_count.load(Ordering::Acquire);
+        self.published(count)
+            .any(|slice| slice.filter.contains_hashed(h1, h2))
+    }
+
+    /// Returns the approximate number of distinct items inserted.
+    pub fn len(&self) -> usize {
+        let count = self.slice_count.load(Ordering::Acquire);
+        self.published(count)
+            .map(|slice| slice.claimed.load(Ordering::Relaxed).min(slice.capacity))
+            .sum()
+    }
+
+    /// Returns `true` if no items have been inserted.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of slices currently allocated.
+    pub fn slice_count(&self) -> usize {
+        self.slice_count.load(Ordering::Acquire)
+    }
+
+    /// Returns the approximate memory usage of all slices in bytes.
+    pub fn memory_usage_bytes(&self) -> usize {
+        let count = self.slice_count.load(Ordering::Acquire);
+        self.published(count)
+            .map(|slice| slice.filter.memory_usage_bytes())
+            .sum()
+    }
+
+    /// Publishes slice `count` after slice `count - 1` filled up, returning
+    /// the new slice count. Safe to call from many threads at once.
+    fn grow(&self, count: usize) -> usize {
+        assert!(
+            count < MAX_SLICES,
+            "Scalable filter cannot add more slices."
+        );
+        self.slices[count].get_or_init(|| {
+            self.new_slice(count)
+                .unwrap_or_else(|err| panic!("{}", err))
+        });
+        // Losing this race means another thread already published the slice.
+        let published = self.slice_count.compare_exchange(
+            count,
+            count + 1,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+        if published.is_ok() {
+            self.hooks.emit(FilterEvent::SliceAdded {
+                slice_count: count + 1,
+                capacity: self.slice(count).capacity,
+            });
+        }
+        self.slice_count.load(Ordering::Acquire)
+    }
+
+    /// Allocates the slice at position `index` of the growth series.
+    fn new_slice(&self, index: usize) -> Result<Slice<T>, BloomError> {
+        let growth = self.growth_factor.saturating_pow(index as u32);
+        let capacity = self.initial_capacity.saturating_mul(growth);
+        let fp_rate = self.initial_fp_rate * self.tightening_ratio.powi(index as i32);
+        Ok(Slice {
+            filter: AtomicBloomFilter::try_new(capacity, FilterParams::FalsePositiveRate(fp_rate))?,
+            capacity,
+            claimed: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns the initialized slice at `index`.
+    fn slice(&self, index: usize) -> &Slice<T> {
+        self.slices[index]
+            .get()
+            .expect("published slices are initialized")
+    }
+
+    /// Iterates over the first `count` (published) slices.
+    fn published(&self, count: usize) -> impl Iterator<Item = &Slice<T>> {
+        (0..count).map(move |index| self.slice(index))
+    }
+}
+
+impl<T: ?Sized + Hash> ApproximateSet<T> for ScalableBloomFilter<T> {
+    fn insert(&mut self, item: &T) {
+        ScalableBloomFilter::insert(self, item);
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        ScalableBloomFilter::contains(self, item)
+    }
+
+    fn estimated_len(&self) -> f64 {
+        self.len() as f64
+    }
+
+    /// A lookup is a false positive if any slice reports one.
+    fn fp_estimate(&self) -> f64 {
+        let count = self.slice_count.load(Ordering::Acquire);
+        1.0 - self
+            .published(count)
+            .map(|slice| 1.0 - slice.filter.fp_estimate())
+            .product::<f64>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_grows_and_keeps_items() {
+        let filter = ScalableBloomFilter::new(100, 0.01);
+        for i in 0..2000u64 {
+            filter.insert(&i);
+        }
+        assert!(filter.slice_count() >= 4);
+        for i in 0..2000u64 {
+            assert!(filter.contains(&i));
+        }
+
+        let false_positives = (2000..12000u64).filter(|i| filter.contains(i)).count();
+        assert!(false_positives < 100, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_insert_reports_duplicates() {
+        let filter = ScalableBloomFilter::new(10, 0.01);
+        assert!(filter.insert("seen"));
+        assert!(!filter.insert("seen"));
+        assert_eq!(filter.len(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_inserts_across_growth() {
+        let filter = ScalableBloomFilter::new(64, 0.01);
+        thread::scope(|scope| {
+            for t in 0..4u64 {
+                let filter = &filter;
+                scope.spawn(move || {
+                    for i in 0..5000 {
+                        filter.insert(&(t * 5000 + i));
+                    }
+                });
+            }
+        });
+
+        for i in 0..20000u64 {
+            assert!(filter.contains(&i));
+        }
+        assert!(filter.slice_count() > 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Growth factor must be at least 2.")]
+    fn test_growth_factor_of_one() {
+        ScalableBloomFilter::<u64>::with_growth(10, 0.01, 1, 0.85);
+    }
+
+    #[test]
+    fn test_invalid_rate() {
+        let err = ScalableBloomFilter::<u64>::try_with_growth(10, 1.0, 2, 0.85).unwrap_err();
+        assert_eq!(err, BloomError::InvalidFalsePositiveRate(1.0));
+    }
+}
@@ -0,0 +1,234 @@
+//! Serde support for [`BloomFilter`] fields, for use with
+//! `#[serde(with = "bloomlib::serde_b64")]`.
+//!
+//! Requires the `serde` feature. The filter is written in the format of
+//! [`BloomFilter::to_bytes`]: as a base64 string (standard alphabet, padded)
+//! in human-readable formats such as JSON or TOML, and as raw bytes in
+//! binary ones. Either form is accepted when deserializing.
+//!
+//! # Examples
+//!
+//! ```
+//! use bloomlib::BloomFilter;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct State {
+//!     generation: u32,
+//!     #[serde(with = "bloomlib::serde_b64")]
+//!     seen: BloomFilter<str>,
+//! }
+//!
+//! let mut seen = BloomFilter::new(100, 0.01);
+//! seen.insert("alice");
+//! let json = serde_json::to_string(&State { generation: 1, seen }).unwrap();
+//! assert!(json.starts_with(r#"{"generation":1,"seen":"QkxNR"#));
+//!
+//! let state: State = serde_json::from_str(&json).unwrap();
+//! assert!(state.seen.contains("alice"));
+//! ```
+
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::Serializer;
+
+use crate::{AllocStore, BitStore, BloomFilter};
+
+/// The most bytes reserved up front for a sequence, whatever length the
+/// input claims, as serde's own `size_hint::cautious` does.
+const MAX_PREALLOCATED_BYTES: usize = 4096;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Serializes `filter` as base64 or raw bytes, depending on the format.
+pub fn serialize<T, B, S>(filter: &BloomFilter<T, B>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: ?Sized + Hash,
+    B: BitStore,
+    S: Serializer,
+{
+    let bytes = filter.to_bytes();
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&encode(&bytes))
+    } else {
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+/// Deserializes a filter written by [`serialize`].
+pub fn deserialize<'de, T, B, D>(deserializer: D) -> Result<BloomFilter<T, B>, D::Error>
+where
+    T: ?Sized + Hash,
+    B: AllocStore,
+    D: Deserializer<'de>,
+{
+    let visitor = FilterVisitor(PhantomData);
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(visitor)
+    } else {
+        deserializer.deserialize_bytes(visitor)
+    }
+}
+
+struct FilterVisitor<T: ?Sized, B>(PhantomData<fn(&T) -> B>);
+
+impl<T: ?Sized + Hash, B: AllocStore> FilterVisitor<T, B> {
+    fn load<E: de::Error>(bytes: &[u8]) -> Result<BloomFilter<T, B>, E> {
+        BloomFilter::from_bytes(bytes).map_err(E::custom)
+    }
+}
+
+impl<'de, T: ?Sized + Hash, B: AllocStore> Visitor<'de> for FilterVisitor<T, B> {
+    type Value = BloomFilter<T, B>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a serialized Bloom filter, as base64 or bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        let bytes = decode(value).ok_or_else(|| E::custom("invalid base64"))?;
+        Self::load(&bytes)
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+        Self::load(value)
+    }
+
+    // Formats without a byte type write bytes as a sequence.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        // The length comes from the input, e.g. a bincode prefix, so it is
+        // only trusted as far as the cap.
+        let capacity = seq.size_hint().unwrap_or(0).min(MAX_PREALLOCATED_BYTES);
+        let mut bytes = Vec::with_capacity(capacity);
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Self::load(&bytes)
+    }
+}
+
+/// Encodes `bytes` as padded base64.
+fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decodes padded base64, returning `None` if it is malformed.
+fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.as_bytes();
+    if !encoded.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3);
+    for (index, chunk) in encoded.chunks(4).enumerate() {
+        let last = index == encoded.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+        let mut bits = 0u32;
+        for &c in &chunk[..4 - padding] {
+            let value = ALPHABET.iter().position(|&a| a == c)?;
+            bits = bits << 6 | value as u32;
+        }
+        bits <<= 6 * padding as u32;
+        bytes.extend_from_slice(&bits.to_be_bytes()[1..4 - padding]);
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        for (plain, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(encode(plain.as_bytes()), encoded);
+            assert_eq!(decode(encoded).unwrap(), plain.as_bytes());
+        }
+        let all: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode(&encode(&all)).unwrap(), all);
+        for malformed in ["Zg=", "Zg=a", "Z===", "Zg==Zg==", "Zm9*"] {
+            assert_eq!(decode(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut filter: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        (0..500).for_each(|i| filter.insert(&i));
+
+        let mut json = Vec::new();
+        serialize(&filter, &mut serde_json::Serializer::new(&mut json)).unwrap();
+        let text = String::from_utf8(json).unwrap();
+        assert_eq!(text, format!("\"{}\"", encode(&filter.to_bytes())));
+
+        let loaded: BloomFilter<u64> =
+            deserialize(&mut serde_json::Deserializer::from_str(&text)).unwrap();
+        assert_eq!(loaded.to_bytes(), filter.to_bytes());
+
+        let error = deserialize::<u64, Vec<u64>, _>(&mut serde_json::Deserializer::from_str(
+            "\"QkxNRg==\"",
+        ))
+        .unwrap_err();
+        assert!(error.to_string().contains("truncated"), "{}", error);
+    }
+
+    #[test]
+    fn test_forged_sequence_length_is_not_preallocated() {
+        /// A sequence that claims to be huge but holds a few bytes.
+        struct Forged(std::vec::IntoIter<u8>);
+
+        impl<'de> SeqAccess<'de> for Forged {
+            type Error = de::value::Error;
+
+            fn next_element_seed<S: de::DeserializeSeed<'de>>(
+                &mut self,
+                seed: S,
+            ) -> Result<Option<S::Value>, Self::Error> {
+                self.0
+                    .next()
+                    .map(|byte| seed.deserialize(de::value::U8Deserializer::new(byte)))
+                    .transpose()
+            }
+
+            fn size_hint(&self) -> Option<usize> {
+                Some(usize::MAX)
+            }
+        }
+
+        let filter: BloomFilter<u64> = BloomFilter::new(100, 0.01);
+        let loaded = FilterVisitor::<u64, Vec<u64>>(PhantomData)
+            .visit_seq(Forged(filter.to_bytes().into_iter()))
+            .unwrap();
+        assert_eq!(loaded.to_bytes(), filter.to_bytes());
+        let error = FilterVisitor::<u64, Vec<u64>>(PhantomData)
+            .visit_seq(Forged(Vec::new().into_iter()))
+            .unwrap_err();
+        assert!(error.to_string().contains("truncated"), "{}", error);
+    }
+}
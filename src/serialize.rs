@@ -0,0 +1,908 @@
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::thread;
+
+use crate::counters::OpCounters;
+use crate::params::Geometry;
+use crate::{trace, AllocStore, BitStore, BloomError, BloomFilter, CancelToken, Progress, Word};
+
+/// The bytes every serialized filter starts with.
+const MAGIC: [u8; 4] = *b"BLMF";
+
+/// The version of the format written by [`BloomFilter::to_bytes`], the only
+/// one read.
+pub(crate) const VERSION: u32 = 2;
+
+/// The length of the header: magic, version, bit count and hash count.
+const HEADER_BYTES: usize = 20;
+
+/// The length of the CRC-32 that ends a filter.
+const CHECKSUM_BYTES: usize = 4;
+
+/// The number of words written or read per I/O call.
+const IO_WORDS: usize = 1024;
+
+/// The fewest words [`BloomFilter::from_bytes_parallel`] gives a thread:
+/// 64 KiB, below which spawning the thread costs more than it saves.
+const MIN_PART_WORDS: usize = 8 * 1024;
+
+/// The hash count [`LoadLimits::new`] allows: enough for a false positive
+/// rate of 2^-64.
+const UNTRUSTED_MAX_HASH_COUNT: u32 = 64;
+
+/// Bounds on the filters [`BloomFilter::from_bytes_with_limits`] and
+/// [`BloomFilter::read_from_with_limits`] accept, for loading filters from
+/// untrusted sources.
+///
+/// Both are checked against the header, before anything is allocated. The
+/// [`Default`] limits accept any filter the platform can allocate, as
+/// [`BloomFilter::from_bytes`] does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadLimits {
+    /// The largest bit array, in bytes, a loaded filter may allocate.
+    pub max_bytes: u64,
+    /// The largest hash count a loaded filter may have. Every lookup costs
+    /// this many probes.
+    pub max_hash_count: u32,
+}
+
+impl LoadLimits {
+    /// Limits filters to `max_bytes` of bits and 64 hashes.
+    pub fn new(max_bytes: u64) -> Self {
+        LoadLimits {
+            max_bytes,
+            max_hash_count: UNTRUSTED_MAX_HASH_COUNT,
+        }
+    }
+}
+
+impl Default for LoadLimits {
+    fn default() -> Self {
+        LoadLimits {
+            max_bytes: u64::MAX,
+            max_hash_count: u32::MAX,
+        }
+    }
+}
+
+/// Encodes the header of a filter of `bit_count` bits and `hash_count` hashes.
+pub(crate) fn encode_header(bit_count: u64, hash_count: u32) -> [u8; HEADER_BYTES] {
+    let mut header = [0u8; HEADER_BYTES];
+    header[0..4].copy_from_slice(&MAGIC);
+    header[4..8].copy_from_slice(&VERSION.to_le_bytes());
+    header[8..16].copy_from_slice(&bit_count.to_le_bytes());
+    header[16..20].copy_from_slice(&hash_count.to_le_bytes());
+    header
+}
+
+/// Decodes a header into the geometry of a filter stored in words of type
+/// `W`, checking it against `limits`.
+fn decode_header<W: Word>(
+    header: &[u8; HEADER_BYTES],
+    limits: &LoadLimits,
+) -> Result<Geometry, BloomError> {
+    if header[0..4] != MAGIC {
+        return Err(BloomError::InvalidFormat("the magic bytes are missing"));
+    }
+    if u32::from_le_bytes(header[4..8].try_into().unwrap()) != VERSION {
+        return Err(BloomError::InvalidFormat(
+            "the format version is unsupported",
+        ));
+    }
+    let bit_count = u64::from_le_bytes(header[8..16].try_into().unwrap());
+    let hash_count = u32::from_le_bytes(header[16..20].try_into().unwrap());
+    let geometry = Geometry::exact::<W>(bit_count, hash_count)?;
+    if hash_count as u64 > bit_count {
+        return Err(BloomError::InvalidFormat(
+            "the hash count exceeds the bit count",
+        ));
+    }
+    if hash_count > limits.max_hash_count {
+        return Err(BloomError::InvalidFormat(
+            "the hash count exceeds the limit",
+        ));
+    }
+    if geometry.word_count as u64 * (W::BITS as u64 / 8) > limits.max_bytes {
+        return Err(BloomError::InvalidFormat(
+            "the bit array exceeds the size limit",
+        ));
+    }
+    Ok(geometry)
+}
+
+/// Returns the words per part when `from_bytes_parallel` splits
+/// `word_count` words between at most `threads` threads.
+fn part_words(word_count: usize, threads: usize) -> usize {
+    let available = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let threads = threads
+        .min(available)
+        .min(word_count / MIN_PART_WORDS)
+        .max(1);
+    word_count.div_ceil(threads).max(1)
+}
+
+/// The CRC-32 (IEEE 802.3) that ends a serialized filter, computed over the
+/// header and the bit array.
+#[derive(Clone, Copy)]
+pub(crate) struct Crc32(u32);
+
+/// The table of the reflected polynomial `0xEDB88320`, one entry per byte.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut crc = byte as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[byte] = crc;
+        byte += 1;
+    }
+    table
+};
+
+impl Crc32 {
+    pub(crate) fn new() -> Self {
+        Crc32(!0)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 >> 8) ^ CRC_TABLE[((self.0 ^ byte as u32) & 0xff) as usize];
+        }
+    }
+
+    /// Extends the checksum as if the `len` bytes whose checksum is `other`
+    /// had been passed to [`update`](Self::update), without reading them.
+    ///
+    /// Appending `len` bytes multiplies the polynomial of the CRC so far by
+    /// `x^(8 len)` modulo the CRC polynomial before the other CRC is added,
+    /// as zlib's `crc32_combine` does.
+    pub(crate) fn append(&mut self, other: Crc32, len: u64) {
+        let mut shift = 1 << 31;
+        let mut power = 1 << 30;
+        let mut remaining = len * 8;
+        while remaining != 0 {
+            if remaining & 1 == 1 {
+                shift = gf2_multiply(power, shift);
+            }
+            power = gf2_multiply(power, power);
+            remaining >>= 1;
+        }
+        self.0 = !(gf2_multiply(shift, !self.0) ^ !other.0);
+    }
+
+    pub(crate) fn finish(self) -> [u8; CHECKSUM_BYTES] {
+        (!self.0).to_le_bytes()
+    }
+}
+
+/// Multiplies two reflected polynomials modulo the CRC polynomial.
+fn gf2_multiply(a: u32, mut b: u32) -> u32 {
+    let mut product = 0;
+    for bit in (0..32).rev() {
+        if a >> bit & 1 == 1 {
+            product ^= b;
+        }
+        b = if b & 1 == 1 {
+            (b >> 1) ^ 0xEDB8_8320
+        } else {
+            b >> 1
+        };
+    }
+    product
+}
+
+/// Reads the checksum ending a filter from `reader` and checks it against
+/// `crc`.
+fn verify_checksum<R: Read>(mut reader: R, crc: Crc32) -> io::Result<()> {
+    let mut checksum = [0u8; CHECKSUM_BYTES];
+    reader.read_exact(&mut checksum)?;
+    if checksum != crc.finish() {
+        return Err(invalid_data(BloomError::InvalidFormat(
+            "the checksum does not match",
+        )));
+    }
+    Ok(())
+}
+
+/// ORs the `index`-th `u64` word of a serialized filter of `geometry` into
+/// `store`, rejecting bits set beyond the bit count.
+pub(crate) fn or_word<B: BitStore>(
+    store: &mut B,
+    geometry: &Geometry,
+    index: usize,
+    word: u64,
+) -> Result<(), BloomError> {
+    let word_count = geometry.bit_count.div_ceil(64) as usize;
+    let spare_bits = (word_count * 64) as u64 - geometry.bit_count;
+    if index + 1 == word_count && spare_bits > 0 && word >> (64 - spare_bits) != 0 {
+        return Err(BloomError::InvalidFormat(
+            "bits are set beyond the bit count",
+        ));
+    }
+
+    let word_bits = B::Word::BITS as usize;
+    let words_per_u64 = 64 / word_bits;
+    for offset in 0..words_per_u64 {
+        let store_index = index * words_per_u64 + offset;
+        let part = B::Word::from_u64(word >> (offset * word_bits));
+        if store_index < geometry.word_count && part != B::Word::ZERO {
+            store.set_bits(store_index, part);
+        }
+    }
+    Ok(())
+}
+
+/// Reads the `u64` words of a serialized filter of `geometry` from `reader`,
+/// adding them to `crc` and passing each to `visit` with its index. With a
+/// `cancel` token, progress through the `total` bytes of the filter is
+/// reported before each block of words.
+fn read_words<R: Read>(
+    mut reader: R,
+    geometry: &Geometry,
+    crc: &mut Crc32,
+    cancel: Option<(&CancelToken, usize)>,
+    mut visit: impl FnMut(usize, u64) -> Result<(), BloomError>,
+) -> io::Result<()> {
+    let word_count = geometry.bit_count.div_ceil(64) as usize;
+    let mut buffer = vec![0u8; IO_WORDS.min(word_count) * 8];
+    let mut index = 0;
+    while index < word_count {
+        if let Some((cancel, total)) = cancel {
+            cancel
+                .checkpoint(Progress::bytes(HEADER_BYTES + index * 8, total))
+                .map_err(io::Error::other)?;
+        }
+        let chunk = &mut buffer[..IO_WORDS.min(word_count - index) * 8];
+        reader.read_exact(chunk)?;
+        crc.update(chunk);
+        for word in chunk.chunks_exact(8) {
+            visit(index, u64::from_le_bytes(word.try_into().unwrap())).map_err(invalid_data)?;
+            index += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Fills a zeroed store from the `u64` words of a serialized filter.
+struct Unpacker<B> {
+    store: B,
+    geometry: Geometry,
+    /// The index of the next `u64` word.
+    next: usize,
+}
+
+impl<B: AllocStore> Unpacker<B> {
+    fn new(geometry: Geometry) -> Self {
+        Unpacker {
+            store: B::zeroed(geometry.word_count),
+            geometry,
+            next: 0,
+        }
+    }
+
+    /// Stores the next word, rejecting bits set beyond the bit count.
+    fn push(&mut self, word: u64) -> Result<(), BloomError> {
+        or_word(&mut self.store, &self.geometry, self.next, word)?;
+        self.next += 1;
+        Ok(())
+    }
+
+    fn finish<T: ?Sized>(self) -> BloomFilter<T, B> {
+        BloomFilter {
+            bit_vec: self.store,
+            bit_count: self.geometry.bit_count,
+            hash_fn_count: self.geometry.hash_count,
+            counters: OpCounters::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized + Hash, B: BitStore> BloomFilter<T, B> {
+    /// Returns the length in bytes of the filter's serialized form.
+    pub fn serialized_len(&self) -> usize {
+        HEADER_BYTES + self.bit_count.div_ceil(64) as usize * 8 + CHECKSUM_BYTES
+    }
+
+    /// Serializes the filter into a byte vector.
+    ///
+    /// The format is a 20-byte header (the magic bytes `BLMF`, a format
+    /// version, the bit count and the hash count), the bit array as
+    /// little-endian `u64` words, and a CRC-32 of both. It does not depend
+    /// on the store, so a filter can be loaded back into a store with
+    /// another word type, or on a platform with another byte order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut bf: BloomFilter<str> = BloomFilter::new(100, 0.01);
+    /// bf.insert("seen");
+    ///
+    /// let bytes = bf.to_bytes();
+    /// let loaded: BloomFilter<str> = BloomFilter::from_bytes(&bytes).unwrap();
+    /// assert!(loaded.contains("seen"));
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_len());
+        self.write_to(&mut bytes)
+            .expect("writing to a Vec cannot fail");
+        bytes
+    }
+
+    /// Serializes the filter into `writer`, in the format of
+    /// [`BloomFilter::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `writer` returns.
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write_until(writer, None)
+    }
+
+    /// Serializes the filter into `writer` like
+    /// [`write_to`](Self::write_to), stopping early if `cancel` is
+    /// cancelled.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `writer` returns, and an error wrapping
+    /// [`BloomError::Cancelled`] if the token is cancelled before the filter
+    /// is written. The filter is unchanged, but `writer` then holds an
+    /// incomplete filter without a checksum, which loading rejects; write
+    /// to a temporary file and rename it once this returns `Ok`.
+    pub fn write_to_cancellable<W: Write>(
+        &self,
+        writer: W,
+        cancel: &CancelToken,
+    ) -> io::Result<()> {
+        self.write_until(writer, Some(cancel))
+    }
+
+    fn write_until<W: Write>(&self, mut writer: W, cancel: Option<&CancelToken>) -> io::Result<()> {
+        trace::timed_span!("bloom.write", bytes = self.serialized_len());
+        let header = encode_header(self.bit_count, self.hash_fn_count);
+        let mut crc = Crc32::new();
+        crc.update(&header);
+        writer.write_all(&header)?;
+
+        let word_count = self.bit_count.div_ceil(64) as usize;
+        let total = self.serialized_len();
+        let mut written = HEADER_BYTES;
+        let mut buffer = Vec::with_capacity(IO_WORDS.min(word_count) * 8);
+        for word in self.packed_words().take(word_count) {
+            buffer.extend_from_slice(&word.to_le_bytes());
+            if buffer.len() == IO_WORDS * 8 {
+                if let Some(cancel) = cancel {
+                    cancel
+                        .checkpoint(Progress::bytes(written, total))
+                        .map_err(io::Error::other)?;
+                }
+                crc.update(&buffer);
+                writer.write_all(&buffer)?;
+                written += buffer.len();
+                buffer.clear();
+            }
+        }
+        crc.update(&buffer);
+        writer.write_all(&buffer)?;
+        writer.write_all(&crc.finish())?;
+        if let Some(cancel) = cancel {
+            cancel.report(Progress::bytes(total, total));
+        }
+        Ok(())
+    }
+
+    /// Merges a filter serialized in the format of
+    /// [`BloomFilter::to_bytes`] into this one, as [`BloomFilter::merge`]
+    /// does, reading it from `reader` a block of words at a time.
+    ///
+    /// Only a small buffer is allocated, so merging snapshots from replicas
+    /// needs no memory for a second filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::BloomFilter;
+    ///
+    /// let mut replica: BloomFilter<str> = BloomFilter::new(100, 0.01);
+    /// replica.insert("banana");
+    /// let snapshot = replica.to_bytes();
+    ///
+    /// let mut local: BloomFilter<str> = BloomFilter::new(100, 0.01);
+    /// local.insert("apple");
+    /// local.merge_from_reader(&snapshot[..]).unwrap();
+    /// assert!(local.contains("apple") && local.contains("banana"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `reader` returns, and an error of kind
+    /// [`io::ErrorKind::InvalidData`] wrapping a [`BloomError`] if the data
+    /// is not a serialized filter, or
+    /// [`BloomError::IncompatibleFilters`] if its geometry differs. The
+    /// checksum can only be verified at the end, so an error after the
+    /// header leaves the filter with part of the other's bits merged in; it
+    /// still contains every item it contained before.
+    pub fn merge_from_reader<R: Read>(&mut self, mut reader: R) -> io::Result<()> {
+        let mut header = [0u8; HEADER_BYTES];
+        reader.read_exact(&mut header)?;
+        let geometry =
+            decode_header::<B::Word>(&header, &LoadLimits::default()).map_err(invalid_data)?;
+        trace::timed_span!(
+            "bloom.merge",
+            bits = self.bit_count,
+            hashes = self.hash_fn_count
+        );
+        if (geometry.bit_count, geometry.hash_count) != (self.bit_count, self.hash_fn_count) {
+            return Err(invalid_data(BloomError::IncompatibleFilters {
+                expected: (self.bit_count, self.hash_fn_count),
+                found: (geometry.bit_count, geometry.hash_count),
+            }));
+        }
+        let mut crc = Crc32::new();
+        crc.update(&header);
+        read_words(&mut reader, &geometry, &mut crc, None, |index, word| {
+            or_word(&mut self.bit_vec, &geometry, index, word)
+        })?;
+        verify_checksum(reader, crc)
+    }
+}
+
+impl<T: ?Sized + Hash, B: AllocStore> BloomFilter<T, B> {
+    /// Deserializes a filter written by [`BloomFilter::to_bytes`].
+    ///
+    /// Items must be hashed the same way as when the filter was built: a
+    /// filter of `str` loaded as a filter of `[u8]` finds nothing.
+    ///
+    /// The checksum is verified before the filter is allocated. Any size of
+    /// filter is accepted; use
+    /// [`from_bytes_with_limits`](Self::from_bytes_with_limits) for bytes
+    /// from untrusted sources.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::InvalidFormat`] if `bytes` is not exactly one
+    /// serialized filter, and [`BloomError::ZeroHashCount`] or
+    /// [`BloomError::CapacityOverflow`] if the header describes a filter that
+    /// cannot be built on this platform.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BloomError> {
+        Self::from_bytes_with_limits(bytes, &LoadLimits::default())
+    }
+
+    /// Deserializes a filter like [`BloomFilter::from_bytes`], rejecting
+    /// filters beyond `limits` with [`BloomError::InvalidFormat`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::{BloomFilter, LoadLimits};
+    ///
+    /// let bytes = BloomFilter::<str>::new(1_000_000, 0.01).to_bytes();
+    /// let limits = LoadLimits::new(64 * 1024);
+    /// assert!(BloomFilter::<str>::from_bytes_with_limits(&bytes, &limits).is_err());
+    /// ```
+    pub fn from_bytes_with_limits(bytes: &[u8], limits: &LoadLimits) -> Result<Self, BloomError> {
+        trace::timed_span!("bloom.read", bytes = bytes.len());
+        let geometry = check_layout::<B::Word>(bytes, limits)?;
+        let (data, checksum) = bytes.split_at(bytes.len() - CHECKSUM_BYTES);
+        let mut crc = Crc32::new();
+        crc.update(data);
+        if checksum != crc.finish() {
+            return Err(BloomError::InvalidFormat("the checksum does not match"));
+        }
+
+        let mut unpacker = Unpacker::<B>::new(geometry);
+        for chunk in data[HEADER_BYTES..].chunks_exact(8) {
+            unpacker.push(u64::from_le_bytes(chunk.try_into().unwrap()))?;
+        }
+        Ok(unpacker.finish())
+    }
+
+    /// Deserializes a filter like
+    /// [`from_bytes_with_limits`](Self::from_bytes_with_limits), splitting
+    /// the bit array between up to `threads` threads that each unpack their
+    /// part and compute its checksum.
+    ///
+    /// No more threads are spawned than
+    /// [`available_parallelism`](std::thread::available_parallelism)
+    /// reports, and none for less than 64 KiB of bits, so a small filter is
+    /// loaded by a single thread.
+    ///
+    /// The format has one CRC-32 over the whole filter, but CRCs of
+    /// consecutive parts combine into the CRC of the whole, so the checksum
+    /// is still verified in full. For multi-gigabyte filters, e.g. read with
+    /// `fs::read` or memory-mapped at start-up, this takes a fraction of the
+    /// time of the single pass of `from_bytes`. Unlike `from_bytes`, the
+    /// filter is allocated before the checksum is verified.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as
+    /// [`from_bytes_with_limits`](Self::from_bytes_with_limits).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bloomlib::{BloomFilter, LoadLimits};
+    ///
+    /// let mut filter: BloomFilter<str> = BloomFilter::new(1_000_000, 0.01);
+    /// filter.insert("alice");
+    /// let bytes = filter.to_bytes();
+    ///
+    /// let loaded: BloomFilter<str> =
+    ///     BloomFilter::from_bytes_parallel(&bytes, &LoadLimits::default(), 4).unwrap();
+    /// assert!(loaded.contains("alice"));
+    /// ```
+    pub fn from_bytes_parallel(
+        bytes: &[u8],
+        limits: &LoadLimits,
+        threads: usize,
+    ) -> Result<Self, BloomError> {
+        assert!(threads > 0, "Threads must be greater than 0.");
+        trace::timed_span!("bloom.read", bytes = bytes.len(), threads = threads);
+        let geometry = check_layout::<B::Word>(bytes, limits)?;
+        let (data, checksum) = bytes.split_at(bytes.len() - CHECKSUM_BYTES);
+        let (header, body) = data.split_at(HEADER_BYTES);
+        let word_count = body.len() / 8;
+        let part_words = part_words(word_count, threads);
+
+        let mut store = B::zeroed(geometry.word_count);
+        // Stores that are not plain `u64` words are filled afterwards.
+        let mut parts = store
+            .as_u64_words_mut()
+            .map(|words| words[..word_count].chunks_mut(part_words));
+        let crcs: Vec<(Crc32, usize)> = thread::scope(|scope| {
+            let handles: Vec<_> = body
+                .chunks(part_words * 8)
+                .map(|bytes| {
+                    let words = parts.as_mut().and_then(Iterator::next);
+                    scope.spawn(move || {
+                        if let Some(words) = words {
+                            for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(8)) {
+                                *word = u64::from_le_bytes(chunk.try_into().unwrap());
+                            }
+                        }
+                        let mut crc = Crc32::new();
+                        crc.update(bytes);
+                        (crc, bytes.len())
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        let mut crc = Crc32::new();
+        crc.update(header);
+        for (part_crc, len) in crcs {
+            crc.append(part_crc, len as u64);
+        }
+        if checksum != crc.finish() {
+            return Err(BloomError::InvalidFormat("the checksum does not match"));
+        }
+
+        let fill =
+            |index: usize| u64::from_le_bytes(body[index * 8..index * 8 + 8].try_into().unwrap());
+        if store.as_u64_words().is_some() {
+            // Setting the last word again is a no-op that checks for bits
+            // set beyond the bit count.
+            if word_count > 0 {
+                or_word(&mut store, &geometry, word_count - 1, fill(word_count - 1))?;
+            }
+        } else {
+            for index in 0..word_count {
+                or_word(&mut store, &geometry, index, fill(index))?;
+            }
+        }
+        Ok(BloomFilter {
+            bit_vec: store,
+            bit_count: geometry.bit_count,
+            hash_fn_count: geometry.hash_count,
+            counters: OpCounters::default(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Deserializes one filter from `reader`, in the format of
+    /// [`BloomFilter::to_bytes`].
+    ///
+    /// Exactly the filter's bytes are consumed, so several filters can be
+    /// read from one stream. The filter is allocated as soon as the header
+    /// is read, whatever its size; use
+    /// [`read_from_with_limits`](Self::read_from_with_limits) for data from
+    /// untrusted sources.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `reader` returns, and an error of kind
+    /// [`io::ErrorKind::InvalidData`] wrapping a [`BloomError`] if the data
+    /// is not a serialized filter.
+    pub fn read_from<R: Read>(reader: R) -> io::Result<Self> {
+        Self::read_from_with_limits(reader, &LoadLimits::default())
+    }
+
+    /// Deserializes one filter from `reader` like
+    /// [`BloomFilter::read_from`], rejecting filters beyond `limits` before
+    /// allocating them.
+    pub fn read_from_with_limits<R: Read>(reader: R, limits: &LoadLimits) -> io::Result<Self> {
+        Self::read_until(reader, limits, None)
+    }
+
+    /// Deserializes one filter from `reader` like
+    /// [`read_from_with_limits`](Self::read_from_with_limits), stopping
+    /// early if `cancel` is cancelled.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`read_from`](Self::read_from), and an error
+    /// wrapping [`BloomError::Cancelled`] if the token is cancelled before
+    /// the filter is read. The partly read filter is dropped, and `reader`
+    /// is left in the middle of it.
+    pub fn read_from_cancellable<R: Read>(
+        reader: R,
+        limits: &LoadLimits,
+        cancel: &CancelToken,
+    ) -> io::Result<Self> {
+        Self::read_until(reader, limits, Some(cancel))
+    }
+
+    fn read_until<R: Read>(
+        mut reader: R,
+        limits: &LoadLimits,
+        cancel: Option<&CancelToken>,
+    ) -> io::Result<Self> {
+        let mut header = [0u8; HEADER_BYTES];
+        reader.read_exact(&mut header)?;
+        let geometry = decode_header::<B::Word>(&header, limits).map_err(invalid_data)?;
+        let total = HEADER_BYTES + geometry.bit_count.div_ceil(64) as usize * 8 + CHECKSUM_BYTES;
+        trace::timed_span!("bloom.read", bytes = total as u64);
+
+        let mut crc = Crc32::new();
+        crc.update(&header);
+        let mut unpacker = Unpacker::<B>::new(geometry);
+        let progress = cancel.map(|cancel| (cancel, total));
+        read_words(&mut reader, &geometry, &mut crc, progress, |_, word| {
+            unpacker.push(word)
+        })?;
+        verify_checksum(reader, crc)?;
+        if let Some(cancel) = cancel {
+            cancel.report(Progress::bytes(total, total));
+        }
+        Ok(unpacker.finish())
+    }
+}
+
+/// Checks that `bytes` holds exactly one serialized filter within `limits`,
+/// returning its geometry.
+fn check_layout<W: Word>(bytes: &[u8], limits: &LoadLimits) -> Result<Geometry, BloomError> {
+    let header = bytes
+        .first_chunk::<HEADER_BYTES>()
+        .ok_or(BloomError::InvalidFormat("the header is truncated"))?;
+    let geometry = decode_header::<W>(header, limits)?;
+    let body_len = geometry.bit_count.div_ceil(64) * 8;
+    if (bytes.len() - HEADER_BYTES) as u64 != body_len + CHECKSUM_BYTES as u64 {
+        return Err(BloomError::InvalidFormat(
+            "the length does not match the bit count",
+        ));
+    }
+    Ok(geometry)
+}
+
+fn invalid_data(err: BloomError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AlignedStore;
+
+    #[test]
+    fn test_round_trip_across_stores() {
+        let mut bf: BloomFilter<u64, Vec<u8>> = BloomFilter::from_params(1000, 0.01);
+        for i in 0..1000u64 {
+            bf.insert(&i);
+        }
+        let bytes = bf.to_bytes();
+        assert_eq!(bytes.len(), bf.serialized_len());
+
+        let wide: BloomFilter<u64> = BloomFilter::from_bytes(&bytes).unwrap();
+        let aligned: BloomFilter<u64, AlignedStore> =
+            BloomFilter::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(wide.bit_count(), bf.bit_count());
+        for i in 0..2000u64 {
+            assert_eq!(wide.contains(&i), bf.contains(&i));
+            assert_eq!(aligned.contains(&i), bf.contains(&i));
+        }
+        assert_eq!(wide.to_bytes(), bytes);
+        assert_eq!(aligned.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_merge_from_reader() {
+        let mut local: BloomFilter<u64, AlignedStore> = BloomFilter::from_params(1000, 0.01);
+        let mut replica: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        (0..500).for_each(|i| local.insert(&i));
+        (500..1000).for_each(|i| replica.insert(&i));
+        let mut merged: BloomFilter<u64> = BloomFilter::from_bytes(&local.to_bytes()).unwrap();
+        merged.merge(&replica).unwrap();
+
+        local
+            .merge_from_reader(replica.to_bytes().as_slice())
+            .unwrap();
+        assert_eq!(local.to_bytes(), merged.to_bytes());
+
+        let other: BloomFilter<u64> = BloomFilter::new(10, 0.01);
+        let err = local
+            .merge_from_reader(other.to_bytes().as_slice())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let truncated = replica.to_bytes();
+        let err = local
+            .merge_from_reader(&truncated[..truncated.len() - 1])
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_rejects_invalid_bytes() {
+        let bf: BloomFilter<str> = BloomFilter::new(1000, 0.01);
+        let bytes = bf.to_bytes();
+        let load = |bytes: &[u8]| BloomFilter::<str>::from_bytes(bytes).unwrap_err();
+
+        assert_eq!(
+            load(&bytes[..10]),
+            BloomError::InvalidFormat("the header is truncated")
+        );
+        assert_eq!(
+            load(&bytes[..bytes.len() - 1]),
+            BloomError::InvalidFormat("the length does not match the bit count")
+        );
+        let mut corrupt = bytes.clone();
+        corrupt[0] = b'X';
+        assert_eq!(
+            load(&corrupt),
+            BloomError::InvalidFormat("the magic bytes are missing")
+        );
+        let mut corrupt = bytes.clone();
+        corrupt[16..20].fill(0);
+        assert_eq!(load(&corrupt), BloomError::ZeroHashCount);
+        let narrow = BloomFilter::<str, Vec<u8>>::from_params(1, 1u32);
+        assert_eq!(narrow.bit_count(), 8);
+        let mut corrupt = narrow.to_bytes();
+        corrupt[HEADER_BYTES + 7] = 0xff;
+        reseal(&mut corrupt);
+        assert_eq!(
+            load(&corrupt),
+            BloomError::InvalidFormat("bits are set beyond the bit count")
+        );
+
+        let err = BloomFilter::<str>::read_from(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    /// Recomputes the checksum of a modified filter.
+    fn reseal(bytes: &mut [u8]) {
+        let (data, checksum) = bytes.split_at_mut(bytes.len() - CHECKSUM_BYTES);
+        let mut crc = Crc32::new();
+        crc.update(data);
+        checksum.copy_from_slice(&crc.finish());
+    }
+
+    #[test]
+    fn test_untrusted_input() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xCBF4_3926u32.to_le_bytes());
+
+        let mut bf: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        (0..1000).for_each(|i| bf.insert(&i));
+        let bytes = bf.to_bytes();
+        let load = |bytes: &[u8], limits: &LoadLimits| {
+            let from_bytes = BloomFilter::<u64>::from_bytes_with_limits(bytes, limits);
+            let read_from = BloomFilter::<u64>::read_from_with_limits(bytes, limits);
+            assert_eq!(from_bytes.is_ok(), read_from.is_ok());
+            from_bytes
+        };
+        let unlimited = LoadLimits::default();
+
+        let mut corrupt = bytes.clone();
+        corrupt[100] ^= 4;
+        assert_eq!(
+            load(&corrupt, &unlimited).unwrap_err(),
+            BloomError::InvalidFormat("the checksum does not match")
+        );
+        let mut corrupt = bytes.clone();
+        corrupt[16..20].copy_from_slice(&100_000u32.to_le_bytes());
+        reseal(&mut corrupt);
+        assert_eq!(
+            load(&corrupt, &unlimited).unwrap_err(),
+            BloomError::InvalidFormat("the hash count exceeds the bit count")
+        );
+        corrupt[16..20].copy_from_slice(&65u32.to_le_bytes());
+        reseal(&mut corrupt);
+        assert!(load(&corrupt, &unlimited).is_ok());
+        assert_eq!(
+            load(&corrupt, &LoadLimits::new(1 << 20)).unwrap_err(),
+            BloomError::InvalidFormat("the hash count exceeds the limit")
+        );
+        assert!(load(&bytes, &LoadLimits::new(1200)).is_ok());
+        assert_eq!(
+            load(&bytes, &LoadLimits::new(1199)).unwrap_err(),
+            BloomError::InvalidFormat("the bit array exceeds the size limit")
+        );
+
+        // A huge header is rejected before anything is allocated.
+        let mut huge = bytes[..HEADER_BYTES].to_vec();
+        huge[8..16].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        let err = BloomFilter::<u64>::read_from_with_limits(&huge[..], &LoadLimits::new(1 << 30))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Version 1, which had no checksum, is not read.
+        let mut v1 = bytes[..bytes.len() - CHECKSUM_BYTES].to_vec();
+        v1[4..8].copy_from_slice(&1u32.to_le_bytes());
+        assert_eq!(
+            load(&v1, &unlimited).unwrap_err(),
+            BloomError::InvalidFormat("the format version is unsupported")
+        );
+
+        // No single-byte corruption or truncation panics or goes unnoticed.
+        let limits = LoadLimits::new(1 << 20);
+        for at in 0..bytes.len() {
+            let mut corrupt = bytes.clone();
+            corrupt[at] ^= 0x80;
+            assert!(load(&corrupt, &limits).is_err(), "byte {}", at);
+            assert!(load(&bytes[..at], &limits).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parallel_load_matches_serial() {
+        let mut filter: BloomFilter<u64> = BloomFilter::new(200_000, 0.01);
+        (0..200_000).for_each(|i| filter.insert(&i));
+        let bytes = filter.to_bytes();
+        let limits = LoadLimits::default();
+
+        let word_count = filter.bit_vec.len();
+        let available = thread::available_parallelism().unwrap().get();
+        for threads in [1, 3, 8, 100_000] {
+            let parts = word_count.div_ceil(part_words(word_count, threads));
+            assert!(parts <= threads.min(available));
+            assert!(parts == 1 || word_count / parts >= MIN_PART_WORDS);
+            let loaded: BloomFilter<u64> =
+                BloomFilter::from_bytes_parallel(&bytes, &limits, threads).unwrap();
+            assert_eq!(loaded.bit_vec, filter.bit_vec);
+            let narrow =
+                BloomFilter::<u64, Vec<u8>>::from_bytes_parallel(&bytes, &limits, threads).unwrap();
+            assert_eq!(narrow.to_bytes(), bytes);
+            let aligned =
+                BloomFilter::<u64, AlignedStore>::from_bytes_parallel(&bytes, &limits, threads)
+                    .unwrap();
+            assert_eq!(aligned.to_bytes(), bytes);
+        }
+
+        for at in [0, HEADER_BYTES + 1, bytes.len() / 2, bytes.len() - 1] {
+            let mut corrupt = bytes.clone();
+            corrupt[at] ^= 0x01;
+            assert!(BloomFilter::<u64>::from_bytes_parallel(&corrupt, &limits, 4).is_err());
+        }
+    }
+}
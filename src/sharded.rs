@@ -0,0 +1,227 @@
+use std::hash::Hash;
+
+use crate::approx::ApproximateSet;
+use crate::const_filter::splitmix64;
+use crate::hashing;
+use crate::{AtomicBloomFilter, BloomError, FilterParams};
+
+/// A concurrent Bloom Filter partitioned into independent shards.
+///
+/// Each item is routed by a remix of its first hash to exactly one of N
+/// [`AtomicBloomFilter`] shards, and all k probes for that item stay inside
+/// its shard. Under heavy concurrent inserts this spreads writes over N
+/// separate allocations instead of one, and lets a NUMA-aware pipeline keep
+/// each shard local: route keys with [`shard_index`](Self::shard_index) to
+/// worker threads pinned per shard, and each shard's memory is first touched
+/// (and so placed) by the threads that write it.
+///
+/// The false positive rate matches an unsharded filter of the same total
+/// size, as long as keys spread evenly over the shards.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::ShardedBloomFilter;
+///
+/// let filter = ShardedBloomFilter::new(10_000, 0.01, 8);
+/// filter.insert(&"key-1");
+/// assert!(filter.contains(&"key-1"));
+/// assert_eq!(filter.shard_count(), 8);
+/// ```
+#[derive(Debug)]
+pub struct ShardedBloomFilter<T: ?Sized> {
+    /// The independent sub-filters.
+    shards: Box<[AtomicBloomFilter<T>]>,
+}
+
+impl<T: ?Sized + Hash> ShardedBloomFilter<T> {
+    /// Creates a filter for `expected_items` in total, split across
+    /// `shard_count` shards each sized for its share of the items.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shard_count` is 0, or under the same conditions as
+    /// [`BloomFilter::new`](crate::BloomFilter::new).
+    pub fn new(expected_items: usize, params: impl Into<FilterParams>, shard_count: usize) -> Self {
+        Self::try_new(expected_items, params, shard_count).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a sharded filter, returning an error instead of panicking.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shard_count` is 0.
+    pub fn try_new(
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+        shard_count: usize,
+    ) -> Result<Self, BloomError> {
+        assert!(shard_count > 0, "Shard count must be greater than 0.");
+        if expected_items == 0 {
+            return Err(BloomError::ZeroExpectedItems);
+        }
+
+        let params = params.into();
+        let items_per_shard = expected_items.div_ceil(shard_count);
+        let shards = (0..shard_count)
+            .map(|_| AtomicBloomFilter::try_new(items_per_shard, params))
+            .collect::<Result<_, _>>()?;
+
+        Ok(ShardedBloomFilter { shards })
+    }
+
+    /// Inserts an item into its shard.
+    pub fn insert(&self, item: &T) {
+        let (h1, h2) = hashing::hash_pair(item);
+        self.shards[self.route(h1)].insert_hashed(h1, h2);
+    }
+
+    /// Checks if an item might be in the Bloom Filter.
+    ///
+    /// Returns `true` if the item might be present (with a probability of false positive).
+    /// Returns `false` if the item is definitely not present.
+    pub fn contains(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair(item);
+        self.shards[self.route(h1)].contains_hashed(h1, h2)
+    }
+
+    /// Returns the index of the shard that holds `item`.
+    pub fn shard_index(&self, item: &T) -> usize {
+        let (h1, _) = hashing::hash_pair(item);
+        self.route(h1)
+    }
+
+    /// Returns the shards, e.g. to inspect or convert them individually.
+    ///
+    /// Only items whose [`shard_index`](Self::shard_index) matches should be
+    /// inserted into a shard directly.
+    pub fn shards(&self) -> &[AtomicBloomFilter<T>] {
+        &self.shards
+    }
+
+    /// Returns the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Clears all bits in every shard.
+    ///
+    /// Inserts running concurrently with `clear` may or may not be retained.
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.clear();
+        }
+    }
+
+    /// Returns the approximate memory usage of all shards in bytes.
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.memory_usage_bytes())
+            .sum()
+    }
+
+    /// Returns the number of hash functions (k) being used.
+    pub fn hash_count(&self) -> u32 {
+        self.shards[0].hash_count()
+    }
+
+    /// Maps `h1` onto a shard with a multiply-shift of its SplitMix64 remix.
+    ///
+    /// The shard's first probe is the multiply-shift of `h1` itself, so
+    /// routing on its high bits would confine that probe to about 1/N of
+    /// each shard's bits and raise the false positive rate.
+    #[inline]
+    fn route(&self, h1: u64) -> usize {
+        hashing::fastrange(splitmix64(h1), self.shards.len() as u64) as usize
+    }
+}
+
+impl<T: ?Sized + Hash> ApproximateSet<T> for ShardedBloomFilter<T> {
+    fn insert(&mut self, item: &T) {
+        ShardedBloomFilter::insert(self, item);
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        ShardedBloomFilter::contains(self, item)
+    }
+
+    fn estimated_len(&self) -> f64 {
+        self.shards
+            .iter()
+            .map(|shard| shard.estimated_items())
+            .sum()
+    }
+
+    /// A lookup probes a single shard, so this is the mean of the shards'
+    /// rates.
+    fn fp_estimate(&self) -> f64 {
+        let total: f64 = self.shards.iter().map(|shard| shard.fp_estimate()).sum();
+        total / self.shards.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_sharded_inserts() {
+        let filter = ShardedBloomFilter::new(8000, 0.01, 4);
+        thread::scope(|scope| {
+            for t in 0..4u64 {
+                let filter = &filter;
+                scope.spawn(move || {
+                    for i in 0..2000 {
+                        filter.insert(&(t * 2000 + i));
+                    }
+                });
+            }
+        });
+
+        for i in 0..8000u64 {
+            assert!(filter.contains(&i));
+            assert!(filter.shards()[filter.shard_index(&i)].contains(&i));
+        }
+    }
+
+    #[test]
+    fn test_keys_spread_over_shards() {
+        let filter: ShardedBloomFilter<u64> = ShardedBloomFilter::new(1000, 0.01, 4);
+        let mut counts = [0usize; 4];
+        for i in 0..4000u64 {
+            counts[filter.shard_index(&i)] += 1;
+        }
+        for count in counts {
+            assert!((800..1200).contains(&count), "uneven shards: {:?}", counts);
+        }
+    }
+
+    #[test]
+    fn test_sharding_keeps_false_positive_rate() {
+        let plain: AtomicBloomFilter<u64> = AtomicBloomFilter::new(100_000, 0.01);
+        let sharded: ShardedBloomFilter<u64> = ShardedBloomFilter::new(100_000, 0.01, 16);
+        for i in 0..100_000u64 {
+            plain.insert(&i);
+            sharded.insert(&i);
+        }
+        let rate = |contains: &dyn Fn(&u64) -> bool| {
+            (100_000..300_000u64).filter(|i| contains(i)).count() as f64 / 200_000.0
+        };
+        let plain_rate = rate(&|i| plain.contains(i));
+        let sharded_rate = rate(&|i| sharded.contains(i));
+        assert!(
+            sharded_rate < plain_rate * 1.15 + 0.0005,
+            "sharded {} vs plain {}",
+            sharded_rate,
+            plain_rate
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Shard count must be greater than 0.")]
+    fn test_panic_on_zero_shards() {
+        ShardedBloomFilter::<i32>::new(100, 0.01, 0);
+    }
+}
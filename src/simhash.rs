@@ -0,0 +1,279 @@
+//! SimHash fingerprints for near-duplicate detection.
+//!
+//! A SimHash fingerprint condenses a document's weighted features, e.g.
+//! its words or n-grams, into 64 bits such that similar documents get
+//! fingerprints differing in few bits: each feature's hash votes, with the
+//! feature's weight, for or against every bit, and the fingerprint keeps
+//! the bits that won. Documents sharing most of their weight share most of
+//! the votes, so the Hamming [`distance`] of their fingerprints is small,
+//! while unrelated documents differ in about 32 bits.
+//!
+//! [`SimHasher`] accumulates the features of a document and [`text`]
+//! fingerprints the n-grams of a byte string. [`SimHashIndex`] finds, among
+//! many stored fingerprints, those within a Hamming distance of a query
+//! without comparing it to each of them.
+//!
+//! Feature hashes come from the standard library's `DefaultHasher`, as a
+//! [`BloomFilter`](crate::BloomFilter)'s do, so fingerprints are only
+//! comparable between builds that hash the same.
+//!
+//! # Examples
+//!
+//! ```
+//! use bloomlib::simhash::{self, SimHashIndex};
+//!
+//! let mut index = SimHashIndex::new(8);
+//! let original: &[u8] = b"Bloom filters answer membership queries in fixed memory, at the cost of false positives.";
+//! index.insert(simhash::text(original, 4), "bloom");
+//! index.insert(simhash::text(b"Lorem ipsum dolor sit amet, consectetur adipiscing elit.", 4), "lorem");
+//!
+//! let edited: &[u8] = b"Bloom filters answer membership queries in fixed memory, at the price of false positives.";
+//! let near = index.near(simhash::text(edited, 4));
+//! assert_eq!(near.iter().map(|&(_, name)| *name).collect::<Vec<_>>(), ["bloom"]);
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::const_filter::splitmix64;
+use crate::hashing::hash_pair;
+use crate::ngram;
+
+/// Returns the number of bits in which two fingerprints differ.
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Returns the fingerprint of the `n`-byte windows of `data`, each weighted
+/// 1, hashed with [`ngram::rolling_hashes`].
+///
+/// # Panics
+///
+/// Panics if `n` is 0.
+pub fn text(data: &[u8], n: usize) -> u64 {
+    let mut hasher = SimHasher::new();
+    for hash in ngram::rolling_hashes(data, n) {
+        // The rolling hash is reduced modulo 2^61 - 1, so its top bits would
+        // always vote the same way.
+        hasher.add_hash(splitmix64(hash), 1.0);
+    }
+    hasher.finish()
+}
+
+/// Accumulates the weighted features of a document into a SimHash
+/// fingerprint.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::simhash::{self, SimHasher};
+///
+/// let mut a = SimHasher::new();
+/// let mut b = SimHasher::new();
+/// for (word, weight) in [("rust", 3.0), ("bloom", 2.0), ("filter", 2.0), ("fast", 1.0)] {
+///     a.add(word, weight);
+///     b.add(word, weight);
+/// }
+/// b.add("slow", 0.5);
+/// assert!(simhash::distance(a.finish(), b.finish()) < 8);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimHasher {
+    /// The sum of the votes for each bit, positive for 1.
+    votes: [f64; 64],
+}
+
+impl SimHasher {
+    /// Creates a hasher without features, whose fingerprint is 0.
+    pub fn new() -> Self {
+        SimHasher { votes: [0.0; 64] }
+    }
+
+    /// Adds a feature with a weight, e.g. its term frequency. A negative
+    /// weight removes a feature added before.
+    pub fn add<T: ?Sized + Hash>(&mut self, feature: &T, weight: f64) {
+        self.add_hash(hash_pair(feature).0, weight);
+    }
+
+    /// Adds a feature by its 64-bit hash, which must be well mixed: every
+    /// bit should be set for about half of the features.
+    pub fn add_hash(&mut self, hash: u64, weight: f64) {
+        for (bit, votes) in self.votes.iter_mut().enumerate() {
+            if hash >> bit & 1 == 1 {
+                *votes += weight;
+            } else {
+                *votes -= weight;
+            }
+        }
+    }
+
+    /// Returns the fingerprint of the features added so far.
+    pub fn finish(&self) -> u64 {
+        self.votes
+            .iter()
+            .enumerate()
+            .filter(|&(_, &votes)| votes > 0.0)
+            .fold(0, |fingerprint, (bit, _)| fingerprint | 1 << bit)
+    }
+}
+
+impl Default for SimHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Finds the stored fingerprints within a fixed Hamming distance of a
+/// query.
+///
+/// For a maximum distance d, the fingerprints are cut into d + 1 blocks of
+/// bits. Two fingerprints at most d bits apart agree on at least one whole
+/// block, so the index keeps a table per block and compares a query only
+/// with the fingerprints sharing one of its blocks: a small fraction of
+/// them for small d. Each table holds every fingerprint, so memory grows
+/// with d.
+#[derive(Debug, Clone)]
+pub struct SimHashIndex<V> {
+    max_distance: u32,
+    /// The shift and mask of each block.
+    blocks: Vec<(u32, u64)>,
+    /// For each block, the entries by the value of that block.
+    tables: Vec<HashMap<u64, Vec<usize>>>,
+    entries: Vec<(u64, V)>,
+}
+
+impl<V> SimHashIndex<V> {
+    /// Creates an empty index answering queries for fingerprints at most
+    /// `max_distance` bits away.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_distance` is 64 or more.
+    pub fn new(max_distance: u32) -> Self {
+        assert!(max_distance < 64, "max_distance must be less than 64.");
+        let count = max_distance + 1;
+        let mut shift = 0;
+        let blocks: Vec<(u32, u64)> = (0..count)
+            .map(|block| {
+                // The first 64 % count blocks are one bit longer.
+                let width = 64 / count + u32::from(block < 64 % count);
+                let mask = u64::MAX >> (64 - width);
+                let block = (shift, mask);
+                shift += width;
+                block
+            })
+            .collect();
+        SimHashIndex {
+            max_distance,
+            tables: vec![HashMap::new(); blocks.len()],
+            blocks,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the maximum distance of the fingerprints queries return.
+    pub fn max_distance(&self) -> u32 {
+        self.max_distance
+    }
+
+    /// Returns the number of fingerprints stored.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no fingerprint is stored.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Stores a fingerprint with a value, e.g. the document's id.
+    pub fn insert(&mut self, fingerprint: u64, value: V) {
+        let index = self.entries.len();
+        for (table, &block) in self.tables.iter_mut().zip(&self.blocks) {
+            table
+                .entry(block_of(fingerprint, block))
+                .or_default()
+                .push(index);
+        }
+        self.entries.push((fingerprint, value));
+    }
+
+    /// Returns the distance and value of every stored fingerprint at most
+    /// [`max_distance`](Self::max_distance) bits from `fingerprint`, nearest
+    /// first.
+    pub fn near(&self, fingerprint: u64) -> Vec<(u32, &V)> {
+        let mut near = Vec::new();
+        for (position, (table, &block)) in self.tables.iter().zip(&self.blocks).enumerate() {
+            let Some(candidates) = table.get(&block_of(fingerprint, block)) else {
+                continue;
+            };
+            for &index in candidates {
+                let (stored, ref value) = self.entries[index];
+                // A fingerprint sharing an earlier block was seen there.
+                let seen = self.blocks[..position]
+                    .iter()
+                    .any(|&block| block_of(stored, block) == block_of(fingerprint, block));
+                let distance = distance(stored, fingerprint);
+                if !seen && distance <= self.max_distance {
+                    near.push((distance, value));
+                }
+            }
+        }
+        near.sort_by_key(|&(distance, _)| distance);
+        near
+    }
+}
+
+fn block_of(fingerprint: u64, (shift, mask): (u32, u64)) -> u64 {
+    fingerprint >> shift & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_matches_linear_scan() {
+        let fingerprints: Vec<u64> = (0..2000u64).map(splitmix64).collect();
+        for max_distance in [0, 3, 7, 63] {
+            let mut index = SimHashIndex::new(max_distance);
+            for (id, &fingerprint) in fingerprints.iter().enumerate() {
+                index.insert(fingerprint, id);
+            }
+            assert_eq!(index.len(), 2000);
+            for (i, &fingerprint) in fingerprints.iter().enumerate().step_by(97) {
+                // Flip some bits of a stored fingerprint.
+                let query = fingerprint ^ (splitmix64(!(i as u64)) & splitmix64(i as u64 + 7));
+                let mut expected: Vec<(u32, usize)> = fingerprints
+                    .iter()
+                    .enumerate()
+                    .map(|(id, &stored)| (distance(stored, query), id))
+                    .filter(|&(distance, _)| distance <= max_distance)
+                    .collect();
+                expected.sort();
+                let mut found: Vec<(u32, usize)> = index
+                    .near(query)
+                    .into_iter()
+                    .map(|(d, &id)| (d, id))
+                    .collect();
+                found.sort();
+                assert_eq!(found, expected, "max_distance = {}", max_distance);
+            }
+        }
+    }
+
+    #[test]
+    fn test_similar_texts_have_near_fingerprints() {
+        let original: Vec<u8> = (0..4000u32)
+            .map(|i| b'a' + (i.wrapping_mul(2_654_435_761) >> 28) as u8)
+            .collect();
+        let mut edited = original.clone();
+        edited[1000..1040].copy_from_slice(&[b'z'; 40]);
+        let unrelated: Vec<u8> = original.iter().rev().map(|byte| byte ^ 1).collect();
+
+        let fingerprint = text(&original, 8);
+        assert!(distance(fingerprint, text(&edited, 8)) <= 6);
+        assert!(distance(fingerprint, text(&unrelated, 8)) >= 16);
+        assert_eq!(SimHasher::new().finish(), 0);
+    }
+}
@@ -0,0 +1,350 @@
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::counters::OpCounters;
+use crate::params::Geometry;
+use crate::serialize::{or_word, Crc32};
+use crate::{hashing, AllocStore, BitStore, BloomError, BloomFilter};
+
+/// The bytes every serialized part starts with.
+const MAGIC: [u8; 4] = *b"BLMP";
+
+/// The version of the format written by [`FilterPart::to_bytes`].
+const VERSION: u32 = 1;
+
+/// The length of the header: magic, version, bit count, hash count, part
+/// index and part count.
+const HEADER_BYTES: usize = 28;
+
+/// One of the pieces a filter's bit array is cut into by
+/// [`BloomFilter::split_into`], reassembled by [`BloomFilter::recombine`].
+///
+/// Part `i` of `n` holds the `i`-th of `n` equal ranges of 64-bit words of
+/// the bit array. Every probe of a key belongs to the part whose range
+/// covers its bit, so the parts partition the probes by key hash: a part
+/// can be built on its own by [`insert`](Self::insert)ing every key into
+/// it, which sets just its share of the bits. A huge filter can so be
+/// built, stored and shipped as `n` pieces of `m / n` bits each, on as
+/// many machines, and recombined where it is served.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::BloomFilter;
+///
+/// let keys = ["apple", "banana", "cherry"];
+/// let empty: BloomFilter<str> = BloomFilter::new(1000, 0.01);
+///
+/// // Each worker builds one part from all the keys...
+/// let parts = empty.split_into(4).into_iter().map(|mut part| {
+///     keys.iter().for_each(|key| part.insert(key));
+///     part.to_bytes()
+/// });
+///
+/// // ...and the parts are shipped and reassembled.
+/// let parts = parts.map(|bytes| bloomlib::FilterPart::from_bytes(&bytes).unwrap());
+/// let filter: BloomFilter<str> = BloomFilter::recombine(parts).unwrap();
+/// assert!(keys.iter().all(|key| filter.contains(key)));
+/// ```
+pub struct FilterPart<T: ?Sized> {
+    bit_count: u64,
+    hash_fn_count: u32,
+    index: u32,
+    part_count: u32,
+    /// The index of the first 64-bit word of the part.
+    first_word: u64,
+    /// The part's words of the bit array.
+    words: Vec<u64>,
+    _marker: PhantomData<fn(&T)>,
+}
+
+/// Returns the range of 64-bit words part `index` of `part_count` holds of
+/// a bit array of `bit_count` bits.
+fn word_range(bit_count: u64, index: u32, part_count: u32) -> (u64, u64) {
+    let word_count = bit_count.div_ceil(64);
+    let bound = |index: u32| hashing::fastrange_of(index as u64, part_count as u64, word_count);
+    (bound(index), bound(index + 1))
+}
+
+impl<T: ?Sized + Hash, B: BitStore> BloomFilter<T, B> {
+    /// Cuts the bit array into `part_count` parts of (nearly) equal size.
+    ///
+    /// Splitting an empty filter gives empty parts to build independently;
+    /// see [`FilterPart`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `part_count` is 0.
+    pub fn split_into(&self, part_count: u32) -> Vec<FilterPart<T>> {
+        assert!(part_count > 0, "Part count must be greater than 0.");
+        let mut words = self.packed_words();
+        (0..part_count)
+            .map(|index| {
+                let (first_word, end) = word_range(self.bit_count, index, part_count);
+                FilterPart {
+                    bit_count: self.bit_count,
+                    hash_fn_count: self.hash_fn_count,
+                    index,
+                    part_count,
+                    first_word,
+                    words: words.by_ref().take((end - first_word) as usize).collect(),
+                    _marker: PhantomData,
+                }
+            })
+            .collect()
+    }
+}
+
+impl<T: ?Sized + Hash, B: AllocStore> BloomFilter<T, B> {
+    /// Reassembles a filter from all the parts of one
+    /// [`split_into`](Self::split_into), in any order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::IncompatibleFilters`] if the parts come from
+    /// filters of different bit or hash counts, or
+    /// [`BloomError::InvalidFormat`] if they were split differently, or a
+    /// part is missing or repeated.
+    pub fn recombine(parts: impl IntoIterator<Item = FilterPart<T>>) -> Result<Self, BloomError> {
+        let mut parts: Vec<FilterPart<T>> = parts.into_iter().collect();
+        let Some(first) = parts.first() else {
+            return Err(BloomError::InvalidFormat("there are no parts"));
+        };
+        let (bit_count, hash_count, part_count) =
+            (first.bit_count, first.hash_fn_count, first.part_count);
+        for part in &parts {
+            if (part.bit_count, part.hash_fn_count) != (bit_count, hash_count) {
+                return Err(BloomError::IncompatibleFilters {
+                    expected: (bit_count, hash_count),
+                    found: (part.bit_count, part.hash_fn_count),
+                });
+            }
+            if part.part_count != part_count {
+                return Err(BloomError::InvalidFormat(
+                    "the parts come from different splits",
+                ));
+            }
+        }
+        parts.sort_unstable_by_key(|part| part.index);
+        if parts.len() != part_count as usize
+            || parts
+                .iter()
+                .zip(0..)
+                .any(|(part, index)| part.index != index)
+        {
+            return Err(BloomError::InvalidFormat("a part is missing or repeated"));
+        }
+
+        let geometry = Geometry::exact::<B::Word>(bit_count, hash_count)?;
+        let mut store = B::zeroed(geometry.word_count);
+        for part in &parts {
+            for (offset, &word) in part.words.iter().enumerate() {
+                or_word(
+                    &mut store,
+                    &geometry,
+                    part.first_word as usize + offset,
+                    word,
+                )?;
+            }
+        }
+        Ok(BloomFilter {
+            bit_vec: store,
+            bit_count,
+            hash_fn_count: hash_count,
+            counters: OpCounters::default(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: ?Sized + Hash> FilterPart<T> {
+    /// Sets the probes of `item` that fall into this part, and ignores the
+    /// others.
+    pub fn insert(&mut self, item: &T) {
+        let (h1, h2) = hashing::hash_pair(item);
+        for i in 0..self.hash_fn_count {
+            let bit_index = hashing::bit_index(h1, h2, i, self.bit_count);
+            if let Some(word) = (bit_index / 64)
+                .checked_sub(self.first_word)
+                .and_then(|offset| self.words.get_mut(offset as usize))
+            {
+                *word |= 1 << (bit_index % 64);
+            }
+        }
+    }
+
+    /// Returns the position of the part, from 0.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the number of parts the filter was split into.
+    pub fn part_count(&self) -> u32 {
+        self.part_count
+    }
+
+    /// Serializes the part for storage or shipping: a header with the
+    /// filter's bit and hash count and the part's position, the part's
+    /// words as little-endian `u64`s, and a CRC-32 of both.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_BYTES + self.words.len() * 8 + 4);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.bit_count.to_le_bytes());
+        bytes.extend_from_slice(&self.hash_fn_count.to_le_bytes());
+        bytes.extend_from_slice(&self.index.to_le_bytes());
+        bytes.extend_from_slice(&self.part_count.to_le_bytes());
+        for word in &self.words {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        let mut crc = Crc32::new();
+        crc.update(&bytes);
+        bytes.extend_from_slice(&crc.finish());
+        bytes
+    }
+
+    /// Deserializes a part written by [`to_bytes`](Self::to_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::InvalidFormat`] if the bytes are not a valid
+    /// part.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BloomError> {
+        let Some((header, rest)) = bytes.split_first_chunk::<HEADER_BYTES>() else {
+            return Err(BloomError::InvalidFormat("the header is truncated"));
+        };
+        if header[0..4] != MAGIC {
+            return Err(BloomError::InvalidFormat("the magic bytes are missing"));
+        }
+        if u32::from_le_bytes(header[4..8].try_into().unwrap()) != VERSION {
+            return Err(BloomError::InvalidFormat(
+                "the format version is unsupported",
+            ));
+        }
+        let bit_count = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let hash_fn_count = u32::from_le_bytes(header[16..20].try_into().unwrap());
+        let index = u32::from_le_bytes(header[20..24].try_into().unwrap());
+        let part_count = u32::from_le_bytes(header[24..28].try_into().unwrap());
+        Geometry::exact::<u64>(bit_count, hash_fn_count)?;
+        if index >= part_count {
+            return Err(BloomError::InvalidFormat(
+                "the part index exceeds the part count",
+            ));
+        }
+
+        let (first_word, end) = word_range(bit_count, index, part_count);
+        let words_len = (end - first_word) as usize * 8;
+        if rest.len() != words_len + 4 {
+            return Err(BloomError::InvalidFormat(
+                "the length does not match the header",
+            ));
+        }
+        let mut crc = Crc32::new();
+        crc.update(&bytes[..HEADER_BYTES + words_len]);
+        if rest[words_len..] != crc.finish() {
+            return Err(BloomError::InvalidFormat("the checksum does not match"));
+        }
+        Ok(FilterPart {
+            bit_count,
+            hash_fn_count,
+            index,
+            part_count,
+            first_word,
+            words: rest[..words_len]
+                .chunks_exact(8)
+                .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+                .collect(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: ?Sized> Clone for FilterPart<T> {
+    fn clone(&self) -> Self {
+        FilterPart {
+            words: self.words.clone(),
+            _marker: PhantomData,
+            ..*self
+        }
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for FilterPart<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilterPart")
+            .field("bit_count", &self.bit_count)
+            .field("hash_count", &self.hash_fn_count)
+            .field("index", &self.index)
+            .field("part_count", &self.part_count)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_recombine() {
+        let mut filter: BloomFilter<u64> = BloomFilter::new(10_000, 0.01);
+        (0..10_000u64).for_each(|i| filter.insert(&i));
+
+        for part_count in [1, 3, 7, 1000] {
+            let parts = filter.split_into(part_count);
+            assert_eq!(parts.len(), part_count as usize);
+            let recombined: BloomFilter<u64, Vec<u8>> =
+                BloomFilter::recombine(parts.into_iter().rev()).unwrap();
+            assert!((0..10_000u64).all(|i| recombined.contains(&i)));
+            let recombined: BloomFilter<u64> =
+                BloomFilter::recombine(filter.split_into(part_count)).unwrap();
+            assert_eq!(recombined.to_bytes(), filter.to_bytes());
+        }
+
+        // Parts built separately from all keys add up to the whole filter.
+        let parts = BloomFilter::<u64>::new(10_000, 0.01)
+            .split_into(5)
+            .into_iter()
+            .map(|mut part| {
+                (0..10_000u64).for_each(|i| part.insert(&i));
+                FilterPart::from_bytes(&part.to_bytes()).unwrap()
+            });
+        let built: BloomFilter<u64> = BloomFilter::recombine(parts).unwrap();
+        assert_eq!(built.to_bytes(), filter.to_bytes());
+    }
+
+    #[test]
+    fn test_invalid_parts() {
+        let filter: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        let mut parts = filter.split_into(4);
+        parts.pop();
+        assert_eq!(
+            BloomFilter::<u64>::recombine(parts.clone()).unwrap_err(),
+            BloomError::InvalidFormat("a part is missing or repeated")
+        );
+        parts.push(parts[0].clone());
+        assert!(BloomFilter::<u64>::recombine(parts.clone()).is_err());
+        parts.pop();
+        parts.extend(BloomFilter::<u64>::new(1000, 0.01).split_into(5).pop());
+        assert_eq!(
+            BloomFilter::<u64>::recombine(parts).unwrap_err(),
+            BloomError::InvalidFormat("the parts come from different splits")
+        );
+        let other = BloomFilter::<u64>::new(100, 0.01).split_into(1);
+        assert!(matches!(
+            BloomFilter::<u64>::recombine(filter.split_into(1).into_iter().chain(other)),
+            Err(BloomError::IncompatibleFilters { .. })
+        ));
+
+        let mut bytes = filter.split_into(3)[1].to_bytes();
+        assert_eq!(FilterPart::<u64>::from_bytes(&bytes).unwrap().index(), 1);
+        *bytes.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            FilterPart::<u64>::from_bytes(&bytes).unwrap_err(),
+            BloomError::InvalidFormat("the checksum does not match")
+        );
+        for len in 0..bytes.len() {
+            assert!(FilterPart::<u64>::from_bytes(&bytes[..len]).is_err());
+        }
+    }
+}
@@ -0,0 +1,631 @@
+//! Empirical checks of filter configurations.
+//!
+//! The sizing formulas assume ideal, independent hash functions. [`simulate`]
+//! checks what a configuration achieves in practice: it fills a filter with
+//! random keys, probes it with keys that were never inserted, and reports the
+//! observed rate with a confidence interval.
+//!
+//! # Examples
+//!
+//! ```
+//! use bloomlib::stats;
+//!
+//! let estimate = stats::simulate(10_000, 0.01, 100_000, 42).unwrap();
+//! let (low, high) = estimate.interval(0.99);
+//! assert!(low < 0.015 && high > 0.005);
+//! ```
+//!
+//! [`hash_quality`] looks one step earlier, at where the probes of a key
+//! sample land, to tell whether a hasher and the keys interact badly.
+//!
+//! [`saturation`] inspects a filled filter instead: it measures the density
+//! of set bits per block, which exposes hot regions that the overall
+//! [`BloomFilter::fill_ratio`] averages away.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{BuildHasher, BuildHasherDefault, Hash};
+
+use crate::{hashing, params, BitStore, BloomError, BloomFilter, FilterParams, Word};
+
+/// The result of a false positive simulation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FpEstimate {
+    /// The number of absent keys that the filter reported as present.
+    pub false_positives: u64,
+    /// The number of absent keys probed.
+    pub sample_size: u64,
+    /// The rate predicted for the filter's actual bit count and hash count,
+    /// `(1 - e^(-kn/m))^k`.
+    pub predicted_rate: f64,
+    /// The number of bits (m) of the simulated filter.
+    pub bit_count: u64,
+    /// The number of hash functions (k) of the simulated filter.
+    pub hash_count: u32,
+}
+
+impl FpEstimate {
+    /// Returns the observed false positive rate.
+    pub fn rate(&self) -> f64 {
+        if self.sample_size == 0 {
+            return 0.0;
+        }
+        self.false_positives as f64 / self.sample_size as f64
+    }
+
+    /// Returns the Wilson score interval for the true false positive rate at
+    /// the given confidence level (e.g. `0.95`).
+    ///
+    /// Unlike the normal approximation, the Wilson interval stays inside
+    /// `[0, 1]` and remains meaningful when few or no false positives were
+    /// observed, which is the usual case for low target rates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not between 0.0 and 1.0, exclusive.
+    pub fn interval(&self, confidence: f64) -> (f64, f64) {
+        assert!(
+            confidence > 0.0 && confidence < 1.0,
+            "Confidence must be between 0.0 and 1.0, exclusive."
+        );
+        if self.sample_size == 0 {
+            return (0.0, 1.0);
+        }
+        let z = normal_quantile(0.5 + confidence / 2.0);
+        let n = self.sample_size as f64;
+        let p = self.rate();
+
+        let z2 = z * z;
+        let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let margin = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+        ((center - margin).max(0.0), (center + margin).min(1.0))
+    }
+}
+
+/// Measures the false positive rate of a filter sized for `expected_items`.
+///
+/// A [`BloomFilter`] is built with `params` and filled with `expected_items`
+/// random keys; then `sample_size` further keys, all distinct from the
+/// inserted ones, are looked up. The keys come from a generator seeded with
+/// `seed`, so a run is reproducible.
+///
+/// The standard error of the estimate is about `sqrt(p / sample_size)`, so
+/// resolving a rate `p` to within 10% takes roughly `100 / p` samples.
+///
+/// # Errors
+///
+/// Returns the same errors as [`BloomFilter::try_new`].
+pub fn simulate(
+    expected_items: usize,
+    params: impl Into<FilterParams>,
+    sample_size: usize,
+    seed: u64,
+) -> Result<FpEstimate, BloomError> {
+    let mut filter: BloomFilter<u64> = BloomFilter::try_new(expected_items, params)?;
+    let mut rng = SplitMix64(seed);
+
+    let mut batch = Vec::with_capacity(SIMULATION_BATCH);
+    let mut remaining = expected_items;
+    while remaining > 0 {
+        batch.clear();
+        batch.extend((0..remaining.min(SIMULATION_BATCH)).map(|_| rng.next_u64()));
+        filter.insert_keys(&batch);
+        remaining -= batch.len();
+    }
+
+    let mut false_positives = 0;
+    let mut remaining = sample_size;
+    while remaining > 0 {
+        batch.clear();
+        batch.extend((0..remaining.min(SIMULATION_BATCH)).map(|_| rng.next_u64()));
+        false_positives += filter
+            .contains_keys(&batch)
+            .into_iter()
+            .filter(|&found| found)
+            .count() as u64;
+        remaining -= batch.len();
+    }
+
+    let predicted_rate = params::false_positive_rate(
+        filter.bit_count(),
+        filter.hash_count(),
+        expected_items as u64,
+    );
+
+    Ok(FpEstimate {
+        false_positives,
+        sample_size: sample_size as u64,
+        predicted_rate,
+        bit_count: filter.bit_count(),
+        hash_count: filter.hash_count(),
+    })
+}
+
+/// The number of equal, contiguous ranges of the bit array whose probe counts
+/// [`hash_quality`] compares.
+pub const PARTITIONS: usize = 16;
+
+/// The chi-square z-score above which [`HashQualityReport::is_suspicious`]
+/// flags a sample; a good hash exceeds it with probability of about 0.05%.
+const SUSPICIOUS_Z_SCORE: f64 = 3.3;
+
+/// The partition deviation, in standard deviations, above which
+/// [`HashQualityReport::is_suspicious`] flags a sample; a good hash exceeds
+/// it in any of the [`PARTITIONS`] with probability of about 0.1%.
+const SUSPICIOUS_DEVIATION: f64 = 4.0;
+
+/// How evenly the probes of a key sample spread over a bit array, as
+/// computed by [`hash_quality`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashQualityReport {
+    /// The number of keys in the sample.
+    pub keys: u64,
+    /// The number of probes (keys times k).
+    pub probes: u64,
+    /// Pearson's chi-square statistic of the probe counts per bin, where the
+    /// bit array is split into `bins` equal ranges.
+    pub chi_square: f64,
+    /// The number of bins the chi-square statistic is computed over.
+    pub bins: u64,
+    /// The chi-square statistic mapped to a standard normal score with the
+    /// Wilson-Hilferty transformation. Values near 0 mean uniform; large
+    /// positive values mean clumping.
+    pub z_score: f64,
+    /// The probe counts of each of the [`PARTITIONS`] ranges of the bit array.
+    pub partition_counts: [u64; PARTITIONS],
+    /// The largest deviation of a partition count from its expected value,
+    /// in standard deviations.
+    pub max_partition_deviation: f64,
+}
+
+impl HashQualityReport {
+    /// Returns `true` if the probes are distributed so unevenly that a good
+    /// hash would almost never produce the sample: the chi-square z-score
+    /// or a partition deviation is far beyond what chance explains.
+    ///
+    /// A flagged sample means the filter will fill some regions faster than
+    /// others and exceed its target false positive rate for these keys.
+    pub fn is_suspicious(&self) -> bool {
+        self.z_score > SUSPICIOUS_Z_SCORE || self.max_partition_deviation > SUSPICIOUS_DEVIATION
+    }
+}
+
+/// Analyzes how the filter's hasher spreads the probes of `keys` over a bit
+/// array of `bit_count` bits with `hash_count` probes per key.
+///
+/// Pass the geometry of the filter the keys are meant for, e.g.
+/// `hash_quality(&keys, filter.bit_count(), filter.hash_count())`. A sample
+/// of a few thousand keys or more gives a meaningful result.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{stats, BloomFilter};
+///
+/// let filter: BloomFilter<u64> = BloomFilter::new(10_000, 0.01);
+/// let keys: Vec<u64> = (0..10_000).collect();
+///
+/// let report = stats::hash_quality(&keys, filter.bit_count(), filter.hash_count());
+/// assert!(!report.is_suspicious());
+/// ```
+///
+/// # Panics
+///
+/// Panics if `bit_count` or `hash_count` is 0.
+pub fn hash_quality<'a, K: ?Sized + Hash + 'a>(
+    keys: impl IntoIterator<Item = &'a K>,
+    bit_count: u64,
+    hash_count: u32,
+) -> HashQualityReport {
+    hash_quality_with(
+        &BuildHasherDefault::<DefaultHasher>::default(),
+        keys,
+        bit_count,
+        hash_count,
+    )
+}
+
+/// Like [`hash_quality`], but hashes with hashers from `build_hasher`, to
+/// compare candidate hashers on the same keys.
+///
+/// # Panics
+///
+/// Panics if `bit_count` or `hash_count` is 0.
+pub fn hash_quality_with<'a, S: BuildHasher, K: ?Sized + Hash + 'a>(
+    build_hasher: &S,
+    keys: impl IntoIterator<Item = &'a K>,
+    bit_count: u64,
+    hash_count: u32,
+) -> HashQualityReport {
+    assert!(
+        bit_count > 0 && hash_count > 0,
+        "Bit count and hash count must be greater than 0."
+    );
+    let pairs: Vec<(u64, u64)> = keys
+        .into_iter()
+        .map(|key| hashing::hash_pair_with(build_hasher, key))
+        .collect();
+    let probes = pairs.len() as u64 * hash_count as u64;
+
+    // Aim for about 20 expected probes per bin, so the chi-square
+    // approximation holds, but use no more bins than words.
+    let word_count = bit_count.div_ceil(64);
+    let bins = (probes / 20).clamp(2, word_count.max(2));
+    let mut bin_counts = vec![0u64; bins as usize];
+    let mut partition_counts = [0u64; PARTITIONS];
+    for &(h1, h2) in &pairs {
+        for i in 0..hash_count {
+            let bit_index = hashing::bit_index(h1, h2, i, bit_count);
+            bin_counts[hashing::fastrange_of(bit_index, bit_count, bins) as usize] += 1;
+            partition_counts
+                [hashing::fastrange_of(bit_index, bit_count, PARTITIONS as u64) as usize] += 1;
+        }
+    }
+
+    let expected = probes as f64 / bins as f64;
+    let chi_square = if probes == 0 {
+        0.0
+    } else {
+        bin_counts
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum()
+    };
+    let df = (bins - 1) as f64;
+    let spread = 2.0 / (9.0 * df);
+    let z_score = ((chi_square / df).cbrt() - (1.0 - spread)) / spread.sqrt();
+
+    let partition_expected = probes as f64 / PARTITIONS as f64;
+    let max_partition_deviation = if probes == 0 {
+        0.0
+    } else {
+        partition_counts
+            .iter()
+            .map(|&count| (count as f64 - partition_expected).abs() / partition_expected.sqrt())
+            .fold(0.0, f64::max)
+    };
+
+    HashQualityReport {
+        keys: pairs.len() as u64,
+        probes,
+        chi_square,
+        bins,
+        z_score,
+        partition_counts,
+        max_partition_deviation,
+    }
+}
+
+/// The number of density ranges in a [`SaturationReport::histogram`].
+pub const SATURATION_BUCKETS: usize = 20;
+
+/// The probability below which [`SaturationReport::is_skewed`] flags a
+/// filter.
+const SKEWED_P_VALUE: f64 = 1e-3;
+
+/// The density of set bits per block of a filter, as computed by
+/// [`saturation`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaturationReport {
+    /// The number of bits per block. The last block may be shorter.
+    pub block_bits: u64,
+    /// The fraction of all bits that are set.
+    pub fill_ratio: f64,
+    /// The fraction of set bits in each block, in bit order.
+    pub block_fill: Vec<f64>,
+    /// The number of blocks per density range: bucket `i` counts the blocks
+    /// with a fill in `[i / SATURATION_BUCKETS, (i + 1) / SATURATION_BUCKETS)`,
+    /// and the last bucket also counts full blocks.
+    pub histogram: [u64; SATURATION_BUCKETS],
+    /// The index of the block with the most set bits for its length.
+    pub hottest_block: usize,
+    /// The probability that a filter with the same fill, but uniformly
+    /// spread bits, has a block as full as the hottest one (Bonferroni
+    /// corrected for the number of blocks).
+    pub hottest_p_value: f64,
+}
+
+impl SaturationReport {
+    /// Returns `true` if the hottest block is so much fuller than the filter
+    /// as a whole that uniform hashing would almost never produce it.
+    ///
+    /// Keys probing a saturated block see a false positive rate well above
+    /// the one the overall fill ratio predicts.
+    pub fn is_skewed(&self) -> bool {
+        self.hottest_p_value < SKEWED_P_VALUE
+    }
+}
+
+/// Measures the density of set bits in each `block_bits`-bit block of
+/// `filter`.
+///
+/// Blocks of a few hundred to a few thousand bits give a useful picture: a
+/// uniformly filled block then rarely strays far from the overall fill.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{stats, BloomFilter};
+///
+/// let mut filter: BloomFilter<u64> = BloomFilter::new(10_000, 0.01);
+/// for key in 0..10_000 {
+///     filter.insert(&key);
+/// }
+///
+/// let report = stats::saturation(&filter, 4096);
+/// assert!((report.fill_ratio - 0.5).abs() < 0.05);
+/// assert!(!report.is_skewed());
+/// ```
+///
+/// # Panics
+///
+/// Panics if `block_bits` is not a positive multiple of 64.
+pub fn saturation<T: ?Sized + Hash, B: BitStore>(
+    filter: &BloomFilter<T, B>,
+    block_bits: u64,
+) -> SaturationReport {
+    assert!(
+        block_bits > 0 && block_bits.is_multiple_of(64),
+        "Block size must be a positive multiple of 64 bits."
+    );
+    let bit_count = filter.bit_count;
+    let block_count = bit_count.div_ceil(block_bits) as usize;
+    let words_per_block = (block_bits / B::Word::BITS as u64) as usize;
+
+    let mut block_ones = vec![0u64; block_count];
+    for index in 0..filter.used_words() {
+        block_ones[index / words_per_block] +=
+            filter.bit_vec.word(index).to_u64().count_ones() as u64;
+    }
+    let block_len = |block: usize| block_bits.min(bit_count - block as u64 * block_bits);
+
+    let mut block_fill = Vec::with_capacity(block_count);
+    let mut histogram = [0u64; SATURATION_BUCKETS];
+    for (block, &ones) in block_ones.iter().enumerate() {
+        let fill = ones as f64 / block_len(block) as f64;
+        let bucket = ((fill * SATURATION_BUCKETS as f64) as usize).min(SATURATION_BUCKETS - 1);
+        histogram[bucket] += 1;
+        block_fill.push(fill);
+    }
+
+    let fill_ratio = block_ones.iter().sum::<u64>() as f64 / bit_count as f64;
+    let hottest_block = block_fill
+        .iter()
+        .enumerate()
+        .fold(0, |hottest, (block, &fill)| {
+            if fill > block_fill[hottest] {
+                block
+            } else {
+                hottest
+            }
+        });
+    let tail = binomial_upper_tail(
+        block_len(hottest_block),
+        fill_ratio,
+        block_ones[hottest_block],
+    );
+
+    SaturationReport {
+        block_bits,
+        fill_ratio,
+        block_fill,
+        histogram,
+        hottest_block,
+        hottest_p_value: (tail * block_count as f64).min(1.0),
+    }
+}
+
+/// Returns the probability that a binomial variable with `trials` trials of
+/// success probability `p` is at least `successes`.
+fn binomial_upper_tail(trials: u64, p: f64, successes: u64) -> f64 {
+    if successes == 0 {
+        return 1.0;
+    }
+    if p <= 0.0 {
+        return 0.0;
+    }
+    if p >= 1.0 {
+        return 1.0;
+    }
+    let odds = p / (1.0 - p);
+    // The log probability of exactly `successes` successes.
+    let log_first = (0..successes)
+        .map(|i| ((trials - i) as f64 / (i + 1) as f64).ln())
+        .sum::<f64>()
+        + successes as f64 * p.ln()
+        + (trials - successes) as f64 * (1.0 - p).ln();
+
+    // Sum the following terms relative to the first; they shrink
+    // geometrically once past the mean.
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for j in successes..trials {
+        term *= (trials - j) as f64 / (j + 1) as f64 * odds;
+        sum += term;
+        if term < sum * 1e-17 {
+            break;
+        }
+    }
+    (log_first.exp() * sum).min(1.0)
+}
+
+/// The number of keys generated and inserted or probed at a time.
+const SIMULATION_BATCH: usize = 4096;
+
+/// The SplitMix64 generator. Its output is a bijection of a counter, so the
+/// first 2^64 values it yields are all distinct: probe keys never collide
+/// with inserted ones.
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Returns the `p`-quantile of the standard normal distribution, using
+/// Acklam's rational approximation (relative error below 1.2e-9).
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+
+    if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normal_quantile() {
+        assert!((normal_quantile(0.975) - 1.959_964).abs() < 1e-6);
+        assert!((normal_quantile(0.995) - 2.575_829).abs() < 1e-6);
+        assert!((normal_quantile(0.001) + 3.090_232).abs() < 1e-6);
+        assert_eq!(normal_quantile(0.5), 0.0);
+    }
+
+    #[test]
+    fn test_simulation_matches_prediction() {
+        let estimate = simulate(20_000, 0.01, 200_000, 7).unwrap();
+        assert_eq!(estimate.sample_size, 200_000);
+        let (low, high) = estimate.interval(0.999);
+        assert!(
+            low <= estimate.predicted_rate && estimate.predicted_rate <= high,
+            "{:?}",
+            estimate
+        );
+        assert_eq!(simulate(20_000, 0.01, 200_000, 7).unwrap(), estimate);
+    }
+
+    /// A hasher that returns the last integer written to it, as a naive
+    /// "identity" hash for integer keys would.
+    #[derive(Default)]
+    struct Identity(u64);
+
+    impl std::hash::Hasher for Identity {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                self.0 = self.0 << 8 | byte as u64;
+            }
+        }
+
+        fn write_u64(&mut self, value: u64) {
+            self.0 = value;
+        }
+    }
+
+    #[test]
+    fn test_hash_quality_flags_identity_hash() {
+        let keys: Vec<u64> = (0..20_000).collect();
+        let filter: BloomFilter<u64> = BloomFilter::new(20_000, 0.01);
+        let (m, k) = (filter.bit_count(), filter.hash_count());
+
+        let good = hash_quality(&keys, m, k);
+        assert_eq!(good.probes, 20_000 * k as u64);
+        assert_eq!(good.partition_counts.iter().sum::<u64>(), good.probes);
+        assert!(!good.is_suspicious(), "{:?}", good);
+
+        let bad = hash_quality_with(&BuildHasherDefault::<Identity>::default(), &keys, m, k);
+        assert!(bad.is_suspicious(), "{:?}", bad);
+    }
+
+    #[test]
+    fn test_binomial_upper_tail() {
+        assert_eq!(binomial_upper_tail(10, 0.5, 0), 1.0);
+        assert!((binomial_upper_tail(10, 0.5, 10) - 0.5f64.powi(10)).abs() < 1e-15);
+        assert!((binomial_upper_tail(10, 0.5, 8) - 56.0 / 1024.0).abs() < 1e-12);
+        assert!((binomial_upper_tail(4, 0.25, 2) - 0.261_718_75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_saturation_detects_hot_block() {
+        let mut filter: BloomFilter<u64> = BloomFilter::new(20_000, 0.01);
+        for key in 0..20_000 {
+            filter.insert(&key);
+        }
+        let report = saturation(&filter, 1024);
+        let blocks = filter.bit_count().div_ceil(1024);
+        assert_eq!(report.block_fill.len() as u64, blocks);
+        assert_eq!(report.histogram.iter().sum::<u64>(), blocks);
+        assert_eq!(report.fill_ratio, filter.fill_ratio());
+        assert!(!report.is_skewed(), "{:?}", report.hottest_p_value);
+
+        let mut narrow: BloomFilter<u64, Vec<u8>> = BloomFilter::from_params(20_000, 0.01);
+        for key in 0..20_000 {
+            narrow.insert(&key);
+        }
+        assert!(!saturation(&narrow, 1024).is_skewed());
+
+        // Saturate the fifth block, as keys clustering on it would.
+        filter.bit_vec[64..80].fill(u64::MAX);
+        let report = saturation(&filter, 1024);
+        assert_eq!(report.hottest_block, 4);
+        assert_eq!(report.block_fill[4], 1.0);
+        assert_eq!(report.histogram[SATURATION_BUCKETS - 1], 1);
+        assert!(report.is_skewed());
+    }
+
+    #[test]
+    fn test_interval_without_false_positives() {
+        let estimate = FpEstimate {
+            false_positives: 0,
+            sample_size: 1000,
+            predicted_rate: 0.0,
+            bit_count: 64,
+            hash_count: 1,
+        };
+        let (low, high) = estimate.interval(0.95);
+        assert_eq!(low, 0.0);
+        assert!(high > 0.0 && high < 0.01);
+        assert!(simulate(0, 0.01, 10, 0).is_err());
+    }
+}
@@ -0,0 +1,510 @@
+use std::fmt::Debug;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// An unsigned integer type used as a storage word of the bit array.
+///
+/// Implemented for `u8`, `u16`, `u32` and `u64`. Narrow words suit
+/// microcontrollers where 64-bit operations are emulated, and also round the
+/// filter size up to a smaller multiple.
+pub trait Word:
+    Copy + Eq + Debug + BitAnd<Output = Self> + BitOr<Output = Self> + BitOrAssign + sealed::Sealed
+{
+    /// The number of bits in the word.
+    const BITS: u32;
+    /// The word with no bits set.
+    const ZERO: Self;
+
+    /// Returns a word with only the bit at `offset` set.
+    fn bit(offset: u32) -> Self;
+
+    /// Widens the word to a `u64`, keeping bit positions.
+    fn to_u64(self) -> u64;
+
+    /// Narrows a `u64` to the word, keeping its low bits.
+    fn from_u64(value: u64) -> Self;
+
+    /// Views a slice of words as `u64`s, if the words are `u64`s.
+    #[inline]
+    fn as_u64_slice(words: &[Self]) -> Option<&[u64]> {
+        // SAFETY: `Word` is sealed and `u64` is its only 64-bit implementor,
+        // so the cast is the identity.
+        (Self::BITS == 64)
+            .then(|| unsafe { std::slice::from_raw_parts(words.as_ptr().cast(), words.len()) })
+    }
+
+    /// Views a mutable slice of words as `u64`s, if the words are `u64`s.
+    #[inline]
+    fn as_u64_slice_mut(words: &mut [Self]) -> Option<&mut [u64]> {
+        // SAFETY: as in `as_u64_slice`.
+        (Self::BITS == 64).then(|| unsafe {
+            std::slice::from_raw_parts_mut(words.as_mut_ptr().cast(), words.len())
+        })
+    }
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+
+            impl Word for $t {
+                const BITS: u32 = <$t>::BITS;
+                const ZERO: Self = 0;
+
+                #[inline]
+                fn bit(offset: u32) -> Self {
+                    1 << offset
+                }
+
+                #[inline]
+                fn to_u64(self) -> u64 {
+                    self as u64
+                }
+
+                #[inline]
+                fn from_u64(value: u64) -> Self {
+                    value as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_word!(u8, u16, u32, u64);
+
+/// Backing storage for the bit array of a [`BloomFilter`](crate::BloomFilter).
+///
+/// The filter only reads whole words and ORs masks into them, so a store does
+/// not need to be a contiguous slice.
+pub trait BitStore {
+    /// The word type the bits are packed into.
+    type Word: Word;
+
+    /// Returns the number of words in the store.
+    fn word_count(&self) -> usize;
+
+    /// Returns the word at `index`.
+    fn word(&self, index: usize) -> Self::Word;
+
+    /// Sets the bits of `mask` in the word at `index`.
+    fn set_bits(&mut self, index: usize, mask: Self::Word);
+
+    /// Resets every word to zero.
+    fn clear(&mut self);
+
+    /// Returns the approximate memory used by the store in bytes.
+    fn memory_usage_bytes(&self) -> usize {
+        self.word_count() * std::mem::size_of::<Self::Word>()
+    }
+
+    /// Returns the bits as one contiguous slice of `u64` words, if the store
+    /// keeps them that way.
+    ///
+    /// Stores that do are probed with the vectorized kernels (e.g. AVX-512
+    /// where the CPU supports it) instead of one word at a time.
+    fn as_u64_words(&self) -> Option<&[u64]> {
+        None
+    }
+
+    /// Mutable counterpart of [`as_u64_words`](Self::as_u64_words).
+    fn as_u64_words_mut(&mut self) -> Option<&mut [u64]> {
+        None
+    }
+}
+
+/// A [`BitStore`] that can allocate itself for a requested number of words.
+pub trait AllocStore: BitStore + Sized {
+    /// Creates a store of `word_count` zeroed words.
+    fn zeroed(word_count: usize) -> Self;
+}
+
+// `Vec<W>` is a store for any allocator when the `allocator_api` feature is
+// on, and for the global allocator otherwise; the body is the same.
+macro_rules! impl_vec_store {
+    ($($alloc:ident: $bound:path)?) => {
+        impl<W: Word $(, $alloc: $bound)?> BitStore for Vec<W $(, $alloc)?> {
+            type Word = W;
+
+            #[inline]
+            fn word_count(&self) -> usize {
+                self.len()
+            }
+
+            #[inline]
+            fn word(&self, index: usize) -> W {
+                self[index]
+            }
+
+            #[inline]
+            fn set_bits(&mut self, index: usize, mask: W) {
+                self[index] |= mask;
+            }
+
+            fn clear(&mut self) {
+                for slot in self.iter_mut() {
+                    *slot = W::ZERO;
+                }
+            }
+
+            fn memory_usage_bytes(&self) -> usize {
+                self.capacity() * std::mem::size_of::<W>()
+            }
+
+            #[inline]
+            fn as_u64_words(&self) -> Option<&[u64]> {
+                W::as_u64_slice(self)
+            }
+
+            #[inline]
+            fn as_u64_words_mut(&mut self) -> Option<&mut [u64]> {
+                W::as_u64_slice_mut(self)
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "allocator_api"))]
+impl_vec_store!();
+#[cfg(feature = "allocator_api")]
+impl_vec_store!(A: std::alloc::Allocator);
+
+#[cfg(not(feature = "allocator_api"))]
+impl<W: Word> AllocStore for Vec<W> {
+    fn zeroed(word_count: usize) -> Self {
+        vec![W::ZERO; word_count]
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+impl<W: Word, A: std::alloc::Allocator + Default> AllocStore for Vec<W, A> {
+    fn zeroed(word_count: usize) -> Self {
+        zeroed_vec_in(word_count, A::default())
+    }
+}
+
+/// Allocates `word_count` zeroed words in `alloc`, letting the allocator hand
+/// out pre-zeroed memory (e.g. fresh pages) rather than writing the zeros.
+#[cfg(feature = "allocator_api")]
+pub(crate) fn zeroed_vec_in<W: Word, A: std::alloc::Allocator>(
+    word_count: usize,
+    alloc: A,
+) -> Vec<W, A> {
+    use std::alloc::{handle_alloc_error, Layout};
+
+    let layout = Layout::array::<W>(word_count).expect("capacity overflow");
+    if layout.size() == 0 {
+        return Vec::new_in(alloc);
+    }
+    let ptr = alloc
+        .allocate_zeroed(layout)
+        .unwrap_or_else(|_| handle_alloc_error(layout));
+    // SAFETY: the block was allocated by `alloc` with the layout of
+    // `word_count` words, and all-zero bytes are a valid unsigned integer.
+    unsafe { Vec::from_raw_parts_in(ptr.cast::<W>().as_ptr(), word_count, word_count, alloc) }
+}
+
+/// A fixed array of words is a store of `N` words that needs no allocator,
+/// for filters of a size known at compile time; see
+/// [`BloomFilter::with_array`](crate::BloomFilter::with_array).
+impl<W: Word, const N: usize> BitStore for [W; N] {
+    type Word = W;
+
+    #[inline]
+    fn word_count(&self) -> usize {
+        N
+    }
+
+    #[inline]
+    fn word(&self, index: usize) -> W {
+        self[index]
+    }
+
+    #[inline]
+    fn set_bits(&mut self, index: usize, mask: W) {
+        self[index] |= mask;
+    }
+
+    fn clear(&mut self) {
+        *self = [W::ZERO; N];
+    }
+
+    #[inline]
+    fn as_u64_words(&self) -> Option<&[u64]> {
+        W::as_u64_slice(self)
+    }
+
+    #[inline]
+    fn as_u64_words_mut(&mut self) -> Option<&mut [u64]> {
+        W::as_u64_slice_mut(self)
+    }
+}
+
+/// The size and alignment of a cache line on current x86-64 and ARM cores.
+const CACHE_LINE_BYTES: usize = 64;
+
+/// One cache line of bit array words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, align(64))]
+struct CacheLine([u64; CACHE_LINE_BYTES / 8]);
+
+/// A [`BitStore`] of `u64` words aligned to and padded out to whole cache lines.
+///
+/// `Vec<u64>` only guarantees 8-byte alignment, so a 64-byte run of words may
+/// straddle two cache lines. This store starts on a line boundary and rounds
+/// its length up to whole lines, so every aligned group of eight words (what
+/// the AVX-512 kernels and cache-line-blocked layouts touch at once) lies in
+/// exactly one line.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{AlignedStore, BloomFilter};
+///
+/// let mut bf = BloomFilter::<str, AlignedStore>::from_params(1000, 0.01);
+/// bf.insert("seen");
+/// assert!(bf.contains("seen"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlignedStore {
+    /// The words, a cache line at a time.
+    lines: Vec<CacheLine>,
+}
+
+impl AlignedStore {
+    /// The number of words per cache line.
+    const LINE_WORDS: usize = CACHE_LINE_BYTES / 8;
+
+    /// Returns the words as one slice.
+    pub fn as_slice(&self) -> &[u64] {
+        // SAFETY: `CacheLine` is `repr(C)` around `[u64; 8]` and exactly 64
+        // bytes, so the lines are a gap-free run of `u64`s.
+        unsafe {
+            std::slice::from_raw_parts(
+                self.lines.as_ptr().cast(),
+                self.lines.len() * Self::LINE_WORDS,
+            )
+        }
+    }
+
+    /// Returns the words as one mutable slice.
+    pub fn as_mut_slice(&mut self) -> &mut [u64] {
+        // SAFETY: as in `as_slice`.
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.lines.as_mut_ptr().cast(),
+                self.lines.len() * Self::LINE_WORDS,
+            )
+        }
+    }
+}
+
+impl BitStore for AlignedStore {
+    type Word = u64;
+
+    #[inline]
+    fn word_count(&self) -> usize {
+        self.lines.len() * Self::LINE_WORDS
+    }
+
+    #[inline]
+    fn word(&self, index: usize) -> u64 {
+        self.lines[index / Self::LINE_WORDS].0[index % Self::LINE_WORDS]
+    }
+
+    #[inline]
+    fn set_bits(&mut self, index: usize, mask: u64) {
+        self.lines[index / Self::LINE_WORDS].0[index % Self::LINE_WORDS] |= mask;
+    }
+
+    fn clear(&mut self) {
+        self.as_mut_slice().fill(0);
+    }
+
+    fn memory_usage_bytes(&self) -> usize {
+        self.lines.capacity() * CACHE_LINE_BYTES
+    }
+
+    #[inline]
+    fn as_u64_words(&self) -> Option<&[u64]> {
+        Some(self.as_slice())
+    }
+
+    #[inline]
+    fn as_u64_words_mut(&mut self) -> Option<&mut [u64]> {
+        Some(self.as_mut_slice())
+    }
+}
+
+impl AllocStore for AlignedStore {
+    fn zeroed(word_count: usize) -> Self {
+        AlignedStore {
+            lines: vec![CacheLine([0; Self::LINE_WORDS]); word_count.div_ceil(Self::LINE_WORDS)],
+        }
+    }
+}
+
+/// A [`BitStore`] of `u64` words allocated in fixed-size chunks on first write.
+///
+/// A filter sized for a large expected item count normally allocates its whole
+/// bit array up front, even if only a few items are ever inserted. This store
+/// starts out empty: reads of an untouched chunk see zeros, and a chunk is
+/// allocated the first time one of its bits is set. No single allocation is
+/// larger than [`CHUNK_BYTES`](Self::CHUNK_BYTES), so a multi-gigabyte filter
+/// does not need one contiguous block from a fragmented heap.
+///
+/// Probes are spread uniformly, so each insert touches about k random chunks
+/// and the memory grows quickly once a filter sees real traffic; the savings
+/// are for filters that stay sparsely populated.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{BloomFilter, ChunkedStore};
+///
+/// let mut bf = BloomFilter::<str, ChunkedStore>::from_params(100_000_000, 0.01);
+/// assert_eq!(bf.memory_usage_bytes(), 0);
+/// bf.insert("seen");
+/// assert!(bf.contains("seen"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkedStore {
+    /// The chunks, `None` until first written.
+    chunks: Vec<Option<Box<[u64]>>>,
+    /// The number of words in the store.
+    word_count: usize,
+}
+
+impl ChunkedStore {
+    /// The size of one chunk in bytes.
+    pub const CHUNK_BYTES: usize = 1 << 20;
+
+    /// The number of words per chunk.
+    const CHUNK_WORDS: usize = Self::CHUNK_BYTES / 8;
+
+    /// Returns the number of chunks allocated so far.
+    pub fn allocated_chunks(&self) -> usize {
+        self.chunks.iter().filter(|chunk| chunk.is_some()).count()
+    }
+
+    /// Returns the number of words in chunk `index`, which is shorter than a
+    /// full chunk only for the last one.
+    fn chunk_len(&self, index: usize) -> usize {
+        (self.word_count - index * Self::CHUNK_WORDS).min(Self::CHUNK_WORDS)
+    }
+}
+
+impl BitStore for ChunkedStore {
+    type Word = u64;
+
+    #[inline]
+    fn word_count(&self) -> usize {
+        self.word_count
+    }
+
+    #[inline]
+    fn word(&self, index: usize) -> u64 {
+        assert!(index < self.word_count, "word index out of bounds");
+        match &self.chunks[index / Self::CHUNK_WORDS] {
+            Some(chunk) => chunk[index % Self::CHUNK_WORDS],
+            None => 0,
+        }
+    }
+
+    #[inline]
+    fn set_bits(&mut self, index: usize, mask: u64) {
+        assert!(index < self.word_count, "word index out of bounds");
+        let chunk_index = index / Self::CHUNK_WORDS;
+        let len = self.chunk_len(chunk_index);
+        let chunk = self.chunks[chunk_index].get_or_insert_with(|| vec![0; len].into_boxed_slice());
+        chunk[index % Self::CHUNK_WORDS] |= mask;
+    }
+
+    /// Frees every chunk, returning the store to its initial, unallocated state.
+    fn clear(&mut self) {
+        self.chunks.fill(None);
+    }
+
+    fn memory_usage_bytes(&self) -> usize {
+        self.chunks
+            .iter()
+            .flatten()
+            .map(|chunk| chunk.len() * 8)
+            .sum()
+    }
+}
+
+impl AllocStore for ChunkedStore {
+    fn zeroed(word_count: usize) -> Self {
+        ChunkedStore {
+            chunks: vec![None; word_count.div_ceil(Self::CHUNK_WORDS)],
+            word_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BloomFilter;
+
+    #[test]
+    fn test_aligned_store_layout() {
+        let store = AlignedStore::zeroed(9);
+        assert_eq!(store.word_count(), 16);
+        assert_eq!(store.as_slice().as_ptr() as usize % CACHE_LINE_BYTES, 0);
+        assert_eq!(store.memory_usage_bytes(), 128);
+    }
+
+    #[test]
+    fn test_aligned_store_matches_vec() {
+        let mut aligned = BloomFilter::<u64, AlignedStore>::from_params(1000, 0.01);
+        let mut plain = BloomFilter::<u64>::new(1000, 0.01);
+        for i in 0..1000u64 {
+            aligned.insert(&i);
+            plain.insert(&i);
+        }
+        for i in 0..5000u64 {
+            assert_eq!(aligned.contains(&i), plain.contains(&i));
+        }
+        assert_eq!(
+            aligned.freeze().contains_batch(&[1u64, 5000]),
+            [true, plain.contains(&5000)]
+        );
+    }
+
+    #[test]
+    fn test_chunked_store_allocates_on_write() {
+        let words = ChunkedStore::CHUNK_WORDS;
+        let mut store = ChunkedStore::zeroed(2 * words + 10);
+        assert_eq!(store.memory_usage_bytes(), 0);
+        assert_eq!(store.word(2 * words + 9), 0);
+
+        store.set_bits(2 * words + 9, 0b101);
+        assert_eq!(store.allocated_chunks(), 1);
+        assert_eq!(store.memory_usage_bytes(), 80);
+        assert_eq!(store.word(2 * words + 9), 0b101);
+
+        store.set_bits(3, 1);
+        assert_eq!(store.allocated_chunks(), 2);
+        store.clear();
+        assert_eq!(store.memory_usage_bytes(), 0);
+        assert_eq!(store.word(3), 0);
+    }
+
+    #[test]
+    fn test_chunked_store_matches_vec() {
+        let mut chunked = BloomFilter::<u64, ChunkedStore>::from_params(1_000_000, 0.01);
+        let mut plain = BloomFilter::<u64>::new(1_000_000, 0.01);
+        for i in 0..1000u64 {
+            chunked.insert(&i);
+            plain.insert(&i);
+        }
+        for i in 0..5000u64 {
+            assert_eq!(chunked.contains(&i), plain.contains(&i));
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use crate::const_filter::splitmix64;
+use crate::{hashing, ApproximateSet, BloomError, BloomFilter, FilterParams};
+
+/// Seeded lookup tables for tabulation hashing, usable wherever a
+/// `BuildHasher` is, as `&TabulationHash`.
+///
+/// Simple tabulation splits a 64-bit word into its 8 bytes, looks each up
+/// in a table of random words of its own, and XORs the results. With the
+/// tables filled at random it is 3-independent and satisfies Chernoff-type
+/// concentration bounds like fully random hashing (Pătraşcu and Thorup,
+/// 2012), so analyses of a filter that assume random hash functions rest on
+/// proven properties, which fixed functions such as SipHash lack.
+///
+/// [`TabulationHasher`] tabulates each 8-byte word of the hashed bytes and
+/// combines the words by rotating and XORing. For keys of one fixed width up
+/// to 8 bytes, such as integers, every hash is therefore simple tabulation
+/// of the key; longer keys are combined from several tabulated words, which
+/// mixes well in practice but is outside that guarantee.
+///
+/// The tables take 16 KiB and are filled from a 64-bit seed by SplitMix64,
+/// so the same seed gives the same hashes everywhere. Keep it secret if
+/// adversarial keys are a concern.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TabulationHash {
+    seed: u64,
+    tables: Box<[[u64; 256]; 8]>,
+}
+
+impl TabulationHash {
+    /// Fills the tables from `seed`.
+    pub fn new(seed: u64) -> Self {
+        let mut tables = Box::new([[0; 256]; 8]);
+        let mut state = seed;
+        for entry in tables.iter_mut().flatten() {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            *entry = splitmix64(state);
+        }
+        TabulationHash { seed, tables }
+    }
+
+    /// Returns the seed the tables were filled from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Looks up each byte of `word` in its table and XORs the results.
+    #[inline]
+    fn tabulate(&self, word: u64) -> u64 {
+        word.to_le_bytes()
+            .iter()
+            .zip(self.tables.iter())
+            .fold(0, |hash, (&byte, table)| hash ^ table[byte as usize])
+    }
+}
+
+impl fmt::Debug for TabulationHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TabulationHash")
+            .field("seed", &self.seed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> BuildHasher for &'a TabulationHash {
+    type Hasher = TabulationHasher<'a>;
+
+    fn build_hasher(&self) -> TabulationHasher<'a> {
+        TabulationHasher {
+            tables: self,
+            state: 0,
+            tail: 0,
+            tail_len: 0,
+            length: 0,
+        }
+    }
+}
+
+/// A hasher of [`TabulationHash`].
+#[derive(Debug, Clone)]
+pub struct TabulationHasher<'a> {
+    tables: &'a TabulationHash,
+    state: u64,
+    /// The bytes written since the last whole word, little-endian.
+    tail: u64,
+    tail_len: usize,
+    length: usize,
+}
+
+impl TabulationHasher<'_> {
+    fn compress(&mut self, word: u64) {
+        self.state = self.state.rotate_left(23) ^ self.tables.tabulate(word);
+    }
+}
+
+impl Hasher for TabulationHasher<'_> {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len();
+        if self.tail_len > 0 {
+            let take = bytes.len().min(8 - self.tail_len);
+            for (offset, &byte) in bytes[..take].iter().enumerate() {
+                self.tail |= (byte as u64) << (8 * (self.tail_len + offset));
+            }
+            self.tail_len += take;
+            bytes = &bytes[take..];
+            if self.tail_len < 8 {
+                return;
+            }
+            self.compress(self.tail);
+            (self.tail, self.tail_len) = (0, 0);
+        }
+        let mut words = bytes.chunks_exact(8);
+        for word in words.by_ref() {
+            self.compress(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        for (offset, &byte) in words.remainder().iter().enumerate() {
+            self.tail |= (byte as u64) << (8 * offset);
+        }
+        self.tail_len = words.remainder().len();
+    }
+
+    fn finish(&self) -> u64 {
+        // For a whole number of words the last word only holds the length,
+        // so fixed-width keys are still hashed by one rotated tabulation
+        // XOR a constant.
+        let mut state = self.clone();
+        state.compress(self.tail | (self.length as u64) << 56);
+        state.state
+    }
+}
+
+/// A [`BloomFilter`] whose items are hashed by [`TabulationHash`] instead
+/// of the standard library's `DefaultHasher`.
+///
+/// Filters built with the same seed have the same bits for the same items,
+/// so they can be merged or saved and reloaded with
+/// [`from_filter`](Self::from_filter) like any [`BloomFilter`]; a plain
+/// `BloomFilter`'s lookups would miss their items.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{TabulationBloomFilter, TabulationHash};
+///
+/// let mut filter: TabulationBloomFilter<u64> =
+///     TabulationBloomFilter::new(TabulationHash::new(42), 1000, 0.01);
+/// filter.insert(&7);
+/// assert!(filter.contains(&7));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TabulationBloomFilter<T: ?Sized> {
+    filter: BloomFilter<T>,
+    hash: TabulationHash,
+}
+
+impl<T: ?Sized + Hash> TabulationBloomFilter<T> {
+    /// Creates an empty filter hashing with `hash`.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`BloomFilter::new`].
+    pub fn new(
+        hash: TabulationHash,
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Self {
+        Self::try_new(hash, expected_items, params).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates an empty filter hashing with `hash`, returning an error
+    /// instead of panicking.
+    pub fn try_new(
+        hash: TabulationHash,
+        expected_items: usize,
+        params: impl Into<FilterParams>,
+    ) -> Result<Self, BloomError> {
+        Ok(TabulationBloomFilter {
+            filter: BloomFilter::try_new(expected_items, params)?,
+            hash,
+        })
+    }
+
+    /// Treats `filter` as built with `hash`, e.g. a filter loaded from
+    /// bytes.
+    pub fn from_filter(hash: TabulationHash, filter: BloomFilter<T>) -> Self {
+        TabulationBloomFilter { filter, hash }
+    }
+
+    /// Inserts an item.
+    pub fn insert(&mut self, item: &T) {
+        let (h1, h2) = hashing::hash_pair_with(&&self.hash, item);
+        self.filter.insert_hashes(h1, h2);
+        self.filter.counters.record_inserts(1);
+    }
+
+    /// Checks if an item might be in the filter.
+    pub fn contains(&self, item: &T) -> bool {
+        let (h1, h2) = hashing::hash_pair_with(&&self.hash, item);
+        let found = self.filter.contains_hashes(h1, h2);
+        self.filter.counters.record_query(found);
+        found
+    }
+
+    /// Adds every item of `other` to this filter.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BloomError::KeyMismatch`] if the filters have different
+    /// seeds, or [`BloomError::IncompatibleFilters`] if their bit counts or
+    /// hash counts differ.
+    pub fn union(&mut self, other: &Self) -> Result<(), BloomError> {
+        if self.hash.seed != other.hash.seed {
+            return Err(BloomError::KeyMismatch);
+        }
+        self.filter.merge(&other.filter)
+    }
+
+    /// Returns the tables items are hashed with.
+    pub fn hash(&self) -> &TabulationHash {
+        &self.hash
+    }
+
+    /// Returns the underlying filter.
+    pub fn filter(&self) -> &BloomFilter<T> {
+        &self.filter
+    }
+
+    /// Unwraps the underlying filter.
+    pub fn into_inner(self) -> BloomFilter<T> {
+        self.filter
+    }
+}
+
+impl<T: ?Sized + Hash> ApproximateSet<T> for TabulationBloomFilter<T> {
+    fn insert(&mut self, item: &T) {
+        TabulationBloomFilter::insert(self, item);
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        TabulationBloomFilter::contains(self, item)
+    }
+
+    fn estimated_len(&self) -> f64 {
+        self.filter.estimated_items()
+    }
+
+    fn fp_estimate(&self) -> f64 {
+        self.filter
+            .fill_ratio()
+            .powi(self.filter.hash_count() as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats;
+
+    #[test]
+    fn test_tabulation_filter() {
+        let hash = TabulationHash::new(7);
+        let keys: Vec<u64> = (0..10_000).collect();
+        let mut filter = TabulationBloomFilter::new(hash.clone(), keys.len(), 0.01);
+        keys.iter().for_each(|key| filter.insert(key));
+        assert!(keys.iter().all(|key| filter.contains(key)));
+        let false_positives = (10_000..110_000u64)
+            .filter(|key| filter.contains(key))
+            .count();
+        assert!(false_positives < 1_500, "{}", false_positives);
+
+        let report = stats::hash_quality_with(
+            &&hash,
+            &keys,
+            filter.filter().bit_count(),
+            filter.filter().hash_count(),
+        );
+        assert!(!report.is_suspicious());
+
+        // Same seed, same hashes; another seed, other hashes.
+        let same = (&TabulationHash::new(7)).hash_one("key");
+        assert_eq!((&hash).hash_one("key"), same);
+        assert_ne!((&TabulationHash::new(8)).hash_one("key"), same);
+        let other = TabulationBloomFilter::new(TabulationHash::new(8), keys.len(), 0.01);
+        assert_eq!(filter.union(&other), Err(BloomError::KeyMismatch));
+    }
+}
@@ -0,0 +1,114 @@
+//! Optional `tracing` spans around expensive operations.
+//!
+//! [`timed_span!`] opens an info-level span for the rest of the enclosing
+//! block and, when the block exits (early returns included), emits an event
+//! in it with the elapsed time as `elapsed_us`. Without the `tracing`
+//! feature it expands to nothing, so call sites need no `cfg` of their own.
+
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+/// An entered span that reports its duration when dropped.
+#[cfg(feature = "tracing")]
+pub(crate) struct TimedSpan {
+    _span: tracing::span::EnteredSpan,
+    start: Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl TimedSpan {
+    pub(crate) fn enter(span: tracing::Span) -> Self {
+        TimedSpan {
+            _span: span.entered(),
+            start: Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for TimedSpan {
+    // Runs before the span guard is dropped, so the event lands in the span.
+    fn drop(&mut self) {
+        tracing::info!(elapsed_us = self.start.elapsed().as_micros() as u64, "done");
+    }
+}
+
+/// Times the rest of the enclosing block in a span named `$name` with the
+/// given fields, e.g. `timed_span!("bloom.merge", bits = self.bit_count);`.
+macro_rules! timed_span {
+    ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {
+        #[cfg(feature = "tracing")]
+        let _timed_span = $crate::trace::TimedSpan::enter(
+            tracing::info_span!($name $(, $field = $value)*),
+        );
+    };
+}
+
+pub(crate) use timed_span;
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::BloomFilter;
+
+    /// Records the names of new spans and the spans events are emitted in.
+    #[derive(Default)]
+    struct SpanLog {
+        next_id: AtomicU64,
+        spans: Mutex<Vec<&'static str>>,
+        current: Mutex<Vec<u64>>,
+        events: Mutex<Vec<&'static str>>,
+    }
+
+    impl Subscriber for SpanLog {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            self.spans.lock().unwrap().push(span.metadata().name());
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {
+            let current = *self.current.lock().unwrap().last().unwrap();
+            let name = self.spans.lock().unwrap()[current as usize - 1];
+            self.events.lock().unwrap().push(name);
+        }
+
+        fn enter(&self, span: &Id) {
+            self.current.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _: &Id) {
+            self.current.lock().unwrap().pop();
+        }
+    }
+
+    #[test]
+    fn test_operations_are_traced() {
+        let log = Arc::new(SpanLog::default());
+        tracing::subscriber::with_default(Arc::clone(&log), || {
+            let mut left: BloomFilter<str> = BloomFilter::new(100, 0.01);
+            let right: BloomFilter<str> = BloomFilter::new(100, 0.01);
+            left.merge(&right).unwrap();
+            let bytes = left.to_bytes();
+            let _: BloomFilter<str> = BloomFilter::read_from(&bytes[..]).unwrap();
+            // A failed load still closes its span.
+            assert!(BloomFilter::<str>::from_bytes(&bytes[1..]).is_err());
+        });
+
+        let expected = ["bloom.merge", "bloom.write", "bloom.read", "bloom.read"];
+        assert_eq!(*log.spans.lock().unwrap(), expected);
+        assert_eq!(*log.events.lock().unwrap(), expected);
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::HotSwapFilter;
+
+/// Reloads a [`HotSwapFilter`] whenever its backing file is replaced, e.g.
+/// by a nightly job that rebuilds the filter.
+///
+/// Requires the `watch` feature. The watcher follows the file's directory
+/// through the operating system's notifications (inotify, FSEvents, ...),
+/// and whenever the file is created, replaced or written it loads it with
+/// the given function and swaps the result in. Readers keep running
+/// throughout, as with [`HotSwapFilter::swap`].
+///
+/// The builder should write the new filter to a temporary file in the same
+/// directory and rename it over the old one, so that the watcher never
+/// sees a partial file. A load that fails, e.g. on a file still being
+/// written in place, keeps the current filter; the error is kept for
+/// [`take_error`](Self::take_error) and the next change is loaded as usual.
+///
+/// Watching stops when the watcher is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use std::sync::Arc;
+/// use bloomlib::{BloomFilter, FilterWatcher, HotSwapFilter};
+///
+/// let load = |path: &std::path::Path| BloomFilter::<str>::read_from(BufReader::new(File::open(path)?));
+/// let slot = Arc::new(HotSwapFilter::new(load("blocklist.bloom".as_ref())?));
+/// let _watcher = FilterWatcher::new(Arc::clone(&slot), "blocklist.bloom", load)?;
+///
+/// // Serve from `slot`; rebuilds of blocklist.bloom are picked up.
+/// assert!(!slot.load().contains("example.com"));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct FilterWatcher {
+    /// Watches until dropped.
+    _watcher: RecommendedWatcher,
+    path: PathBuf,
+    reloads: Arc<AtomicU64>,
+    error: Arc<Mutex<Option<io::Error>>>,
+}
+
+impl FilterWatcher {
+    /// Starts watching the file at `path`, reloading `slot` with `load`
+    /// whenever it changes. The current contents are not loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file's directory cannot be watched.
+    pub fn new<F, L>(
+        slot: Arc<HotSwapFilter<F>>,
+        path: impl AsRef<Path>,
+        load: L,
+    ) -> notify::Result<Self>
+    where
+        F: Send + Sync + 'static,
+        L: Fn(&Path) -> io::Result<F> + Send + 'static,
+    {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| notify::Error::path_not_found().add_path(path.to_owned()))?
+            .to_owned();
+        // Renames replace the file itself, so its directory is watched.
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.canonicalize()?,
+            _ => std::env::current_dir()?,
+        };
+        let path = dir.join(file_name);
+
+        let reloads = Arc::new(AtomicU64::new(0));
+        let error = Arc::new(Mutex::new(None));
+        let handler = {
+            let (path, reloads, error) = (path.clone(), Arc::clone(&reloads), Arc::clone(&error));
+            move |event: notify::Result<Event>| {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(err) => return store(&error, io::Error::other(err)),
+                };
+                let replaced = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+                if !replaced || !event.paths.contains(&path) {
+                    return;
+                }
+                match load(&path) {
+                    Ok(filter) => {
+                        slot.swap(filter);
+                        // Releases the swap to callers that see the count.
+                        reloads.fetch_add(1, Ordering::Release);
+                    }
+                    Err(err) => store(&error, err),
+                }
+            }
+        };
+        let mut watcher = notify::recommended_watcher(handler)?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        Ok(FilterWatcher {
+            _watcher: watcher,
+            path,
+            reloads,
+            error,
+        })
+    }
+
+    /// Returns the absolute path of the watched file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the number of times the filter was reloaded. A reload is
+    /// counted after its filter is swapped in, so once the count has grown,
+    /// loads from the slot return the new filter or a later one.
+    pub fn reload_count(&self) -> u64 {
+        self.reloads.load(Ordering::Acquire)
+    }
+
+    /// Returns the error of the last failed load or watch, if any since the
+    /// last call, and clears it.
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.error
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+    }
+}
+
+fn store(slot: &Mutex<Option<io::Error>>, err: io::Error) {
+    *slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(err);
+}
+
+impl fmt::Debug for FilterWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilterWatcher")
+            .field("path", &self.path)
+            .field("reloads", &self.reload_count())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BloomFilter;
+    use std::fs;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn wait_for(condition: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !condition() {
+            if Instant::now() > deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        true
+    }
+
+    #[test]
+    fn test_reload_on_replace() {
+        let dir = std::env::temp_dir().join(format!("bloomlib-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("filter.bloom");
+        let replace = |item: &str| {
+            let mut filter: BloomFilter<str> = BloomFilter::new(100, 0.01);
+            filter.insert(item);
+            fs::write(dir.join("filter.tmp"), filter.to_bytes()).unwrap();
+            fs::rename(dir.join("filter.tmp"), &path).unwrap();
+        };
+        replace("first");
+
+        let load = |path: &Path| BloomFilter::<str>::read_from(fs::File::open(path)?);
+        let slot = Arc::new(HotSwapFilter::new(load(&path).unwrap()));
+        let watcher = FilterWatcher::new(Arc::clone(&slot), &path, load).unwrap();
+        assert!(slot.load().contains("first"));
+
+        replace("second");
+        assert!(wait_for(|| watcher.reload_count() >= 1));
+        assert!(slot.load().contains("second"));
+        assert!(!slot.load().contains("first"));
+
+        // A corrupt file keeps the current filter.
+        fs::write(dir.join("filter.tmp"), b"not a filter").unwrap();
+        fs::rename(dir.join("filter.tmp"), &path).unwrap();
+        assert!(wait_for(|| watcher.take_error().is_some()));
+        assert!(slot.load().contains("second"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
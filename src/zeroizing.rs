@@ -0,0 +1,98 @@
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::{AllocStore, BitStore};
+
+/// A [`BitStore`] of `u64` words that are overwritten with zeros when the
+/// filter is cleared or dropped.
+///
+/// Requires the `zeroize` feature. A filter over sensitive identifiers,
+/// such as email addresses or card numbers, otherwise leaves its bits in
+/// freed memory, where they can surface in core dumps or later
+/// allocations. The zeros are written with the `zeroize` crate, so the
+/// compiler cannot elide them as dead stores.
+///
+/// # Examples
+///
+/// ```
+/// use bloomlib::{BloomFilter, ZeroizingStore};
+///
+/// let mut bf = BloomFilter::<str, ZeroizingStore>::from_params(1000, 0.01);
+/// bf.insert("alice@example.com");
+/// bf.clear(); // the bits are wiped, as they are when `bf` is dropped
+/// assert!(!bf.contains("alice@example.com"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ZeroizingStore(Vec<u64>);
+
+impl BitStore for ZeroizingStore {
+    type Word = u64;
+
+    #[inline]
+    fn word_count(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    fn word(&self, index: usize) -> u64 {
+        self.0[index]
+    }
+
+    #[inline]
+    fn set_bits(&mut self, index: usize, mask: u64) {
+        self.0[index] |= mask;
+    }
+
+    fn clear(&mut self) {
+        self.0.as_mut_slice().zeroize();
+    }
+
+    fn memory_usage_bytes(&self) -> usize {
+        self.0.capacity() * 8
+    }
+
+    #[inline]
+    fn as_u64_words(&self) -> Option<&[u64]> {
+        Some(&self.0)
+    }
+
+    #[inline]
+    fn as_u64_words_mut(&mut self) -> Option<&mut [u64]> {
+        Some(&mut self.0)
+    }
+}
+
+impl AllocStore for ZeroizingStore {
+    fn zeroed(word_count: usize) -> Self {
+        ZeroizingStore(vec![0; word_count])
+    }
+}
+
+impl Drop for ZeroizingStore {
+    fn drop(&mut self) {
+        // Also wipes any spare capacity.
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for ZeroizingStore {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BloomFilter;
+
+    #[test]
+    fn test_zeroizing_store_matches_vec() {
+        let mut wiped = BloomFilter::<u64, ZeroizingStore>::from_params(1000, 0.01);
+        let mut plain: BloomFilter<u64> = BloomFilter::new(1000, 0.01);
+        for i in 0..500 {
+            wiped.insert(&i);
+            plain.insert(&i);
+        }
+        assert!(wiped.packed_words().eq(plain.packed_words()));
+
+        wiped.clear();
+        assert!(wiped.bit_vec.0.iter().all(|&word| word == 0));
+        assert_eq!(wiped.bit_vec.word_count(), plain.bit_vec.word_count());
+    }
+}
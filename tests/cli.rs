@@ -0,0 +1,355 @@
+//! End-to-end tests of the `bloom` binary.
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Runs `bloom` with `args`, feeding `stdin` to it.
+fn bloom(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bloom"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Returns a fresh path in a per-test temporary directory.
+fn temp_path(test: &str, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bloomlib-cli-{}-{}", std::process::id(), test));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+fn stdout(output: &Output) -> &str {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_build_query_info() {
+    let filter = temp_path("build", "fruit.bloom");
+    let filter = filter.to_str().unwrap();
+    stdout(&bloom(&["build", "-o", filter], "apple\nbanana\ncherry\n"));
+
+    let output = bloom(&["query", filter], "apple\npear\n");
+    assert_eq!(stdout(&output), "apple\ttrue\npear\tfalse\n");
+    let output = bloom(&["query", "--absent", filter, "-"], "apple\npear\n");
+    assert_eq!(stdout(&output), "pear\n");
+
+    let output = bloom(&["info", filter], "");
+    let info = stdout(&output);
+    assert!(info.contains("estimated items:     3\n"), "{}", info);
+
+    // The file loads as a filter of `str` in Rust.
+    let loaded = bloomlib::BloomFilter::<str>::from_bytes(&std::fs::read(filter).unwrap());
+    assert!(loaded.unwrap().contains("banana"));
+}
+
+#[test]
+fn test_build_from_file_with_size() {
+    let keys = temp_path("sized", "keys.txt");
+    let filter = temp_path("sized", "keys.bloom");
+    let lines: String = (0..1000).map(|i| format!("key-{}\n", i)).collect();
+    std::fs::write(&keys, lines).unwrap();
+
+    let (keys, filter) = (keys.to_str().unwrap(), filter.to_str().unwrap());
+    stdout(&bloom(
+        &["build", keys, "-o", filter, "-n", "5000", "--hashes", "5"],
+        "",
+    ));
+    let output = bloom(&["query", "--present", filter, keys], "");
+    assert_eq!(stdout(&output).lines().count(), 1000);
+
+    let output = bloom(&["info", "missing.bloom"], "");
+    assert!(!output.status.success());
+
+    // Built in parts of 1 KB, the file is the same.
+    let external = temp_path("sized", "external.bloom");
+    let external = external.to_str().unwrap();
+    stdout(&bloom(
+        &[
+            "build",
+            keys,
+            "-o",
+            external,
+            "-n",
+            "5000",
+            "--hashes",
+            "5",
+            "--max-memory",
+            "1024",
+        ],
+        "",
+    ));
+    assert_eq!(
+        std::fs::read(external).unwrap(),
+        std::fs::read(filter).unwrap()
+    );
+}
+
+#[test]
+fn test_merge() {
+    let paths: Vec<String> = ["a.bloom", "b.bloom", "merged.bloom", "other.bloom"]
+        .iter()
+        .map(|name| temp_path("merge", name).to_str().unwrap().to_owned())
+        .collect();
+    let [a, b, merged, other] = [&paths[0], &paths[1], &paths[2], &paths[3]];
+    stdout(&bloom(&["build", "-o", a, "-n", "100"], "apple\n"));
+    stdout(&bloom(&["build", "-o", b, "-n", "100"], "banana\n"));
+    stdout(&bloom(&["build", "-o", other, "-n", "100000"], "cherry\n"));
+
+    stdout(&bloom(&["merge", a, b, "-o", merged], ""));
+    let output = bloom(&["query", "--present", merged], "apple\nbanana\ncherry\n");
+    assert_eq!(stdout(&output), "apple\nbanana\n");
+
+    let output = bloom(&["merge", a, other, "-o", merged], "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("other.bloom: Cannot combine"));
+}
+
+#[test]
+fn test_convert() {
+    let filter = temp_path("convert", "fruit.bloom");
+    let prefix = temp_path("convert", "fruit.part");
+    let recombined = temp_path("convert", "recombined.bloom");
+    let (filter, prefix, recombined) = (
+        filter.to_str().unwrap(),
+        prefix.to_str().unwrap(),
+        recombined.to_str().unwrap(),
+    );
+    stdout(&bloom(
+        &["build", "-o", filter, "-n", "10000"],
+        "apple\nbanana\n",
+    ));
+
+    stdout(&bloom(
+        &["convert", filter, "--parts", "3", "-o", prefix],
+        "",
+    ));
+    let parts: Vec<String> = (0..3).map(|i| format!("{}.{}", prefix, i)).collect();
+    let part = bloomlib::FilterPart::<str>::from_bytes(&std::fs::read(&parts[1]).unwrap());
+    assert_eq!(part.unwrap().part_count(), 3);
+
+    // Parts recombine in any order into the same file.
+    let args = ["convert", &parts[2], &parts[0], &parts[1], "-o", recombined];
+    stdout(&bloom(&args, ""));
+    assert_eq!(
+        std::fs::read(filter).unwrap(),
+        std::fs::read(recombined).unwrap()
+    );
+
+    let output = bloom(&["convert", &parts[0], &parts[1], "-o", recombined], "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing"));
+}
+
+#[test]
+fn test_diff() {
+    let paths: Vec<String> = ["a.bloom", "b.bloom", "other.bloom"]
+        .iter()
+        .map(|name| temp_path("diff", name).to_str().unwrap().to_owned())
+        .collect();
+    let [a, b, other] = [&paths[0], &paths[1], &paths[2]];
+    let keys: String = (0..100).map(|i| format!("key-{}\n", i)).collect();
+    stdout(&bloom(&["build", "-o", a, "-n", "1000"], &keys));
+    stdout(&bloom(
+        &["build", "-o", b, "-n", "1000"],
+        &keys[..keys.len() / 2],
+    ));
+    stdout(&bloom(&["build", "-o", other, "-n", "10"], ""));
+
+    let output = bloom(&["diff", a, a], "");
+    let report = stdout(&output);
+    assert!(report.contains("differing words:     0 of "), "{}", report);
+    assert!(report.contains("diverging items:     0\n"), "{}", report);
+
+    let output = bloom(&["diff", a, b], "");
+    let report = stdout(&output);
+    assert!(report.contains("bits only in each:   "), "{}", report);
+    assert!(report.contains(" / 0\n"), "{}", report);
+    let diverging: f64 = report
+        .lines()
+        .find_map(|line| line.strip_prefix("diverging items:"))
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    assert!((diverging - 50.0).abs() < 10.0, "{}", report);
+
+    let output = bloom(&["diff", a, other], "");
+    assert!(stdout(&output).contains("compatible:          no\n"));
+}
+
+#[test]
+fn test_dedup() {
+    let output = bloom(&["dedup"], "b\na\nb\nc\na\n");
+    assert_eq!(stdout(&output), "b\na\nc\n");
+
+    // With a filter file, lines stay deduplicated across runs.
+    let filter = temp_path("dedup", "seen.bloom");
+    let filter = filter.to_str().unwrap();
+    let output = bloom(&["dedup", "--filter", filter, "-n", "1000"], "a\nb\n");
+    assert_eq!(stdout(&output), "a\nb\n");
+    let output = bloom(&["dedup", "--filter", filter], "b\nc\nc\n");
+    assert_eq!(stdout(&output), "c\n");
+}
+
+#[test]
+fn test_bench() {
+    let output = bloom(&["bench", "-n", "2000", "--fp", "0.05", "--json"], "");
+    let report = stdout(&output);
+    assert!(
+        report.starts_with("{\"items\": 2000, \"fp\": 0.05, \"hasher\": \"default\", \"bits\": "),
+        "{}",
+        report
+    );
+    assert!(report.trim_end().ends_with('}'), "{}", report);
+
+    let output = bloom(&["bench", "-n", "2000", "--hasher", "keyed"], "");
+    let report = stdout(&output);
+    assert!(
+        report.contains("hasher:              keyed\n"),
+        "{}",
+        report
+    );
+    assert!(report.contains(" ns/op\n"), "{}", report);
+}
+
+#[test]
+fn test_estimate() {
+    let output = bloom(&["estimate", "-n", "1000000", "--fp", "0.01"], "");
+    let estimate = stdout(&output);
+    assert!(
+        estimate.contains("hashes:              7\n"),
+        "{}",
+        estimate
+    );
+    assert!(estimate.contains("(1.14 MiB)"), "{}", estimate);
+
+    let output = bloom(&["estimate", "-n", "1000000", "--memory", "1MiB"], "");
+    let estimate = stdout(&output);
+    assert!(
+        estimate.contains("bits:                8388608\n"),
+        "{}",
+        estimate
+    );
+    assert!(
+        estimate.contains("false positive rate: 0.017"),
+        "{}",
+        estimate
+    );
+}
+
+/// Sends one HTTP/1.0 request and returns the status code and body.
+#[cfg(feature = "serve")]
+fn http(addr: &str, method: &str, path: &str, body: &[u8]) -> (u16, Vec<u8>) {
+    use std::io::Read;
+
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.0\r\nContent-Length: {}\r\n\r\n",
+        method,
+        path,
+        body.len()
+    )
+    .unwrap();
+    stream.write_all(body).unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let head = std::str::from_utf8(&response[..split]).unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, response[split + 4..].to_vec())
+}
+
+#[cfg(feature = "serve")]
+#[test]
+fn test_serve() {
+    use std::io::{BufRead, BufReader};
+
+    let seed = temp_path("serve", "seed.bloom");
+    stdout(&bloom(&["build", "-o", seed.to_str().unwrap()], "pear\n"));
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bloom"))
+        .args(["serve", "--addr", "127.0.0.1:0", "--max-memory", "67108864"])
+        .arg("--filter")
+        .arg(format!("seed={}", seed.display()))
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(child.stderr.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let addr = line.trim().rsplit(' ').next().unwrap().to_owned();
+    let text = |(status, body): (u16, Vec<u8>)| (status, String::from_utf8(body).unwrap());
+
+    assert_eq!(
+        text(http(&addr, "PUT", "/filters/fruit?items=1000&fp=0.01", b"")).0,
+        201
+    );
+    assert_eq!(
+        text(http(&addr, "PUT", "/filters/fruit?items=10", b"")).0,
+        409
+    );
+    assert_eq!(
+        text(http(&addr, "PUT", "/filters/huge?items=100000000", b"")).0,
+        507
+    );
+    assert_eq!(
+        text(http(
+            &addr,
+            "POST",
+            "/filters/fruit/insert",
+            b"apple\nbanana\n"
+        )),
+        (200, "{\"inserted\": 2}".into())
+    );
+    assert_eq!(
+        text(http(
+            &addr,
+            "POST",
+            "/filters/fruit/check",
+            b"apple\npear\n"
+        )),
+        (200, "[true, false]".into())
+    );
+
+    // A filter of another size cannot be merged in; the preloaded one can.
+    let (status, _) = http(&addr, "POST", "/filters/fruit/merge?from=seed", b"");
+    assert_eq!(status, 409);
+    let (_, bytes) = http(&addr, "GET", "/filters/seed", b"");
+    assert_eq!(text(http(&addr, "PUT", "/filters/copy", &bytes)).0, 201);
+    assert_eq!(
+        text(http(&addr, "POST", "/filters/copy/check", b"pear\napple\n")),
+        (200, "[true, false]".into())
+    );
+
+    let (status, stats) = text(http(&addr, "GET", "/filters/fruit/stats", b""));
+    assert_eq!(status, 200);
+    assert!(stats.contains("\"estimated_items\": 2,"), "{}", stats);
+    assert_eq!(
+        text(http(&addr, "GET", "/filters", b"")),
+        (200, "[\"copy\", \"fruit\", \"seed\"]".into())
+    );
+    assert_eq!(text(http(&addr, "DELETE", "/filters/copy", b"")).0, 200);
+    assert_eq!(text(http(&addr, "GET", "/filters/copy/stats", b"")).0, 404);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}